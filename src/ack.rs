@@ -0,0 +1,54 @@
+use crate::clock::{Clock, SystemClock};
+use crate::types::{MessageId, Timestamp};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default time during which repeated copies of a message are not acked
+/// again.
+pub const DEFAULT_ACK_WINDOW: Duration = Duration::from_secs(30);
+
+/// Receiver-side memory of recently acked message ids, so that a sender
+/// retransmitting aggressively gets one Delivered receipt per window rather
+/// than one per copy. A copy arriving after the window is acked again, in
+/// case the first receipt was lost.
+#[derive(Clone)]
+pub struct AckSuppression {
+    acked: Arc<Mutex<HashMap<MessageId, Timestamp>>>,
+    window: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl AckSuppression {
+    pub fn new(window: Duration) -> Self {
+        Self::with_clock(window, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(window: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            acked: Arc::default(),
+            window,
+            clock,
+        }
+    }
+
+    /// Whether a copy of `id` received now should be acked, recording the
+    /// ack if so.
+    pub fn should_ack(&self, id: MessageId) -> bool {
+        let now = self.clock.now();
+        let mut acked = self.acked.lock().unwrap();
+        acked.retain(|_, at| now.duration_since(*at).unwrap_or_default() < self.window);
+        if acked.contains_key(&id) {
+            tracing::debug!(message.id = %id, "duplicate ack suppressed");
+            return false;
+        }
+        acked.insert(id, now);
+        true
+    }
+}
+
+impl Default for AckSuppression {
+    fn default() -> Self {
+        Self::new(DEFAULT_ACK_WINDOW)
+    }
+}
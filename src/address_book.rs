@@ -0,0 +1,155 @@
+use crate::clock::{Clock, SystemClock};
+use crate::transport::Transport;
+use crate::types::{timestamp_millis, PeerId, Timestamp, UserId};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+/// How to reach a peer again on a particular kind of link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerAddress {
+    /// IP transports (TCP, UDP, WebSocket).
+    Socket(SocketAddr),
+    /// Bluetooth LE device address.
+    Ble([u8; 6]),
+    /// Serial device path, e.g. a LoRa modem on `/dev/ttyUSB0`.
+    Serial(String),
+    /// Anything else, tagged with the transport's own name.
+    Other { transport: String, address: Vec<u8> },
+}
+
+/// What the [`AddressBook`] knows about one peer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressEntry {
+    pub peer: PeerId,
+    /// The user behind the peer, once known.
+    pub user: Option<UserId>,
+    /// Known addresses, most recently used first.
+    pub addresses: Vec<PeerAddress>,
+    #[serde(with = "timestamp_millis")]
+    pub last_seen: Timestamp,
+}
+
+/// Persistent record of how to reach peers, so a restarted node can
+/// reconnect to its old neighbours instead of waiting to rediscover them.
+/// Entries are stored unencrypted.
+#[derive(Clone)]
+pub struct AddressBook {
+    tree: sled::Tree,
+    clock: Arc<dyn Clock>,
+}
+
+impl AddressBook {
+    /// Open (or create) the address book stored at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let db = sled::open(path).with_context(|| format!("open sled at {}", path.display()))?;
+        Self::from_db(&db)
+    }
+
+    /// Ephemeral address book, for tests.
+    pub fn in_memory() -> Result<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .context("open temporary sled")?;
+        Self::from_db(&db)
+    }
+
+    /// Address book kept in `db` alongside other data, e.g. the message
+    /// store.
+    pub fn from_db(db: &sled::Db) -> Result<Self> {
+        Ok(Self {
+            tree: db
+                .open_tree("address_book")
+                .context("open address book tree")?,
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Note that `peer` (run by `user`, if known) was just reachable at
+    /// `address`.
+    pub fn record(&self, peer: PeerId, user: Option<UserId>, address: PeerAddress) -> Result<()> {
+        let mut entry = self.get(&peer)?.unwrap_or(AddressEntry {
+            peer,
+            user: None,
+            addresses: Vec::new(),
+            last_seen: self.clock.now(),
+        });
+        entry.user = user.or(entry.user);
+        entry.addresses.retain(|known| *known != address);
+        entry.addresses.insert(0, address);
+        entry.last_seen = self.clock.now();
+        self.tree.insert(peer.0, bincode::serialize(&entry)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, peer: &PeerId) -> Result<Option<AddressEntry>> {
+        self.tree
+            .get(peer.0)?
+            .map(|raw| bincode::deserialize(&raw).map_err(Into::into))
+            .transpose()
+    }
+
+    /// The most recently seen peer run by `user`.
+    pub fn by_user(&self, user: &UserId) -> Result<Option<AddressEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .find(|entry| entry.user.as_ref() == Some(user)))
+    }
+
+    /// Every entry, most recently seen first.
+    pub fn entries(&self) -> Result<Vec<AddressEntry>> {
+        let mut entries = self
+            .tree
+            .iter()
+            .values()
+            .map(|raw| Ok(bincode::deserialize::<AddressEntry>(&raw?)?))
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.last_seen));
+        Ok(entries)
+    }
+
+    pub fn remove(&self, peer: &PeerId) -> Result<Option<AddressEntry>> {
+        self.tree
+            .remove(peer.0)?
+            .map(|raw| bincode::deserialize(&raw).map_err(Into::into))
+            .transpose()
+    }
+
+    /// Ask `transport` to connect to every known peer, trying each peer's
+    /// addresses in order until one works. Returns how many peers were
+    /// reached; failures are logged and skipped.
+    pub async fn reconnect(&self, transport: &dyn Transport) -> Result<usize> {
+        let mut reached = 0;
+        for entry in self.entries()? {
+            for address in &entry.addresses {
+                match transport.connect(address).await {
+                    Ok(()) => {
+                        reached += 1;
+                        break;
+                    }
+                    Err(e) => tracing::debug!(
+                        peer = %entry.peer,
+                        ?address,
+                        "reconnect failed: {e:#}"
+                    ),
+                }
+            }
+        }
+        Ok(reached)
+    }
+
+    pub async fn flush(&self) -> Result<()> {
+        self.tree.flush_async().await?;
+        Ok(())
+    }
+}
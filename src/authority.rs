@@ -0,0 +1,101 @@
+use crate::message::Message;
+use crate::types::{MessageId, UserId};
+use crate::validator::{MessageValidator, RejectReason};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Admission policy for broadcasts from privileged senders (e.g. an
+/// emergency authority): they must be signed and sequenced, and each must
+/// carry a higher [`Message::seq`] than the last one accepted from that
+/// sender, so an old alert cannot be re-flooded by an attacker. Broadcasts
+/// from anyone else, and unicasts, are left to other policies.
+///
+/// The highest sequence seen is shared between clones, and persisted when
+/// the policy has a store, so a rebooted receiver still refuses alerts it
+/// had already moved past.
+#[derive(Clone, Default)]
+pub struct AuthorityBroadcasts {
+    authorities: HashSet<UserId>,
+    highest: Arc<Mutex<HashMap<UserId, (u64, MessageId)>>>,
+    store: Option<sled::Tree>,
+}
+
+impl AuthorityBroadcasts {
+    pub fn new(authorities: impl IntoIterator<Item = UserId>) -> Self {
+        Self {
+            authorities: authorities.into_iter().collect(),
+            highest: Arc::default(),
+            store: None,
+        }
+    }
+
+    /// Persist the highest sequence per authority in `tree`, loading any
+    /// already recorded there. See
+    /// [`MessageManager::with_authority_broadcasts`](crate::MessageManager::with_authority_broadcasts)
+    /// for keeping it in the message store.
+    pub fn with_store(mut self, tree: sled::Tree) -> Result<Self> {
+        {
+            let mut highest = self.highest.lock().unwrap();
+            for entry in tree.iter() {
+                let (key, raw) = entry?;
+                let Ok(user) = <[u8; 32]>::try_from(key.as_ref()) else {
+                    continue;
+                };
+                let (seq, id): (u64, MessageId) = bincode::deserialize(&raw)?;
+                let mark = highest.entry(UserId(user)).or_insert((seq, id));
+                if seq > mark.0 {
+                    *mark = (seq, id);
+                }
+            }
+        }
+        self.store = Some(tree);
+        Ok(self)
+    }
+
+    pub fn is_authority(&self, user: &UserId) -> bool {
+        self.authorities.contains(user)
+    }
+
+    /// Highest broadcast sequence accepted from `authority`.
+    pub fn highest_seq(&self, authority: &UserId) -> Option<u64> {
+        self.highest
+            .lock()
+            .unwrap()
+            .get(authority)
+            .map(|(seq, _)| *seq)
+    }
+}
+
+impl MessageValidator for AuthorityBroadcasts {
+    fn validate(&self, msg: &Message) -> Result<(), RejectReason> {
+        if msg.recipient.is_some() || !self.is_authority(&msg.sender) {
+            return Ok(());
+        }
+        if msg.signature.is_empty() || msg.verify_signature().is_err() {
+            return Err(RejectReason::Unauthenticated);
+        }
+        let seq = msg.seq.ok_or(RejectReason::Replay)?;
+        let mut highest = self.highest.lock().unwrap();
+        match highest.get(&msg.sender) {
+            // The same message again is a duplicate, not a replay; the
+            // seen-set deals with it.
+            Some(&(last, id)) if seq == last && id == msg.id => return Ok(()),
+            Some(&(last, _)) if seq <= last => {
+                tracing::warn!(sender = %msg.sender, seq, last, "replayed authority broadcast");
+                return Err(RejectReason::Replay);
+            }
+            _ => {}
+        }
+        highest.insert(msg.sender, (seq, msg.id));
+        if let Some(store) = &self.store {
+            let result = bincode::serialize(&(seq, msg.id))
+                .map_err(anyhow::Error::from)
+                .and_then(|raw| Ok(store.insert(msg.sender.0, raw).map(|_| ())?));
+            if let Err(e) = result {
+                tracing::warn!("failed to persist authority sequence: {e:#}");
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::message_manager::MessageManager;
+use crate::routing::RoutingEngine;
+use crate::routing_control::RoutingControl;
+use crate::transport::Transport;
+use crate::types::{PeerId, UserId};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// One destination advertised in a [`RoutingControl::Beacon`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeaconEntry {
+    pub destination: UserId,
+    /// The advertiser's next hop, so that neighbour can ignore routes
+    /// through itself (split horizon).
+    pub next_hop: PeerId,
+    pub hop_count: u8,
+    pub link_quality: f32,
+}
+
+/// Tuning for [`ProactiveBeacon`].
+#[derive(Debug, Clone, Copy)]
+pub struct BeaconConfig {
+    /// Time between beacons.
+    pub interval: Duration,
+    /// Most destinations advertised per beacon; the shortest routes win.
+    pub max_entries: usize,
+}
+
+impl Default for BeaconConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(15),
+            max_entries: 64,
+        }
+    }
+}
+
+/// DSDV-style proactive routing: every node periodically broadcasts its
+/// known destinations with a per-origin sequence number, so neighbours build
+/// tables before any traffic flows. It fills the same [`RoutingEngine`] the
+/// reactive strategy reads, so it can run alongside
+/// [`AodvReactive`](crate::AodvReactive), which then finds routes without
+/// issuing RREQs.
+#[derive(Clone)]
+pub struct ProactiveBeacon {
+    identity: Identity,
+    local_peer: PeerId,
+    routing: RoutingEngine,
+    transport: Arc<dyn Transport>,
+    config: BeaconConfig,
+    seq: Arc<AtomicU32>,
+    /// Persists `seq` when set; see [`with_store`](Self::with_store).
+    store: Option<MessageManager>,
+    /// Highest sequence number seen per origin; older beacons are stale.
+    last_seq: Arc<Mutex<HashMap<UserId, u32>>>,
+}
+
+impl ProactiveBeacon {
+    /// Beacon as `identity`, heard by neighbours as `local_peer`.
+    pub fn new(
+        identity: Identity,
+        local_peer: PeerId,
+        routing: RoutingEngine,
+        transport: Arc<dyn Transport>,
+        config: BeaconConfig,
+    ) -> Self {
+        Self {
+            identity,
+            local_peer,
+            routing,
+            transport,
+            config,
+            seq: Arc::new(AtomicU32::new(0)),
+            store: None,
+            last_seq: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Keep the beacon sequence number in `messages`' store, so neighbours
+    /// do not ignore our beacons as stale after a restart.
+    pub fn with_store(mut self, messages: MessageManager) -> Self {
+        self.store = Some(messages);
+        self
+    }
+
+    fn next_seq(&self) -> Result<u32> {
+        match &self.store {
+            // Wrapping after 2^32 beacons is acceptable.
+            Some(store) => Ok(store.next_counter("beacon")? as u32),
+            None => Ok(self.seq.fetch_add(1, Ordering::Relaxed)),
+        }
+    }
+
+    /// Routing table filled by received beacons.
+    pub fn routing(&self) -> &RoutingEngine {
+        &self.routing
+    }
+
+    /// Broadcast one signed beacon summarising our routing table.
+    pub async fn beacon_once(&self) -> Result<()> {
+        let mut routes = self.routing.dump().await;
+        routes.sort_by_key(|r| r.hop_count);
+        let entries = routes
+            .into_iter()
+            .take(self.config.max_entries)
+            .map(|r| BeaconEntry {
+                destination: r.destination,
+                next_hop: r.next_hop,
+                hop_count: r.hop_count,
+                link_quality: r.link_quality,
+            })
+            .collect();
+        let beacon = RoutingControl::Beacon {
+            origin: self.identity.user_id(),
+            seq: self.next_seq()?,
+            entries,
+        };
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Routing(beacon),
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await
+    }
+
+    /// Beacon every `config.interval` until the task is aborted.
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let beacon = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(beacon.config.interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = beacon.beacon_once().await {
+                    tracing::warn!("routing beacon failed: {e:#}");
+                }
+            }
+        })
+    }
+
+    /// Learn routes from a neighbour's beacon heard via `from`. Returns
+    /// false if `msg` is not a beacon or is stale; errors if it is not signed
+    /// by its origin.
+    pub async fn handle_beacon(
+        &self,
+        msg: &Message,
+        from: PeerId,
+        link_quality: f32,
+    ) -> Result<bool> {
+        let MessageContent::Routing(RoutingControl::Beacon {
+            origin,
+            seq,
+            entries,
+        }) = &msg.content
+        else {
+            return Ok(false);
+        };
+        msg.verify_signature()?;
+        if *origin != msg.sender {
+            anyhow::bail!("beacon not signed by its origin");
+        }
+        let local = self.identity.user_id();
+        if *origin == local {
+            return Ok(false);
+        }
+        {
+            let mut last_seq = self.last_seq.lock().unwrap();
+            if last_seq.get(origin).is_some_and(|last| seq <= last) {
+                return Ok(false);
+            }
+            last_seq.insert(*origin, *seq);
+        }
+
+        self.routing
+            .refresh_route(*origin, from, 1, link_quality)
+            .await;
+        let entries = entries
+            .iter()
+            .filter(|e| e.destination != local && e.next_hop != self.local_peer);
+        for entry in entries {
+            self.routing
+                .refresh_route(
+                    entry.destination,
+                    from,
+                    entry.hop_count.saturating_add(1),
+                    entry.link_quality.min(link_quality),
+                )
+                .await;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,155 @@
+//! Synchronous wrappers over the async API, for callers without a tokio
+//! runtime (simple CLIs, FFI boundaries). All calls share one internal
+//! runtime, created on first use.
+//!
+//! Do not call these from inside an async context: blocking on the shared
+//! runtime from one of its own tasks panics.
+
+use crate::identity::Identity;
+use crate::message::{DeliveryStatus, Message, MessageContent};
+use crate::message_manager::MessageFilter;
+use crate::routing::{RouteInfo, RouteLookup};
+use crate::types::{MessageId, PeerId, UserId};
+use anyhow::Result;
+use std::future::Future;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("disaster-mesh-blocking")
+            .enable_all()
+            .build()
+            .expect("build blocking runtime")
+    })
+}
+
+/// Run any future from the library to completion on the shared runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+/// Blocking [`crate::MessageManager`].
+#[derive(Clone)]
+pub struct MessageManager {
+    inner: crate::MessageManager,
+}
+
+impl MessageManager {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        block_on(crate::MessageManager::open(path)).map(Self::from)
+    }
+
+    pub fn in_memory() -> Result<Self> {
+        block_on(crate::MessageManager::in_memory()).map(Self::from)
+    }
+
+    /// The async manager, sharing the same store.
+    pub fn inner(&self) -> &crate::MessageManager {
+        &self.inner
+    }
+
+    pub fn add_identity(&self, identity: Identity) -> UserId {
+        self.inner.add_identity(identity)
+    }
+
+    pub fn create_message(
+        &self,
+        sender: UserId,
+        recipient: Option<UserId>,
+        content: MessageContent,
+    ) -> Result<Message> {
+        block_on(self.inner.create_message(sender, recipient, content))
+    }
+
+    pub fn list_messages(&self, filter: &MessageFilter) -> Result<Vec<Message>> {
+        block_on(self.inner.list_messages(filter))
+    }
+
+    pub fn validate_message(&self, msg: &Message) -> Result<()> {
+        block_on(self.inner.validate_message(msg))
+    }
+
+    pub fn deliver(&self, msg: Message) -> Result<bool> {
+        block_on(self.inner.deliver(msg))
+    }
+
+    pub fn handle_receipt(&self, msg: &Message) -> Result<Option<DeliveryStatus>> {
+        block_on(self.inner.handle_receipt(msg))
+    }
+
+    pub fn message_status(&self, id: &MessageId) -> Option<DeliveryStatus> {
+        block_on(self.inner.message_status(id))
+    }
+
+    pub fn pending_count(&self) -> usize {
+        block_on(self.inner.pending_count())
+    }
+}
+
+impl From<crate::MessageManager> for MessageManager {
+    fn from(inner: crate::MessageManager) -> Self {
+        Self { inner }
+    }
+}
+
+/// Blocking [`crate::RoutingEngine`].
+#[derive(Clone)]
+pub struct RoutingEngine {
+    inner: crate::RoutingEngine,
+}
+
+impl RoutingEngine {
+    pub fn new(max_age: Duration) -> Self {
+        Self::from(crate::RoutingEngine::new(max_age))
+    }
+
+    /// The async engine, sharing the same table.
+    pub fn inner(&self) -> &crate::RoutingEngine {
+        &self.inner
+    }
+
+    pub fn update_route(
+        &self,
+        destination: UserId,
+        next_hop: PeerId,
+        hop_count: u8,
+        link_quality: f32,
+    ) {
+        block_on(
+            self.inner
+                .update_route(destination, next_hop, hop_count, link_quality),
+        )
+    }
+
+    pub fn next_hop(&self, destination: &UserId) -> Option<PeerId> {
+        block_on(self.inner.next_hop(destination))
+    }
+
+    pub fn lookup(&self, destination: &UserId) -> RouteLookup {
+        block_on(self.inner.lookup(destination))
+    }
+
+    pub fn invalidate(&self, destination: &UserId) -> Option<RouteInfo> {
+        block_on(self.inner.invalidate(destination))
+    }
+
+    pub fn cleanup(&self) {
+        block_on(self.inner.cleanup())
+    }
+
+    pub fn dump(&self) -> Vec<RouteInfo> {
+        block_on(self.inner.dump())
+    }
+}
+
+impl From<crate::RoutingEngine> for RoutingEngine {
+    fn from(inner: crate::RoutingEngine) -> Self {
+        Self { inner }
+    }
+}
@@ -0,0 +1,153 @@
+use crate::address_book::PeerAddress;
+use crate::compression::CompressionAlgorithm;
+use crate::framing::Framing;
+use crate::stats::{ChannelOccupancy, MeshStats};
+use crate::transport::{Transport, TransportEvent, DEFAULT_EVENT_CAPACITY};
+use crate::types::PeerId;
+use crate::wire::{append_crc32, strip_crc32, WireFormat, CHECKSUM_LEN};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// Wraps a link with no integrity checking of its own (raw radio, serial,
+/// UDP) so every frame carries a CRC-32 trailer. Received frames that fail
+/// the check are dropped before anything tries to decode them, and counted
+/// in [`MeshStats::checksum_failures`]. Both ends of the link must use it.
+pub struct ChecksummedTransport {
+    inner: Box<dyn Transport>,
+    stats: Arc<MeshStats>,
+    tx: broadcast::Sender<TransportEvent>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl ChecksummedTransport {
+    pub fn new(inner: Box<dyn Transport>) -> Self {
+        let (tx, _) = broadcast::channel(DEFAULT_EVENT_CAPACITY);
+        Self {
+            inner,
+            stats: Arc::new(MeshStats::default()),
+            tx,
+            task: None,
+        }
+    }
+
+    /// Share counters with other components instead of using a private set.
+    pub fn with_stats(mut self, stats: Arc<MeshStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<MeshStats> {
+        self.stats.clone()
+    }
+}
+
+#[async_trait]
+impl Transport for ChecksummedTransport {
+    /// Start the link and begin relaying its events, verified.
+    async fn start(&mut self) -> Result<()> {
+        self.inner.start().await?;
+        let mut events = self.inner.subscribe_events();
+        let (tx, stats) = (self.tx.clone(), self.stats.clone());
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(TransportEvent::DataReceived { peer, data }) => match strip_crc32(&data) {
+                        Ok(payload) => TransportEvent::DataReceived {
+                            peer,
+                            data: payload.to_vec(),
+                        },
+                        Err(e) => {
+                            MeshStats::incr(&stats.checksum_failures);
+                            tracing::debug!("dropping frame from {peer:?}: {e:#}");
+                            continue;
+                        }
+                    },
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("link events lagged, {skipped} dropped");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
+                let _ = tx.send(event);
+            }
+        }));
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown().await?;
+        tokio::task::yield_now().await;
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, mut data: Vec<u8>) -> Result<()> {
+        append_crc32(&mut data);
+        self.inner.send(peer, data).await
+    }
+
+    async fn broadcast(&self, mut data: Vec<u8>) -> Result<()> {
+        append_crc32(&mut data);
+        self.inner.broadcast(data).await
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.inner.get_peers()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.tx.subscribe()
+    }
+
+    /// The link's MTU less the trailer.
+    fn mtu(&self) -> usize {
+        self.inner.mtu().saturating_sub(CHECKSUM_LEN)
+    }
+
+    fn link_quality(&self) -> f32 {
+        self.inner.link_quality()
+    }
+
+    fn latency_hint(&self) -> Option<Duration> {
+        self.inner.latency_hint()
+    }
+
+    fn is_secure(&self) -> bool {
+        self.inner.is_secure()
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.inner.wire_format()
+    }
+
+    fn framing(&self) -> Framing {
+        self.inner.framing()
+    }
+
+    fn compression(&self) -> CompressionAlgorithm {
+        self.inner.compression()
+    }
+
+    async fn connect(&self, address: &PeerAddress) -> Result<()> {
+        self.inner.connect(address).await
+    }
+
+    fn peer_address(&self, peer: PeerId) -> Option<PeerAddress> {
+        self.inner.peer_address(peer)
+    }
+
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        Some(ChannelOccupancy::of(&self.tx, DEFAULT_EVENT_CAPACITY))
+    }
+
+    fn is_alive(&self) -> bool {
+        self.inner.is_alive()
+    }
+}
@@ -0,0 +1,229 @@
+use crate::wire::{crc32, WireFormat, MAX_MESSAGE_BYTES};
+use anyhow::Result;
+use miniz_oxide::deflate::{compress_to_vec, compress_to_vec_zlib};
+use miniz_oxide::inflate::{decompress_to_vec_with_limit, decompress_to_vec_zlib_with_limit};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Level used by the built-in compressors unless told otherwise: a middle
+/// ground between ratio and CPU.
+pub const DEFAULT_COMPRESSION_LEVEL: u8 = 6;
+
+/// Algorithm recorded in the one-byte header written by
+/// [`WireFormat::encode_compressed`], so the receiver knows how to undo it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CompressionAlgorithm {
+    #[default]
+    None = 0,
+    /// zlib-wrapped DEFLATE; the cheapest real compression on offer.
+    Deflate = 1,
+    /// gzip (RFC 1952), for interop with off-the-shelf tooling.
+    Gzip = 2,
+    /// Zstandard: better ratio than DEFLATE at similar speed, for links
+    /// whose nodes can afford the larger code size.
+    #[cfg(feature = "zstd")]
+    Zstd = 3,
+}
+
+impl CompressionAlgorithm {
+    pub fn from_id(id: u8) -> Result<Self> {
+        Ok(match id {
+            0 => Self::None,
+            1 => Self::Deflate,
+            2 => Self::Gzip,
+            #[cfg(feature = "zstd")]
+            3 => Self::Zstd,
+            #[cfg(not(feature = "zstd"))]
+            3 => anyhow::bail!("zstd compression is not built in"),
+            other => anyhow::bail!("unknown compression algorithm {other}"),
+        })
+    }
+
+    /// Built-in compressor for this algorithm, at the default level.
+    pub fn compressor(self) -> Box<dyn Compressor> {
+        match self {
+            Self::None => Box::new(NoCompression),
+            Self::Deflate => Box::new(Deflate::default()),
+            Self::Gzip => Box::new(Gzip::default()),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Box::new(Zstd::default()),
+        }
+    }
+}
+
+/// Payload compression applied to serialized messages in the wire layer.
+/// Implementations must bound `decompress` output by `limit` so a small
+/// frame cannot expand into an allocation bomb.
+pub trait Compressor: Send + Sync {
+    fn algorithm(&self) -> CompressionAlgorithm;
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>>;
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>>;
+}
+
+/// Pass-through, for links where CPU matters more than airtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCompression;
+
+impl Compressor for NoCompression {
+    fn algorithm(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::None
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        if data.len() > limit {
+            anyhow::bail!("payload of {} bytes exceeds limit", data.len());
+        }
+        Ok(data.to_vec())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Deflate {
+    /// 0 (store) to 10 (smallest).
+    pub level: u8,
+}
+
+impl Default for Deflate {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+impl Compressor for Deflate {
+    fn algorithm(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::Deflate
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(compress_to_vec_zlib(data, self.level))
+    }
+
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        decompress_to_vec_zlib_with_limit(data, limit)
+            .map_err(|e| anyhow::anyhow!("deflate decompression failed: {e}"))
+    }
+}
+
+/// Fixed gzip header: magic, DEFLATE, no flags, no mtime, unknown OS.
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+/// CRC-32 and length of the uncompressed data.
+const GZIP_TRAILER_LEN: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Gzip {
+    /// 0 (store) to 10 (smallest).
+    pub level: u8,
+}
+
+impl Default for Gzip {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+impl Compressor for Gzip {
+    fn algorithm(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::Gzip
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut out = GZIP_HEADER.to_vec();
+        out.extend(compress_to_vec(data, self.level));
+        out.extend_from_slice(&crc32(data).to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        Ok(out)
+    }
+
+    /// Accepts the member layout written by [`compress`](Self::compress);
+    /// optional header fields (file name, comment, ...) are not supported.
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        if data.len() < GZIP_HEADER.len() + GZIP_TRAILER_LEN || data[..4] != GZIP_HEADER[..4] {
+            anyhow::bail!("not a plain gzip member");
+        }
+        let (body, trailer) =
+            data[GZIP_HEADER.len()..].split_at(data.len() - GZIP_HEADER.len() - GZIP_TRAILER_LEN);
+        let plain = decompress_to_vec_with_limit(body, limit)
+            .map_err(|e| anyhow::anyhow!("gzip decompression failed: {e}"))?;
+        let crc = u32::from_le_bytes(trailer[..4].try_into().expect("4 bytes"));
+        let len = u32::from_le_bytes(trailer[4..].try_into().expect("4 bytes"));
+        if crc32(&plain) != crc || plain.len() as u32 != len {
+            anyhow::bail!("gzip trailer mismatch");
+        }
+        Ok(plain)
+    }
+}
+
+/// Level used by [`Zstd`] unless told otherwise; zstd's own default.
+#[cfg(feature = "zstd")]
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone, Copy)]
+pub struct Zstd {
+    /// 1 (fastest) to 22 (smallest).
+    pub level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl Default for Zstd {
+    fn default() -> Self {
+        Self {
+            level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Compressor for Zstd {
+    fn algorithm(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::Zstd
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        zstd::bulk::compress(data, self.level)
+            .map_err(|e| anyhow::anyhow!("zstd compression failed: {e}"))
+    }
+
+    fn decompress(&self, data: &[u8], limit: usize) -> Result<Vec<u8>> {
+        zstd::bulk::decompress(data, limit)
+            .map_err(|e| anyhow::anyhow!("zstd decompression failed: {e}"))
+    }
+}
+
+impl WireFormat {
+    /// [`encode`](Self::encode), then compress with `compressor` behind a
+    /// one-byte [`CompressionAlgorithm`] header.
+    pub fn encode_compressed<T: Serialize>(
+        self,
+        value: &T,
+        compressor: &dyn Compressor,
+    ) -> Result<Vec<u8>> {
+        let payload = compressor.compress(&self.encode(value)?)?;
+        let mut frame = Vec::with_capacity(payload.len() + 1);
+        frame.push(compressor.algorithm() as u8);
+        frame.extend(payload);
+        Ok(frame)
+    }
+
+    /// Inverse of [`encode_compressed`](Self::encode_compressed): the header
+    /// picks the built-in decompressor, whatever this node sends with.
+    /// Output beyond [`MAX_MESSAGE_BYTES`] is refused while inflating.
+    pub fn decode_compressed<T: DeserializeOwned>(self, frame: &[u8]) -> Result<T> {
+        let (&id, payload) = frame
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("empty frame"))?;
+        let plain = CompressionAlgorithm::from_id(id)?
+            .compressor()
+            .decompress(payload, MAX_MESSAGE_BYTES as usize)?;
+        self.decode(&plain)
+    }
+}
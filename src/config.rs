@@ -0,0 +1,166 @@
+use crate::file_transfer::{
+    DEFAULT_CHUNK_SIZE, DEFAULT_MAX_CONCURRENT_TRANSFERS, DEFAULT_MAX_FILE_SIZE,
+    DEFAULT_TRANSFER_IDLE_TIMEOUT,
+};
+use crate::forwarding::{DEFAULT_HOP_LIMIT, DEFAULT_TTL_DECREMENT};
+use crate::message::TtlMode;
+use crate::message_manager::{
+    RetentionPolicy, StoreRecovery, WriteBatching, DEFAULT_MAX_FUTURE_SKEW, DEFAULT_MAX_TTL,
+    DEFAULT_STORE_PATH, DEFAULT_WRITE_BATCH_DELAY,
+};
+use crate::node::DEFAULT_ROUTE_MAX_AGE;
+use crate::routing::DEFAULT_NEGATIVE_TTL;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Node-wide tuning in one place, loadable from a TOML file so field
+/// operators do not have to rebuild to adjust it. Missing keys take the
+/// library defaults; durations are given in whole seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MeshConfig {
+    /// Location of the message store.
+    pub store_path: PathBuf,
+    /// Whether a corrupt store is an error or is moved aside and replaced.
+    pub store_recovery: StoreRecovery,
+    pub ttl_mode: TtlMode,
+    /// How long message ids stay "seen"; unset derives it from
+    /// `max_ttl_secs` and `max_future_skew_secs`.
+    pub dedup_window_secs: Option<u64>,
+    /// Longest TTL accepted on incoming messages.
+    pub max_ttl_secs: u64,
+    /// Tolerated clock skew for incoming message timestamps.
+    pub max_future_skew_secs: u64,
+    pub retention_max_messages: Option<usize>,
+    pub retention_max_bytes: Option<u64>,
+    /// Flush the store after every write; see
+    /// [`MessageManager::with_flush_on_write`](crate::MessageManager::with_flush_on_write).
+    pub flush_on_write: bool,
+    /// Flush the store once this many writes have accumulated instead; see
+    /// [`MessageManager::with_write_batching`](crate::MessageManager::with_write_batching).
+    pub write_batch_max_writes: Option<usize>,
+    /// Longest a batched write waits for its flush, in seconds.
+    pub write_batch_max_delay_secs: u64,
+    pub route_max_age_secs: u64,
+    pub negative_route_ttl_secs: u64,
+    /// Cap on routing-table entries, for memory-constrained devices.
+    pub max_routes: Option<usize>,
+    /// Link-quality margin by which a route one hop longer beats a shorter
+    /// one; `None` always prefers fewer hops.
+    pub route_quality_threshold: Option<f32>,
+    /// Halve unrefreshed routes' link quality this often; see
+    /// [`RoutingEngine::with_quality_decay`](crate::RoutingEngine::with_quality_decay).
+    pub route_quality_half_life_secs: Option<u64>,
+    /// How willing this node is to relay, from 0 (leaf only) to 1; see
+    /// [`Forwarder::with_willingness`](crate::Forwarder::with_willingness).
+    pub forwarding_willingness: f32,
+    /// Hard hop limit enforced by the forwarder.
+    pub max_hops: u8,
+    pub ttl_decrement_secs: u64,
+    /// Payload bytes per file-transfer chunk; lower it for small-MTU links.
+    pub chunk_size: usize,
+    /// Largest incoming file, in bytes, a `FileReceiver` accepts.
+    pub max_file_size: u64,
+    /// Incoming transfers a `FileReceiver` keeps open at once.
+    pub max_concurrent_transfers: usize,
+    /// How long an incoming transfer may go without a chunk before it is
+    /// abandoned.
+    pub transfer_idle_timeout_secs: u64,
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        Self {
+            store_path: PathBuf::from(DEFAULT_STORE_PATH),
+            store_recovery: StoreRecovery::default(),
+            ttl_mode: TtlMode::default(),
+            dedup_window_secs: None,
+            max_ttl_secs: DEFAULT_MAX_TTL.as_secs(),
+            max_future_skew_secs: DEFAULT_MAX_FUTURE_SKEW.as_secs(),
+            retention_max_messages: None,
+            retention_max_bytes: None,
+            flush_on_write: false,
+            write_batch_max_writes: None,
+            write_batch_max_delay_secs: DEFAULT_WRITE_BATCH_DELAY.as_secs(),
+            route_max_age_secs: DEFAULT_ROUTE_MAX_AGE.as_secs(),
+            negative_route_ttl_secs: DEFAULT_NEGATIVE_TTL.as_secs(),
+            max_routes: None,
+            route_quality_threshold: None,
+            route_quality_half_life_secs: None,
+            forwarding_willingness: 1.0,
+            max_hops: DEFAULT_HOP_LIMIT,
+            ttl_decrement_secs: DEFAULT_TTL_DECREMENT.as_secs(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_concurrent_transfers: DEFAULT_MAX_CONCURRENT_TRANSFERS,
+            transfer_idle_timeout_secs: DEFAULT_TRANSFER_IDLE_TIMEOUT.as_secs(),
+        }
+    }
+}
+
+impl MeshConfig {
+    /// Parse a TOML document.
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        toml::from_str(s).context("parse mesh config")
+    }
+
+    /// Read and parse the TOML file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        Self::from_toml_str(&text)
+    }
+
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string(self).context("serialize mesh config")
+    }
+
+    pub fn retention(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            max_messages: self.retention_max_messages,
+            max_bytes: self.retention_max_bytes,
+        }
+    }
+
+    pub fn write_batching(&self) -> Option<WriteBatching> {
+        self.write_batch_max_writes.map(|max_writes| WriteBatching {
+            max_writes,
+            max_delay: Duration::from_secs(self.write_batch_max_delay_secs),
+        })
+    }
+
+    pub fn dedup_window(&self) -> Option<Duration> {
+        self.dedup_window_secs.map(Duration::from_secs)
+    }
+
+    pub fn max_ttl(&self) -> Duration {
+        Duration::from_secs(self.max_ttl_secs)
+    }
+
+    pub fn max_future_skew(&self) -> Duration {
+        Duration::from_secs(self.max_future_skew_secs)
+    }
+
+    pub fn route_max_age(&self) -> Duration {
+        Duration::from_secs(self.route_max_age_secs)
+    }
+
+    pub fn negative_route_ttl(&self) -> Duration {
+        Duration::from_secs(self.negative_route_ttl_secs)
+    }
+
+    pub fn route_quality_half_life(&self) -> Option<Duration> {
+        self.route_quality_half_life_secs.map(Duration::from_secs)
+    }
+
+    pub fn ttl_decrement(&self) -> Duration {
+        Duration::from_secs(self.ttl_decrement_secs)
+    }
+
+    pub fn transfer_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.transfer_idle_timeout_secs)
+    }
+}
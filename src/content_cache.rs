@@ -0,0 +1,144 @@
+use crate::clock::{Clock, SystemClock};
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::routing_control::RoutingControl;
+use crate::types::Timestamp;
+use anyhow::Result;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Default number of bulletins a [`ContentCache`] keeps.
+pub const DEFAULT_CONTENT_CACHE_CAPACITY: usize = 128;
+
+/// Default time a bulletin stays in a [`ContentCache`].
+pub const DEFAULT_CONTENT_CACHE_TTL: Duration = Duration::from_secs(1800);
+
+/// SHA-256 of a message's content, the key peers request cached content by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ContentHash(pub [u8; 32]);
+
+impl ContentHash {
+    pub fn of(content: &MessageContent) -> Self {
+        let bytes = bincode::serialize(content).unwrap_or_default();
+        let hash = digest::digest(&digest::SHA256, &bytes);
+        Self(hash.as_ref().try_into().expect("SHA-256 is 32 bytes"))
+    }
+}
+
+#[derive(Default)]
+struct CacheInner {
+    entries: HashMap<ContentHash, (Message, Timestamp)>,
+    /// Insertion order, oldest first, for eviction.
+    order: VecDeque<ContentHash>,
+}
+
+/// Bounded cache of broadcast content seen while relaying, so a neighbour
+/// of the requester can answer a [`RoutingControl::ContentRequest`] instead
+/// of the origin. Entries are the original signed messages, verified before
+/// they are cached so a forged copy cannot take a genuine one's place.
+#[derive(Clone)]
+pub struct ContentCache {
+    inner: Arc<RwLock<CacheInner>>,
+    capacity: usize,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl ContentCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self::with_clock(capacity, ttl, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(capacity: usize, ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(CacheInner::default())),
+            capacity,
+            ttl,
+            clock,
+        }
+    }
+
+    /// Cache `msg` if it is a live broadcast of application content with a
+    /// valid signature. Returns whether it was stored; when full the oldest
+    /// entry is evicted.
+    pub async fn store(&self, msg: &Message) -> bool {
+        let now = self.clock.now();
+        let cacheable = msg.recipient.is_none()
+            && !matches!(msg.content, MessageContent::Routing(_))
+            && !msg.is_expired_at(now)
+            && msg.verify_signature().is_ok();
+        if self.capacity == 0 || !cacheable {
+            return false;
+        }
+        let hash = ContentHash::of(&msg.content);
+        let mut inner = self.inner.write().await;
+        if inner.entries.contains_key(&hash) {
+            return false;
+        }
+        while inner.entries.len() >= self.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+        inner.order.push_back(hash);
+        inner.entries.insert(hash, (msg.clone(), now + self.ttl));
+        true
+    }
+
+    /// Cached message with content `hash`, unless it or its cache entry has
+    /// expired.
+    pub async fn get(&self, hash: &ContentHash) -> Option<Message> {
+        let now = self.clock.now();
+        let inner = self.inner.read().await;
+        let (msg, expires) = inner.entries.get(hash)?;
+        (*expires > now && !msg.is_expired_at(now)).then(|| msg.clone())
+    }
+
+    /// Answer a content request: the cached message it asks for, if any.
+    pub async fn answer(&self, request: &Message) -> Option<Message> {
+        let MessageContent::Routing(RoutingControl::ContentRequest { hash }) = &request.content
+        else {
+            return None;
+        };
+        self.get(hash).await
+    }
+
+    /// Drop expired entries.
+    pub async fn purge_expired(&self) {
+        let now = self.clock.now();
+        let mut inner = self.inner.write().await;
+        let CacheInner { entries, order } = &mut *inner;
+        entries.retain(|_, (msg, expires)| *expires > now && !msg.is_expired_at(now));
+        order.retain(|hash| entries.contains_key(hash));
+    }
+
+    pub async fn len(&self) -> usize {
+        self.inner.read().await.entries.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+impl Default for ContentCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONTENT_CACHE_CAPACITY, DEFAULT_CONTENT_CACHE_TTL)
+    }
+}
+
+/// Signed broadcast asking nearby peers for the content with `hash`.
+pub fn content_request(identity: &Identity, hash: ContentHash) -> Result<Message> {
+    let mut msg = Message::new(
+        identity.user_id(),
+        None,
+        MessageContent::Routing(RoutingControl::ContentRequest { hash }),
+    );
+    msg.sign(identity)?;
+    Ok(msg)
+}
@@ -0,0 +1,179 @@
+use crate::clock::{Clock, SystemClock};
+use crate::routing::{RouteEvent, RoutingEngine};
+use crate::types::{Timestamp, UserId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// How long an RREQ's `(origin, request_id)` is remembered by default.
+pub const DEFAULT_RREQ_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Throttling parameters for route discovery.
+#[derive(Debug, Clone, Copy)]
+pub struct DiscoveryConfig {
+    /// Minimum gap between the first and second RREQ for a destination. Each
+    /// further retry doubles the gap.
+    pub min_interval: Duration,
+    /// RREQs sent after the first before giving up.
+    pub max_retries: u32,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_secs(1),
+            max_retries: 4,
+        }
+    }
+}
+
+/// Outcome of asking [`RouteDiscovery`] whether to send an RREQ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryDecision {
+    /// Send an RREQ now.
+    Send,
+    /// A route is already known; no RREQ needed.
+    RouteKnown,
+    /// Still inside the backoff interval.
+    Throttled,
+    /// Retries exhausted; discovery for this destination has been abandoned.
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DiscoveryState {
+    attempts: u32,
+    last_sent: Timestamp,
+    failed: bool,
+}
+
+/// Per-destination RREQ throttling with exponential backoff. Publishes
+/// [`RouteEvent::RouteDiscoveryFailed`] on the routing engine's channel when
+/// it gives up on a destination.
+#[derive(Clone)]
+pub struct RouteDiscovery {
+    routing: RoutingEngine,
+    config: DiscoveryConfig,
+    pending: Arc<RwLock<HashMap<UserId, DiscoveryState>>>,
+}
+
+impl RouteDiscovery {
+    pub fn new(routing: RoutingEngine, config: DiscoveryConfig) -> Self {
+        Self {
+            routing,
+            config,
+            pending: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Decide whether an RREQ for `destination` may be sent now, recording the
+    /// attempt if so.
+    pub async fn request(&self, destination: UserId) -> DiscoveryDecision {
+        if self.routing.next_hop(&destination).await.is_some() {
+            self.pending.write().await.remove(&destination);
+            return DiscoveryDecision::RouteKnown;
+        }
+        if self.routing.is_unreachable(&destination) {
+            return DiscoveryDecision::Failed;
+        }
+
+        let now = self.routing.clock().now();
+        let mut pending = self.pending.write().await;
+        let Some(state) = pending.get_mut(&destination) else {
+            pending.insert(
+                destination,
+                DiscoveryState {
+                    attempts: 1,
+                    last_sent: now,
+                    failed: false,
+                },
+            );
+            return DiscoveryDecision::Send;
+        };
+        if state.failed {
+            return DiscoveryDecision::Failed;
+        }
+
+        let backoff = self.config.min_interval * 2u32.saturating_pow(state.attempts - 1);
+        let waited = now.duration_since(state.last_sent).unwrap_or_default();
+        if waited < backoff {
+            return DiscoveryDecision::Throttled;
+        }
+        if state.attempts > self.config.max_retries {
+            state.failed = true;
+            self.routing.mark_unreachable(destination);
+            self.routing
+                .publish(RouteEvent::RouteDiscoveryFailed { destination });
+            return DiscoveryDecision::Failed;
+        }
+        state.attempts += 1;
+        state.last_sent = now;
+        DiscoveryDecision::Send
+    }
+
+    /// Forget any throttling state for `destination`, e.g. once a route has
+    /// been learned or the application wants to retry from scratch.
+    pub async fn reset(&self, destination: &UserId) {
+        self.pending.write().await.remove(destination);
+    }
+
+    /// Destinations with a discovery in progress, i.e. not yet resolved,
+    /// abandoned or cancelled.
+    pub async fn pending_discoveries(&self) -> Vec<UserId> {
+        self.pending
+            .read()
+            .await
+            .iter()
+            .filter(|(_, state)| !state.failed)
+            .map(|(destination, _)| *destination)
+            .collect()
+    }
+
+    /// Abandon the discovery in progress for `destination`: its retries
+    /// stop counting toward failure and it leaves
+    /// [`pending_discoveries`](Self::pending_discoveries). Nothing is
+    /// remembered, so a later send to the destination starts a fresh
+    /// discovery.
+    pub async fn cancel_discovery(&self, destination: UserId) {
+        self.pending.write().await.remove(&destination);
+    }
+}
+
+/// Recently relayed RREQs keyed by `(origin, request_id)`. RREQs are
+/// rebroadcast by every relay, so without this a single discovery can turn
+/// into a broadcast storm; it is independent of message-id deduplication.
+#[derive(Clone)]
+pub struct RreqCache {
+    seen: Arc<Mutex<HashMap<(UserId, u32), Timestamp>>>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl RreqCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            seen: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+            clock,
+        }
+    }
+
+    /// Record an RREQ, returning true if it was not seen within the TTL.
+    pub fn insert(&self, origin: UserId, request_id: u32) -> bool {
+        let now = self.clock.now();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, expires| *expires > now);
+        seen.insert((origin, request_id), now + self.ttl).is_none()
+    }
+}
+
+impl Default for RreqCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_RREQ_CACHE_TTL)
+    }
+}
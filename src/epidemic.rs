@@ -0,0 +1,214 @@
+use crate::clock::{Clock, SystemClock};
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent, TtlMode};
+use crate::routing_control::RoutingControl;
+use crate::transport::Transport;
+use crate::types::{MessageId, PeerId, UserId};
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Default number of messages an [`EpidemicBuffer`] carries.
+pub const DEFAULT_CARRY_CAPACITY: usize = 256;
+
+#[derive(Default)]
+struct BufferInner {
+    messages: HashMap<MessageId, Message>,
+    /// Insertion order, oldest first, for eviction.
+    order: VecDeque<MessageId>,
+}
+
+/// Bounded store of messages carried for destinations with no current route.
+/// Expired messages are never handed out; when full the oldest is evicted.
+#[derive(Clone)]
+pub struct EpidemicBuffer {
+    inner: Arc<RwLock<BufferInner>>,
+    capacity: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl EpidemicBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self::with_clock(capacity, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(capacity: usize, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(BufferInner::default())),
+            capacity,
+            clock,
+        }
+    }
+
+    fn is_live(&self, msg: &Message) -> bool {
+        !msg.is_expired_at(self.clock.now())
+    }
+
+    /// Carry `msg`. Returns false if it is expired, already held, or its
+    /// QoS class rules out store-and-forward.
+    pub async fn store(&self, msg: Message) -> bool {
+        let carried = msg
+            .qos_policy()
+            .map_or(true, |policy| policy.store_and_forward);
+        if self.capacity == 0 || !carried || !self.is_live(&msg) {
+            return false;
+        }
+        let mut inner = self.inner.write().await;
+        if inner.messages.contains_key(&msg.id) {
+            return false;
+        }
+        while inner.messages.len() >= self.capacity {
+            let Some(oldest) = inner.order.pop_front() else {
+                break;
+            };
+            inner.messages.remove(&oldest);
+        }
+        inner.order.push_back(msg.id);
+        inner.messages.insert(msg.id, msg);
+        true
+    }
+
+    /// Drop messages whose TTL has run out.
+    pub async fn purge_expired(&self) {
+        let mut inner = self.inner.write().await;
+        let BufferInner { messages, order } = &mut *inner;
+        messages.retain(|_, msg| self.is_live(msg));
+        order.retain(|id| messages.contains_key(id));
+    }
+
+    /// Summary vector: ids of all live carried messages.
+    pub async fn summary(&self) -> Vec<MessageId> {
+        self.purge_expired().await;
+        self.inner.read().await.order.iter().copied().collect()
+    }
+
+    /// Which of `offered` we do not hold yet.
+    pub async fn missing(&self, offered: &[MessageId]) -> Vec<MessageId> {
+        let inner = self.inner.read().await;
+        offered
+            .iter()
+            .filter(|id| !inner.messages.contains_key(id))
+            .copied()
+            .collect()
+    }
+
+    /// Live messages among `ids`. Relative TTLs are handed on aged by the
+    /// time we carried them.
+    pub async fn get(&self, ids: &[MessageId]) -> Vec<Message> {
+        let now = self.clock.now();
+        let inner = self.inner.read().await;
+        ids.iter()
+            .filter_map(|id| inner.messages.get(id))
+            .filter(|msg| self.is_live(msg))
+            .map(|msg| {
+                let mut msg = msg.clone();
+                if msg.ttl_mode == TtlMode::Relative {
+                    msg.ttl = msg.remaining_ttl(now);
+                    msg.received_at = Some(now);
+                }
+                msg
+            })
+            .collect()
+    }
+
+    /// Stop carrying `target` if `sender` originated it. Returns whether it
+    /// was removed.
+    pub async fn recall(&self, target: &MessageId, sender: &UserId) -> bool {
+        let mut inner = self.inner.write().await;
+        if inner
+            .messages
+            .get(target)
+            .map_or(true, |msg| msg.sender != *sender)
+        {
+            return false;
+        }
+        inner.messages.remove(target);
+        inner.order.retain(|id| id != target);
+        true
+    }
+
+    pub async fn contains(&self, id: &MessageId) -> bool {
+        self.inner.read().await.messages.contains_key(id)
+    }
+
+    pub async fn len(&self) -> usize {
+        self.inner.read().await.messages.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+/// DTN-style epidemic exchange on top of an [`EpidemicBuffer`]. On contact
+/// each side offers a summary vector, the other requests what it lacks and
+/// the carrier sends those messages.
+#[derive(Clone)]
+pub struct Epidemic {
+    buffer: EpidemicBuffer,
+    identity: Identity,
+    transport: Arc<dyn Transport>,
+}
+
+impl Epidemic {
+    pub fn new(buffer: EpidemicBuffer, identity: Identity, transport: Arc<dyn Transport>) -> Self {
+        Self {
+            buffer,
+            identity,
+            transport,
+        }
+    }
+
+    pub fn buffer(&self) -> &EpidemicBuffer {
+        &self.buffer
+    }
+
+    /// Buffer a message until a contact opportunity arises.
+    pub async fn carry(&self, msg: Message) -> bool {
+        self.buffer.store(msg).await
+    }
+
+    /// Offer our summary vector to a newly connected `peer`.
+    pub async fn on_peer_connected(&self, peer: PeerId) -> Result<()> {
+        let ids = self.buffer.summary().await;
+        self.send_control(peer, RoutingControl::Summary { ids })
+            .await
+    }
+
+    /// Handle summary/request control from `from`. Returns false if `msg` is
+    /// not part of the epidemic exchange.
+    pub async fn handle_control(&self, msg: &Message, from: PeerId) -> Result<bool> {
+        match &msg.content {
+            MessageContent::Routing(RoutingControl::Summary { ids }) => {
+                let ids = self.buffer.missing(ids).await;
+                if !ids.is_empty() {
+                    self.send_control(from, RoutingControl::Request { ids })
+                        .await?;
+                }
+                Ok(true)
+            }
+            MessageContent::Routing(RoutingControl::Request { ids }) => {
+                for carried in self.buffer.get(ids).await {
+                    self.transport
+                        .send(from, self.transport.encode_message(&carried)?)
+                        .await?;
+                }
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn send_control(&self, peer: PeerId, control: RoutingControl) -> Result<()> {
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Routing(control),
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .send(peer, self.transport.encode_message(&msg)?)
+            .await
+    }
+}
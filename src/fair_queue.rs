@@ -0,0 +1,100 @@
+use crate::message::MessagePriority;
+use crate::types::UserId;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// Default number of items one flow may have waiting in a [`FairQueue`].
+pub const DEFAULT_FLOW_CAPACITY: usize = 64;
+
+/// Default number of flows a [`FairQueue`] keeps at once.
+pub const DEFAULT_MAX_FLOWS: usize = 256;
+
+/// Outbound scheduler sharing the link fairly between flows (destinations,
+/// or senders for broadcasts): each non-empty flow gets one turn per round
+/// instead of strict FIFO, so a chatty destination cannot starve the rest.
+/// Emergency items bypass the rotation and always go first.
+#[derive(Debug)]
+pub struct FairQueue<T, K = UserId> {
+    emergency: VecDeque<T>,
+    flows: HashMap<K, VecDeque<T>>,
+    /// Flows with waiting items, next to be served first.
+    rotation: VecDeque<K>,
+    flow_capacity: usize,
+    max_flows: usize,
+}
+
+impl<T, K: Copy + Eq + Hash> FairQueue<T, K> {
+    pub fn new(flow_capacity: usize) -> Self {
+        Self {
+            emergency: VecDeque::new(),
+            flows: HashMap::new(),
+            rotation: VecDeque::new(),
+            flow_capacity,
+            max_flows: DEFAULT_MAX_FLOWS,
+        }
+    }
+
+    /// Keep at most `max_flows` flows waiting at once (default
+    /// [`DEFAULT_MAX_FLOWS`]), so minting new flow keys cannot grow the
+    /// queue without bound or buy extra turns.
+    pub fn with_max_flows(mut self, max_flows: usize) -> Self {
+        self.max_flows = max_flows;
+        self
+    }
+
+    /// Queue `item` on `flow`. Returns false, dropping it, if that flow
+    /// (or, for Emergency items, the emergency lane) is already full, or if
+    /// it would be a new flow and the flow limit is reached.
+    pub fn push(&mut self, flow: K, priority: MessagePriority, item: T) -> bool {
+        if priority == MessagePriority::Emergency {
+            if self.emergency.len() >= self.flow_capacity {
+                return false;
+            }
+            self.emergency.push_back(item);
+            return true;
+        }
+        if !self.flows.contains_key(&flow) && self.flows.len() >= self.max_flows {
+            return false;
+        }
+        let queue = self.flows.entry(flow).or_default();
+        if queue.len() >= self.flow_capacity {
+            return false;
+        }
+        if queue.is_empty() {
+            self.rotation.push_back(flow);
+        }
+        queue.push_back(item);
+        true
+    }
+
+    /// Next item to send: any Emergency item, otherwise the oldest item of
+    /// the flow whose turn it is.
+    pub fn pop(&mut self) -> Option<T> {
+        if let Some(item) = self.emergency.pop_front() {
+            return Some(item);
+        }
+        let flow = self.rotation.pop_front()?;
+        let queue = self.flows.get_mut(&flow)?;
+        let item = queue.pop_front();
+        if queue.is_empty() {
+            self.flows.remove(&flow);
+        } else {
+            self.rotation.push_back(flow);
+        }
+        item
+    }
+
+    pub fn len(&self) -> usize {
+        self.emergency.len() + self.flows.values().map(VecDeque::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T, K: Copy + Eq + Hash> Default for FairQueue<T, K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_FLOW_CAPACITY)
+    }
+}
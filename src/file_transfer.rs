@@ -0,0 +1,395 @@
+use crate::clock::{Clock, SystemClock};
+use crate::config::MeshConfig;
+use crate::message::{Message, MessageContent};
+use crate::message_manager::MessageManager;
+use crate::routing_control::RoutingControl;
+use crate::types::{MessageId, Timestamp, UserId};
+use anyhow::{Context, Result};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Payload bytes per [`MessageContent::FileChunk`], comfortably below
+/// [`MAX_MESSAGE_BYTES`](crate::MAX_MESSAGE_BYTES).
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Largest file a [`FileReceiver`] accepts unless configured otherwise.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Default cap on incoming transfers a [`FileReceiver`] keeps open at once.
+pub const DEFAULT_MAX_CONCURRENT_TRANSFERS: usize = 16;
+
+/// Default time an incoming transfer may go without a new chunk before a
+/// [`FileReceiver`] abandons it.
+pub const DEFAULT_TRANSFER_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Streams files over the mesh as numbered [`MessageContent::FileChunk`]
+/// messages, reading one chunk at a time instead of loading the whole file.
+#[derive(Clone)]
+pub struct FileTransfer {
+    messages: MessageManager,
+    chunk_size: usize,
+}
+
+impl FileTransfer {
+    pub fn new(messages: MessageManager) -> Self {
+        Self {
+            messages,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+
+    /// Split files into chunks of `chunk_size` bytes (default
+    /// [`DEFAULT_CHUNK_SIZE`]).
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Use the chunk size from `config`.
+    pub fn with_config(self, config: &MeshConfig) -> Self {
+        self.with_chunk_size(config.chunk_size)
+    }
+
+    /// Start sending the file at `path` from `sender` to `recipient`.
+    pub async fn open(
+        &self,
+        sender: UserId,
+        recipient: UserId,
+        path: impl AsRef<Path>,
+    ) -> Result<OutgoingTransfer> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .context("file has no usable name")?
+            .to_string();
+        let file = File::open(path)
+            .await
+            .with_context(|| format!("open {}", path.display()))?;
+        let total_size = file.metadata().await?.len();
+        let total_chunks = u32::try_from(total_size.div_ceil(self.chunk_size as u64).max(1))
+            .context("file has too many chunks")?;
+        Ok(OutgoingTransfer {
+            transfer_id: MessageId::new(),
+            name,
+            file,
+            total_size,
+            total_chunks,
+            next_index: 0,
+            chunk_size: self.chunk_size,
+            sender,
+            recipient,
+            messages: self.messages.clone(),
+        })
+    }
+}
+
+/// Sending side of one file transfer.
+pub struct OutgoingTransfer {
+    transfer_id: MessageId,
+    name: String,
+    file: File,
+    total_size: u64,
+    total_chunks: u32,
+    next_index: u32,
+    chunk_size: usize,
+    sender: UserId,
+    recipient: UserId,
+    messages: MessageManager,
+}
+
+impl OutgoingTransfer {
+    pub fn transfer_id(&self) -> MessageId {
+        self.transfer_id
+    }
+
+    pub fn total_chunks(&self) -> u32 {
+        self.total_chunks
+    }
+
+    /// Next chunk message in order, or `None` once all have been produced.
+    pub async fn next_chunk(&mut self) -> Result<Option<Message>> {
+        if self.next_index >= self.total_chunks {
+            return Ok(None);
+        }
+        let msg = self.chunk(self.next_index).await?;
+        self.next_index += 1;
+        Ok(Some(msg))
+    }
+
+    /// Re-create chunk `index`, e.g. to fill a gap reported by the receiver.
+    pub async fn chunk(&mut self, index: u32) -> Result<Message> {
+        if index >= self.total_chunks {
+            anyhow::bail!("chunk {index} out of range");
+        }
+        let offset = index as u64 * self.chunk_size as u64;
+        let len = (self.total_size - offset).min(self.chunk_size as u64) as usize;
+        let mut data = vec![0; len];
+        self.file.seek(SeekFrom::Start(offset)).await?;
+        self.file.read_exact(&mut data).await?;
+        let content = MessageContent::FileChunk {
+            transfer_id: self.transfer_id,
+            name: self.name.clone(),
+            index,
+            total_chunks: self.total_chunks,
+            offset,
+            total_size: self.total_size,
+            data,
+        };
+        self.messages
+            .create_message(self.sender, Some(self.recipient), content)
+            .await
+    }
+
+    /// Chunks listed in a receiver's [`RoutingControl::FragNack`].
+    pub async fn resend(&mut self, missing: &[u32]) -> Result<Vec<Message>> {
+        let mut chunks = Vec::with_capacity(missing.len());
+        for &index in missing {
+            chunks.push(self.chunk(index).await?);
+        }
+        Ok(chunks)
+    }
+}
+
+/// Progress of an incoming transfer, reported for every accepted chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferProgress {
+    pub transfer_id: MessageId,
+    /// Final location of the file; written as `<path>.part` until complete.
+    pub path: PathBuf,
+    pub received_bytes: u64,
+    pub total_size: u64,
+    pub complete: bool,
+}
+
+struct Incoming {
+    file: File,
+    path: PathBuf,
+    total_chunks: u32,
+    total_size: u64,
+    received: BTreeSet<u32>,
+    received_bytes: u64,
+    last_chunk: Timestamp,
+}
+
+/// Receiving side of file transfers: writes chunks straight to disk under a
+/// download directory and tracks which are still missing. Transfers are
+/// keyed by sender and transfer id, and never replace an existing file.
+/// Each open transfer holds a file handle and a `.part` file, so only a
+/// bounded number run at once and idle ones are abandoned.
+#[derive(Clone)]
+pub struct FileReceiver {
+    dir: PathBuf,
+    max_file_size: u64,
+    max_concurrent: usize,
+    idle_timeout: Duration,
+    incoming: Arc<Mutex<HashMap<(UserId, MessageId), Incoming>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl FileReceiver {
+    /// Store received files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_clock(dir, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(dir: impl Into<PathBuf>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_concurrent: DEFAULT_MAX_CONCURRENT_TRANSFERS,
+            idle_timeout: DEFAULT_TRANSFER_IDLE_TIMEOUT,
+            incoming: Arc::new(Mutex::new(HashMap::new())),
+            clock,
+        }
+    }
+
+    /// Refuse transfers announcing more than `max` bytes (default
+    /// [`DEFAULT_MAX_FILE_SIZE`]).
+    pub fn with_max_file_size(mut self, max: u64) -> Self {
+        self.max_file_size = max;
+        self
+    }
+
+    /// Refuse new transfers while `max` are in progress (default
+    /// [`DEFAULT_MAX_CONCURRENT_TRANSFERS`]).
+    pub fn with_max_concurrent_transfers(mut self, max: usize) -> Self {
+        self.max_concurrent = max;
+        self
+    }
+
+    /// Abandon transfers that receive no chunk for `timeout` (default
+    /// [`DEFAULT_TRANSFER_IDLE_TIMEOUT`]); see
+    /// [`purge_idle`](Self::purge_idle).
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    /// Use the file transfer limits from `config`.
+    pub fn with_config(self, config: &MeshConfig) -> Self {
+        self.with_max_file_size(config.max_file_size)
+            .with_max_concurrent_transfers(config.max_concurrent_transfers)
+            .with_idle_timeout(config.transfer_idle_timeout())
+    }
+
+    /// Write the chunk carried by `msg` to disk. Returns `None` if `msg` is
+    /// not a file chunk or repeats one already written. A chunk starting a
+    /// new transfer first purges idle ones, and fails if the concurrency
+    /// limit is still reached.
+    pub async fn receive(&self, msg: &Message) -> Result<Option<TransferProgress>> {
+        let MessageContent::FileChunk {
+            transfer_id,
+            name,
+            index,
+            total_chunks,
+            offset,
+            total_size,
+            data,
+        } = &msg.content
+        else {
+            return Ok(None);
+        };
+        if *total_size > self.max_file_size {
+            anyhow::bail!("file of {total_size} bytes exceeds the size limit");
+        }
+        // Every chunk but that of an empty file carries at least one byte.
+        if u64::from(*total_chunks) > (*total_size).max(1) {
+            anyhow::bail!("file chunk count disagrees with its size");
+        }
+        if index >= total_chunks || offset.saturating_add(data.len() as u64) > *total_size {
+            anyhow::bail!("file chunk {index} out of range");
+        }
+
+        let key = (msg.sender, *transfer_id);
+        let now = self.clock.now();
+        let mut incoming = self.incoming.lock().await;
+        if !incoming.contains_key(&key) {
+            self.purge_idle_locked(&mut incoming, now).await;
+            if incoming.len() >= self.max_concurrent {
+                anyhow::bail!("too many file transfers in progress");
+            }
+        }
+        if let Entry::Vacant(slot) = incoming.entry(key) {
+            // Never let a peer choose a path outside the download directory.
+            let name = Path::new(name)
+                .file_name()
+                .context("file chunk has no usable name")?;
+            let path = self.dir.join(name);
+            if tokio::fs::try_exists(&path).await? {
+                anyhow::bail!("{} already exists", path.display());
+            }
+            let file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(part_path(&path))
+                .await
+                .with_context(|| format!("create {}", part_path(&path).display()))?;
+            slot.insert(Incoming {
+                file,
+                path,
+                total_chunks: *total_chunks,
+                total_size: *total_size,
+                received: BTreeSet::new(),
+                received_bytes: 0,
+                last_chunk: now,
+            });
+        }
+        let entry = incoming.get_mut(&key).expect("inserted above");
+        if entry.total_chunks != *total_chunks || entry.total_size != *total_size {
+            anyhow::bail!("file chunk disagrees with transfer size");
+        }
+        if !entry.received.insert(*index) {
+            return Ok(None);
+        }
+        entry.last_chunk = now;
+        entry.file.seek(SeekFrom::Start(*offset)).await?;
+        entry.file.write_all(data).await?;
+        entry.received_bytes += data.len() as u64;
+
+        let complete = entry.received.len() as u32 == entry.total_chunks;
+        let progress = TransferProgress {
+            transfer_id: *transfer_id,
+            path: entry.path.clone(),
+            received_bytes: entry.received_bytes,
+            total_size: entry.total_size,
+            complete,
+        };
+        if complete {
+            let mut done = incoming.remove(&key).expect("present");
+            done.file.flush().await?;
+            if tokio::fs::try_exists(&done.path).await? {
+                anyhow::bail!("{} already exists", done.path.display());
+            }
+            tokio::fs::rename(part_path(&done.path), &done.path).await?;
+        }
+        Ok(Some(progress))
+    }
+
+    /// Abandon transfers that have received no chunk for the idle timeout,
+    /// closing and deleting their `.part` files. Call periodically; new
+    /// transfers also trigger it. Returns how many were abandoned.
+    pub async fn purge_idle(&self) -> usize {
+        let now = self.clock.now();
+        let mut incoming = self.incoming.lock().await;
+        self.purge_idle_locked(&mut incoming, now).await
+    }
+
+    async fn purge_idle_locked(
+        &self,
+        incoming: &mut HashMap<(UserId, MessageId), Incoming>,
+        now: Timestamp,
+    ) -> usize {
+        let idle: Vec<_> = incoming
+            .iter()
+            .filter(|(_, entry)| {
+                now.duration_since(entry.last_chunk)
+                    .is_ok_and(|quiet| quiet >= self.idle_timeout)
+            })
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &idle {
+            let entry = incoming.remove(key).expect("listed above");
+            let part = part_path(&entry.path);
+            drop(entry.file);
+            if let Err(e) = tokio::fs::remove_file(&part).await {
+                tracing::warn!("failed to remove {}: {e}", part.display());
+            }
+        }
+        idle.len()
+    }
+
+    /// Chunk indices of `sender`'s `transfer_id` not yet received, or `None`
+    /// if no such transfer is in progress.
+    pub async fn missing(&self, sender: &UserId, transfer_id: &MessageId) -> Option<Vec<u32>> {
+        let incoming = self.incoming.lock().await;
+        let entry = incoming.get(&(*sender, *transfer_id))?;
+        Some(
+            (0..entry.total_chunks)
+                .filter(|i| !entry.received.contains(i))
+                .collect(),
+        )
+    }
+
+    /// [`RoutingControl::FragNack`] asking the sender for the missing chunks.
+    pub async fn nack(&self, sender: &UserId, transfer_id: &MessageId) -> Option<RoutingControl> {
+        let missing = self.missing(sender, transfer_id).await?;
+        Some(RoutingControl::FragNack {
+            msg_id: *transfer_id,
+            missing,
+        })
+    }
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut part = path.as_os_str().to_owned();
+    part.push(".part");
+    part.into()
+}
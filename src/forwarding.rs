@@ -0,0 +1,905 @@
+use crate::clock::{Clock, SystemClock};
+use crate::config::MeshConfig;
+use crate::content_cache::ContentCache;
+use crate::discovery::{DiscoveryDecision, RouteDiscovery, RreqCache};
+use crate::epidemic::Epidemic;
+use crate::fair_queue::{FairQueue, DEFAULT_FLOW_CAPACITY};
+use crate::geo::GeoPoint;
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent, MessagePriority, TtlMode};
+use crate::message_manager::DEFAULT_DEDUP_WINDOW;
+use crate::reputation::{Reputation, ReputationEvent};
+use crate::routing::{RouteLookup, RoutingEngine};
+use crate::routing_control::RoutingControl;
+use crate::stats::MeshStats;
+use crate::suppression::{BroadcastSuppression, HeldRebroadcast};
+use crate::transport::Transport;
+use crate::types::{MessageId, PeerId, Timestamp, UserId};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Default hop limit applied by the built-in strategies.
+pub const DEFAULT_MAX_HOPS: u8 = 16;
+
+/// Default hard hop limit enforced by [`Forwarder`] regardless of strategy,
+/// bounding how far a message can travel even if a peer misbehaves.
+pub const DEFAULT_HOP_LIMIT: u8 = 32;
+
+/// Lifetime a relay deducts from a relative-TTL message, standing in for
+/// the unknown in-transit time.
+pub const DEFAULT_TTL_DECREMENT: Duration = Duration::from_secs(1);
+
+/// Congestion admission control: the outbound relay queue's fill level
+/// (0.0 to 1.0 of `queue_capacity`) above which relayed messages of each
+/// priority are shed. Emergency traffic is never shed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdmissionConfig {
+    pub urgent: f32,
+    pub normal: f32,
+    pub background: f32,
+    /// Queued relay transmissions at which the link counts as saturated.
+    pub queue_capacity: usize,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            urgent: 0.95,
+            normal: 0.75,
+            background: 0.5,
+            queue_capacity: 256,
+        }
+    }
+}
+
+impl AdmissionConfig {
+    /// Whether a message of `priority` may be relayed at queue fill `load`.
+    pub fn admits(&self, priority: MessagePriority, load: f32) -> bool {
+        let threshold = match priority {
+            MessagePriority::Emergency => return true,
+            MessagePriority::Urgent => self.urgent,
+            MessagePriority::Normal => self.normal,
+            MessagePriority::Background => self.background,
+        };
+        load < threshold
+    }
+}
+
+/// Flood scope by priority: the hop count at which relays stop forwarding
+/// messages of each priority, on top of the [`Forwarder`]'s own hop limit.
+/// Emergency traffic always gets the full hop limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HopScope {
+    pub urgent: u8,
+    pub normal: u8,
+    pub background: u8,
+}
+
+impl Default for HopScope {
+    fn default() -> Self {
+        Self {
+            urgent: 24,
+            normal: 16,
+            background: 8,
+        }
+    }
+}
+
+impl HopScope {
+    /// Hop limit for a message of `priority` under an overall `max_hops`.
+    pub fn limit(&self, priority: MessagePriority, max_hops: u8) -> u8 {
+        let scope = match priority {
+            MessagePriority::Emergency => return max_hops,
+            MessagePriority::Urgent => self.urgent,
+            MessagePriority::Normal => self.normal,
+            MessagePriority::Background => self.background,
+        };
+        scope.min(max_hops)
+    }
+}
+
+/// What to do with a received message that is not (only) for us.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardDecision {
+    /// Do not forward (duplicate, expired, over the hop limit, ...).
+    Drop,
+    /// Rebroadcast to all neighbours.
+    Broadcast,
+    /// Send to a single next hop.
+    Unicast(PeerId),
+    /// No route known – start route discovery for the destination.
+    Discover(UserId),
+}
+
+/// Encoded relay transmissions waiting for [`Forwarder::send_queued`]:
+/// `None` for a broadcast, otherwise the next hop.
+type OutboundQueue = Arc<std::sync::Mutex<FairQueue<(Option<PeerId>, Vec<u8>)>>>;
+
+/// Pluggable forwarding policy consulted by [`Forwarder`] for every received
+/// message.
+#[async_trait]
+pub trait ForwardingStrategy: Send + Sync {
+    async fn decide(&self, msg: &Message, from: PeerId) -> ForwardDecision;
+}
+
+/// Shared seen-set used by the built-in strategies to forward each message id
+/// at most once. Like the store's dedup, an id is remembered for a window
+/// from its first sighting (default [`DEFAULT_DEDUP_WINDOW`]) and then
+/// forgotten, so a long-running relay's set does not grow without bound.
+#[derive(Clone)]
+struct SeenSet {
+    inner: Arc<RwLock<SeenIds>>,
+    window: Duration,
+}
+
+#[derive(Default)]
+struct SeenIds {
+    first_seen: HashMap<MessageId, Timestamp>,
+    /// Ids in first-sighting order, for expiring the oldest.
+    order: VecDeque<(Timestamp, MessageId)>,
+}
+
+impl Default for SeenSet {
+    fn default() -> Self {
+        Self {
+            inner: Arc::default(),
+            window: DEFAULT_DEDUP_WINDOW,
+        }
+    }
+}
+
+impl SeenSet {
+    /// Record `id` as seen at `now`, returning true if it had not been seen
+    /// within the window. Sightings that left the window are dropped.
+    async fn insert(&self, id: MessageId, now: Timestamp) -> bool {
+        let mut seen = self.inner.write().await;
+        while let Some(&(at, oldest)) = seen.order.front() {
+            if now.duration_since(at).unwrap_or_default() <= self.window {
+                break;
+            }
+            seen.order.pop_front();
+            seen.first_seen.remove(&oldest);
+        }
+        if seen.first_seen.contains_key(&id) {
+            return false;
+        }
+        seen.first_seen.insert(id, now);
+        seen.order.push_back((now, id));
+        true
+    }
+
+    /// Ids currently remembered.
+    async fn len(&self) -> usize {
+        self.inner.read().await.first_seen.len()
+    }
+}
+
+/// Checks shared by the built-in strategies: first sighting, under
+/// `max_hops` and still live. Logs the reason when `msg` fails one.
+async fn passes_common_checks(
+    seen: &SeenSet,
+    msg: &Message,
+    max_hops: u8,
+    clock: &dyn Clock,
+) -> bool {
+    let decision = if !seen.insert(msg.id, clock.now()).await {
+        "dropped-duplicate"
+    } else if msg.hop_count >= max_hops {
+        "dropped-hop-limit"
+    } else if msg.is_expired_at(clock.now()) {
+        "dropped-expired"
+    } else {
+        return true;
+    };
+    tracing::debug!(decision, "message dropped");
+    false
+}
+
+/// Count and log a drop made by [`Forwarder`] itself.
+fn dropped(counter: &AtomicU64, decision: &'static str) -> Result<ForwardDecision> {
+    MeshStats::incr(counter);
+    tracing::debug!(decision, "message dropped");
+    Ok(ForwardDecision::Drop)
+}
+
+/// Simple controlled flooding: every message is rebroadcast once per id while
+/// it is within its TTL and hop limit.
+#[derive(Clone)]
+pub struct ControlledFlood {
+    seen: SeenSet,
+    max_hops: u8,
+    clock: Arc<dyn Clock>,
+}
+
+impl ControlledFlood {
+    pub fn new(max_hops: u8) -> Self {
+        Self::with_clock(max_hops, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(max_hops: u8, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            seen: SeenSet::default(),
+            max_hops,
+            clock,
+        }
+    }
+
+    /// Forget a message id `window` after first seeing it (default
+    /// [`DEFAULT_DEDUP_WINDOW`]).
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.seen.window = window;
+        self
+    }
+
+    /// Message ids currently remembered as already forwarded.
+    pub async fn seen_count(&self) -> usize {
+        self.seen.len().await
+    }
+}
+
+impl Default for ControlledFlood {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_HOPS)
+    }
+}
+
+#[async_trait]
+impl ForwardingStrategy for ControlledFlood {
+    async fn decide(&self, msg: &Message, _from: PeerId) -> ForwardDecision {
+        if !passes_common_checks(&self.seen, msg, self.max_hops, self.clock.as_ref()).await {
+            return ForwardDecision::Drop;
+        }
+        ForwardDecision::Broadcast
+    }
+}
+
+/// AODV-style reactive forwarding: unicast along known routes (anycast to the
+/// nearest advertised provider, a multi-recipient message to its last
+/// pending recipient), flood only broadcasts, routing control, other
+/// multi-recipient messages and route-less emergency messages, and request
+/// discovery for other unknown destinations.
+#[derive(Clone)]
+pub struct AodvReactive {
+    routing: RoutingEngine,
+    seen: SeenSet,
+    max_hops: u8,
+    clock: Arc<dyn Clock>,
+}
+
+impl AodvReactive {
+    pub fn new(routing: RoutingEngine) -> Self {
+        Self::with_clock(routing, DEFAULT_MAX_HOPS, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(routing: RoutingEngine, max_hops: u8, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            routing,
+            seen: SeenSet::default(),
+            max_hops,
+            clock,
+        }
+    }
+
+    /// Forget a message id `window` after first seeing it (default
+    /// [`DEFAULT_DEDUP_WINDOW`]).
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.seen.window = window;
+        self
+    }
+
+    /// Message ids currently remembered as already forwarded.
+    pub async fn seen_count(&self) -> usize {
+        self.seen.len().await
+    }
+}
+
+#[async_trait]
+impl ForwardingStrategy for AodvReactive {
+    async fn decide(&self, msg: &Message, _from: PeerId) -> ForwardDecision {
+        if !passes_common_checks(&self.seen, msg, self.max_hops, self.clock.as_ref()).await {
+            return ForwardDecision::Drop;
+        }
+        if let (None, Some(service)) = (&msg.recipient, &msg.anycast) {
+            return match self.routing.nearest_provider(service).await {
+                Some(route) => ForwardDecision::Unicast(route.next_hop),
+                None if msg.priority == MessagePriority::Emergency => ForwardDecision::Broadcast,
+                None => {
+                    tracing::debug!(decision = "dropped-no-provider", "message dropped");
+                    ForwardDecision::Drop
+                }
+            };
+        }
+        let destination = match (msg.recipient, msg.pending_recipients().as_slice()) {
+            (Some(dest), _) => Some(dest),
+            // One recipient of a multi-recipient message left: route to it.
+            (None, [last]) => Some(*last),
+            _ => None,
+        };
+        match (destination, &msg.content) {
+            (None, _) | (_, MessageContent::Routing(_)) => ForwardDecision::Broadcast,
+            (Some(dest), _) => match self.routing.lookup(&dest).await {
+                RouteLookup::Route(peer) => ForwardDecision::Unicast(peer),
+                // Emergency traffic cannot wait for a discovery round trip.
+                _ if msg.priority == MessagePriority::Emergency => ForwardDecision::Broadcast,
+                // Known unreachable: fail fast instead of re-flooding RREQs.
+                RouteLookup::Unreachable => {
+                    tracing::debug!(decision = "dropped-unreachable", "message dropped");
+                    ForwardDecision::Drop
+                }
+                RouteLookup::Unknown => ForwardDecision::Discover(dest),
+            },
+        }
+    }
+}
+
+/// Forwarding pipeline: asks the configured strategy what to do with each
+/// received message and carries the decision out on the transport.
+#[derive(Clone)]
+pub struct Forwarder {
+    identity: Identity,
+    local_peer: PeerId,
+    strategy: Arc<dyn ForwardingStrategy>,
+    transport: Arc<dyn Transport>,
+    discovery: Option<RouteDiscovery>,
+    rreq_seen: RreqCache,
+    epidemic: Option<Epidemic>,
+    content_cache: Option<ContentCache>,
+    reputation: Option<Reputation>,
+    admission: Option<AdmissionConfig>,
+    queue: Option<OutboundQueue>,
+    suppression: Option<BroadcastSuppression>,
+    hop_scope: Option<HopScope>,
+    max_hops: u8,
+    ttl_decrement: Duration,
+    clock: Arc<dyn Clock>,
+    position: Arc<std::sync::RwLock<Option<GeoPoint>>>,
+    willingness: Arc<std::sync::RwLock<f32>>,
+    stats: Arc<MeshStats>,
+    next_request_id: Arc<AtomicU32>,
+}
+
+impl Forwarder {
+    pub fn new(
+        identity: Identity,
+        local_peer: PeerId,
+        strategy: Arc<dyn ForwardingStrategy>,
+        transport: Arc<dyn Transport>,
+    ) -> Self {
+        Self {
+            identity,
+            local_peer,
+            strategy,
+            transport,
+            discovery: None,
+            rreq_seen: RreqCache::default(),
+            epidemic: None,
+            content_cache: None,
+            reputation: None,
+            admission: None,
+            queue: None,
+            suppression: None,
+            hop_scope: None,
+            max_hops: DEFAULT_HOP_LIMIT,
+            ttl_decrement: DEFAULT_TTL_DECREMENT,
+            clock: Arc::new(SystemClock),
+            position: Arc::new(std::sync::RwLock::new(None)),
+            willingness: Arc::new(std::sync::RwLock::new(1.0)),
+            stats: Arc::new(MeshStats::default()),
+            next_request_id: Arc::new(AtomicU32::new(0)),
+        }
+    }
+
+    /// Throttle RREQs issued for [`ForwardDecision::Discover`] through
+    /// `discovery`.
+    pub fn with_discovery(mut self, discovery: RouteDiscovery) -> Self {
+        self.discovery = Some(discovery);
+        self
+    }
+
+    /// Suppress duplicate RREQs with `cache` instead of a private one.
+    pub fn with_rreq_cache(mut self, cache: RreqCache) -> Self {
+        self.rreq_seen = cache;
+        self
+    }
+
+    /// Carry messages that have no route in `epidemic` until a contact
+    /// opportunity arises.
+    pub fn with_epidemic(mut self, epidemic: Epidemic) -> Self {
+        self.epidemic = Some(epidemic);
+        self
+    }
+
+    /// Queue relayed transmissions instead of sending them at once, and
+    /// send them with [`send_queued`](Self::send_queued) in fair
+    /// round-robin order across destinations (senders, for broadcasts).
+    /// Each flow holds at most `flow_capacity` waiting messages and at most
+    /// [`DEFAULT_MAX_FLOWS`](crate::DEFAULT_MAX_FLOWS) flows wait at once,
+    /// so forged sender ids cannot grow the queue without bound; Emergency
+    /// traffic jumps the queue.
+    pub fn with_fair_queue(mut self, flow_capacity: usize) -> Self {
+        self.queue = Some(Arc::new(std::sync::Mutex::new(FairQueue::new(
+            flow_capacity,
+        ))));
+        self
+    }
+
+    /// Send up to `budget` queued transmissions in fair order. Returns how
+    /// many were sent. A no-op without [`with_fair_queue`](Self::with_fair_queue).
+    pub async fn send_queued(&self, budget: usize) -> Result<usize> {
+        let Some(queue) = &self.queue else {
+            return Ok(0);
+        };
+        let mut sent = 0;
+        while sent < budget {
+            let Some((next_hop, data)) = queue.lock().unwrap().pop() else {
+                break;
+            };
+            match next_hop {
+                Some(peer) => self.transport.send(peer, data).await?,
+                None => self.transport.broadcast(data).await?,
+            }
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Transmissions waiting in the fair queue.
+    pub fn queued(&self) -> usize {
+        self.queue
+            .as_ref()
+            .map_or(0, |queue| queue.lock().unwrap().len())
+    }
+
+    /// Hold non-emergency rebroadcasts in `suppression` and drop those that
+    /// enough neighbours already covered. Held rebroadcasts go out from
+    /// [`release_suppressed`](Self::release_suppressed).
+    pub fn with_suppression(mut self, suppression: BroadcastSuppression) -> Self {
+        self.suppression = Some(suppression);
+        self
+    }
+
+    /// Assessment delay of the broadcast suppression, if enabled.
+    pub fn suppression_delay(&self) -> Option<Duration> {
+        self.suppression.as_ref().map(BroadcastSuppression::delay)
+    }
+
+    /// Count a copy of `id` heard from a neighbour towards suppressing our
+    /// held rebroadcast of it. [`handle_incoming`](Self::handle_incoming)
+    /// does this itself; callers that drop duplicates before relaying, like
+    /// [`MeshNode`](crate::MeshNode), report them here.
+    pub fn overheard(&self, id: MessageId) {
+        if let Some(suppression) = &self.suppression {
+            suppression.overheard(id);
+        }
+    }
+
+    /// Send the held rebroadcasts whose assessment has ended and that were
+    /// not suppressed, through the fair queue if there is one. Returns how
+    /// many were sent or queued. [`MeshNode::run`](crate::MeshNode::run)
+    /// calls this; call it periodically when using the forwarder alone.
+    pub async fn release_suppressed(&self) -> Result<usize> {
+        let Some(suppression) = &self.suppression else {
+            return Ok(0);
+        };
+        let (send, suppressed) = suppression.release();
+        if suppressed > 0 {
+            self.stats
+                .suppressed_rebroadcasts
+                .fetch_add(suppressed as u64, Ordering::Relaxed);
+            tracing::debug!(
+                decision = "suppressed-density",
+                suppressed,
+                "rebroadcasts dropped"
+            );
+        }
+        let mut released = 0;
+        for held in send {
+            if self
+                .relay(held.flow, held.priority, None, held.data)
+                .await?
+            {
+                released += 1;
+            } else {
+                let _ = dropped(&self.stats.congestion_drops, "dropped-queue-full");
+            }
+        }
+        Ok(released)
+    }
+
+    /// Transmit a relayed frame now, or queue it on `flow` when a fair
+    /// queue is set. Returns false if the queue refused it.
+    async fn relay(
+        &self,
+        flow: UserId,
+        priority: MessagePriority,
+        next_hop: Option<PeerId>,
+        data: Vec<u8>,
+    ) -> Result<bool> {
+        if let Some(queue) = &self.queue {
+            return Ok(queue.lock().unwrap().push(flow, priority, (next_hop, data)));
+        }
+        match next_hop {
+            Some(peer) => self.transport.send(peer, data).await?,
+            None => self.transport.broadcast(data).await?,
+        }
+        Ok(true)
+    }
+
+    /// Cache broadcast content we relay in `cache` and answer
+    /// [`RoutingControl::ContentRequest`]s from it directly.
+    pub fn with_content_cache(mut self, cache: ContentCache) -> Self {
+        self.content_cache = Some(cache);
+        self
+    }
+
+    /// Score relaying peers and drop all traffic from blacklisted ones.
+    pub fn with_reputation(mut self, reputation: Reputation) -> Self {
+        self.reputation = Some(reputation);
+        self
+    }
+
+    /// Shed low-priority relaying when the outbound relay queue fills up,
+    /// per `config`. Congestion is measured on the relay queue, so this
+    /// sets one up with [`DEFAULT_FLOW_CAPACITY`] if
+    /// [`with_fair_queue`](Self::with_fair_queue) has not; relays then wait
+    /// for [`send_queued`](Self::send_queued).
+    pub fn with_admission(mut self, config: AdmissionConfig) -> Self {
+        if self.queue.is_none() {
+            self = self.with_fair_queue(DEFAULT_FLOW_CAPACITY);
+        }
+        self.admission = Some(config);
+        self
+    }
+
+    /// Drop messages once their hop count reaches `max_hops` (default
+    /// [`DEFAULT_HOP_LIMIT`]).
+    pub fn with_max_hops(mut self, max_hops: u8) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    /// Flood lower-priority messages less far, as set by `scope`.
+    pub fn with_hop_scope(mut self, scope: HopScope) -> Self {
+        self.hop_scope = Some(scope);
+        self
+    }
+
+    /// Lifetime deducted from relative-TTL messages at each hop (default
+    /// [`DEFAULT_TTL_DECREMENT`]).
+    pub fn with_ttl_decrement(mut self, decrement: Duration) -> Self {
+        self.ttl_decrement = decrement;
+        self
+    }
+
+    /// Age relative TTLs by `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Apply the hop limit and TTL decrement from `config`.
+    pub fn with_config(self, config: &MeshConfig) -> Self {
+        self.with_max_hops(config.max_hops)
+            .with_ttl_decrement(config.ttl_decrement())
+            .with_willingness(config.forwarding_willingness)
+    }
+
+    /// Known position of this node, used to stop relaying geo-scoped
+    /// messages outside their region.
+    pub fn with_position(self, position: GeoPoint) -> Self {
+        self.set_position(Some(position));
+        self
+    }
+
+    /// Update (or forget) the node position, e.g. from a GPS fix. Without a
+    /// position every geo-scoped message is forwarded.
+    pub fn set_position(&self, position: Option<GeoPoint>) {
+        *self.position.write().unwrap() = position;
+    }
+
+    /// How willing this node is to relay for others, from 0 (leaf only) to
+    /// 1 (the default), e.g. lowered on battery or a metered uplink. It is
+    /// advertised to neighbours so their routes avoid us; at 0 we also stop
+    /// relaying anything but Emergency traffic. Our own traffic is
+    /// unaffected.
+    pub fn with_willingness(self, willingness: f32) -> Self {
+        self.set_willingness(willingness);
+        self
+    }
+
+    /// Change the forwarding willingness. Call
+    /// [`advertise_willingness`](Self::advertise_willingness) to tell
+    /// neighbours straight away; new neighbours learn it on connect.
+    pub fn set_willingness(&self, willingness: f32) {
+        *self.willingness.write().unwrap() = willingness.clamp(0.0, 1.0);
+    }
+
+    pub fn willingness(&self) -> f32 {
+        *self.willingness.read().unwrap()
+    }
+
+    /// Broadcast our forwarding willingness to neighbours.
+    pub async fn advertise_willingness(&self) -> Result<()> {
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Routing(self.willingness_advert()),
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await
+    }
+
+    fn willingness_advert(&self) -> RoutingControl {
+        RoutingControl::Willingness {
+            origin: self.identity.user_id(),
+            willingness: self.willingness(),
+        }
+    }
+
+    /// Share counters with other components instead of using a private set.
+    pub fn with_stats(mut self, stats: Arc<MeshStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<MeshStats> {
+        self.stats.clone()
+    }
+
+    /// Decide on and perform forwarding of `msg` received from `from`.
+    #[tracing::instrument(
+        name = "forward",
+        skip_all,
+        fields(message.id = %msg.id, sender = %msg.sender, hop_count = msg.hop_count)
+    )]
+    pub async fn handle_incoming(&self, msg: &Message, from: PeerId) -> Result<ForwardDecision> {
+        if !self.check_reputation(msg, from) {
+            return dropped(&self.stats.reputation_drops, "dropped-reputation");
+        }
+        // Definitive loop check, independent of hop count and the seen-set.
+        // Not held against `from`: flooding neighbours echo our own relays
+        // back as a matter of course.
+        if msg.path.contains(&self.local_peer) {
+            return dropped(&self.stats.loop_drops, "dropped-loop");
+        }
+        if let Some(suppression) = &self.suppression {
+            suppression.overheard(msg.id);
+        }
+        if self.is_duplicate_rreq(msg) {
+            return dropped(&self.stats.rreq_duplicates, "dropped-duplicate");
+        }
+        let max_hops = self.hop_limit(msg.priority);
+        if msg.hop_count >= max_hops {
+            return dropped(&self.stats.hop_limit_drops, "dropped-hop-limit");
+        }
+        if self.outside_region(msg) {
+            return dropped(&self.stats.geo_drops, "dropped-out-of-region");
+        }
+        if let (MessageContent::Recall { target }, Some(epidemic)) = (&msg.content, &self.epidemic)
+        {
+            if msg.verify_signature().is_ok() && epidemic.buffer().recall(target, &msg.sender).await
+            {
+                tracing::debug!(decision = "recalled", %target, "carried message recalled");
+            }
+        }
+        let decision = self.strategy.decide(msg, from).await;
+        if let Some(cache) = &self.content_cache {
+            // A fresh request straight from its requester that we can serve
+            // stops here: the requester gets the cached original and nobody
+            // further needs to hear the request. Duplicates were answered
+            // (or passed on) the first time.
+            if decision != ForwardDecision::Drop && msg.hop_count == 0 {
+                if let Some(cached) = cache.answer(msg).await {
+                    MeshStats::incr(&self.stats.content_cache_hits);
+                    tracing::debug!(decision = "served-from-cache", "content request answered");
+                    self.transport
+                        .send(from, self.transport.encode_message(&cached)?)
+                        .await?;
+                    return Ok(ForwardDecision::Drop);
+                }
+            }
+            cache.store(msg).await;
+        }
+        if decision != ForwardDecision::Drop
+            && self.willingness() <= 0.0
+            && msg.priority != MessagePriority::Emergency
+            && msg.sender != self.identity.user_id()
+        {
+            return dropped(&self.stats.unwilling_drops, "dropped-unwilling");
+        }
+        let mut forwarded = msg.clone();
+        forwarded.hop_count = forwarded.hop_count.saturating_add(1);
+        forwarded.path.push(self.local_peer);
+        // Never hand a neighbour a message it would have to drop.
+        if decision != ForwardDecision::Drop && forwarded.hop_count >= max_hops {
+            return dropped(&self.stats.hop_limit_drops, "dropped-hop-limit");
+        }
+        if forwarded.ttl_mode == TtlMode::Relative {
+            // Never more than we received, less the time we held it.
+            let now = self.clock.now();
+            forwarded.ttl = forwarded
+                .remaining_ttl(now)
+                .saturating_sub(self.ttl_decrement);
+            forwarded.received_at = Some(now);
+            if decision != ForwardDecision::Drop && forwarded.ttl.is_zero() {
+                return dropped(&self.stats.expired_drops, "dropped-expired");
+            }
+        }
+        if decision != ForwardDecision::Drop && !self.admits(msg.priority) {
+            return dropped(&self.stats.congestion_drops, "dropped-congestion");
+        }
+        // Path-MTU probes that do not fit our link must die here; that is
+        // how their sender finds the bottleneck.
+        if decision != ForwardDecision::Drop
+            && matches!(
+                forwarded.content,
+                MessageContent::Routing(RoutingControl::MtuProbe { .. })
+            )
+            && self.transport.encode_message(&forwarded)?.len() > self.transport.mtu()
+        {
+            tracing::debug!(decision = "dropped-mtu", "message dropped");
+            return Ok(ForwardDecision::Drop);
+        }
+        if decision != ForwardDecision::Drop {
+            tracing::debug!(decision = "forwarded", action = ?decision, "message forwarded");
+        }
+        match &decision {
+            ForwardDecision::Drop => {}
+            ForwardDecision::Broadcast | ForwardDecision::Unicast(_) => {
+                let next_hop = match &decision {
+                    ForwardDecision::Unicast(peer) => Some(*peer),
+                    _ => None,
+                };
+                let data = self.transport.encode_message(&forwarded)?;
+                if let (None, Some(suppression)) = (next_hop, &self.suppression) {
+                    if forwarded.priority != MessagePriority::Emergency {
+                        let held = HeldRebroadcast {
+                            flow: forwarded.sender,
+                            priority: forwarded.priority,
+                            data,
+                        };
+                        suppression.hold(forwarded.id, held);
+                        return Ok(decision);
+                    }
+                }
+                let flow = forwarded.recipient.unwrap_or(forwarded.sender);
+                if !self.relay(flow, forwarded.priority, next_hop, data).await? {
+                    return dropped(&self.stats.congestion_drops, "dropped-queue-full");
+                }
+            }
+            ForwardDecision::Discover(dest) => {
+                if let Some(epidemic) = &self.epidemic {
+                    epidemic.carry(forwarded).await;
+                }
+                self.discover(*dest).await?;
+            }
+        }
+        Ok(decision)
+    }
+
+    /// Whether `msg` carries only RREQs, all of which were already seen.
+    fn is_duplicate_rreq(&self, msg: &Message) -> bool {
+        let MessageContent::Routing(control) = &msg.content else {
+            return false;
+        };
+        let packets = control.clone().expand();
+        if packets.is_empty()
+            || !packets
+                .iter()
+                .all(|p| matches!(p, RoutingControl::Rreq { .. }))
+        {
+            return false;
+        }
+        // Record every RREQ, even after finding a new one.
+        let mut any_new = false;
+        for packet in packets {
+            if let RoutingControl::Rreq {
+                origin, request_id, ..
+            } = packet
+            {
+                any_new |= self.rreq_seen.insert(origin, request_id);
+            }
+        }
+        !any_new
+    }
+
+    /// Hop limit for relaying a message of `priority`.
+    fn hop_limit(&self, priority: MessagePriority) -> u8 {
+        self.hop_scope
+            .map_or(self.max_hops, |scope| scope.limit(priority, self.max_hops))
+    }
+
+    /// Whether admission control lets a message of `priority` through at the
+    /// current outbound queue depth.
+    fn admits(&self, priority: MessagePriority) -> bool {
+        let Some(admission) = &self.admission else {
+            return true;
+        };
+        let load = self.queued() as f32 / admission.queue_capacity.max(1) as f32;
+        admission.admits(priority, load)
+    }
+
+    /// Whether `msg` is geo-scoped and we are known to be outside its region.
+    /// Fails open when we do not know our position.
+    fn outside_region(&self, msg: &Message) -> bool {
+        match (&msg.geo, *self.position.read().unwrap()) {
+            (Some(geo), Some(position)) => !geo.contains(&position),
+            _ => false,
+        }
+    }
+
+    /// Whether `msg` relayed by `from` passes reputation checks. Signed
+    /// messages feed the sender's score; unsigned ones are scored neutrally.
+    fn check_reputation(&self, msg: &Message, from: PeerId) -> bool {
+        let Some(reputation) = &self.reputation else {
+            return true;
+        };
+        if reputation.is_blacklisted(&from) {
+            return false;
+        }
+        if msg.signature.is_empty() {
+            return true;
+        }
+        if msg.verify_signature().is_ok() {
+            reputation.record(from, ReputationEvent::ValidSignature);
+            true
+        } else {
+            reputation.record(from, ReputationEvent::InvalidSignature);
+            false
+        }
+    }
+
+    /// Start route discovery for `destination`, subject to throttling if a
+    /// [`RouteDiscovery`] is configured. Returns whether an RREQ was sent.
+    pub async fn discover(&self, destination: UserId) -> Result<bool> {
+        if let Some(discovery) = &self.discovery {
+            if discovery.request(destination).await != DiscoveryDecision::Send {
+                return Ok(false);
+            }
+        }
+        self.send_rreq(destination).await?;
+        Ok(true)
+    }
+
+    /// Send our routing table to a newly connected `peer` so partitions that
+    /// just met converge quickly, along with our forwarding willingness if
+    /// it is below 1. Call this on `TransportEvent::PeerConnected`.
+    pub async fn send_routes(&self, peer: PeerId, routing: &RoutingEngine) -> Result<()> {
+        let mut routes = RoutingControl::RouteExchange(routing.export().await);
+        if self.willingness() < 1.0 {
+            routes = RoutingControl::Batch(vec![routes, self.willingness_advert()]);
+        }
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Routing(routes),
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .send(peer, self.transport.encode_message(&msg)?)
+            .await
+    }
+
+    /// Broadcast a signed route request for `destination`.
+    pub async fn send_rreq(&self, destination: UserId) -> Result<()> {
+        let local_id = self.identity.user_id();
+        let rreq = RoutingControl::Rreq {
+            origin: local_id,
+            destination,
+            request_id: self.next_request_id.fetch_add(1, Ordering::Relaxed),
+            hop_count: 0,
+        };
+        let mut msg = Message::new(local_id, None, MessageContent::Routing(rreq));
+        msg.sign(&self.identity)?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await
+    }
+}
@@ -0,0 +1,395 @@
+use crate::clock::{Clock, SystemClock};
+use crate::message::Message;
+use crate::message_manager::DEFAULT_MAX_TTL;
+use crate::path_mtu::PathMtu;
+use crate::routing_control::RoutingControl;
+use crate::stats::MeshStats;
+use crate::types::{MessageId, Timestamp};
+use crate::wire::WireFormat;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One chunk of an encoded message too large for the link MTU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fragment {
+    pub msg_id: MessageId,
+    pub index: u32,
+    pub total: u32,
+    pub data: Vec<u8>,
+}
+
+/// Split `payload` into fragments of at most `chunk_size` bytes. An empty
+/// payload still produces a single (empty) fragment.
+pub fn fragment(msg_id: MessageId, payload: &[u8], chunk_size: usize) -> Vec<Fragment> {
+    let chunks: Vec<&[u8]> = if payload.is_empty() {
+        vec![&[]]
+    } else {
+        payload.chunks(chunk_size.max(1)).collect()
+    };
+    let total = chunks.len() as u32;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, data)| Fragment {
+            msg_id,
+            index: index as u32,
+            total,
+            data: data.to_vec(),
+        })
+        .collect()
+}
+
+/// Default cap on transfers being reassembled at once.
+pub const DEFAULT_MAX_REASSEMBLIES: usize = 64;
+
+/// Default cap on bytes buffered across all incomplete transfers.
+pub const DEFAULT_MAX_REASSEMBLY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Default smallest chunk a sender is expected to use, which bounds the
+/// fragment count a transfer may claim.
+pub const DEFAULT_MIN_FRAGMENT_CHUNK: usize = 16;
+
+/// Longest fragments are retained for retransmission or reassembly,
+/// whatever TTL is asked for.
+pub const MAX_FRAGMENT_RETENTION: Duration = DEFAULT_MAX_TTL;
+
+/// `ttl` after `now`, capped at [`MAX_FRAGMENT_RETENTION`] so an absurd TTL
+/// cannot overflow the clock.
+fn expiry(now: Timestamp, ttl: Duration) -> Timestamp {
+    let cap = now + MAX_FRAGMENT_RETENTION;
+    now.checked_add(ttl).map_or(cap, |expires| expires.min(cap))
+}
+
+struct Partial {
+    total: u32,
+    chunks: BTreeMap<u32, Vec<u8>>,
+    /// Bytes buffered in `chunks`.
+    bytes: usize,
+    expires: Timestamp,
+    /// Order in which transfers started; the lowest is evicted first.
+    started: u64,
+}
+
+#[derive(Default)]
+struct Partials {
+    by_id: HashMap<MessageId, Partial>,
+    bytes: usize,
+    next: u64,
+}
+
+impl Partials {
+    fn remove(&mut self, msg_id: &MessageId) -> Option<Partial> {
+        let partial = self.by_id.remove(msg_id)?;
+        self.bytes -= partial.bytes;
+        Some(partial)
+    }
+
+    /// Evict the oldest transfer other than `keep`.
+    fn evict_oldest(&mut self, keep: &MessageId) -> bool {
+        let oldest = self
+            .by_id
+            .iter()
+            .filter(|(id, _)| *id != keep)
+            .min_by_key(|(_, p)| p.started)
+            .map(|(id, _)| *id);
+        oldest.and_then(|id| self.remove(&id)).is_some()
+    }
+}
+
+/// Receiver side of chunked transfers. Collects fragments per message and
+/// reports the indices still missing so the sender can retransmit only those.
+/// The number of transfers in progress and the bytes they buffer are capped
+/// so a peer cannot exhaust memory by opening transfers it never finishes;
+/// the oldest incomplete transfer is evicted to make room.
+#[derive(Clone)]
+pub struct Reassembler {
+    partial: Arc<Mutex<Partials>>,
+    ttl: Duration,
+    max_partials: usize,
+    max_bytes: usize,
+    min_chunk: usize,
+    stats: Arc<MeshStats>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Reassembler {
+    /// Incomplete transfers are abandoned `ttl` after their first fragment.
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            partial: Arc::new(Mutex::new(Partials::default())),
+            ttl,
+            max_partials: DEFAULT_MAX_REASSEMBLIES,
+            max_bytes: DEFAULT_MAX_REASSEMBLY_BYTES,
+            min_chunk: DEFAULT_MIN_FRAGMENT_CHUNK,
+            stats: Arc::new(MeshStats::default()),
+            clock,
+        }
+    }
+
+    /// Reassemble at most `max_partials` transfers at once, buffering at
+    /// most `max_bytes` between them (defaults [`DEFAULT_MAX_REASSEMBLIES`]
+    /// and [`DEFAULT_MAX_REASSEMBLY_BYTES`]).
+    pub fn with_limits(mut self, max_partials: usize, max_bytes: usize) -> Self {
+        self.max_partials = max_partials.max(1);
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Expect chunks of at least `min_chunk` bytes (default
+    /// [`DEFAULT_MIN_FRAGMENT_CHUNK`]). Transfers claiming more fragments
+    /// than the byte budget holds at that size are ignored.
+    pub fn with_min_chunk(mut self, min_chunk: usize) -> Self {
+        self.min_chunk = min_chunk.max(1);
+        self
+    }
+
+    /// Share counters with other components instead of using a private set.
+    pub fn with_stats(mut self, stats: Arc<MeshStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Store `fragment`. Returns the reassembled payload once every index has
+    /// arrived. Fragments disagreeing with the known total, or claiming more
+    /// fragments than the budget holds, are ignored.
+    pub fn insert(&self, fragment: Fragment) -> Option<Vec<u8>> {
+        let max_total = (self.max_bytes / self.min_chunk).max(1);
+        if fragment.index >= fragment.total || fragment.total as usize > max_total {
+            return None;
+        }
+        if fragment.data.len() > self.max_bytes {
+            return None;
+        }
+        let msg_id = fragment.msg_id;
+        let mut partial = self.partial.lock().unwrap();
+        if !partial.by_id.contains_key(&msg_id) {
+            while partial.by_id.len() >= self.max_partials && partial.evict_oldest(&msg_id) {
+                MeshStats::incr(&self.stats.reassembly_evictions);
+            }
+            let started = partial.next;
+            partial.next += 1;
+            partial.by_id.insert(
+                msg_id,
+                Partial {
+                    total: fragment.total,
+                    chunks: BTreeMap::new(),
+                    bytes: 0,
+                    expires: expiry(self.clock.now(), self.ttl),
+                    started,
+                },
+            );
+        }
+        let entry = partial.by_id.get_mut(&msg_id)?;
+        if entry.total != fragment.total {
+            return None;
+        }
+        let added = fragment.data.len();
+        let replaced = entry.chunks.get(&fragment.index).map_or(0, Vec::len);
+        if entry.bytes + added - replaced > self.max_bytes {
+            // This transfer alone is over budget; drop it, not the others.
+            partial.remove(&msg_id);
+            MeshStats::incr(&self.stats.reassembly_evictions);
+            return None;
+        }
+        entry.chunks.insert(fragment.index, fragment.data);
+        entry.bytes = entry.bytes + added - replaced;
+        let complete = entry.chunks.len() as u32 == entry.total;
+        partial.bytes = partial.bytes + added - replaced;
+        if complete {
+            let done = partial.remove(&msg_id)?;
+            return Some(done.chunks.into_values().flatten().collect());
+        }
+        while partial.bytes > self.max_bytes && partial.evict_oldest(&msg_id) {
+            MeshStats::incr(&self.stats.reassembly_evictions);
+        }
+        None
+    }
+
+    /// Indices of `msg_id` not yet received, or `None` if no transfer is in
+    /// progress.
+    pub fn missing(&self, msg_id: &MessageId) -> Option<Vec<u32>> {
+        let partial = self.partial.lock().unwrap();
+        let entry = partial.by_id.get(msg_id)?;
+        Some(
+            (0..entry.total)
+                .filter(|i| !entry.chunks.contains_key(i))
+                .collect(),
+        )
+    }
+
+    /// [`RoutingControl::FragNack`] asking for the fragments still missing.
+    pub fn nack(&self, msg_id: &MessageId) -> Option<RoutingControl> {
+        let missing = self.missing(msg_id)?;
+        Some(RoutingControl::FragNack {
+            msg_id: *msg_id,
+            missing,
+        })
+    }
+
+    /// Drop transfers whose TTL ran out before completion.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now();
+        let mut partial = self.partial.lock().unwrap();
+        let expired: Vec<MessageId> = partial
+            .by_id
+            .iter()
+            .filter(|(_, p)| p.expires <= now)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired {
+            partial.remove(&id);
+        }
+    }
+}
+
+/// Whether a message may, must or must not be fragmented.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FragmentPolicy {
+    /// Fragment only what does not fit the MTU.
+    #[default]
+    Auto,
+    /// Send whole or not at all, e.g. latency-sensitive control traffic
+    /// that is useless if it arrives piecemeal.
+    Never,
+    /// Always go through fragments, for their per-fragment retransmission.
+    Always,
+}
+
+/// A message ready for the link, whole or in fragments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outbound {
+    Whole(Vec<u8>),
+    Fragments(Vec<Fragment>),
+}
+
+struct Outgoing {
+    fragments: Vec<Fragment>,
+    expires: Timestamp,
+}
+
+/// Sender side of chunked transfers. Retains fragments until the receiver
+/// acknowledges the full set (an empty `FragNack`) or the TTL expires.
+#[derive(Clone)]
+pub struct FragmentSender {
+    outgoing: Arc<Mutex<HashMap<MessageId, Outgoing>>>,
+    path_mtu: Option<PathMtu>,
+    clock: Arc<dyn Clock>,
+}
+
+impl FragmentSender {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            outgoing: Arc::new(Mutex::new(HashMap::new())),
+            path_mtu: None,
+            clock,
+        }
+    }
+
+    /// Fragment unicasts for the path MTU `path_mtu` has discovered to
+    /// their recipient, when it is below the link MTU.
+    pub fn with_path_mtu(mut self, path_mtu: PathMtu) -> Self {
+        self.path_mtu = Some(path_mtu);
+        self
+    }
+
+    /// Fragment `payload` for transmission and retain the fragments for up
+    /// to `ttl` (at most [`MAX_FRAGMENT_RETENTION`]) so they can be
+    /// retransmitted.
+    pub fn split(
+        &self,
+        msg_id: MessageId,
+        payload: &[u8],
+        chunk_size: usize,
+        ttl: Duration,
+    ) -> Vec<Fragment> {
+        let fragments = fragment(msg_id, payload, chunk_size);
+        self.outgoing.lock().unwrap().insert(
+            msg_id,
+            Outgoing {
+                fragments: fragments.clone(),
+                expires: expiry(self.clock.now(), ttl),
+            },
+        );
+        fragments
+    }
+
+    /// Encode `msg` in `format` and fragment it as `policy` says for a link
+    /// carrying `mtu` bytes per frame, or for the smaller path MTU to its
+    /// recipient if known. Fragments are retained for the message's TTL.
+    /// Fails under [`FragmentPolicy::Never`] if the message does not fit.
+    pub fn prepare(
+        &self,
+        msg: &Message,
+        format: WireFormat,
+        mtu: usize,
+        policy: FragmentPolicy,
+    ) -> Result<Outbound> {
+        let mtu = match (&self.path_mtu, &msg.recipient) {
+            (Some(path_mtu), Some(dest)) => path_mtu.effective_mtu(dest, mtu),
+            _ => mtu,
+        };
+        let encoded = format.encode(msg)?;
+        let fits = encoded.len() <= mtu;
+        match policy {
+            FragmentPolicy::Auto | FragmentPolicy::Never if fits => Ok(Outbound::Whole(encoded)),
+            FragmentPolicy::Never => anyhow::bail!(
+                "message of {} bytes exceeds the {mtu}-byte MTU and may not be fragmented",
+                encoded.len()
+            ),
+            FragmentPolicy::Auto | FragmentPolicy::Always => Ok(Outbound::Fragments(
+                self.split(msg.id, &encoded, mtu, msg.ttl),
+            )),
+        }
+    }
+
+    /// Fragments to retransmit in response to a `FragNack`. An empty
+    /// `missing` list acknowledges the whole transfer and releases it.
+    pub fn handle_nack(&self, msg_id: &MessageId, missing: &[u32]) -> Vec<Fragment> {
+        let mut outgoing = self.outgoing.lock().unwrap();
+        if missing.is_empty() {
+            outgoing.remove(msg_id);
+            return Vec::new();
+        }
+        let Some(entry) = outgoing.get(msg_id) else {
+            return Vec::new();
+        };
+        if entry.expires <= self.clock.now() {
+            outgoing.remove(msg_id);
+            return Vec::new();
+        }
+        entry
+            .fragments
+            .iter()
+            .filter(|f| missing.contains(&f.index))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether fragments of `msg_id` are still held for retransmission.
+    pub fn is_retained(&self, msg_id: &MessageId) -> bool {
+        self.outgoing.lock().unwrap().contains_key(msg_id)
+    }
+
+    /// Release transfers whose TTL ran out.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now();
+        self.outgoing.lock().unwrap().retain(|_, o| o.expires > now);
+    }
+}
+
+impl Default for FragmentSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
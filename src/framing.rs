@@ -0,0 +1,159 @@
+use crate::wire::MAX_MESSAGE_BYTES;
+use anyhow::Result;
+
+/// Bytes of big-endian length header written by [`Framing::LengthPrefixed`].
+pub const LENGTH_PREFIX_LEN: usize = 4;
+
+/// Frame terminator used by [`Framing::Delimited`] (SLIP `END`).
+pub const FRAME_END: u8 = 0xC0;
+const FRAME_ESC: u8 = 0xDB;
+const FRAME_ESC_END: u8 = 0xDC;
+const FRAME_ESC_ESC: u8 = 0xDD;
+
+/// How a link separates encoded messages. Byte streams need explicit
+/// framing; packet links deliver one message per datagram.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Every read is exactly one message, e.g. LoRa packets or WebSocket
+    /// frames.
+    #[default]
+    Datagram,
+    /// 4-byte big-endian length, then the payload, e.g. for TCP.
+    LengthPrefixed,
+    /// SLIP-style: payload bytes escaped, each frame terminated by
+    /// [`FRAME_END`]. Suits serial lines, where a receiver can resync after
+    /// line noise at the next terminator.
+    Delimited,
+}
+
+impl Framing {
+    /// Wrap one encoded message for the wire.
+    pub fn frame(self, payload: &[u8]) -> Result<Vec<u8>> {
+        if payload.len() as u64 > MAX_MESSAGE_BYTES {
+            anyhow::bail!("payload of {} bytes exceeds limit", payload.len());
+        }
+        Ok(match self {
+            Framing::Datagram => payload.to_vec(),
+            Framing::LengthPrefixed => {
+                let mut out = Vec::with_capacity(LENGTH_PREFIX_LEN + payload.len());
+                out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+                out.extend_from_slice(payload);
+                out
+            }
+            Framing::Delimited => {
+                let mut out = Vec::with_capacity(payload.len() + 2);
+                for &byte in payload {
+                    match byte {
+                        FRAME_END => out.extend_from_slice(&[FRAME_ESC, FRAME_ESC_END]),
+                        FRAME_ESC => out.extend_from_slice(&[FRAME_ESC, FRAME_ESC_ESC]),
+                        _ => out.push(byte),
+                    }
+                }
+                out.push(FRAME_END);
+                out
+            }
+        })
+    }
+
+    /// Fresh decoder for one incoming stream using this framing.
+    pub fn decoder(self) -> FrameDecoder {
+        FrameDecoder {
+            framing: self,
+            buf: Vec::new(),
+        }
+    }
+}
+
+/// Incremental splitter turning received bytes back into message payloads.
+/// Keep one per peer connection; partial frames are buffered until the
+/// rest arrives.
+#[derive(Debug, Clone)]
+pub struct FrameDecoder {
+    framing: Framing,
+    buf: Vec<u8>,
+}
+
+impl FrameDecoder {
+    /// Feed received bytes, returning every payload they complete. Fails on
+    /// frames over [`MAX_MESSAGE_BYTES`] or a broken escape; the buffered
+    /// partial frame is discarded so the stream can recover.
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        match self.framing {
+            Framing::Datagram => {
+                if data.len() as u64 > MAX_MESSAGE_BYTES {
+                    anyhow::bail!("payload of {} bytes exceeds limit", data.len());
+                }
+                Ok(vec![data.to_vec()])
+            }
+            Framing::LengthPrefixed => self.push_length_prefixed(data),
+            Framing::Delimited => self.push_delimited(data),
+        }
+    }
+
+    /// Bytes held for a frame that is not complete yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    fn push_length_prefixed(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.buf.extend_from_slice(data);
+        let mut payloads = Vec::new();
+        let mut start = 0;
+        while let Some(header) = self.buf.get(start..start + LENGTH_PREFIX_LEN) {
+            let len = u32::from_be_bytes(header.try_into().expect("4-byte header")) as usize;
+            if len as u64 > MAX_MESSAGE_BYTES {
+                self.buf.clear();
+                anyhow::bail!("frame of {len} bytes exceeds limit");
+            }
+            let body = start + LENGTH_PREFIX_LEN;
+            let Some(payload) = self.buf.get(body..body + len) else {
+                break;
+            };
+            payloads.push(payload.to_vec());
+            start = body + len;
+        }
+        self.buf.drain(..start);
+        Ok(payloads)
+    }
+
+    fn push_delimited(&mut self, data: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut payloads = Vec::new();
+        for &byte in data {
+            if byte != FRAME_END {
+                if self.buf.len() as u64 >= MAX_MESSAGE_BYTES * 2 {
+                    self.buf.clear();
+                    anyhow::bail!("unterminated frame exceeds limit");
+                }
+                self.buf.push(byte);
+                continue;
+            }
+            let raw = std::mem::take(&mut self.buf);
+            // Back-to-back terminators delimit nothing.
+            if raw.is_empty() {
+                continue;
+            }
+            payloads.push(unescape(&raw)?);
+        }
+        Ok(payloads)
+    }
+}
+
+fn unescape(raw: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(raw.len());
+    let mut bytes = raw.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != FRAME_ESC {
+            out.push(byte);
+            continue;
+        }
+        match bytes.next() {
+            Some(&FRAME_ESC_END) => out.push(FRAME_END),
+            Some(&FRAME_ESC_ESC) => out.push(FRAME_ESC),
+            _ => anyhow::bail!("invalid escape in delimited frame"),
+        }
+    }
+    if out.len() as u64 > MAX_MESSAGE_BYTES {
+        anyhow::bail!("frame of {} bytes exceeds limit", out.len());
+    }
+    Ok(out)
+}
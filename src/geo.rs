@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+/// Mean Earth radius used for great-circle distances.
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+
+/// A WGS84 position in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        Self { lat, lon }
+    }
+
+    /// Great-circle (haversine) distance to `other` in metres.
+    pub fn distance_m(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_M * a.sqrt().asin()
+    }
+}
+
+/// Region a message is relevant to, for geocast-style scoping of alerts
+/// such as "evacuate sector 4".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoHint {
+    pub lat: f64,
+    pub lon: f64,
+    pub radius_m: f64,
+}
+
+impl GeoHint {
+    pub fn center(&self) -> GeoPoint {
+        GeoPoint::new(self.lat, self.lon)
+    }
+
+    /// Whether `position` lies within the region.
+    pub fn contains(&self, position: &GeoPoint) -> bool {
+        self.center().distance_m(position) <= self.radius_m
+    }
+}
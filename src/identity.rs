@@ -0,0 +1,122 @@
+use crate::types::UserId;
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier, VerifyingKey};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+/// Something that holds a signing key, such as a hardware token or an OS
+/// keystore, and signs on our behalf without exposing it.
+pub trait Signer: Send + Sync {
+    /// The verifying key, which doubles as the user's id.
+    fn public_key(&self) -> UserId;
+
+    /// Ed25519 signature over `data`.
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// In-process [`Signer`]; keys held this way can also back ratchet
+/// sessions and snapshots, which external signers cannot.
+pub type SoftwareSigner = Identity;
+
+/// A node's Ed25519 identity. The [`UserId`] is the raw verifying key, so any
+/// peer can check signatures without a key directory.
+#[derive(Clone)]
+pub struct Identity {
+    signing_key: SigningKey,
+}
+
+impl Identity {
+    /// Fresh random identity.
+    pub fn generate() -> Self {
+        Self::from_secret_bytes(&rand::random())
+    }
+
+    pub fn from_secret_bytes(secret: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret),
+        }
+    }
+
+    /// Raw secret key, e.g. for an encrypted backup. Handle with care.
+    pub(crate) fn secret_bytes(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    pub fn user_id(&self) -> UserId {
+        UserId(self.signing_key.verifying_key().to_bytes())
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        self.signing_key.sign(data).to_bytes().to_vec()
+    }
+
+    /// X25519 key for session key agreement. Derived from, but independent
+    /// of, the signing key, so neither key is used for two purposes.
+    pub(crate) fn x25519_secret(&self) -> [u8; 32] {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, &self.secret_bytes());
+        let tag = hmac::sign(&key, b"DisasterMesh X25519 identity");
+        tag.as_ref().try_into().expect("SHA-256 output is 32 bytes")
+    }
+
+    /// Our X25519 session key, signed so peers can bind it to our `UserId`.
+    pub fn session_key(&self) -> SessionKey {
+        let public = x25519(self.x25519_secret(), X25519_BASEPOINT_BYTES);
+        SessionKey {
+            user: self.user_id(),
+            public,
+            signature: self.sign(&session_key_bytes(&public)),
+        }
+    }
+}
+
+/// A user's X25519 key for [`RatchetSession`](crate::RatchetSession)s,
+/// signed with their identity key. Peers need it to open a session to them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionKey {
+    pub user: UserId,
+    pub public: [u8; 32],
+    pub signature: Vec<u8>,
+}
+
+impl SessionKey {
+    /// Check that `user` signed this key.
+    pub fn verify(&self) -> Result<()> {
+        verify_signature(
+            &self.user,
+            &session_key_bytes(&self.public),
+            &self.signature,
+        )
+        .context("session key not signed by its user")
+    }
+}
+
+fn session_key_bytes(public: &[u8; 32]) -> Vec<u8> {
+    [b"DisasterMesh session key".as_slice(), public].concat()
+}
+
+impl Signer for Identity {
+    fn public_key(&self) -> UserId {
+        self.user_id()
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(Identity::sign(self, data))
+    }
+}
+
+impl std::fmt::Debug for Identity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Never print key material.
+        f.debug_struct("Identity")
+            .field("user_id", &self.user_id())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Check that `signature` over `data` was made by `signer`.
+pub fn verify_signature(signer: &UserId, data: &[u8], signature: &[u8]) -> Result<()> {
+    let key = VerifyingKey::from_bytes(&signer.0).context("signer is not a valid public key")?;
+    let signature = Signature::from_slice(signature).context("malformed signature")?;
+    key.verify(data, &signature).context("signature mismatch")
+}
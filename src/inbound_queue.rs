@@ -0,0 +1,112 @@
+use crate::clock::{Clock, SystemClock};
+use crate::message::{Message, MessagePriority, TtlMode};
+use crate::types::{PeerId, Timestamp};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+/// Default number of received messages an [`InboundQueue`] holds.
+pub const DEFAULT_INBOUND_CAPACITY: usize = 1024;
+
+/// Expiry given to messages whose TTL runs past it, about 35,000 years
+/// after the epoch.
+fn far_future() -> Timestamp {
+    UNIX_EPOCH + Duration::from_secs(1 << 40)
+}
+
+/// Processing order: priority, then earliest expiry, then arrival.
+type Urgency = (MessagePriority, Timestamp, u64);
+
+/// Received messages waiting to be validated and delivered, most urgent
+/// first: by priority, and within a priority by how soon the message
+/// expires, so one about to die is handled before fresher traffic. When
+/// full, the least urgent message is the one dropped.
+#[derive(Clone)]
+pub struct InboundQueue {
+    waiting: Arc<Mutex<BTreeMap<Urgency, (PeerId, Message)>>>,
+    next_arrival: Arc<AtomicU64>,
+    ready: Arc<Notify>,
+    capacity: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl InboundQueue {
+    pub fn new(capacity: usize) -> Self {
+        Self::with_clock(capacity, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(capacity: usize, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            waiting: Arc::default(),
+            next_arrival: Arc::default(),
+            ready: Arc::new(Notify::new()),
+            capacity,
+            clock,
+        }
+    }
+
+    /// Queue `msg` received from `from`. Returns false if it was dropped
+    /// because the queue is full of more urgent messages.
+    pub fn push(&self, from: PeerId, msg: Message) -> bool {
+        // The TTL is straight off the wire and not yet validated, so an
+        // absurd one must not overflow; it just sorts as never expiring.
+        let start = match msg.ttl_mode {
+            TtlMode::Absolute => msg.timestamp,
+            TtlMode::Relative => self.clock.now(),
+        };
+        let deadline = start
+            .checked_add(msg.ttl)
+            .map_or_else(far_future, |deadline| deadline.min(far_future()));
+        let arrival = self.next_arrival.fetch_add(1, Ordering::Relaxed);
+        let key = (msg.priority, deadline, arrival);
+        let mut waiting = self.waiting.lock().unwrap();
+        if waiting.len() >= self.capacity {
+            match waiting.last_key_value() {
+                Some((least, _)) if *least > key => {
+                    waiting.pop_last();
+                }
+                _ => return false,
+            }
+        }
+        waiting.insert(key, (from, msg));
+        drop(waiting);
+        self.ready.notify_one();
+        true
+    }
+
+    /// The most urgent waiting message, if any.
+    pub fn pop(&self) -> Option<(PeerId, Message)> {
+        self.waiting
+            .lock()
+            .unwrap()
+            .pop_first()
+            .map(|(_, entry)| entry)
+    }
+
+    /// Wait for and take the most urgent message.
+    pub async fn next(&self) -> (PeerId, Message) {
+        loop {
+            let ready = self.ready.notified();
+            if let Some(entry) = self.pop() {
+                return entry;
+            }
+            ready.await;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.waiting.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InboundQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_INBOUND_CAPACITY)
+    }
+}
@@ -1,15 +1,106 @@
 //! DisasterMesh core library – basic data structures & traits
 
+pub mod ack;
+pub mod address_book;
+pub mod authority;
+pub mod beacon;
+pub mod blocking;
+pub mod checksum;
+pub mod clock;
+pub mod compression;
+pub mod config;
+pub mod content_cache;
+pub mod discovery;
+pub mod epidemic;
+pub mod fair_queue;
+pub mod file_transfer;
+pub mod forwarding;
+pub mod fragment;
+pub mod framing;
+pub mod geo;
+pub mod identity;
+pub mod inbound_queue;
+pub mod link_quality;
 pub mod message;
 pub mod message_manager;
-pub mod transport;
+pub mod metered;
+pub mod multi_transport;
+pub mod neighbor;
+pub mod node;
+pub mod node_control;
+pub mod partition;
+pub mod path_mtu;
+pub mod presence;
+pub mod qos;
+pub mod ratchet;
+pub mod reorder;
+pub mod reputation;
 pub mod routing;
 pub mod routing_control;
+pub mod service;
+pub mod snapshot;
+pub mod stats;
+pub mod store_crypto;
+pub mod suppression;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod topology;
+pub mod transport;
 pub mod types;
+pub mod validator;
+pub mod watchdog;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+pub mod wire;
 
+pub use ack::*;
+pub use address_book::*;
+pub use authority::*;
+pub use beacon::*;
+pub use checksum::*;
+pub use clock::*;
+pub use compression::*;
+pub use config::*;
+pub use content_cache::*;
+pub use discovery::*;
+pub use epidemic::*;
+pub use fair_queue::*;
+pub use file_transfer::*;
+pub use forwarding::*;
+pub use fragment::*;
+pub use framing::*;
+pub use geo::*;
+pub use identity::*;
+pub use inbound_queue::*;
+pub use link_quality::*;
 pub use message::*;
 pub use message_manager::*;
-pub use transport::*;
+pub use metered::*;
+pub use multi_transport::*;
+pub use neighbor::*;
+pub use node::*;
+pub use node_control::*;
+pub use partition::*;
+pub use path_mtu::*;
+pub use presence::*;
+pub use qos::*;
+pub use ratchet::*;
+pub use reorder::*;
+pub use reputation::*;
 pub use routing::*;
 pub use routing_control::*;
+pub use service::*;
+pub use snapshot::*;
+pub use stats::*;
+pub use store_crypto::*;
+pub use suppression::*;
+#[cfg(feature = "testkit")]
+pub use testkit::*;
+pub use topology::*;
+pub use transport::*;
 pub use types::*;
+pub use validator::*;
+pub use watchdog::*;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
+pub use wire::*;
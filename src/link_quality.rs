@@ -0,0 +1,67 @@
+use crate::types::PeerId;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+/// Default EWMA smoothing factor (weight of the newest sample).
+pub const DEFAULT_LINK_QUALITY_ALPHA: f32 = 0.2;
+
+/// Per-peer exponentially weighted moving average of link-quality samples
+/// (ack success, RSSI, loss, ...). Transports can return the smoothed value
+/// from `Transport::link_quality` instead of a jittery instantaneous one.
+#[derive(Debug, Clone)]
+pub struct LinkQualityTracker {
+    alpha: f32,
+    peers: Arc<RwLock<HashMap<PeerId, f32>>>,
+}
+
+impl LinkQualityTracker {
+    /// `alpha` is clamped to (0, 1]; higher reacts faster, lower smooths more.
+    pub fn new(alpha: f32) -> Self {
+        Self {
+            alpha: alpha.clamp(f32::EPSILON, 1.0),
+            peers: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Feed a sample for `peer` (clamped to [0, 1]) and return the new
+    /// smoothed value. The first sample seeds the average.
+    pub fn record(&self, peer: PeerId, sample: f32) -> f32 {
+        let sample = if sample.is_nan() {
+            0.0
+        } else {
+            sample.clamp(0.0, 1.0)
+        };
+        let mut peers = self.peers.write().unwrap();
+        let smoothed = match peers.get(&peer) {
+            Some(prev) => self.alpha * sample + (1.0 - self.alpha) * prev,
+            None => sample,
+        };
+        peers.insert(peer, smoothed);
+        smoothed
+    }
+
+    /// Smoothed quality for `peer`, if any samples were recorded.
+    pub fn quality(&self, peer: &PeerId) -> Option<f32> {
+        self.peers.read().unwrap().get(peer).copied()
+    }
+
+    /// Mean smoothed quality across all peers (0.0 when none are known).
+    pub fn overall(&self) -> f32 {
+        let peers = self.peers.read().unwrap();
+        if peers.is_empty() {
+            return 0.0;
+        }
+        peers.values().sum::<f32>() / peers.len() as f32
+    }
+
+    /// Forget a peer, e.g. on disconnect.
+    pub fn remove(&self, peer: &PeerId) {
+        self.peers.write().unwrap().remove(peer);
+    }
+}
+
+impl Default for LinkQualityTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_LINK_QUALITY_ALPHA)
+    }
+}
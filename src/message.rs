@@ -1,25 +1,122 @@
-use crate::types::{MessageId, Timestamp, UserId};
+use crate::geo::GeoHint;
+use crate::identity::{verify_signature, Signer};
+use crate::qos::{QosClass, QosPolicy};
+use crate::service::ServiceId;
+use crate::types::{
+    timestamp_millis, timestamp_to_millis, MessageId, PeerId, Timestamp, UserId, DEFAULT_TTL,
+};
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
-/// Content variants for messages
+/// Content variants for messages. Externally tagged (serde default) so the
+/// enum round-trips through non-self-describing formats such as bincode.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(tag = "type", content = "data")]
 pub enum MessageContent {
     Text(String),
-    File { name: String, data: Vec<u8> },
+    File {
+        name: String,
+        data: Vec<u8>,
+    },
     Routing(crate::routing_control::RoutingControl),
+    /// One piece of a streamed file, see [`FileTransfer`](crate::FileTransfer).
+    FileChunk {
+        transfer_id: MessageId,
+        name: String,
+        index: u32,
+        total_chunks: u32,
+        offset: u64,
+        total_size: u64,
+        data: Vec<u8>,
+    },
+    /// End-to-end receipt sent by the recipient back to the original sender.
+    Receipt {
+        original_id: MessageId,
+        kind: ReceiptKind,
+    },
+    /// Structured sensor readings (GPS, air quality, battery, ...).
+    Telemetry {
+        kind: String,
+        readings: Vec<(String, f64)>,
+        unit: Option<String>,
+    },
+    /// Periodic liveness announcement, see [`PresenceTracker`](crate::PresenceTracker).
+    Presence {
+        status: crate::presence::PresenceStatus,
+    },
+    /// Best-effort request from the original sender to stop relaying and
+    /// discard `target`.
+    Recall {
+        target: MessageId,
+    },
 }
 
-/// Priority levels – lower value is higher priority
+impl MessageContent {
+    /// Whether this content may be sent without a recipient, i.e. flooded to
+    /// the whole mesh. Private payloads such as file transfers and receipts
+    /// must always be addressed.
+    pub fn broadcast_allowed(&self) -> bool {
+        match self {
+            MessageContent::Text(_)
+            | MessageContent::Routing(_)
+            | MessageContent::Telemetry { .. }
+            | MessageContent::Presence { .. }
+            | MessageContent::Recall { .. } => true,
+            MessageContent::File { .. }
+            | MessageContent::FileChunk { .. }
+            | MessageContent::Receipt { .. } => false,
+        }
+    }
+}
+
+/// Kind of end-to-end receipt, distinct from link-layer acks.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ReceiptKind {
+    /// The recipient's node received the message.
+    Delivered,
+    /// The recipient's app surfaced the message to the user.
+    Read,
+}
+
+/// Sender-side delivery state of a unicast message. Ordered so a status
+/// never moves backwards (e.g. `Read` is not downgraded to `Delivered`).
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeliveryStatus {
+    Sent,
+    Delivered,
+    Read,
+}
+
+impl From<ReceiptKind> for DeliveryStatus {
+    fn from(kind: ReceiptKind) -> Self {
+        match kind {
+            ReceiptKind::Delivered => Self::Delivered,
+            ReceiptKind::Read => Self::Read,
+        }
+    }
+}
+
+/// Priority levels – lower value is higher priority
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub enum MessagePriority {
     Emergency = 0,
     Urgent = 1,
+    #[default]
     Normal = 2,
     Background = 3,
 }
 
+/// How a message's `ttl` is interpreted.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TtlMode {
+    /// `ttl` counts from `timestamp`; relies on roughly synchronised clocks.
+    #[default]
+    Absolute,
+    /// `ttl` is the remaining lifetime, reduced by every forwarding node.
+    /// Robust against clock skew since no wall-clock comparison is made.
+    Relative,
+}
+
 /// Main envelope for all messages shared across the mesh
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -27,10 +124,93 @@ pub struct Message {
     pub sender: UserId,
     pub recipient: Option<UserId>,
     pub content: MessageContent,
+    /// Encoded as milliseconds since the epoch; sub-millisecond precision is
+    /// not carried over the wire or covered by the signature.
+    #[serde(with = "timestamp_millis")]
     pub timestamp: Timestamp,
     pub ttl: Duration,
+    pub ttl_mode: TtlMode,
     pub hop_count: u8,
+    pub priority: MessagePriority,
+    /// Region the message is scoped to; relays outside it stop forwarding.
+    pub geo: Option<GeoHint>,
+    /// Message this one replies to, for threaded conversations.
+    pub in_reply_to: Option<MessageId>,
+    /// Quality-of-service class; `None` leaves behaviour to `priority` alone.
+    pub qos: Option<QosClass>,
+    /// Deliver to the nearest node providing this service instead of a
+    /// specific recipient. `recipient` is `None` for anycast messages.
+    pub anycast: Option<ServiceId>,
+    /// Deliver to each of these users (`recipient` is `None`): one copy
+    /// addresses a small group instead of a unicast per member.
+    pub recipients: Vec<UserId>,
+    /// Members of `recipients` already delivered to, added by the nodes
+    /// that delivered. Not signed, so relays can prune as they go.
+    pub delivered_to: Vec<UserId>,
+    /// Position in the sender's stream of messages to this recipient (or of
+    /// its broadcasts), starting at 1, for in-order delivery with a
+    /// [`ReorderBuffer`](crate::ReorderBuffer).
+    pub seq: Option<u64>,
+    /// Peers that have relayed this message so far, oldest first.
+    pub path: Vec<PeerId>,
     pub signature: Vec<u8>,
+    /// When this node received the message, by its own clock; a relative
+    /// TTL ages from here. Local bookkeeping, never sent or stored.
+    #[serde(skip)]
+    pub received_at: Option<Timestamp>,
+}
+
+/// Bytes covered by a message's signature: the one canonical encoding used
+/// both when signing and when verifying, so the in-memory and wire forms of
+/// a field can never disagree. Fields are bincode-encoded as a tuple in the
+/// order below, with the timestamp truncated to milliseconds as on the wire.
+/// Fields rewritten in transit (hop count, path, delivery progress, and a
+/// relative TTL) are excluded so forwarding does not invalidate it.
+///
+/// The destructuring is exhaustive on purpose: adding a field to [`Message`]
+/// fails to compile until it is placed here or explicitly left unsigned.
+pub fn signing_bytes(msg: &Message) -> Result<Vec<u8>> {
+    let Message {
+        id,
+        sender,
+        recipient,
+        content,
+        timestamp,
+        ttl,
+        ttl_mode,
+        hop_count: _,
+        priority,
+        geo,
+        in_reply_to,
+        qos,
+        anycast,
+        recipients,
+        delivered_to: _,
+        seq,
+        path: _,
+        signature: _,
+        received_at: _,
+    } = msg;
+    let ttl = match ttl_mode {
+        TtlMode::Absolute => *ttl,
+        TtlMode::Relative => Duration::ZERO,
+    };
+    Ok(bincode::serialize(&(
+        id,
+        sender,
+        recipient,
+        content,
+        timestamp_to_millis(*timestamp),
+        &ttl,
+        ttl_mode,
+        priority,
+        geo,
+        in_reply_to,
+        qos,
+        anycast,
+        recipients,
+        seq,
+    ))?)
 }
 
 impl Message {
@@ -41,9 +221,242 @@ impl Message {
             recipient,
             content,
             timestamp: std::time::SystemTime::now(),
-            ttl: Duration::from_secs(3600),
+            ttl: DEFAULT_TTL,
+            ttl_mode: TtlMode::default(),
             hop_count: 0,
+            priority: MessagePriority::default(),
+            geo: None,
+            in_reply_to: None,
+            qos: None,
+            anycast: None,
+            recipients: Vec::new(),
+            delivered_to: Vec::new(),
+            seq: None,
+            path: Vec::new(),
             signature: Vec::new(),
+            received_at: None,
+        }
+    }
+
+    /// Whether the message has outlived its TTL at `now`. Relative-TTL
+    /// messages expire once relays, and the time we have held them, have
+    /// used up their remaining lifetime.
+    pub fn is_expired_at(&self, now: Timestamp) -> bool {
+        match self.ttl_mode {
+            TtlMode::Absolute => now.duration_since(self.timestamp).unwrap_or_default() > self.ttl,
+            TtlMode::Relative => self.remaining_ttl(now).is_zero(),
+        }
+    }
+
+    /// Lifetime left at `now`. A relative TTL ages from `received_at`, and
+    /// not at all before the message is received.
+    pub fn remaining_ttl(&self, now: Timestamp) -> Duration {
+        let start = match self.ttl_mode {
+            TtlMode::Absolute => Some(self.timestamp),
+            TtlMode::Relative => self.received_at,
+        };
+        let age = start.map_or(Duration::ZERO, |start| {
+            now.duration_since(start).unwrap_or_default()
+        });
+        self.ttl.saturating_sub(age)
+    }
+
+    /// Sign the message with `signer`, whose key must match `sender`.
+    pub fn sign(&mut self, signer: &(impl Signer + ?Sized)) -> Result<()> {
+        if signer.public_key() != self.sender {
+            anyhow::bail!("signing identity does not match message sender");
+        }
+        self.signature = signer.sign(&signing_bytes(self)?)?;
+        Ok(())
+    }
+
+    /// Verify that `sender` signed this message.
+    pub fn verify_signature(&self) -> Result<()> {
+        if self.signature.is_empty() {
+            anyhow::bail!("message is unsigned");
+        }
+        verify_signature(&self.sender, &signing_bytes(self)?, &self.signature)
+    }
+
+    /// Members of [`recipients`](Self::recipients) not yet delivered to.
+    pub fn pending_recipients(&self) -> Vec<UserId> {
+        self.recipients
+            .iter()
+            .filter(|user| !self.delivered_to.contains(user))
+            .copied()
+            .collect()
+    }
+
+    /// Build a receipt for `original`, addressed back to its sender.
+    pub fn receipt(from: UserId, original: &Message, kind: ReceiptKind) -> Self {
+        Self::new(
+            from,
+            Some(original.sender),
+            MessageContent::Receipt {
+                original_id: original.id,
+                kind,
+            },
+        )
+    }
+
+    /// Attach `class` and take its priority and, if it has one, its TTL.
+    pub fn apply_qos(&mut self, class: QosClass) {
+        let policy = class.policy();
+        self.qos = Some(class);
+        self.priority = policy.priority;
+        if let Some(ttl) = policy.ttl {
+            self.ttl = ttl;
+        }
+    }
+
+    /// Full QoS policy of the message, if it has a class.
+    pub fn qos_policy(&self) -> Option<QosPolicy> {
+        self.qos.map(QosClass::policy)
+    }
+
+    /// Start building a message with the fluent [`MessageBuilder`] API.
+    pub fn builder() -> MessageBuilder {
+        MessageBuilder::default()
+    }
+}
+
+/// Fluent constructor for [`Message`]. `sender` and `content` are required;
+/// TTL defaults to [`DEFAULT_TTL`] and priority to `Normal`, unless a QoS
+/// class says otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct MessageBuilder {
+    sender: Option<UserId>,
+    recipient: Option<UserId>,
+    content: Option<MessageContent>,
+    ttl: Option<Duration>,
+    ttl_mode: Option<TtlMode>,
+    priority: Option<MessagePriority>,
+    geo: Option<GeoHint>,
+    in_reply_to: Option<MessageId>,
+    qos: Option<QosClass>,
+    anycast: Option<ServiceId>,
+    recipients: Vec<UserId>,
+    seq: Option<u64>,
+    content_bucket: Option<Duration>,
+}
+
+impl MessageBuilder {
+    pub fn sender(mut self, sender: UserId) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    pub fn to(mut self, recipient: UserId) -> Self {
+        self.recipient = Some(recipient);
+        self
+    }
+
+    pub fn content(mut self, content: MessageContent) -> Self {
+        self.content = Some(content);
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn ttl_mode(mut self, mode: TtlMode) -> Self {
+        self.ttl_mode = Some(mode);
+        self
+    }
+
+    pub fn priority(mut self, priority: MessagePriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Scope the message to a geographic region.
+    pub fn geo(mut self, geo: GeoHint) -> Self {
+        self.geo = Some(geo);
+        self
+    }
+
+    /// Mark the message as a reply to `parent`.
+    pub fn in_reply_to(mut self, parent: MessageId) -> Self {
+        self.in_reply_to = Some(parent);
+        self
+    }
+
+    /// Attach a QoS class. Its priority and TTL apply unless set
+    /// explicitly with [`priority`](Self::priority) or [`ttl`](Self::ttl).
+    pub fn qos(mut self, class: QosClass) -> Self {
+        self.qos = Some(class);
+        self
+    }
+
+    /// Address the message to the nearest provider of `service` rather than
+    /// a specific user. Cannot be combined with [`to`](Self::to).
+    pub fn anycast(mut self, service: ServiceId) -> Self {
+        self.anycast = Some(service);
+        self
+    }
+
+    /// Address the message to several users at once. Cannot be combined
+    /// with [`to`](Self::to) or [`anycast`](Self::anycast).
+    pub fn to_many(mut self, recipients: Vec<UserId>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// Set the sequence number instead of letting
+    /// [`MessageManager`](crate::MessageManager) assign the next one.
+    pub fn seq(mut self, seq: u64) -> Self {
+        self.seq = Some(seq);
+        self
+    }
+
+    /// Derive the id from sender, content and the timestamp rounded down to
+    /// `bucket` (see [`MessageId::from_content`]) instead of a random UUID.
+    pub fn content_id(mut self, bucket: Duration) -> Self {
+        self.content_bucket = Some(bucket);
+        self
+    }
+
+    /// Finish the message, failing if a required field is missing.
+    pub fn build(self) -> Result<Message> {
+        let sender = self
+            .sender
+            .ok_or_else(|| anyhow::anyhow!("MessageBuilder: sender is required"))?;
+        let content = self
+            .content
+            .ok_or_else(|| anyhow::anyhow!("MessageBuilder: content is required"))?;
+        if self.anycast.is_some() && self.recipient.is_some() {
+            anyhow::bail!("MessageBuilder: anycast messages cannot have a recipient");
+        }
+        if !self.recipients.is_empty() && (self.recipient.is_some() || self.anycast.is_some()) {
+            anyhow::bail!("MessageBuilder: multi-recipient messages cannot also use to or anycast");
+        }
+        let mut message = Message::new(sender, self.recipient, content);
+        if let Some(class) = self.qos {
+            message.apply_qos(class);
+        }
+        if let Some(ttl) = self.ttl {
+            message.ttl = ttl;
+        }
+        message.ttl_mode = self.ttl_mode.unwrap_or_default();
+        if let Some(priority) = self.priority {
+            message.priority = priority;
+        }
+        message.geo = self.geo;
+        message.in_reply_to = self.in_reply_to;
+        message.anycast = self.anycast;
+        message.recipients = self.recipients;
+        message.seq = self.seq;
+        if let Some(bucket) = self.content_bucket {
+            let secs = message
+                .timestamp
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let bucket = secs / bucket.as_secs().max(1);
+            message.id = MessageId::from_content(&message.sender, &message.content, bucket);
         }
+        Ok(message)
     }
 }
@@ -1,34 +1,1290 @@
-use crate::message::{Message, MessageContent};
-use crate::types::{MessageId, UserId};
+use crate::authority::AuthorityBroadcasts;
+use crate::clock::{Clock, SystemClock};
+use crate::config::MeshConfig;
+use crate::identity::{Identity, SessionKey, Signer};
+use crate::message::{
+    signing_bytes, DeliveryStatus, Message, MessageBuilder, MessageContent, MessagePriority,
+    TtlMode,
+};
+use crate::qos::{QosClass, RetransmitBudget};
+use crate::ratchet::{
+    open_sessions, seal_sessions, RatchetEnvelope, RatchetSession, MAX_SESSIONS_PER_PEER,
+};
+use crate::reorder::ReorderBuffer;
+use crate::store_crypto::{StoreCipher, STORE_SALT_LEN};
+use crate::transport::Transport;
+use crate::types::{MessageId, Timestamp, UserId};
+use crate::validator::MessageValidator;
 use anyhow::{Context, Result};
-use sled::Db;
-use std::sync::Arc;
-use std::time::{Duration, SystemTime};
+use futures::Stream;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use sled::transaction::{
+    ConflictableTransactionError, ConflictableTransactionResult, TransactionError,
+};
+use sled::{Db, Transactional};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Capacity of the channel feeding [`MessageManager::subscribe`].
+pub const DEFAULT_INBOX_CAPACITY: usize = 256;
+
+/// Longest TTL [`MessageManager::validate_message`] accepts by default, so
+/// no message can outlive the dedup window.
+pub const DEFAULT_MAX_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// How far ahead of our clock a message timestamp may be before
+/// [`MessageManager::validate_message`] rejects it.
+pub const DEFAULT_MAX_FUTURE_SKEW: Duration = Duration::from_secs(120);
+
+/// How long a message id stays "seen" by default: the longest accepted TTL
+/// plus the tolerated clock skew, after which no copy can still be live.
+pub const DEFAULT_DEDUP_WINDOW: Duration = DEFAULT_MAX_TTL.saturating_add(DEFAULT_MAX_FUTURE_SKEW);
+
+/// How long [`MessageManager::screen_message`] remembers an invalid
+/// message, long enough for the copies a flood brings in.
+pub const DEFAULT_REJECTION_WINDOW: Duration = Duration::from_secs(120);
+
+/// Most invalid messages [`MessageManager::screen_message`] remembers at
+/// once.
+pub const DEFAULT_MAX_REJECTED: usize = 1024;
+
+/// Store location used by [`MessageManager::new`].
+pub const DEFAULT_STORE_PATH: &str = ".disastermesh_store";
+
+/// What [`MessageManager::open_with_recovery`] does when the store on disk
+/// cannot be opened because it is corrupt.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StoreRecovery {
+    /// Return the error and leave the store untouched for inspection.
+    #[default]
+    FailFast,
+    /// Move the corrupt store aside to `<path>.corrupt-<millis>` and start
+    /// with an empty one, so an unattended node keeps relaying.
+    SelfHeal,
+}
+
+/// Keys in the `store_meta` tree of an encrypted store.
+const STORE_SALT_KEY: &[u8] = b"salt";
+const STORE_CHECK_KEY: &[u8] = b"key_check";
+
+/// Selects stored messages for [`MessageManager::list_messages`] and
+/// [`MessageManager::stream_messages`]. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct MessageFilter {
+    pub recipient: Option<UserId>,
+    /// Inclusive lower bound on the message timestamp.
+    pub since: Option<Timestamp>,
+    /// Exclusive upper bound on the message timestamp.
+    pub until: Option<Timestamp>,
+}
+
+impl MessageFilter {
+    pub fn matches(&self, msg: &Message) -> bool {
+        self.recipient.map_or(true, |r| msg.recipient == Some(r))
+            && self.since.map_or(true, |t| msg.timestamp >= t)
+            && self.until.map_or(true, |t| msg.timestamp < t)
+    }
+}
+
+/// Default longest time a write waits in a batch for its flush.
+pub const DEFAULT_WRITE_BATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Group commit for the store: writes are applied to sled straight away but
+/// flushed to disk together, once `max_writes` have accumulated or the
+/// oldest has waited `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBatching {
+    pub max_writes: usize,
+    pub max_delay: Duration,
+}
+
+/// Writes not yet flushed under [`WriteBatching`].
+#[derive(Default)]
+struct PendingWrites {
+    count: usize,
+    since: Option<Timestamp>,
+}
+
+/// Bounds on the message store. Oldest messages (by timestamp) are evicted
+/// first; unacknowledged messages still within their TTL are never evicted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub max_messages: Option<usize>,
+    /// Limit on the total encoded size of stored messages, file bodies
+    /// included and counted once however many messages share them.
+    pub max_bytes: Option<u64>,
+}
+
+/// Local signing keys, e.g. several personas or an old and a rotated key.
+/// `keys` holds the in-process subset of `signers`.
+#[derive(Default)]
+struct Identities {
+    signers: HashMap<UserId, Arc<dyn Signer>>,
+    keys: HashMap<UserId, Identity>,
+    default: Option<UserId>,
+}
+
+/// Why a sent message was given up on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeadLetterReason {
+    /// Its TTL ran out before any receipt arrived.
+    Expired,
+}
+
+/// A sent message that could not be delivered, published on
+/// [`MessageManager::subscribe_dead_letters`] and kept until removed with
+/// [`MessageManager::remove_dead_letter`].
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub message: Message,
+    pub reason: DeadLetterReason,
+}
+
+/// Start of a recipient's current retransmission-budget window and the
+/// retransmissions spent in it, indexed by priority.
+type BudgetWindow = (Timestamp, [u32; 4]);
 
 #[derive(Clone)]
 pub struct MessageManager {
     db: Arc<Db>,
+    statuses: sled::Tree,
+    /// Transmissions so far and time of the last one, per reliable message.
+    retransmits: sled::Tree,
+    /// Verified recalls, keyed by target id then recaller, so a recall by
+    /// anyone but the target's sender neither drops it nor hides the real
+    /// one.
+    recalled: sled::Tree,
+    /// Last sequence number used, per sender and recipient.
+    sequences: sled::Tree,
+    /// Next sequence number to release, per incoming stream; see
+    /// [`ReorderBuffer::with_store`].
+    reorder_positions: sled::Tree,
+    /// Reason and stored form of each dead-lettered message.
+    dead_letters: sled::Tree,
+    dead_letter_events: broadcast::Sender<DeadLetter>,
+    retransmit_budget: Option<RetransmitBudget>,
+    budget_spent: Arc<std::sync::Mutex<HashMap<UserId, BudgetWindow>>>,
+    /// First-seen timestamp per message id.
+    seen: sled::Tree,
+    /// Until when each recently rejected message is rejected unchecked, by
+    /// fingerprint; see [`screen_message`](Self::screen_message).
+    rejected: Arc<std::sync::Mutex<HashMap<Vec<u8>, Timestamp>>>,
+    /// Explicit dedup window; derived from `max_ttl` when unset.
+    dedup_window: Option<Duration>,
+    max_ttl: Duration,
+    max_future_skew: Duration,
+    /// Accept messages without a signature; see
+    /// [`with_unsigned_messages`](Self::with_unsigned_messages).
+    allow_unsigned: bool,
+    flush_on_write: bool,
+    write_batching: Option<WriteBatching>,
+    pending_writes: Arc<std::sync::Mutex<PendingWrites>>,
+    flushes: Arc<AtomicU64>,
+    clock: Arc<dyn Clock>,
+    identities: Arc<RwLock<Identities>>,
+    ttl_mode: TtlMode,
+    /// Hard cap on the lifetime of undelivered Emergency messages.
+    ttl_extension: Option<Duration>,
+    retention: RetentionPolicy,
+    validators: Vec<Arc<dyn MessageValidator>>,
+    inbox: broadcast::Sender<Message>,
+    reorder: Option<ReorderBuffer>,
+    /// Encrypts message bodies at rest, if enabled.
+    cipher: Option<StoreCipher>,
+    sessions: sled::Tree,
+    /// Peers' verified session keys, by user.
+    session_keys: sled::Tree,
+    /// File bodies of stored messages, each distinct body kept once.
+    files: FileBlobs,
+    /// Serialises load-advance-store of ratchet sessions.
+    session_lock: Arc<std::sync::Mutex<()>>,
 }
 
 impl MessageManager {
+    /// Open the store at [`DEFAULT_STORE_PATH`].
     pub async fn new() -> Result<Self> {
-        let db = sled::open(".disastermesh_store").context("open sled")?;
-        Ok(Self { db: Arc::new(db) })
+        Self::open(DEFAULT_STORE_PATH).await
+    }
+
+    /// Open (or create) the store at `path`.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::open_with_recovery(path, StoreRecovery::FailFast).await
+    }
+
+    /// Open (or create) the store at `path`, handling corruption as
+    /// `recovery` says. sled already replays and truncates a torn log on
+    /// open, so an error that survives that is treated as unrecoverable.
+    /// Only corruption triggers self-healing; a store locked by another
+    /// process is always an error.
+    pub async fn open_with_recovery(
+        path: impl AsRef<Path>,
+        recovery: StoreRecovery,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let db = match sled::open(path) {
+            Ok(db) => db,
+            Err(err) if recovery == StoreRecovery::SelfHeal && is_corruption(&err) => {
+                let aside = quarantine(path)?;
+                tracing::error!(
+                    store = %path.display(),
+                    moved_to = %aside.display(),
+                    "store is corrupt ({err}); starting with an empty one"
+                );
+                sled::open(path)
+                    .with_context(|| format!("open fresh sled at {}", path.display()))?
+            }
+            Err(err) if is_corruption(&err) => {
+                return Err(err).with_context(|| format!("store at {} is corrupt", path.display()))
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("open sled at {}", path.display()))
+            }
+        };
+        Self::from_db(db)
+    }
+
+    /// Open the store named by `config` and apply its tuning.
+    pub async fn from_config(config: &MeshConfig) -> Result<Self> {
+        Ok(
+            Self::open_with_recovery(&config.store_path, config.store_recovery)
+                .await?
+                .with_config(config),
+        )
+    }
+
+    /// Ephemeral store that lives only as long as the manager. Useful for
+    /// tests, since every instance is isolated.
+    pub async fn in_memory() -> Result<Self> {
+        let db = sled::Config::new()
+            .temporary(true)
+            .open()
+            .context("open temporary sled")?;
+        Self::from_db(db)
+    }
+
+    fn from_db(db: Db) -> Result<Self> {
+        let statuses = db
+            .open_tree("delivery_status")
+            .context("open status tree")?;
+        let sessions = db.open_tree("sessions").context("open session tree")?;
+        let session_keys = db
+            .open_tree("session_keys")
+            .context("open session key tree")?;
+        let seen = db.open_tree("seen").context("open seen tree")?;
+        let retransmits = db
+            .open_tree("retransmits")
+            .context("open retransmit tree")?;
+        let recalled = db.open_tree("recalled").context("open recall tree")?;
+        let sequences = db.open_tree("sequences").context("open sequence tree")?;
+        let reorder_positions = db
+            .open_tree("reorder_positions")
+            .context("open reorder position tree")?;
+        let dead_letters = db
+            .open_tree("dead_letters")
+            .context("open dead-letter tree")?;
+        let files = FileBlobs::open(&db)?;
+        Ok(Self {
+            db: Arc::new(db),
+            statuses,
+            retransmits,
+            recalled,
+            sequences,
+            reorder_positions,
+            dead_letters,
+            dead_letter_events: broadcast::channel(DEFAULT_INBOX_CAPACITY).0,
+            retransmit_budget: Some(RetransmitBudget::default()),
+            budget_spent: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            seen,
+            rejected: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            dedup_window: None,
+            max_ttl: DEFAULT_MAX_TTL,
+            max_future_skew: DEFAULT_MAX_FUTURE_SKEW,
+            allow_unsigned: false,
+            flush_on_write: false,
+            write_batching: None,
+            pending_writes: Arc::new(std::sync::Mutex::new(PendingWrites::default())),
+            flushes: Arc::new(AtomicU64::new(0)),
+            clock: Arc::new(SystemClock),
+            identities: Arc::new(RwLock::new(Identities::default())),
+            ttl_mode: TtlMode::default(),
+            ttl_extension: None,
+            retention: RetentionPolicy::default(),
+            validators: Vec::new(),
+            inbox: broadcast::channel(DEFAULT_INBOX_CAPACITY).0,
+            reorder: None,
+            cipher: None,
+            sessions,
+            session_keys,
+            files,
+            session_lock: Arc::new(std::sync::Mutex::new(())),
+        })
+    }
+
+    /// Also run `validator` on every message checked by
+    /// [`validate_message`](Self::validate_message). Validators run in the
+    /// order added; the first rejection wins.
+    pub fn with_validator(mut self, validator: Box<dyn MessageValidator>) -> Self {
+        self.validators.push(Arc::from(validator));
+        self
+    }
+
+    /// Enforce `policy` on delivered messages, keeping its per-authority
+    /// high-water marks in this store so replays stay refused across
+    /// restarts.
+    pub fn with_authority_broadcasts(self, policy: AuthorityBroadcasts) -> Result<Self> {
+        let tree = self
+            .db
+            .open_tree("authority_seqs")
+            .context("open authority sequence tree")?;
+        Ok(self.with_validator(Box::new(policy.with_store(tree)?)))
+    }
+
+    /// Apply the TTL mode, dedup window and retention policy from `config`.
+    pub fn with_config(self, config: &MeshConfig) -> Self {
+        let manager = self
+            .with_ttl_mode(config.ttl_mode)
+            .with_max_ttl(config.max_ttl())
+            .with_max_future_skew(config.max_future_skew())
+            .with_flush_on_write(config.flush_on_write)
+            .with_write_batching(config.write_batching())
+            .with_retention(config.retention());
+        match config.dedup_window() {
+            Some(window) => manager.with_dedup_window(window),
+            None => manager,
+        }
+    }
+
+    pub(crate) fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Use a custom clock for timestamps and TTL checks.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Forget sightings older than `window`, so a message legitimately
+    /// re-sent after a long gap is accepted again. By default the window is
+    /// the maximum TTL plus the tolerated clock skew.
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Reject messages with a TTL above `max` (default [`DEFAULT_MAX_TTL`]).
+    pub fn with_max_ttl(mut self, max: Duration) -> Self {
+        self.max_ttl = max;
+        self
+    }
+
+    fn dedup_window(&self) -> Duration {
+        self.dedup_window
+            .unwrap_or_else(|| self.max_ttl.saturating_add(self.max_future_skew))
+    }
+
+    /// Reject absolute-TTL messages timestamped more than `skew` ahead of
+    /// our clock (default [`DEFAULT_MAX_FUTURE_SKEW`]). Without a bound, a
+    /// node with a fast or tampered clock could issue messages that never
+    /// expire.
+    pub fn with_max_future_skew(mut self, skew: Duration) -> Self {
+        self.max_future_skew = skew;
+        self
+    }
+
+    /// Accept unsigned messages from the mesh, e.g. from legacy devices that
+    /// cannot sign. Signed messages are still verified. Off by default.
+    pub fn with_unsigned_messages(mut self) -> Self {
+        self.allow_unsigned = true;
+        self
+    }
+
+    /// Flush the store to disk after every message, receipt-status and
+    /// seen-marker write, so a power cut loses nothing already accepted.
+    /// Emergency messages are flushed regardless. Off by default, leaving
+    /// flushing to sled's background thread.
+    pub fn with_flush_on_write(mut self, flush: bool) -> Self {
+        self.flush_on_write = flush;
+        self
+    }
+
+    /// Flush writes in batches instead of one at a time, trading a little
+    /// durability latency for throughput during bursts. Takes precedence
+    /// over [`with_flush_on_write`](Self::with_flush_on_write); emergency
+    /// messages are still flushed at once. [`MeshNode::run`](crate::MeshNode::run)
+    /// closes a batch whose oldest write has waited `max_delay` even if no
+    /// further write arrives; without a node, call
+    /// [`flush_due`](Self::flush_due) periodically.
+    pub fn with_write_batching(mut self, batching: Option<WriteBatching>) -> Self {
+        self.write_batching = batching;
+        self
+    }
+
+    /// Force all pending writes to disk.
+    pub async fn sync(&self) -> Result<()> {
+        *self.pending_writes.lock().unwrap() = PendingWrites::default();
+        self.db.flush_async().await.context("flush store")?;
+        self.flushes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Flush the current write batch, if any. Returns whether there was
+    /// one.
+    pub async fn flush(&self) -> Result<bool> {
+        if self.pending_writes.lock().unwrap().count == 0 {
+            return Ok(false);
+        }
+        self.sync().await?;
+        Ok(true)
+    }
+
+    /// Flush the current write batch if its oldest write has waited the
+    /// batching `max_delay`, so the tail of a burst is not left unflushed.
+    /// Returns whether it flushed.
+    pub async fn flush_due(&self) -> Result<bool> {
+        let Some(batching) = self.write_batching else {
+            return Ok(false);
+        };
+        let due = match self.pending_writes.lock().unwrap().since {
+            Some(since) => {
+                self.clock.now().duration_since(since).unwrap_or_default() >= batching.max_delay
+            }
+            None => false,
+        };
+        if due {
+            self.sync().await?;
+        }
+        Ok(due)
+    }
+
+    /// Batching this manager flushes with, if any.
+    pub fn write_batching(&self) -> Option<WriteBatching> {
+        self.write_batching
+    }
+
+    /// How many times this manager has flushed the store to disk.
+    pub fn flush_count(&self) -> u64 {
+        self.flushes.load(Ordering::Relaxed)
+    }
+
+    async fn flush_if(&self, critical: bool) -> Result<()> {
+        let due = match self.write_batching {
+            _ if critical => true,
+            Some(batching) => {
+                let now = self.clock.now();
+                let mut pending = self.pending_writes.lock().unwrap();
+                pending.count += 1;
+                let since = *pending.since.get_or_insert(now);
+                pending.count >= batching.max_writes
+                    || now.duration_since(since).unwrap_or_default() >= batching.max_delay
+            }
+            None => self.flush_on_write,
+        };
+        if due {
+            self.sync().await?;
+        }
+        Ok(())
+    }
+
+    /// Limit retransmissions per recipient (default
+    /// [`RetransmitBudget::default`]); `None` removes the limit.
+    pub fn with_retransmit_budget(mut self, budget: Option<RetransmitBudget>) -> Self {
+        self.retransmit_budget = budget;
+        self
+    }
+
+    /// TTL mode for messages created by this node, e.g. [`TtlMode::Relative`]
+    /// on deployments whose clocks cannot be trusted.
+    pub fn with_ttl_mode(mut self, mode: TtlMode) -> Self {
+        self.ttl_mode = mode;
+        self
+    }
+
+    /// Keep undelivered Emergency messages alive past their TTL, up to `cap`
+    /// after they were created, so they keep being retransmitted as the
+    /// topology changes. Retransmitted copies carry the extended TTL. Other
+    /// priorities expire normally; `None` (the default) disables extension.
+    pub fn with_ttl_extension(mut self, cap: Option<Duration>) -> Self {
+        self.ttl_extension = cap;
+        self
+    }
+
+    /// Bound the store; the policy is enforced after every insert.
+    pub fn with_retention(mut self, policy: RetentionPolicy) -> Self {
+        self.retention = policy;
+        self
+    }
+
+    /// Encrypt stored message bodies, file bodies, dead letters and ratchet
+    /// sessions under a key derived from `passphrase`, and decrypt them
+    /// transparently on read. Metadata stays in the clear: message ids,
+    /// delivery states, the seen-set, retransmission bookkeeping, recall
+    /// records, sequence counters and reorder positions, and dead-letter
+    /// reasons. Enable it when the store is created: an existing plaintext
+    /// store is refused, as is a wrong passphrase for an encrypted one.
+    pub fn with_encryption(mut self, passphrase: &str) -> Result<Self> {
+        let meta = self.db.open_tree("store_meta").context("open meta tree")?;
+        let salt = match meta.get(STORE_SALT_KEY)? {
+            Some(salt) => salt.to_vec(),
+            None if self.db.iter().next().is_some() => {
+                anyhow::bail!("store already holds unencrypted messages")
+            }
+            None => {
+                let mut salt = [0; STORE_SALT_LEN];
+                rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut salt);
+                meta.insert(STORE_SALT_KEY, &salt)?;
+                salt.to_vec()
+            }
+        };
+        let cipher = StoreCipher::derive(passphrase, &salt)?;
+        match meta.get(STORE_CHECK_KEY)? {
+            Some(check) => {
+                cipher
+                    .open(STORE_CHECK_KEY, &check)
+                    .context("wrong store passphrase")?;
+            }
+            None => {
+                meta.insert(STORE_CHECK_KEY, cipher.seal(STORE_CHECK_KEY, &[])?)?;
+            }
+        }
+        self.cipher = Some(cipher);
+        Ok(self)
     }
 
-    /// Create a new signed (signature omitted in stub) message
+    /// Serialize `msg` for the main tree, encrypting it if enabled.
+    fn encode_stored(&self, msg: &Message) -> Result<Vec<u8>> {
+        let plain = bincode::serialize(msg)?;
+        match &self.cipher {
+            Some(cipher) => cipher.seal(&msg.id.to_bytes(), &plain),
+            None => Ok(plain),
+        }
+    }
+
+    /// Write `msg` to the main tree. A file body is stored separately, once
+    /// per distinct content, and shared by every message carrying it.
+    fn insert_stored(&self, msg: &Message) -> Result<()> {
+        let key = msg.id.to_bytes();
+        let MessageContent::File { name, data } = &msg.content else {
+            self.db.insert(key, self.encode_stored(msg)?)?;
+            return Ok(());
+        };
+        if data.is_empty() {
+            self.db.insert(key, self.encode_stored(msg)?)?;
+            return Ok(());
+        }
+        self.files.retain(self.cipher.as_ref(), &key, data)?;
+        let mut stripped = msg.clone();
+        stripped.content = MessageContent::File {
+            name: name.clone(),
+            data: Vec::new(),
+        };
+        self.db.insert(key, self.encode_stored(&stripped)?)?;
+        Ok(())
+    }
+
+    /// Remove `key` from the main tree, dropping its file body once no
+    /// other message references it. Returns the bytes freed.
+    fn remove_stored(&self, key: &[u8]) -> Result<u64> {
+        let removed = self.db.remove(key)?.map_or(0, |raw| raw.len() as u64);
+        Ok(removed + self.files.release(key)?)
+    }
+
+    /// Number of distinct file bodies in the store.
+    pub fn file_blob_count(&self) -> usize {
+        self.files.blobs.len()
+    }
+
+    /// Stored message with id `id`, if any.
+    fn stored_message(&self, id: &MessageId) -> Result<Option<Message>> {
+        let key = id.to_bytes();
+        match self.db.get(key)? {
+            Some(raw) if !raw.is_empty() => Ok(Some(open_stored(
+                self.cipher.as_ref(),
+                &self.files,
+                &key,
+                &raw,
+            )?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Hand delivered messages to subscribers in each sender's sequence
+    /// order, via `buffer`. They are still stored as soon as they arrive,
+    /// and each stream's position is kept in the store so a restart resumes
+    /// it. [`MeshNode::run`](crate::MeshNode::run) calls
+    /// [`flush_reorder`](Self::flush_reorder) to release messages stuck
+    /// behind a gap; call it periodically when using the manager alone.
+    pub fn with_reorder_buffer(mut self, buffer: ReorderBuffer) -> Self {
+        self.reorder = Some(buffer.with_store(self.reorder_positions.clone()));
+        self
+    }
+
+    /// Gap hold time of the reorder buffer, if there is one.
+    pub fn reorder_hold(&self) -> Option<Duration> {
+        self.reorder.as_ref().map(ReorderBuffer::hold)
+    }
+
+    /// Add a local identity to sign with. The first one added becomes the
+    /// default.
+    pub fn add_identity(&self, identity: Identity) -> UserId {
+        let user = self.add_signer(Arc::new(identity.clone()));
+        self.identities.write().unwrap().keys.insert(user, identity);
+        user
+    }
+
+    /// Add a local identity whose key lives outside the process, e.g. in
+    /// an HSM. Messages from it are signed through `signer`; ratchet
+    /// sessions and snapshots still need an [`Identity`].
+    pub fn add_signer(&self, signer: Arc<dyn Signer>) -> UserId {
+        let user = signer.public_key();
+        let mut identities = self.identities.write().unwrap();
+        identities.keys.remove(&user);
+        identities.signers.insert(user, signer);
+        identities.default.get_or_insert(user);
+        user
+    }
+
+    /// Forget a local identity, e.g. after rotating away from a compromised
+    /// key. If it was the default, another remaining identity takes over.
+    /// Returns the key if it was held in process.
+    pub fn remove_identity(&self, user: &UserId) -> Option<Identity> {
+        let removed = self.identities.write().unwrap().keys.remove(user);
+        self.remove_signer(user);
+        removed
+    }
+
+    /// Forget a local identity of either kind.
+    pub fn remove_signer(&self, user: &UserId) -> Option<Arc<dyn Signer>> {
+        let mut identities = self.identities.write().unwrap();
+        identities.keys.remove(user);
+        let removed = identities.signers.remove(user)?;
+        if identities.default == Some(*user) {
+            identities.default = identities.signers.keys().next().copied();
+        }
+        Some(removed)
+    }
+
+    /// Identity used when no selector is given, if its key is held in
+    /// process.
+    pub fn default_identity(&self) -> Option<Identity> {
+        let identities = self.identities.read().unwrap();
+        identities
+            .default
+            .and_then(|user| identities.keys.get(&user).cloned())
+    }
+
+    /// Make the local identity `user` the default.
+    pub fn set_default_identity(&self, user: &UserId) -> Result<()> {
+        let mut identities = self.identities.write().unwrap();
+        if !identities.signers.contains_key(user) {
+            anyhow::bail!("unknown local identity");
+        }
+        identities.default = Some(*user);
+        Ok(())
+    }
+
+    /// Whether `user` is one of our local identities, i.e. messages addressed
+    /// to it are for this node.
+    pub fn is_local(&self, user: &UserId) -> bool {
+        self.identities.read().unwrap().signers.contains_key(user)
+    }
+
+    /// All local identities.
+    pub fn identities(&self) -> Vec<Identity> {
+        self.identities
+            .read()
+            .unwrap()
+            .keys
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Local identity for `user`, if we hold its key.
+    pub fn identity(&self, user: &UserId) -> Option<Identity> {
+        self.identities.read().unwrap().keys.get(user).cloned()
+    }
+
+    /// Signer for the local identity `user`, wherever its key is held.
+    pub fn signer(&self, user: &UserId) -> Option<Arc<dyn Signer>> {
+        self.identities.read().unwrap().signers.get(user).cloned()
+    }
+
+    /// Create a message signed by the local identity selected by `identity`,
+    /// or by the default identity when `None`.
+    pub async fn create_message_as(
+        &self,
+        identity: Option<&UserId>,
+        recipient: Option<UserId>,
+        content: MessageContent,
+    ) -> Result<Message> {
+        let sender = match identity {
+            Some(user) if self.is_local(user) => *user,
+            Some(_) => anyhow::bail!("unknown local identity"),
+            None => self
+                .identities
+                .read()
+                .unwrap()
+                .default
+                .context("no local identity")?,
+        };
+        self.create_message(sender, recipient, content).await
+    }
+
+    /// Create a new message. It is signed when `sender` is one of our local
+    /// identities. Broadcasts of content that is not
+    /// [`broadcast_allowed`](MessageContent::broadcast_allowed) are rejected.
     pub async fn create_message(
         &self,
         sender: UserId,
         recipient: Option<UserId>,
         content: MessageContent,
     ) -> Result<Message> {
-        let message = Message::new(sender, recipient, content);
-        self.db
-            .insert(message.id.to_bytes(), bincode::serialize(&message)?)?;
+        self.create(self.draft(sender, recipient, content)).await
+    }
+
+    /// Like [`create_message`](Self::create_message), with a QoS class
+    /// attached. Only [`QosClass::Reliable`] unicasts are tracked for
+    /// receipts and offered by
+    /// [`due_retransmissions`](Self::due_retransmissions).
+    pub async fn create_message_with_qos(
+        &self,
+        sender: UserId,
+        recipient: Option<UserId>,
+        content: MessageContent,
+        qos: QosClass,
+    ) -> Result<Message> {
+        let mut message = self.draft(sender, recipient, content);
+        message.apply_qos(qos);
+        self.create(message).await
+    }
+
+    /// Create a reply to `parent`, addressed to its sender if `parent` was a
+    /// unicast and broadcast otherwise. See [`thread`](Self::thread).
+    pub async fn create_reply(
+        &self,
+        sender: UserId,
+        parent: &Message,
+        content: MessageContent,
+    ) -> Result<Message> {
+        let recipient = parent.recipient.map(|_| parent.sender);
+        let mut message = self.draft(sender, recipient, content);
+        message.in_reply_to = Some(parent.id);
+        self.create(message).await
+    }
+
+    /// Create a message from `builder`, for fields the other constructors do
+    /// not expose (priority, TTL, ...). It is stamped with our clock and
+    /// signed like any other; the builder's TTL mode applies.
+    pub async fn create_from(&self, builder: MessageBuilder) -> Result<Message> {
+        self.create(builder.build()?).await
+    }
+
+    fn draft(&self, sender: UserId, recipient: Option<UserId>, content: MessageContent) -> Message {
+        let mut message = Message::new(sender, recipient, content);
+        message.ttl_mode = self.ttl_mode;
+        message
+    }
+
+    #[tracing::instrument(
+        name = "create",
+        skip_all,
+        fields(message.id = %message.id, sender = %message.sender)
+    )]
+    async fn create(&self, mut message: Message) -> Result<Message> {
+        if message.recipient.is_none()
+            && message.recipients.is_empty()
+            && !message.content.broadcast_allowed()
+        {
+            anyhow::bail!("content may not be broadcast; a recipient is required");
+        }
+        message.timestamp = self.clock.now();
+        // A multi-recipient message would leave gaps in every recipient's
+        // broadcast stream, so it is not sequenced.
+        if message.seq.is_none()
+            && message.recipients.is_empty()
+            && !matches!(message.content, MessageContent::Routing(_))
+        {
+            message.seq = Some(self.next_seq(&message.sender, message.recipient.as_ref())?);
+        }
+        if let Some(signer) = self.signer(&message.sender) {
+            message.sign(signer.as_ref())?;
+        }
+        self.insert_stored(&message)?;
+        self.record_seen(&message.id).await?;
+        let acked = message
+            .qos_policy()
+            .map_or(true, |policy| policy.retransmit.is_some());
+        if acked
+            && message.recipient.is_some()
+            && !matches!(message.content, MessageContent::Receipt { .. })
+        {
+            self.set_status(&message.id, DeliveryStatus::Sent)?;
+        }
+        self.enforce_retention().await?;
+        self.flush_if(message.priority == MessagePriority::Emergency)
+            .await?;
+        tracing::debug!("message created");
         Ok(message)
     }
 
+    /// Next sequence number from `sender` to `recipient` (or to everyone),
+    /// persisted so it keeps increasing across restarts.
+    fn next_seq(&self, sender: &UserId, recipient: Option<&UserId>) -> Result<u64> {
+        let mut key = sender.0.to_vec();
+        key.extend_from_slice(&recipient.map_or([0; 32], |user| user.0));
+        self.bump_sequence(key)
+    }
+
+    /// Next value of the persistent counter `name`, starting at 1, e.g. for
+    /// protocol sequence numbers that must survive a restart.
+    pub fn next_counter(&self, name: &str) -> Result<u64> {
+        self.bump_sequence(format!("counter/{name}").into_bytes())
+    }
+
+    fn bump_sequence(&self, key: Vec<u8>) -> Result<u64> {
+        let updated = self.sequences.update_and_fetch(key, |last| {
+            let last = last.map_or(0, |raw| {
+                u64::from_be_bytes(raw.try_into().expect("8-byte sequence"))
+            });
+            Some((last + 1).to_be_bytes().to_vec())
+        })?;
+        let raw = updated.expect("sequence was just written");
+        Ok(u64::from_be_bytes(
+            raw.as_ref().try_into().expect("8-byte sequence"),
+        ))
+    }
+
+    /// Evict the oldest evictable messages until the store is within the
+    /// retention policy. Returns how many were evicted.
+    pub async fn enforce_retention(&self) -> Result<usize> {
+        let RetentionPolicy {
+            max_messages,
+            max_bytes,
+        } = self.retention;
+        if max_messages.is_none() && max_bytes.is_none() {
+            return Ok(0);
+        }
+        let now = self.clock.now();
+        let mut stored = Vec::new();
+        for entry in self.db.iter() {
+            let (key, raw) = entry?;
+            if raw.is_empty() {
+                continue;
+            }
+            let msg = open_stored(self.cipher.as_ref(), &self.files, &key, &raw)?;
+            stored.push((msg.timestamp, key, raw.len() as u64, msg));
+        }
+        stored.sort_by_key(|(timestamp, ..)| *timestamp);
+
+        // File bodies count once, however many messages share them.
+        let mut count = stored.len();
+        let mut bytes: u64 =
+            stored.iter().map(|(_, _, size, _)| size).sum::<u64>() + self.files.bytes()?;
+        let mut evicted = 0;
+        for (_, key, _, msg) in stored {
+            let over = max_messages.is_some_and(|max| count > max)
+                || max_bytes.is_some_and(|max| bytes > max);
+            if !over {
+                break;
+            }
+            let pending = self.message_status(&msg.id).await == Some(DeliveryStatus::Sent)
+                && !msg.is_expired_at(now);
+            if pending {
+                continue;
+            }
+            let freed = self.remove_stored(&key)?;
+            self.statuses.remove(msg.id.to_bytes())?;
+            self.retransmits.remove(msg.id.to_bytes())?;
+            count -= 1;
+            bytes = bytes.saturating_sub(freed);
+            evicted += 1;
+        }
+        Ok(evicted)
+    }
+
+    /// All stored messages matching `filter`, loaded eagerly.
+    pub async fn list_messages(&self, filter: &MessageFilter) -> Result<Vec<Message>> {
+        let mut messages = Vec::new();
+        for entry in self.db.iter() {
+            let (key, raw) = entry?;
+            if let Some(msg) = decode_stored(self.cipher.as_ref(), &self.files, &key, &raw, filter)?
+            {
+                messages.push(msg);
+            }
+        }
+        Ok(messages)
+    }
+
+    /// The stored conversation rooted at `root`: the root itself (if stored)
+    /// and every direct or indirect reply, oldest first.
+    pub async fn thread(&self, root: MessageId) -> Result<Vec<Message>> {
+        let mut children: HashMap<MessageId, Vec<Message>> = HashMap::new();
+        let mut thread = Vec::new();
+        for entry in self.db.iter() {
+            let (key, raw) = entry?;
+            if raw.is_empty() {
+                continue;
+            }
+            let msg = open_stored(self.cipher.as_ref(), &self.files, &key, &raw)?;
+            if msg.id == root {
+                thread.push(msg);
+            } else if let Some(parent) = msg.in_reply_to {
+                children.entry(parent).or_default().push(msg);
+            }
+        }
+        let mut frontier = vec![root];
+        while let Some(parent) = frontier.pop() {
+            for reply in children.remove(&parent).unwrap_or_default() {
+                frontier.push(reply.id);
+                thread.push(reply);
+            }
+        }
+        thread.sort_by_key(|msg| msg.timestamp);
+        Ok(thread)
+    }
+
+    /// Lazily iterate stored messages matching `filter`, deserializing each
+    /// one only when polled, so large inboxes can be paged without loading
+    /// everything. Yields the same messages, in the same order, as
+    /// [`list_messages`](Self::list_messages).
+    pub fn stream_messages(
+        &self,
+        filter: MessageFilter,
+    ) -> impl Stream<Item = Result<Message>> + Send + 'static {
+        let cipher = self.cipher.clone();
+        let files = self.files.clone();
+        futures::stream::iter(self.db.iter().filter_map(move |entry| {
+            entry
+                .map_err(Into::into)
+                .and_then(|(key, raw)| decode_stored(cipher.as_ref(), &files, &key, &raw, &filter))
+                .transpose()
+        }))
+    }
+
+    /// Delivery status of a unicast message we sent, if known.
+    pub async fn message_status(&self, id: &MessageId) -> Option<DeliveryStatus> {
+        let raw = self.statuses.get(id.to_bytes()).ok()??;
+        bincode::deserialize(&raw).ok()
+    }
+
+    /// Number of unicast messages we sent that have not been acknowledged by
+    /// a receipt yet.
+    pub async fn pending_count(&self) -> usize {
+        self.statuses
+            .iter()
+            .values()
+            .filter_map(|raw| raw.ok())
+            .filter(|raw| {
+                bincode::deserialize::<DeliveryStatus>(raw).ok() == Some(DeliveryStatus::Sent)
+            })
+            .count()
+    }
+
+    /// Sent unicast messages still awaiting a delivery receipt.
+    pub async fn pending_messages(&self) -> Result<Vec<Message>> {
+        let mut pending = Vec::new();
+        for entry in self.statuses.iter() {
+            let (key, raw) = entry?;
+            if bincode::deserialize::<DeliveryStatus>(&raw)? != DeliveryStatus::Sent {
+                continue;
+            }
+            if let Some(raw) = self.db.get(&key)? {
+                pending.push(open_stored(self.cipher.as_ref(), &self.files, &key, &raw)?);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Move sent messages that expired without a receipt out of the store
+    /// and into the dead-letter tree, publishing each on
+    /// [`subscribe_dead_letters`](Self::subscribe_dead_letters). Returns how
+    /// many were moved. Call periodically.
+    pub async fn purge_expired(&self) -> Result<usize> {
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        for entry in self.statuses.iter() {
+            let (key, raw) = entry?;
+            if bincode::deserialize::<DeliveryStatus>(&raw)? != DeliveryStatus::Sent {
+                continue;
+            }
+            if let Some(raw) = self.db.get(&key)? {
+                let msg = open_stored(self.cipher.as_ref(), &self.files, &key, &raw)?;
+                if self.pending_expired(&msg, now) {
+                    expired.push(msg);
+                }
+            }
+        }
+        for msg in &expired {
+            let key = msg.id.to_bytes();
+            let reason = DeadLetterReason::Expired;
+            self.dead_letters.insert(
+                key,
+                bincode::serialize(&(reason, self.encode_stored(msg)?))?,
+            )?;
+            self.remove_stored(&key)?;
+            self.statuses.remove(key)?;
+            self.retransmits.remove(key)?;
+            tracing::debug!(message.id = %msg.id, "undelivered message dead-lettered");
+            let _ = self.dead_letter_events.send(DeadLetter {
+                message: msg.clone(),
+                reason,
+            });
+        }
+        self.flush_if(false).await?;
+        Ok(expired.len())
+    }
+
+    /// Every message dead-lettered from now on.
+    pub fn subscribe_dead_letters(&self) -> broadcast::Receiver<DeadLetter> {
+        self.dead_letter_events.subscribe()
+    }
+
+    /// Dead-lettered messages not yet removed.
+    pub async fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let mut letters = Vec::new();
+        for entry in self.dead_letters.iter() {
+            let (key, raw) = entry?;
+            letters.push(self.dead_letter(&key, &raw)?);
+        }
+        Ok(letters)
+    }
+
+    /// Take `id` out of the dead-letter tree, e.g. once the user has seen
+    /// it or to send it again.
+    pub async fn remove_dead_letter(&self, id: &MessageId) -> Result<Option<DeadLetter>> {
+        let key = id.to_bytes();
+        self.dead_letters
+            .remove(key)?
+            .map(|raw| self.dead_letter(&key, &raw))
+            .transpose()
+    }
+
+    fn dead_letter(&self, key: &[u8], raw: &[u8]) -> Result<DeadLetter> {
+        let (reason, stored): (DeadLetterReason, Vec<u8>) = bincode::deserialize(raw)?;
+        Ok(DeadLetter {
+            message: open_stored(self.cipher.as_ref(), &self.files, key, &stored)?,
+            reason,
+        })
+    }
+
+    /// Store `messages` as sent and awaiting receipts, e.g. when restoring a
+    /// snapshot. Messages already stored are left alone.
+    pub(crate) fn restore_pending(&self, messages: &[Message]) -> Result<()> {
+        for msg in messages {
+            let key = msg.id.to_bytes();
+            if self.db.contains_key(key)? {
+                continue;
+            }
+            self.insert_stored(msg)?;
+            self.set_status(&msg.id, DeliveryStatus::Sent)?;
+        }
+        Ok(())
+    }
+
+    /// Unacknowledged messages whose QoS class asks for retransmission and
+    /// whose retry interval, scaled to their priority by
+    /// [`for_priority`](crate::RetransmitPolicy::for_priority), has elapsed,
+    /// highest priority first. Report each one actually sent with
+    /// [`record_retransmission`](Self::record_retransmission); until then it
+    /// stays due. Messages stop being offered once they expire or reach
+    /// their attempt limit; ones over their recipient's [`RetransmitBudget`]
+    /// are deferred.
+    pub async fn due_retransmissions(&self) -> Result<Vec<Message>> {
+        let now = self.clock.now();
+        let mut candidates = Vec::new();
+        for entry in self.statuses.iter() {
+            let (key, raw) = entry?;
+            if bincode::deserialize::<DeliveryStatus>(&raw)? != DeliveryStatus::Sent {
+                continue;
+            }
+            let Some(raw) = self.db.get(&key)? else {
+                continue;
+            };
+            let msg = open_stored(self.cipher.as_ref(), &self.files, &key, &raw)?;
+            let Some(retransmit) = msg.qos_policy().and_then(|policy| policy.retransmit) else {
+                continue;
+            };
+            let retransmit = retransmit.for_priority(msg.priority);
+            let (attempts, last): (u32, Timestamp) = match self.retransmits.get(&key)? {
+                Some(raw) => bincode::deserialize(&raw)?,
+                None => (1, msg.timestamp),
+            };
+            let waited = now.duration_since(last).unwrap_or_default();
+            if attempts >= retransmit.max_attempts
+                || waited < retransmit.interval
+                || self.pending_expired(&msg, now)
+            {
+                continue;
+            }
+            candidates.push(msg);
+        }
+        candidates.sort_by_key(|msg| msg.priority);
+
+        let mut due = Vec::new();
+        for msg in candidates {
+            if !self.spend_budget(&msg, now) {
+                continue;
+            }
+            due.push(self.extend_ttl(msg, now)?);
+        }
+        Ok(due)
+    }
+
+    /// Count a retransmission of `id` just sent, starting its next retry
+    /// interval.
+    pub async fn record_retransmission(&self, id: &MessageId) -> Result<()> {
+        let key = id.to_bytes();
+        let attempts: u32 = match self.retransmits.get(key)? {
+            Some(raw) => bincode::deserialize::<(u32, Timestamp)>(&raw)?.0,
+            None => 1,
+        };
+        self.retransmits
+            .insert(key, bincode::serialize(&(attempts + 1, self.clock.now()))?)?;
+        Ok(())
+    }
+
+    /// Lifetime of pending `msg` under the TTL extension, if it applies.
+    /// Only absolute TTLs are extended, and never beyond the maximum TTL
+    /// peers accept.
+    fn extended_ttl(&self, msg: &Message) -> Option<Duration> {
+        let cap = self.ttl_extension?;
+        (msg.priority == MessagePriority::Emergency && msg.ttl_mode == TtlMode::Absolute)
+            .then(|| msg.ttl.max(cap).min(self.max_ttl))
+    }
+
+    /// Whether pending `msg` has outlived its (possibly extended) TTL. We
+    /// created it, so even a relative TTL ages from its timestamp.
+    fn pending_expired(&self, msg: &Message, now: Timestamp) -> bool {
+        let ttl = self.extended_ttl(msg).unwrap_or(msg.ttl);
+        now.duration_since(msg.timestamp).unwrap_or_default() > ttl
+    }
+
+    /// Give a retransmission of `msg` whose own TTL has lapsed another TTL's
+    /// worth of lifetime, bounded by the extension cap, re-signing it so
+    /// receivers accept the new TTL.
+    fn extend_ttl(&self, mut msg: Message, now: Timestamp) -> Result<Message> {
+        let Some(cap) = self.extended_ttl(&msg) else {
+            return Ok(msg);
+        };
+        if !msg.is_expired_at(now) {
+            return Ok(msg);
+        }
+        let age = now.duration_since(msg.timestamp).unwrap_or_default();
+        msg.ttl = (age + msg.ttl).min(cap);
+        if let Some(signer) = self.signer(&msg.sender) {
+            msg.sign(signer.as_ref())?;
+        }
+        Ok(msg)
+    }
+
+    /// Charge one retransmission of `msg` to its recipient's budget. Returns
+    /// false if the budget is exhausted.
+    fn spend_budget(&self, msg: &Message, now: Timestamp) -> bool {
+        let (Some(budget), Some(recipient)) = (&self.retransmit_budget, msg.recipient) else {
+            return true;
+        };
+        let mut spent = self.budget_spent.lock().unwrap();
+        let (start, used) = spent.entry(recipient).or_insert((now, [0; 4]));
+        if now.duration_since(*start).unwrap_or_default() >= budget.window {
+            *start = now;
+            *used = [0; 4];
+        }
+        let slot = msg.priority as usize;
+        if used.iter().sum::<u32>() >= budget.per_peer
+            || used[slot] >= budget.allowance(msg.priority)
+        {
+            return false;
+        }
+        used[slot] += 1;
+        true
+    }
+
+    /// Correlate an incoming receipt with a message we sent and advance its
+    /// status. Returns the new status, or `None` if `msg` is not a receipt for
+    /// one of our messages. The receipt must be signed by the original
+    /// message's recipient; anyone else could otherwise stop its
+    /// retransmissions.
+    pub async fn handle_receipt(&self, msg: &Message) -> Result<Option<DeliveryStatus>> {
+        let MessageContent::Receipt { original_id, kind } = &msg.content else {
+            return Ok(None);
+        };
+        let Some(current) = self.message_status(original_id).await else {
+            return Ok(None);
+        };
+        let Some(original) = self.stored_message(original_id)? else {
+            return Ok(None);
+        };
+        if original.recipient != Some(msg.sender) {
+            anyhow::bail!("receipt not sent by the original recipient");
+        }
+        msg.verify_signature()
+            .context("receipt not signed by the original recipient")?;
+        let status = current.max(DeliveryStatus::from(*kind));
+        self.set_status(original_id, status)?;
+        self.retransmits.remove(original_id.to_bytes())?;
+        self.flush_if(false).await?;
+        Ok(Some(status))
+    }
+
+    /// Recall `target`, which `sender` created earlier: it stops awaiting
+    /// receipts and being retransmitted here, and the returned
+    /// [`MessageContent::Recall`] broadcast asks other nodes to do the same.
+    pub async fn create_recall(&self, sender: UserId, target: MessageId) -> Result<Message> {
+        match self.stored_message(&target)? {
+            Some(msg) if msg.sender == sender => {}
+            _ => anyhow::bail!("can only recall messages sent by this identity"),
+        }
+        let recall = self
+            .create(self.draft(sender, None, MessageContent::Recall { target }))
+            .await?;
+        self.record_recall(&target, &sender).await?;
+        Ok(recall)
+    }
+
+    /// Apply a received [`MessageContent::Recall`]. It must be signed by the
+    /// sender of the target, if we hold the target; otherwise it is kept
+    /// and checked against the target should it arrive later. Returns the
+    /// recalled id, or `None` for other content.
+    pub async fn handle_recall(&self, msg: &Message) -> Result<Option<MessageId>> {
+        let MessageContent::Recall { target } = &msg.content else {
+            return Ok(None);
+        };
+        msg.verify_signature()?;
+        if let Some(stored) = self.stored_message(target)? {
+            if stored.sender != msg.sender {
+                anyhow::bail!("recall not signed by the original sender");
+            }
+        }
+        self.record_recall(target, &msg.sender).await?;
+        Ok(Some(*target))
+    }
+
+    /// Whether `msg` was recalled by its own sender.
+    pub async fn is_recalled(&self, msg: &Message) -> bool {
+        self.recalled
+            .contains_key(recall_key(&msg.id, &msg.sender))
+            .unwrap_or(false)
+    }
+
+    async fn record_recall(&self, target: &MessageId, by: &UserId) -> Result<()> {
+        self.recalled.insert(recall_key(target, by), &[])?;
+        self.statuses.remove(target.to_bytes())?;
+        self.retransmits.remove(target.to_bytes())?;
+        self.flush_if(false).await
+    }
+
+    fn set_status(&self, id: &MessageId, status: DeliveryStatus) -> Result<()> {
+        self.statuses
+            .insert(id.to_bytes(), bincode::serialize(&status)?)?;
+        Ok(())
+    }
+
     /// Placeholder "encryption" – simply serializes with bincode
     pub async fn encrypt_message(
         &self,
@@ -43,28 +1299,625 @@ impl MessageManager {
         Ok(bincode::deserialize(data)?)
     }
 
+    /// Signed session key of our local identity `local`, for peers that
+    /// want to open sessions to it.
+    pub fn session_key(&self, local: &UserId) -> Result<SessionKey> {
+        let identity = self.identity(local).context("unknown local identity")?;
+        Ok(identity.session_key())
+    }
+
+    /// Remember a peer's session key, so sessions to it can be opened.
+    pub fn add_session_key(&self, key: &SessionKey) -> Result<()> {
+        key.verify()?;
+        self.session_keys
+            .insert(key.user.0, bincode::serialize(key)?)?;
+        Ok(())
+    }
+
+    /// Encrypt `content` from our local identity `local` to `peer` in a
+    /// forward-secret ratchet session, opening one on first contact; that
+    /// needs `peer`'s key from [`add_session_key`](Self::add_session_key).
+    /// Session state is persisted in the store, sealed with a key derived
+    /// from `local`'s identity.
+    pub async fn session_encrypt(
+        &self,
+        local: &UserId,
+        peer: &UserId,
+        content: &MessageContent,
+    ) -> Result<Vec<u8>> {
+        let plaintext = bincode::serialize(content)?;
+        let envelope = self.with_sessions(local, peer, |identity, sessions| {
+            if sessions.is_empty() {
+                let raw = self
+                    .session_keys
+                    .get(peer.0)?
+                    .context("no session key known for peer")?;
+                sessions.push(RatchetSession::initiate(
+                    identity,
+                    &bincode::deserialize(&raw)?,
+                )?);
+            }
+            sessions[0].encrypt(&plaintext)
+        })?;
+        Ok(bincode::serialize(&envelope)?)
+    }
+
+    /// Decrypt a session message from `peer` to our local identity `local`,
+    /// accepting the session it opens if it is the first.
+    pub async fn session_decrypt(
+        &self,
+        local: &UserId,
+        peer: &UserId,
+        data: &[u8],
+    ) -> Result<MessageContent> {
+        let envelope: RatchetEnvelope = bincode::deserialize(data)?;
+        let plaintext = self.with_sessions(local, peer, |identity, sessions| {
+            // After simultaneous first contact the peer may be using a
+            // session other than the one we send on.
+            for i in 0..sessions.len() {
+                if let Ok(plaintext) = sessions[i].decrypt(&envelope) {
+                    let session = sessions.remove(i);
+                    sessions.insert(0, session);
+                    return Ok(plaintext);
+                }
+            }
+            let handshake = envelope
+                .header
+                .handshake
+                .as_ref()
+                .context("no session for message")?;
+            if handshake.identity.user != *peer {
+                anyhow::bail!("session opened for another user");
+            }
+            // A replayed first message must not open the session again.
+            if sessions
+                .iter()
+                .any(|session| session.opened_by() == Some(handshake.ephemeral))
+            {
+                anyhow::bail!("session message already received");
+            }
+            let mut session = RatchetSession::respond(identity, handshake)?;
+            let plaintext = session.decrypt(&envelope)?;
+            self.add_session_key(&handshake.identity)?;
+            sessions.insert(0, session);
+            sessions.truncate(MAX_SESSIONS_PER_PEER);
+            Ok(plaintext)
+        })?;
+        Ok(bincode::deserialize(&plaintext)?)
+    }
+
+    fn with_sessions<T>(
+        &self,
+        local: &UserId,
+        peer: &UserId,
+        f: impl FnOnce(&Identity, &mut Vec<RatchetSession>) -> Result<T>,
+    ) -> Result<T> {
+        let _guard = self.session_lock.lock().unwrap();
+        let identity = self.identity(local).context("unknown local identity")?;
+        let key = [local.0, peer.0].concat();
+        let mut sessions = match self.sessions.get(&key)? {
+            Some(raw) => match &self.cipher {
+                Some(cipher) => open_sessions(&identity, &key, &cipher.open(&key, &raw)?)?,
+                None => open_sessions(&identity, &key, &raw)?,
+            },
+            None => Vec::new(),
+        };
+        let out = f(&identity, &mut sessions)?;
+        let mut sealed = seal_sessions(&identity, &key, &sessions)?;
+        // Sessions are already sealed under the identity; with a store
+        // passphrase they also need it, like message bodies.
+        if let Some(cipher) = &self.cipher {
+            sealed = cipher.seal(&key, &sealed)?;
+        }
+        self.sessions.insert(key.as_slice(), sealed)?;
+        Ok(out)
+    }
+
+    /// Whether content sent over `transport` needs application-layer
+    /// encryption. Links that are already confidential skip it.
+    pub fn needs_encryption(&self, transport: &dyn Transport) -> bool {
+        !transport.is_secure()
+    }
+
+    /// Encode content for sending over `transport`, encrypting only when the
+    /// link itself is not secure.
+    pub async fn encode_for_link(
+        &self,
+        content: &MessageContent,
+        peer_pub: &UserId,
+        transport: &dyn Transport,
+    ) -> Result<Vec<u8>> {
+        if self.needs_encryption(transport) {
+            self.encrypt_message(content, peer_pub).await
+        } else {
+            Ok(bincode::serialize(content)?)
+        }
+    }
+
+    /// Inverse of [`encode_for_link`](Self::encode_for_link).
+    pub async fn decode_for_link(
+        &self,
+        data: &[u8],
+        priv_key: &UserId,
+        transport: &dyn Transport,
+    ) -> Result<MessageContent> {
+        if self.needs_encryption(transport) {
+            self.decrypt_message(data, priv_key).await
+        } else {
+            Ok(bincode::deserialize(data)?)
+        }
+    }
+
+    #[tracing::instrument(
+        name = "validate",
+        skip_all,
+        fields(message.id = %msg.id, sender = %msg.sender, hop_count = msg.hop_count)
+    )]
     pub async fn validate_message(&self, msg: &Message) -> Result<()> {
-        // TTL check
-        let age = SystemTime::now()
-            .duration_since(msg.timestamp)
-            .unwrap_or(Duration::from_secs(0));
-        if age > msg.ttl {
+        let now = self.clock.now();
+        if msg.is_expired_at(now) {
+            tracing::debug!(decision = "dropped-expired", "message dropped");
             anyhow::bail!("Message expired")
         }
-        // TODO signature validation
+        if msg.ttl > self.max_ttl {
+            anyhow::bail!("message TTL of {}s exceeds the maximum", msg.ttl.as_secs());
+        }
+        // Relative TTLs never consult the timestamp, so skew cannot extend
+        // their lifetime.
+        if msg.ttl_mode == TtlMode::Absolute {
+            if let Ok(ahead) = msg.timestamp.duration_since(now) {
+                if ahead > self.max_future_skew {
+                    anyhow::bail!("message timestamped {}s in the future", ahead.as_secs());
+                }
+            }
+        }
+        // Messages must verify against their claimed sender, whichever local
+        // identity we are using.
+        if !(self.allow_unsigned && msg.signature.is_empty()) {
+            msg.verify_signature()?;
+        }
+        for validator in &self.validators {
+            validator.validate(msg)?;
+        }
         Ok(())
     }
 
+    /// [`validate_message`](Self::validate_message) for a message straight
+    /// off the mesh, remembering failures: copies of a message found invalid
+    /// in the last [`DEFAULT_REJECTION_WINDOW`] fail at once, so a flood of
+    /// a forgery costs one signature check rather than one per copy. Copies
+    /// are matched on their signed bytes and signature, not their id, so a
+    /// forgery cannot get the genuine message with the same id rejected.
+    pub async fn screen_message(&self, msg: &Message) -> Result<()> {
+        let fingerprint = rejection_fingerprint(msg)?;
+        let now = self.clock.now();
+        {
+            let mut rejected = self.rejected.lock().unwrap();
+            match rejected.get(&fingerprint) {
+                Some(until) if *until > now => anyhow::bail!("message was already rejected"),
+                Some(_) => {
+                    rejected.remove(&fingerprint);
+                }
+                None => {}
+            }
+        }
+        let result = self.validate_message(msg).await;
+        if result.is_err() {
+            let mut rejected = self.rejected.lock().unwrap();
+            if rejected.len() >= DEFAULT_MAX_REJECTED {
+                rejected.retain(|_, until| *until > now);
+            }
+            if rejected.len() < DEFAULT_MAX_REJECTED {
+                rejected.insert(fingerprint, now + DEFAULT_REJECTION_WINDOW);
+            }
+        }
+        result
+    }
+
+    /// Accept a message received from the mesh: validate it, drop duplicates,
+    /// store it and hand it to subscribers. Returns false for duplicates.
+    pub async fn deliver(&self, msg: Message) -> Result<bool> {
+        self.validate_message(&msg).await?;
+        self.deliver_validated(msg).await
+    }
+
+    /// [`deliver`](Self::deliver) for a message that already passed
+    /// [`validate_message`](Self::validate_message) or
+    /// [`screen_message`](Self::screen_message), skipping a second
+    /// signature check.
+    #[tracing::instrument(
+        name = "deliver",
+        skip_all,
+        fields(message.id = %msg.id, sender = %msg.sender, hop_count = msg.hop_count)
+    )]
+    pub async fn deliver_validated(&self, msg: Message) -> Result<bool> {
+        if self.is_recalled(&msg).await {
+            tracing::debug!(decision = "dropped-recalled", "message dropped");
+            return Ok(false);
+        }
+        if !self.is_new_message(&msg.id).await {
+            tracing::debug!(decision = "dropped-duplicate", "message dropped");
+            return Ok(false);
+        }
+        self.insert_stored(&msg)?;
+        self.record_seen(&msg.id).await?;
+        self.flush_if(msg.priority == MessagePriority::Emergency)
+            .await?;
+        tracing::debug!(decision = "delivered", "message delivered");
+        match &self.reorder {
+            Some(reorder) => {
+                for ready in reorder.push(msg).await {
+                    let _ = self.inbox.send(ready);
+                }
+            }
+            None => {
+                let _ = self.inbox.send(msg);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Pass messages whose reorder gap timed out on to subscribers. Returns
+    /// how many were released.
+    pub async fn flush_reorder(&self) -> usize {
+        let Some(reorder) = &self.reorder else {
+            return 0;
+        };
+        let ready = reorder.flush_expired().await;
+        let released = ready.len();
+        for msg in ready {
+            let _ = self.inbox.send(msg);
+        }
+        released
+    }
+
+    /// Every message passed to [`deliver`](Self::deliver) from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Message> {
+        self.inbox.subscribe()
+    }
+
+    /// Delivered messages whose content matches `filter`. Messages missed
+    /// because the consumer lagged are logged and skipped.
+    pub fn subscribe_content<F>(&self, filter: F) -> impl Stream<Item = Message> + Send + 'static
+    where
+        F: Fn(&MessageContent) -> bool + Send + 'static,
+    {
+        futures::stream::unfold((self.subscribe(), filter), |(mut rx, filter)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(msg) if filter(&msg.content) => return Some((msg, (rx, filter))),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("inbox subscriber lagged, {skipped} messages dropped");
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// Delivered [`MessageContent::Text`] messages only, e.g. for a chat UI.
+    pub fn subscribe_text(&self) -> impl Stream<Item = Message> + Send + 'static {
+        self.subscribe_content(|content| matches!(content, MessageContent::Text(_)))
+    }
+
+    /// Delivered [`MessageContent::Telemetry`] messages only, e.g. for a
+    /// sensor dashboard.
+    pub fn subscribe_telemetry(&self) -> impl Stream<Item = Message> + Send + 'static {
+        self.subscribe_content(|content| matches!(content, MessageContent::Telemetry { .. }))
+    }
+
+    /// Delivered [`MessageContent::File`] messages only.
+    pub fn subscribe_files(&self) -> impl Stream<Item = Message> + Send + 'static {
+        self.subscribe_content(|content| matches!(content, MessageContent::File { .. }))
+    }
+
+    /// Whether `id` has not been seen within the dedup window.
     pub async fn is_new_message(&self, id: &MessageId) -> bool {
         // If sled errors, treat as not seen to avoid dropping message.
-        self.db
-            .get(id.to_bytes())
-            .map(|opt| opt.is_none())
-            .unwrap_or(true)
+        match self.seen.get(id.to_bytes()) {
+            Ok(Some(raw)) => bincode::deserialize::<Timestamp>(&raw)
+                .map(|first_seen| !self.within_window(first_seen))
+                .unwrap_or(true),
+            _ => true,
+        }
     }
 
+    /// Record that `id` was seen now, unless an earlier sighting is still
+    /// within the dedup window.
     pub async fn mark_message_seen(&self, id: &MessageId) -> Result<()> {
-        self.db.insert(id.to_bytes(), &[])?;
+        if self.record_seen(id).await? {
+            self.flush_if(false).await?;
+        }
         Ok(())
     }
+
+    /// Record a first sighting without flushing. Returns whether `id` was new.
+    async fn record_seen(&self, id: &MessageId) -> Result<bool> {
+        if !self.is_new_message(id).await {
+            return Ok(false);
+        }
+        self.seen
+            .insert(id.to_bytes(), bincode::serialize(&self.clock.now())?)?;
+        Ok(true)
+    }
+
+    /// Drop sightings that have left the dedup window. Returns how many were
+    /// removed.
+    pub async fn prune_seen(&self) -> Result<usize> {
+        let mut pruned = 0;
+        for entry in self.seen.iter() {
+            let (key, raw) = entry?;
+            let first_seen: Timestamp = bincode::deserialize(&raw)?;
+            if !self.within_window(first_seen) {
+                self.seen.remove(key)?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Every sender sequence counter, by its raw key.
+    pub(crate) fn sequence_entries(&self) -> Result<Vec<(Vec<u8>, u64)>> {
+        let mut entries = Vec::new();
+        for entry in self.sequences.iter() {
+            let (key, raw) = entry?;
+            let raw: [u8; 8] = raw.as_ref().try_into().context("malformed sequence")?;
+            entries.push((key.to_vec(), u64::from_be_bytes(raw)));
+        }
+        Ok(entries)
+    }
+
+    /// Merge sequence counters from a snapshot, keeping the higher value so
+    /// no sequence number is handed out twice.
+    pub(crate) fn restore_sequences(&self, entries: &[(Vec<u8>, u64)]) -> Result<()> {
+        for (key, last) in entries {
+            self.sequences.update_and_fetch(key, |current| {
+                let current = current.map_or(0, |raw| {
+                    u64::from_be_bytes(raw.try_into().expect("8-byte sequence"))
+                });
+                Some(current.max(*last).to_be_bytes().to_vec())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Every recorded sighting with its first-seen time.
+    pub(crate) fn seen_entries(&self) -> Result<Vec<(MessageId, Timestamp)>> {
+        let mut entries = Vec::new();
+        for entry in self.seen.iter() {
+            let (key, raw) = entry?;
+            let key: [u8; 16] = key.as_ref().try_into().context("malformed seen key")?;
+            entries.push((MessageId::from_bytes(&key), bincode::deserialize(&raw)?));
+        }
+        Ok(entries)
+    }
+
+    /// Merge sightings from a snapshot, keeping the earlier first-seen time.
+    pub(crate) fn restore_seen(&self, entries: &[(MessageId, Timestamp)]) -> Result<()> {
+        for (id, first_seen) in entries {
+            let earlier = match self.seen.get(id.to_bytes())? {
+                Some(raw) => bincode::deserialize::<Timestamp>(&raw)?.min(*first_seen),
+                None => *first_seen,
+            };
+            self.seen
+                .insert(id.to_bytes(), bincode::serialize(&earlier)?)?;
+        }
+        Ok(())
+    }
+
+    fn within_window(&self, first_seen: Timestamp) -> bool {
+        self.clock
+            .now()
+            .duration_since(first_seen)
+            .map(|age| age <= self.dedup_window())
+            .unwrap_or(true)
+    }
+}
+
+/// Identifies one signed message for [`MessageManager::screen_message`]:
+/// a digest of everything its signature covers, and the signature.
+fn rejection_fingerprint(msg: &Message) -> Result<Vec<u8>> {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(&signing_bytes(msg)?);
+    ctx.update(&msg.signature);
+    Ok(ctx.finish().as_ref().to_vec())
+}
+
+/// Key in the `recalled` tree for a recall of `target` signed by `by`.
+fn recall_key(target: &MessageId, by: &UserId) -> Vec<u8> {
+    [&target.to_bytes()[..], &by.0].concat()
+}
+
+/// Decode a value from the main tree. Empty values (seen-markers left by
+/// older versions) and messages not matching `filter` yield `None`.
+fn decode_stored(
+    cipher: Option<&StoreCipher>,
+    files: &FileBlobs,
+    key: &[u8],
+    raw: &[u8],
+    filter: &MessageFilter,
+) -> Result<Option<Message>> {
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let msg = open_stored(cipher, files, key, raw)?;
+    Ok(filter.matches(&msg).then_some(msg))
+}
+
+/// Decrypt (if `cipher` is set) and deserialize a stored message, filling
+/// in its file body from `files`.
+fn open_stored(
+    cipher: Option<&StoreCipher>,
+    files: &FileBlobs,
+    key: &[u8],
+    raw: &[u8],
+) -> Result<Message> {
+    let mut msg: Message = match cipher {
+        Some(cipher) => bincode::deserialize(&cipher.open(key, raw)?)?,
+        None => bincode::deserialize(raw)?,
+    };
+    if let MessageContent::File { data, .. } = &mut msg.content {
+        if data.is_empty() {
+            if let Some(body) = files.load(cipher, key)? {
+                *data = body;
+            }
+        }
+    }
+    Ok(msg)
+}
+
+/// Content-addressed file bodies with reference counts, so a file that
+/// arrives in several messages (e.g. over several paths) is stored once.
+/// Bodies are addressed by SHA-256, or by an HMAC under the store key when
+/// the store is encrypted. The three trees only change together, in one
+/// transaction.
+#[derive(Clone)]
+struct FileBlobs {
+    /// Content address of the body to the body, encrypted like messages.
+    blobs: sled::Tree,
+    /// Content address to the number of messages referencing it.
+    counts: sled::Tree,
+    /// Message id to the content address of its file body.
+    refs: sled::Tree,
+}
+
+impl FileBlobs {
+    fn open(db: &Db) -> Result<Self> {
+        Ok(Self {
+            blobs: db.open_tree("file_blobs").context("open file blob tree")?,
+            counts: db
+                .open_tree("file_blob_counts")
+                .context("open file blob count tree")?,
+            refs: db.open_tree("file_refs").context("open file ref tree")?,
+        })
+    }
+
+    /// Reference `data` from message `key`, storing it if no other message
+    /// does yet.
+    fn retain(&self, cipher: Option<&StoreCipher>, key: &[u8], data: &[u8]) -> Result<()> {
+        let id = match cipher {
+            Some(cipher) => cipher.content_id(data),
+            None => digest::digest(&digest::SHA256, data).as_ref().to_vec(),
+        };
+        let body = match cipher {
+            Some(cipher) => cipher.seal(&id, data)?,
+            None => data.to_vec(),
+        };
+        (&self.blobs, &self.counts, &self.refs)
+            .transaction(|(blobs, counts, refs)| {
+                if refs.get(key)?.is_some() {
+                    return Ok(());
+                }
+                let count = blob_count(counts.get(&id)?)?;
+                if count == 0 {
+                    blobs.insert(id.as_slice(), body.as_slice())?;
+                }
+                counts.insert(id.as_slice(), &(count + 1).to_be_bytes())?;
+                refs.insert(key, id.as_slice())?;
+                Ok(())
+            })
+            .map_err(blob_error)
+    }
+
+    /// Drop message `key`'s reference, deleting the body with the last one.
+    /// Returns the bytes freed.
+    fn release(&self, key: &[u8]) -> Result<u64> {
+        (&self.blobs, &self.counts, &self.refs)
+            .transaction(|(blobs, counts, refs)| {
+                let Some(id) = refs.remove(key)? else {
+                    return Ok(0);
+                };
+                match blob_count(counts.get(&id)?)? {
+                    0 | 1 => {
+                        counts.remove(&id)?;
+                        Ok(blobs.remove(&id)?.map_or(0, |body| body.len() as u64))
+                    }
+                    count => {
+                        counts.insert(&id, &(count - 1).to_be_bytes())?;
+                        Ok(0)
+                    }
+                }
+            })
+            .map_err(blob_error)
+    }
+
+    /// File body referenced by message `key`, if it was stored separately.
+    fn load(&self, cipher: Option<&StoreCipher>, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(id) = self.refs.get(key)? else {
+            return Ok(None);
+        };
+        let body = self
+            .blobs
+            .get(&id)?
+            .context("file body missing from store")?;
+        match cipher {
+            Some(cipher) => Ok(Some(cipher.open(&id, &body)?)),
+            None => Ok(Some(body.to_vec())),
+        }
+    }
+
+    /// Bytes taken by all stored bodies.
+    fn bytes(&self) -> Result<u64> {
+        let mut bytes = 0;
+        for entry in self.blobs.iter() {
+            bytes += entry?.1.len() as u64;
+        }
+        Ok(bytes)
+    }
+}
+
+fn blob_count(raw: Option<sled::IVec>) -> ConflictableTransactionResult<u64, String> {
+    let Some(raw) = raw else {
+        return Ok(0);
+    };
+    match raw.as_ref().try_into() {
+        Ok(count) => Ok(u64::from_be_bytes(count)),
+        Err(_) => Err(ConflictableTransactionError::Abort(
+            "malformed file blob count".into(),
+        )),
+    }
+}
+
+fn blob_error(err: TransactionError<String>) -> anyhow::Error {
+    match err {
+        TransactionError::Abort(msg) => anyhow::anyhow!(msg),
+        TransactionError::Storage(err) => err.into(),
+    }
+}
+
+/// Whether `err` means the files on disk are damaged, as opposed to e.g.
+/// the store being locked by another process. sled reports an unreadable
+/// config file as `Unsupported` and missing system pages as a
+/// `ReportableBug`; while opening, both mean the files can't be used.
+fn is_corruption(err: &sled::Error) -> bool {
+    match err {
+        sled::Error::Corruption { .. }
+        | sled::Error::Unsupported(_)
+        | sled::Error::ReportableBug(_) => true,
+        sled::Error::Io(io) => matches!(
+            io.kind(),
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
+
+/// Rename the store at `path` out of the way, returning where it went.
+fn quarantine(path: &Path) -> Result<std::path::PathBuf> {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".corrupt-{millis}"));
+    let aside = std::path::PathBuf::from(aside);
+    std::fs::rename(path, &aside).with_context(|| {
+        format!(
+            "move corrupt store {} to {}",
+            path.display(),
+            aside.display()
+        )
+    })?;
+    Ok(aside)
 }
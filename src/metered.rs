@@ -0,0 +1,307 @@
+use crate::address_book::PeerAddress;
+use crate::clock::{Clock, SystemClock};
+use crate::compression::CompressionAlgorithm;
+use crate::framing::Framing;
+use crate::message::MessagePriority;
+use crate::stats::{ChannelOccupancy, MeshStats};
+use crate::transport::{Transport, TransportEvent, DEFAULT_EVENT_CAPACITY};
+use crate::types::{PeerId, Timestamp};
+use crate::wire::WireFormat;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// Default number of over-budget transmissions a [`MeteredTransport`]
+/// holds for the next interval.
+pub const DEFAULT_DEFERRED_CAPACITY: usize = 256;
+
+/// At most `bytes` may be transmitted per `interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteBudget {
+    pub bytes: u64,
+    pub interval: Duration,
+}
+
+/// A transmission held back by the budget: `None` target for a broadcast.
+type Deferred = (Option<PeerId>, Vec<u8>);
+
+struct Meter {
+    window_start: Timestamp,
+    used: u64,
+    /// Ordered by priority, then submission.
+    deferred: BTreeMap<(MessagePriority, u64), Deferred>,
+    next_seq: u64,
+}
+
+impl Meter {
+    /// Start a new interval if the current one is over.
+    fn roll(&mut self, now: Timestamp, interval: Duration) {
+        if now.duration_since(self.window_start).unwrap_or_default() >= interval {
+            self.window_start = now;
+            self.used = 0;
+        }
+    }
+}
+
+/// Wraps a link to count the bytes it sends and receives in
+/// [`MeshStats`], and optionally to cap transmission per interval for
+/// metered or battery-powered radios. Emergency traffic is always sent.
+/// Anything else that would exceed the budget is deferred until
+/// [`flush_deferred`](Self::flush_deferred) finds room, and when the
+/// deferred queue is full the lowest-priority transmission is dropped.
+pub struct MeteredTransport {
+    inner: Box<dyn Transport>,
+    budget: Option<ByteBudget>,
+    meter: Arc<Mutex<Meter>>,
+    deferred_capacity: usize,
+    stats: Arc<MeshStats>,
+    clock: Arc<dyn Clock>,
+    tx: broadcast::Sender<TransportEvent>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl MeteredTransport {
+    pub fn new(inner: Box<dyn Transport>) -> Self {
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let (tx, _) = broadcast::channel(DEFAULT_EVENT_CAPACITY);
+        Self {
+            inner,
+            budget: None,
+            meter: Arc::new(Mutex::new(Meter {
+                window_start: clock.now(),
+                used: 0,
+                deferred: BTreeMap::new(),
+                next_seq: 0,
+            })),
+            deferred_capacity: DEFAULT_DEFERRED_CAPACITY,
+            stats: Arc::new(MeshStats::default()),
+            clock,
+            tx,
+            task: None,
+        }
+    }
+
+    /// Limit transmission to `budget`.
+    pub fn with_budget(mut self, budget: ByteBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Hold at most `capacity` over-budget transmissions.
+    pub fn with_deferred_capacity(mut self, capacity: usize) -> Self {
+        self.deferred_capacity = capacity;
+        self
+    }
+
+    /// Share counters with other components instead of using a private set.
+    pub fn with_stats(mut self, stats: Arc<MeshStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.meter.lock().unwrap().window_start = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<MeshStats> {
+        self.stats.clone()
+    }
+
+    /// Bytes transmitted in the current budget interval.
+    pub fn budget_used(&self) -> u64 {
+        let mut meter = self.meter.lock().unwrap();
+        if let Some(budget) = self.budget {
+            meter.roll(self.clock.now(), budget.interval);
+        }
+        meter.used
+    }
+
+    /// Transmissions waiting for budget.
+    pub fn deferred(&self) -> usize {
+        self.meter.lock().unwrap().deferred.len()
+    }
+
+    /// Send deferred transmissions, highest priority first, while the
+    /// current interval has room. A transmission larger than the whole
+    /// budget is sent alone in an otherwise unused interval. Returns how
+    /// many were sent. Call periodically.
+    pub async fn flush_deferred(&self) -> Result<usize> {
+        let Some(budget) = self.budget else {
+            return Ok(0);
+        };
+        let mut sent = 0;
+        loop {
+            let (target, data) = {
+                let mut meter = self.meter.lock().unwrap();
+                meter.roll(self.clock.now(), budget.interval);
+                let Some(len) = meter.deferred.values().next().map(|d| d.1.len() as u64) else {
+                    break;
+                };
+                // A frame bigger than the whole budget would never fit, so
+                // it gets an otherwise empty interval to itself.
+                if meter.used > 0 && meter.used + len > budget.bytes {
+                    break;
+                }
+                meter.used += len;
+                let (_, deferred) = meter.deferred.pop_first().expect("peeked above");
+                deferred
+            };
+            self.transmit(target, data).await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Charge `data` against the budget. Returns false if it was deferred
+    /// (or dropped) instead.
+    fn admit(&self, target: Option<PeerId>, data: &[u8]) -> bool {
+        let Some(budget) = self.budget else {
+            return true;
+        };
+        let priority = self
+            .inner
+            .decode_message(data)
+            .map_or(MessagePriority::Normal, |msg| msg.priority);
+        let len = data.len() as u64;
+        let mut meter = self.meter.lock().unwrap();
+        meter.roll(self.clock.now(), budget.interval);
+        if priority == MessagePriority::Emergency || meter.used + len <= budget.bytes {
+            meter.used += len;
+            return true;
+        }
+        let seq = meter.next_seq;
+        meter.next_seq += 1;
+        meter
+            .deferred
+            .insert((priority, seq), (target, data.to_vec()));
+        MeshStats::incr(&self.stats.deferred_sends);
+        if meter.deferred.len() > self.deferred_capacity {
+            meter.deferred.pop_last();
+            MeshStats::incr(&self.stats.budget_drops);
+            tracing::debug!("byte budget exhausted, transmission dropped");
+        }
+        false
+    }
+
+    async fn transmit(&self, target: Option<PeerId>, data: Vec<u8>) -> Result<()> {
+        let len = data.len() as u64;
+        match target {
+            Some(peer) => self.inner.send(peer, data).await?,
+            None => self.inner.broadcast(data).await?,
+        }
+        self.stats.bytes_sent.fetch_add(len, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for MeteredTransport {
+    /// Start the link and begin relaying (and counting) its events.
+    async fn start(&mut self) -> Result<()> {
+        self.inner.start().await?;
+        let mut events = self.inner.subscribe_events();
+        let (tx, stats) = (self.tx.clone(), self.stats.clone());
+        self.task = Some(tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        if let TransportEvent::DataReceived { data, .. } = &event {
+                            stats
+                                .bytes_received
+                                .fetch_add(data.len() as u64, Ordering::Relaxed);
+                        }
+                        let _ = tx.send(event);
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("link events lagged, {skipped} dropped");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown().await?;
+        tokio::task::yield_now().await;
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        if !self.admit(Some(peer), &data) {
+            return Ok(());
+        }
+        self.transmit(Some(peer), data).await
+    }
+
+    /// A broadcast is charged once, as one transmission on the air.
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        if !self.admit(None, &data) {
+            return Ok(());
+        }
+        self.transmit(None, data).await
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.inner.get_peers()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.tx.subscribe()
+    }
+
+    fn mtu(&self) -> usize {
+        self.inner.mtu()
+    }
+
+    fn link_quality(&self) -> f32 {
+        self.inner.link_quality()
+    }
+
+    fn latency_hint(&self) -> Option<Duration> {
+        self.inner.latency_hint()
+    }
+
+    fn is_secure(&self) -> bool {
+        self.inner.is_secure()
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.inner.wire_format()
+    }
+
+    fn framing(&self) -> Framing {
+        self.inner.framing()
+    }
+
+    fn compression(&self) -> CompressionAlgorithm {
+        self.inner.compression()
+    }
+
+    async fn connect(&self, address: &PeerAddress) -> Result<()> {
+        self.inner.connect(address).await
+    }
+
+    fn peer_address(&self, peer: PeerId) -> Option<PeerAddress> {
+        self.inner.peer_address(peer)
+    }
+
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        Some(ChannelOccupancy::of(&self.tx, DEFAULT_EVENT_CAPACITY))
+    }
+
+    fn is_alive(&self) -> bool {
+        self.inner.is_alive()
+    }
+}
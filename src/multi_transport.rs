@@ -0,0 +1,196 @@
+use crate::address_book::PeerAddress;
+use crate::compression::CompressionAlgorithm;
+use crate::framing::Framing;
+use crate::stats::ChannelOccupancy;
+use crate::transport::{Transport, TransportEvent, DEFAULT_EVENT_CAPACITY};
+use crate::types::PeerId;
+use crate::wire::WireFormat;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// Runs several links (e.g. BLE and LoRa) as one [`Transport`]. Events from
+/// all links are merged into a single channel, unicasts go to whichever link
+/// knows the peer and broadcasts go out on every link.
+pub struct MultiTransport {
+    links: Vec<Box<dyn Transport>>,
+    tx: broadcast::Sender<TransportEvent>,
+    capacity: usize,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl MultiTransport {
+    pub fn new(links: Vec<Box<dyn Transport>>) -> Self {
+        let (tx, _) = broadcast::channel(DEFAULT_EVENT_CAPACITY);
+        Self {
+            links,
+            tx,
+            capacity: DEFAULT_EVENT_CAPACITY,
+            tasks: Vec::new(),
+        }
+    }
+
+    pub fn links(&self) -> &[Box<dyn Transport>] {
+        &self.links
+    }
+}
+
+#[async_trait]
+impl Transport for MultiTransport {
+    /// Start every link and begin merging their events.
+    async fn start(&mut self) -> Result<()> {
+        for link in &mut self.links {
+            link.start().await?;
+            let mut events = link.subscribe_events();
+            let tx = self.tx.clone();
+            self.tasks.push(tokio::spawn(async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            let _ = tx.send(event);
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!("link events lagged, {skipped} dropped");
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        for link in &mut self.links {
+            link.shutdown().await?;
+        }
+        // Give the merge tasks a chance to relay final disconnect events.
+        tokio::task::yield_now().await;
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        let link = self
+            .links
+            .iter()
+            .find(|link| link.get_peers().contains(&peer))
+            .ok_or_else(|| anyhow::anyhow!("no link knows peer"))?;
+        link.send(peer, data).await
+    }
+
+    /// Broadcast on every link. A failing link does not stop the others;
+    /// the first error is returned afterwards.
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        let mut result = Ok(());
+        for link in &self.links {
+            if let Err(e) = link.broadcast(data.clone()).await {
+                tracing::warn!("broadcast on link failed: {e:#}");
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// Per-peer outcomes from every link; a peer reachable over several
+    /// links appears once per link.
+    async fn broadcast_detailed(&self, data: Vec<u8>) -> Vec<(PeerId, Result<()>)> {
+        let mut results = Vec::new();
+        for link in &self.links {
+            results.extend(link.broadcast_detailed(data.clone()).await);
+        }
+        results
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        let mut peers = Vec::new();
+        for peer in self.links.iter().flat_map(|link| link.get_peers()) {
+            if !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+        peers
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.tx.subscribe()
+    }
+
+    /// Smallest MTU across links, so any frame fits every radio.
+    fn mtu(&self) -> usize {
+        self.links.iter().map(|link| link.mtu()).min().unwrap_or(0)
+    }
+
+    /// Best quality across links.
+    fn link_quality(&self) -> f32 {
+        self.links
+            .iter()
+            .map(|link| link.link_quality())
+            .fold(0.0, f32::max)
+    }
+
+    /// Slowest latency any link reports, as a conservative estimate.
+    fn latency_hint(&self) -> Option<Duration> {
+        self.links
+            .iter()
+            .filter_map(|link| link.latency_hint())
+            .max()
+    }
+
+    /// Secure only if every link is.
+    fn is_secure(&self) -> bool {
+        !self.links.is_empty() && self.links.iter().all(|link| link.is_secure())
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.links
+            .first()
+            .map(|link| link.wire_format())
+            .unwrap_or_default()
+    }
+
+    fn framing(&self) -> Framing {
+        self.links
+            .first()
+            .map(|link| link.framing())
+            .unwrap_or_default()
+    }
+
+    fn compression(&self) -> CompressionAlgorithm {
+        self.links
+            .first()
+            .map(|link| link.compression())
+            .unwrap_or_default()
+    }
+
+    /// Dial on the first link that can reach `address`.
+    async fn connect(&self, address: &PeerAddress) -> Result<()> {
+        let mut last = None;
+        for link in &self.links {
+            match link.connect(address).await {
+                Ok(()) => return Ok(()),
+                Err(e) => last = Some(e),
+            }
+        }
+        Err(last.unwrap_or_else(|| anyhow::anyhow!("no link can dial {address:?}")))
+    }
+
+    fn peer_address(&self, peer: PeerId) -> Option<PeerAddress> {
+        self.links.iter().find_map(|link| link.peer_address(peer))
+    }
+
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        Some(ChannelOccupancy::of(&self.tx, self.capacity))
+    }
+
+    /// Dead as soon as any link is, so a watchdog can restart it.
+    fn is_alive(&self) -> bool {
+        self.links.iter().all(|link| link.is_alive())
+    }
+}
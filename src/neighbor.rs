@@ -0,0 +1,63 @@
+use crate::clock::{Clock, SystemClock};
+use crate::types::{PeerId, Timestamp};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A directly connected peer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeighborInfo {
+    pub peer: PeerId,
+    pub link_quality: f32,
+    pub last_seen: Timestamp,
+}
+
+/// One-hop neighbours as reported by the transports, e.g. on
+/// `TransportEvent::PeerConnected` / `PeerDisconnected`.
+#[derive(Clone)]
+pub struct NeighborTable {
+    peers: Arc<RwLock<HashMap<PeerId, NeighborInfo>>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl NeighborTable {
+    pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            clock,
+        }
+    }
+
+    /// Record that `peer` was heard with the given link quality.
+    pub async fn update(&self, peer: PeerId, link_quality: f32) {
+        let info = NeighborInfo {
+            peer,
+            link_quality,
+            last_seen: self.clock.now(),
+        };
+        self.peers.write().await.insert(peer, info);
+    }
+
+    pub async fn remove(&self, peer: &PeerId) -> Option<NeighborInfo> {
+        self.peers.write().await.remove(peer)
+    }
+
+    pub async fn get(&self, peer: &PeerId) -> Option<NeighborInfo> {
+        self.peers.read().await.get(peer).copied()
+    }
+
+    /// Snapshot of all current neighbours.
+    pub async fn list(&self) -> Vec<NeighborInfo> {
+        self.peers.read().await.values().copied().collect()
+    }
+}
+
+impl Default for NeighborTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
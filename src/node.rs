@@ -0,0 +1,550 @@
+use crate::ack::AckSuppression;
+use crate::address_book::AddressBook;
+use crate::forwarding::{ControlledFlood, Forwarder};
+use crate::identity::Identity;
+use crate::inbound_queue::InboundQueue;
+use crate::message::{Message, MessageContent, ReceiptKind};
+use crate::message_manager::MessageManager;
+use crate::presence::PresenceTracker;
+use crate::qos::QosClass;
+use crate::routing::RoutingEngine;
+use crate::service::{service_advert, ServiceId};
+use crate::snapshot::{NodeStateSnapshot, SealedIdentities, SNAPSHOT_VERSION};
+use crate::transport::{Transport, TransportEvent};
+use crate::types::{MessageId, PeerId, UserId};
+use anyhow::Result;
+use futures::Stream;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+
+/// Route lifetime used by [`MeshNode::new`].
+pub const DEFAULT_ROUTE_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// A complete mesh node: owns the transport, routing table, message store and
+/// forwarding pipeline, and drives them from transport events. This is what
+/// an application or CLI builds on. Cheap to clone; all parts are shared.
+#[derive(Clone)]
+pub struct MeshNode {
+    identity: Identity,
+    local_peer: PeerId,
+    transport: Arc<dyn Transport>,
+    routing: RoutingEngine,
+    messages: MessageManager,
+    forwarder: Forwarder,
+    /// Services this node provides to anycast senders.
+    services: Arc<std::sync::RwLock<HashSet<ServiceId>>>,
+    inbound_queue: Option<InboundQueue>,
+    /// Set when this node acks unicasts it receives.
+    acks: Option<AckSuppression>,
+    presence: PresenceTracker,
+    /// Where addresses of newly connected peers are remembered.
+    address_book: Option<AddressBook>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl MeshNode {
+    /// Node speaking as `identity` on `transport`, reachable there as
+    /// `local_peer`. Forwards with [`ControlledFlood`] until
+    /// [`with_forwarder`](Self::with_forwarder) says otherwise.
+    pub fn new(
+        identity: Identity,
+        local_peer: PeerId,
+        transport: Arc<dyn Transport>,
+        messages: MessageManager,
+    ) -> Self {
+        messages.add_identity(identity.clone());
+        let forwarder = Forwarder::new(
+            identity.clone(),
+            local_peer,
+            Arc::new(ControlledFlood::default()),
+            transport.clone(),
+        )
+        .with_clock(messages.clock());
+        let (shutdown, _) = watch::channel(false);
+        let mut routing =
+            RoutingEngine::new(DEFAULT_ROUTE_MAX_AGE).with_local(local_peer, identity.user_id());
+        if let Some(latency) = transport.latency_hint() {
+            routing = routing.with_hop_latency(latency);
+        }
+        Self {
+            identity,
+            local_peer,
+            transport,
+            routing,
+            messages,
+            forwarder,
+            services: Arc::default(),
+            inbound_queue: None,
+            acks: None,
+            presence: PresenceTracker::default(),
+            address_book: None,
+            shutdown,
+        }
+    }
+
+    /// Use `routing` instead of a private routing table, e.g. one shared with
+    /// an [`AodvReactive`](crate::AodvReactive) strategy.
+    pub fn with_routing(mut self, routing: RoutingEngine) -> Self {
+        self.routing = routing.with_local(self.local_peer, self.identity.user_id());
+        self
+    }
+
+    /// Relay through `forwarder` instead of the default flooding pipeline.
+    pub fn with_forwarder(mut self, forwarder: Forwarder) -> Self {
+        self.forwarder = forwarder;
+        self
+    }
+
+    /// Process received messages from `queue`, most urgent first, on a
+    /// worker started by [`run`](Self::run), instead of in arrival order.
+    pub fn with_inbound_queue(mut self, queue: InboundQueue) -> Self {
+        self.inbound_queue = Some(queue);
+        self
+    }
+
+    /// Answer every unicast delivered to a local identity with a Delivered
+    /// receipt, acking repeated copies at most once per `acks` window.
+    pub fn with_auto_ack(mut self, acks: AckSuppression) -> Self {
+        self.acks = Some(acks);
+        self
+    }
+
+    /// Track presence announcements in `tracker` instead of a default one
+    /// with [`DEFAULT_PRESENCE_TIMEOUT`](crate::DEFAULT_PRESENCE_TIMEOUT).
+    pub fn with_presence(mut self, tracker: PresenceTracker) -> Self {
+        self.presence = tracker;
+        self
+    }
+
+    /// Record in `book` the address of every peer that connects, where the
+    /// transport reports one, so a restarted node can
+    /// [`reconnect`](AddressBook::reconnect) to it.
+    pub fn with_address_book(mut self, book: AddressBook) -> Self {
+        self.address_book = Some(book);
+        self
+    }
+
+    pub fn user_id(&self) -> UserId {
+        self.identity.user_id()
+    }
+
+    pub fn routing(&self) -> &RoutingEngine {
+        &self.routing
+    }
+
+    pub fn messages(&self) -> &MessageManager {
+        &self.messages
+    }
+
+    /// Roster of users whose presence announcements reached this node.
+    pub fn presence(&self) -> &PresenceTracker {
+        &self.presence
+    }
+
+    /// Create, sign and transmit a message: unicast along a known route,
+    /// broadcast otherwise.
+    pub async fn send(
+        &self,
+        content: MessageContent,
+        recipient: Option<UserId>,
+    ) -> Result<Message> {
+        let user = self.identity.user_id();
+        let msg = self
+            .messages
+            .create_message_as(Some(&user), recipient, content)
+            .await?;
+        self.transmit(&msg).await?;
+        Ok(msg)
+    }
+
+    /// Like [`send`](Self::send), with a QoS class attached.
+    pub async fn send_with_qos(
+        &self,
+        content: MessageContent,
+        recipient: Option<UserId>,
+        qos: QosClass,
+    ) -> Result<Message> {
+        let msg = self
+            .messages
+            .create_message_with_qos(self.identity.user_id(), recipient, content, qos)
+            .await?;
+        self.transmit(&msg).await?;
+        Ok(msg)
+    }
+
+    /// Create, sign and transmit one message for all of `recipients`. Each
+    /// node that delivers it to a recipient prunes that recipient before
+    /// relaying it on.
+    pub async fn send_to_many(
+        &self,
+        content: MessageContent,
+        recipients: Vec<UserId>,
+    ) -> Result<Message> {
+        let builder = Message::builder()
+            .sender(self.identity.user_id())
+            .content(content)
+            .to_many(recipients);
+        let msg = self.messages.create_from(builder).await?;
+        self.transmit(&msg).await?;
+        Ok(msg)
+    }
+
+    /// Start providing `service`: anycast messages for it that reach this
+    /// node are delivered here. Announce it with
+    /// [`advertise_services`](Self::advertise_services).
+    pub fn provide(&self, service: ServiceId) {
+        self.services.write().unwrap().insert(service);
+    }
+
+    pub fn provides(&self, service: &ServiceId) -> bool {
+        self.services.read().unwrap().contains(service)
+    }
+
+    /// Broadcast a signed advertisement of every service this node
+    /// provides. Call periodically, well within the route lifetime.
+    pub async fn advertise_services(&self) -> Result<Message> {
+        let services = self.services.read().unwrap().iter().copied().collect();
+        let msg = service_advert(&self.identity, services)?;
+        self.messages.mark_message_seen(&msg.id).await?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await?;
+        Ok(msg)
+    }
+
+    /// Create, sign and transmit a message for the nearest provider of
+    /// `service`, falling back to a broadcast if none is known.
+    pub async fn send_anycast(
+        &self,
+        content: MessageContent,
+        service: ServiceId,
+    ) -> Result<Message> {
+        let builder = Message::builder()
+            .sender(self.identity.user_id())
+            .content(content)
+            .anycast(service);
+        let msg = self.messages.create_from(builder).await?;
+        self.transmit(&msg).await?;
+        Ok(msg)
+    }
+
+    /// Recall `target`, a message this node sent: broadcast a signed
+    /// [`MessageContent::Recall`] so nodes still holding it drop it.
+    /// Best-effort; copies already delivered stay delivered.
+    pub async fn recall(&self, target: MessageId) -> Result<Message> {
+        let msg = self
+            .messages
+            .create_recall(self.identity.user_id(), target)
+            .await?;
+        self.transmit(&msg).await?;
+        Ok(msg)
+    }
+
+    /// Resend every message that is due per its QoS retransmission policy
+    /// (see [`MessageManager::due_retransmissions`]). Call periodically.
+    /// A failed send is logged and retried next time without using up an
+    /// attempt. Returns how many were resent.
+    pub async fn retransmit(&self) -> Result<usize> {
+        let mut sent = 0;
+        for msg in self.messages.due_retransmissions().await? {
+            if let Err(e) = self.transmit(&msg).await {
+                tracing::warn!("retransmission of {:?} failed: {e:#}", msg.id);
+                continue;
+            }
+            self.messages.record_retransmission(&msg.id).await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    async fn transmit(&self, msg: &Message) -> Result<()> {
+        let data = self.transport.encode_message(msg)?;
+        let next_hop = match (&msg.recipient, &msg.anycast) {
+            (Some(dest), _) => self.routing.next_hop(dest).await,
+            (None, Some(service)) => self
+                .routing
+                .nearest_provider(service)
+                .await
+                .map(|route| route.next_hop),
+            (None, None) => match msg.pending_recipients().as_slice() {
+                [only] => self.routing.next_hop(only).await,
+                _ => None,
+            },
+        };
+        match next_hop {
+            Some(peer) => self.transport.send(peer, data).await,
+            None => self.transport.broadcast(data).await,
+        }
+    }
+
+    /// Capture identities, routes, pending messages, dedup history and
+    /// sequence counters for
+    /// migration to another device. Identity keys are encrypted under
+    /// `passphrase`.
+    pub async fn export_state(&self, passphrase: &str) -> Result<NodeStateSnapshot> {
+        Ok(NodeStateSnapshot {
+            version: SNAPSHOT_VERSION,
+            created: SystemTime::now(),
+            primary: self.identity.user_id(),
+            default_identity: self.messages.default_identity().map(|id| id.user_id()),
+            identities: SealedIdentities::seal(&self.messages.identities(), passphrase)?,
+            routes: self.routing.dump().await,
+            pending: self.messages.pending_messages().await?,
+            seen: self.messages.seen_entries()?,
+            sequences: self.messages.sequence_entries()?,
+        })
+    }
+
+    /// Load a snapshot from [`export_state`](Self::export_state) into this
+    /// node. Build the node with
+    /// [`NodeStateSnapshot::primary_identity`] to keep the old user id;
+    /// routes, pending messages, sightings and sequence counters are merged
+    /// with any this node already has.
+    pub async fn import_state(&self, snapshot: &NodeStateSnapshot, passphrase: &str) -> Result<()> {
+        snapshot.check_version()?;
+        for identity in snapshot.identities.open(passphrase)? {
+            self.messages.add_identity(identity);
+        }
+        if let Some(user) = &snapshot.default_identity {
+            self.messages.set_default_identity(user)?;
+        }
+        self.routing.restore(&snapshot.routes).await;
+        self.messages.restore_pending(&snapshot.pending)?;
+        self.messages.restore_seen(&snapshot.seen)?;
+        self.messages.restore_sequences(&snapshot.sequences)?;
+        Ok(())
+    }
+
+    /// Messages delivered to this node from now on.
+    pub fn inbound(&self) -> impl Stream<Item = Message> + Send + 'static {
+        self.messages.subscribe_content(|_| true)
+    }
+
+    /// Process transport events until [`shutdown`](Self::shutdown) is
+    /// called or the transport closes its event channel. Meanwhile, reorder
+    /// gaps that outlive their hold time are skipped, held rebroadcasts are
+    /// released once assessed and write batches are flushed once due.
+    pub async fn run(&self) -> Result<()> {
+        let worker = self.inbound_queue.clone().map(|queue| {
+            let node = self.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (from, msg) = queue.next().await;
+                    if let Err(e) = node.handle_message(from, msg).await {
+                        tracing::debug!("dropping message from {from:?}: {e:#}");
+                    }
+                }
+            })
+        });
+        let reorder = self.messages.reorder_hold().map(|hold| {
+            let messages = self.messages.clone();
+            tokio::spawn(async move {
+                // Checking twice per hold skips a gap at most half a hold late.
+                let mut ticker = tokio::time::interval((hold / 2).max(Duration::from_millis(10)));
+                loop {
+                    ticker.tick().await;
+                    messages.flush_reorder().await;
+                }
+            })
+        });
+        let suppression = self.forwarder.suppression_delay().map(|delay| {
+            let forwarder = self.forwarder.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval((delay / 2).max(Duration::from_millis(10)));
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = forwarder.release_suppressed().await {
+                        tracing::warn!("releasing held rebroadcasts failed: {e:#}");
+                    }
+                }
+            })
+        });
+        let batching = self.messages.write_batching().map(|batching| {
+            let messages = self.messages.clone();
+            tokio::spawn(async move {
+                let period = (batching.max_delay / 2).max(Duration::from_millis(10));
+                let mut ticker = tokio::time::interval(period);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = messages.flush_due().await {
+                        tracing::warn!("flushing write batch failed: {e:#}");
+                    }
+                }
+            })
+        });
+        let result = self.receive_events().await;
+        for task in [worker, reorder, suppression, batching]
+            .into_iter()
+            .flatten()
+        {
+            task.abort();
+        }
+        result
+    }
+
+    async fn receive_events(&self) -> Result<()> {
+        let mut events = self.transport.subscribe_events();
+        let mut shutdown = self.shutdown.subscribe();
+        loop {
+            if *shutdown.borrow() {
+                return Ok(());
+            }
+            let event = tokio::select! {
+                _ = shutdown.changed() => continue,
+                event = events.recv() => event,
+            };
+            match event {
+                Ok(event) => self.handle_event(event).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("node lagged, {skipped} transport events dropped");
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+
+    /// Ask [`run`](Self::run) to return.
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    pub(crate) async fn handle_event(&self, event: TransportEvent) {
+        match event {
+            TransportEvent::DataReceived { peer, data } => {
+                if let Err(e) = self.receive_data(peer, &data).await {
+                    tracing::debug!("dropping message from {peer:?}: {e:#}");
+                }
+            }
+            TransportEvent::PeerConnected(peer) => {
+                if let (Some(book), Some(address)) =
+                    (&self.address_book, self.transport.peer_address(peer))
+                {
+                    if let Err(e) = book.record(peer, None, address) {
+                        tracing::warn!("failed to record address of {peer:?}: {e:#}");
+                    }
+                }
+                if let Err(e) = self.forwarder.send_routes(peer, &self.routing).await {
+                    tracing::warn!("route exchange with {peer:?} failed: {e:#}");
+                }
+            }
+            TransportEvent::PeerDisconnected(_) => {}
+            TransportEvent::Error(e) => tracing::warn!("transport error: {e}"),
+        }
+    }
+
+    /// Decode `data` and handle it now, or queue it by urgency.
+    async fn receive_data(&self, from: PeerId, data: &[u8]) -> Result<()> {
+        let mut msg = self.transport.decode_message(data)?;
+        msg.received_at = Some(self.messages.clock().now());
+        match &self.inbound_queue {
+            Some(queue) => {
+                if !queue.push(from, msg) {
+                    tracing::debug!("inbound queue full, dropping message from {from:?}");
+                }
+                Ok(())
+            }
+            None => self.handle_message(from, msg).await,
+        }
+    }
+
+    /// Dedup → validate → deliver locally and/or forward.
+    async fn handle_message(&self, from: PeerId, mut msg: Message) -> Result<()> {
+        if !self.messages.is_new_message(&msg.id).await {
+            // A neighbour relaying it too may make our rebroadcast redundant.
+            self.forwarder.overheard(msg.id);
+            // A retransmission may mean our receipt was lost.
+            return self.ack(&msg).await;
+        }
+        self.messages.screen_message(&msg).await?;
+        // Neither deliver nor relay what its sender has taken back.
+        if self.messages.is_recalled(&msg).await {
+            return Ok(());
+        }
+        if let MessageContent::Recall { .. } = &msg.content {
+            self.messages.handle_recall(&msg).await?;
+        }
+        self.presence.ingest(&msg);
+
+        if let MessageContent::Routing(_) = &msg.content {
+            self.routing
+                .apply_control(&msg, from, self.transport.link_quality())
+                .await?;
+        }
+        match msg.recipient {
+            // Addressed to one of our identities: deliver, never relay.
+            Some(recipient) if self.messages.is_local(&recipient) => {
+                if let MessageContent::Receipt { .. } = &msg.content {
+                    self.messages.handle_receipt(&msg).await?;
+                }
+                if self.messages.deliver_validated(msg.clone()).await? {
+                    self.ack(&msg).await?;
+                }
+            }
+            Some(_) => {
+                self.messages.mark_message_seen(&msg.id).await?;
+                self.forwarder.handle_incoming(&msg, from).await?;
+            }
+            // Multi-recipient: deliver if any pending recipient is ours, then
+            // relay to the rest with ours pruned.
+            None if !msg.recipients.is_empty() => {
+                let local: Vec<_> = msg
+                    .pending_recipients()
+                    .into_iter()
+                    .filter(|user| self.messages.is_local(user))
+                    .collect();
+                if local.is_empty() {
+                    self.messages.mark_message_seen(&msg.id).await?;
+                } else {
+                    self.messages.deliver_validated(msg.clone()).await?;
+                    msg.delivered_to.extend(local);
+                }
+                if !msg.pending_recipients().is_empty() {
+                    self.forwarder.handle_incoming(&msg, from).await?;
+                }
+            }
+            // Anycast for a service we provide: we are the nearest provider.
+            None if msg.anycast.is_some_and(|service| self.provides(&service)) => {
+                self.messages.deliver_validated(msg).await?;
+            }
+            None if msg.anycast.is_some() => {
+                self.messages.mark_message_seen(&msg.id).await?;
+                self.forwarder.handle_incoming(&msg, from).await?;
+            }
+            None => {
+                if let MessageContent::Routing(_) = &msg.content {
+                    self.messages.mark_message_seen(&msg.id).await?;
+                } else {
+                    self.messages.deliver_validated(msg.clone()).await?;
+                }
+                self.forwarder.handle_incoming(&msg, from).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send a Delivered receipt for `msg` if auto-ack is on, it is a
+    /// unicast for one of our identities, and it was not acked recently.
+    async fn ack(&self, msg: &Message) -> Result<()> {
+        let (Some(acks), Some(recipient)) = (&self.acks, msg.recipient) else {
+            return Ok(());
+        };
+        if !self.messages.is_local(&recipient)
+            || matches!(
+                msg.content,
+                MessageContent::Receipt { .. } | MessageContent::Routing(_)
+            )
+            || !acks.should_ack(msg.id)
+        {
+            return Ok(());
+        }
+        let mut receipt = Message::receipt(recipient, msg, ReceiptKind::Delivered);
+        if let Some(signer) = self.messages.signer(&recipient) {
+            receipt.sign(signer.as_ref())?;
+        }
+        self.messages.mark_message_seen(&receipt.id).await?;
+        self.transmit(&receipt).await
+    }
+}
@@ -0,0 +1,56 @@
+use crate::message_manager::MessageManager;
+use crate::routing::{RouteInfo, RoutingEngine};
+use crate::stats::{MeshStats, StatsSnapshot};
+use crate::transport::Transport;
+use crate::types::{PeerId, UserId};
+use std::sync::Arc;
+
+/// Introspection and admin facade over a running node, meant to be wired into
+/// a CLI or REST layer. Cheap to clone; all parts are shared.
+#[derive(Clone)]
+pub struct NodeControl {
+    routing: RoutingEngine,
+    messages: MessageManager,
+    transport: Arc<dyn Transport>,
+    stats: Arc<MeshStats>,
+}
+
+impl NodeControl {
+    pub fn new(
+        routing: RoutingEngine,
+        messages: MessageManager,
+        transport: Arc<dyn Transport>,
+        stats: Arc<MeshStats>,
+    ) -> Self {
+        Self {
+            routing,
+            messages,
+            transport,
+            stats,
+        }
+    }
+
+    /// Current routing table.
+    pub async fn routes(&self) -> Vec<RouteInfo> {
+        self.routing.dump().await
+    }
+
+    /// Directly connected peers.
+    pub async fn neighbors(&self) -> Vec<PeerId> {
+        self.transport.get_peers()
+    }
+
+    /// Sent unicast messages still waiting for a receipt.
+    pub async fn pending_count(&self) -> usize {
+        self.messages.pending_count().await
+    }
+
+    /// Drop the route to `destination`. Returns false if none existed.
+    pub async fn clear_route(&self, destination: &UserId) -> bool {
+        self.routing.invalidate(destination).await.is_some()
+    }
+
+    pub async fn stats_snapshot(&self) -> StatsSnapshot {
+        self.stats.snapshot()
+    }
+}
@@ -0,0 +1,197 @@
+use crate::forwarding::Forwarder;
+use crate::neighbor::NeighborTable;
+use crate::routing::RoutingEngine;
+use crate::types::UserId;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+/// Fraction of the reachable set that must vanish between two checks for
+/// [`PartitionDetector`] to report a partition.
+pub const DEFAULT_PARTITION_THRESHOLD: f32 = 0.5;
+
+/// Fewest destinations that must vanish together to count as a partition
+/// rather than ordinary churn.
+pub const DEFAULT_MIN_PARTITION_SIZE: usize = 2;
+
+/// Published on [`PartitionDetector::subscribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionEvent {
+    /// A large part of the mesh became unreachable at once.
+    Partitioned {
+        lost: Vec<UserId>,
+        /// Destinations still reachable.
+        reachable: usize,
+    },
+    /// Enough of the lost nodes are reachable again.
+    Healed {
+        regained: Vec<UserId>,
+        reachable: usize,
+    },
+}
+
+/// Snapshot of local connectivity, from [`PartitionDetector::report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectivityReport {
+    /// Destinations with a route.
+    pub reachable: usize,
+    /// Directly connected peers.
+    pub neighbors: usize,
+    /// Nodes lost in the current partition; empty when the mesh is whole.
+    pub partitioned: Vec<UserId>,
+}
+
+#[derive(Default)]
+struct DetectorState {
+    /// Destinations reachable at the previous check.
+    last_reachable: HashSet<UserId>,
+    /// Nodes lost in the current partition, if any.
+    lost: HashSet<UserId>,
+}
+
+/// Watches the routing and neighbour tables for a sudden collapse of the
+/// reachable set, a likely network partition, and for its recovery.
+#[derive(Clone)]
+pub struct PartitionDetector {
+    routing: RoutingEngine,
+    neighbors: NeighborTable,
+    threshold: f32,
+    min_size: usize,
+    forwarder: Option<Forwarder>,
+    state: Arc<Mutex<DetectorState>>,
+    events: broadcast::Sender<PartitionEvent>,
+}
+
+impl PartitionDetector {
+    pub fn new(routing: RoutingEngine, neighbors: NeighborTable) -> Self {
+        Self {
+            routing,
+            neighbors,
+            threshold: DEFAULT_PARTITION_THRESHOLD,
+            min_size: DEFAULT_MIN_PARTITION_SIZE,
+            forwarder: None,
+            state: Arc::new(Mutex::new(DetectorState::default())),
+            events: broadcast::channel(16).0,
+        }
+    }
+
+    /// Report a partition when at least `threshold` (0.0 to 1.0) of the
+    /// reachable set, and at least `min_size` nodes, disappear between two
+    /// checks. A partition heals once `threshold` of its nodes are back.
+    pub fn with_threshold(mut self, threshold: f32, min_size: usize) -> Self {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self.min_size = min_size.max(1);
+        self
+    }
+
+    /// On healing, send our routing table to every neighbour through
+    /// `forwarder` so both sides converge quickly.
+    pub fn with_route_exchange(mut self, forwarder: Forwarder) -> Self {
+        self.forwarder = Some(forwarder);
+        self
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<PartitionEvent> {
+        self.events.subscribe()
+    }
+
+    pub async fn report(&self) -> ConnectivityReport {
+        let reachable = self.reachable().await.len();
+        let neighbors = self.neighbors.list().await.len();
+        let mut partitioned: Vec<_> = self.state.lock().unwrap().lost.iter().copied().collect();
+        partitioned.sort_by_key(|user| user.0);
+        ConnectivityReport {
+            reachable,
+            neighbors,
+            partitioned,
+        }
+    }
+
+    /// Compare the reachable set with the previous check and publish a
+    /// [`PartitionEvent`] if the mesh split or healed.
+    pub async fn check(&self) -> Option<PartitionEvent> {
+        let reachable = self.reachable().await;
+        let event = {
+            let mut state = self.state.lock().unwrap();
+            let event = if state.lost.is_empty() {
+                self.detect_split(&state.last_reachable, &reachable)
+                    .inspect(|lost| state.lost = lost.iter().copied().collect())
+                    .map(|lost| PartitionEvent::Partitioned {
+                        lost,
+                        reachable: reachable.len(),
+                    })
+            } else {
+                let regained = sorted(state.lost.intersection(&reachable).copied());
+                let healed = regained.len() as f32 >= self.threshold * state.lost.len() as f32;
+                healed.then(|| {
+                    state.lost.clear();
+                    PartitionEvent::Healed {
+                        regained,
+                        reachable: reachable.len(),
+                    }
+                })
+            };
+            state.last_reachable = reachable;
+            event
+        };
+        let event = event?;
+        match &event {
+            PartitionEvent::Partitioned { lost, .. } => {
+                tracing::warn!("mesh partition: {} nodes unreachable", lost.len());
+            }
+            PartitionEvent::Healed { regained, .. } => {
+                tracing::info!("mesh partition healed: {} nodes back", regained.len());
+                self.exchange_routes().await;
+            }
+        }
+        let _ = self.events.send(event.clone());
+        Some(event)
+    }
+
+    fn detect_split(&self, before: &HashSet<UserId>, now: &HashSet<UserId>) -> Option<Vec<UserId>> {
+        let lost = sorted(before.difference(now).copied());
+        let dramatic = lost.len() >= self.min_size
+            && lost.len() as f32 >= self.threshold * before.len() as f32;
+        dramatic.then_some(lost)
+    }
+
+    async fn reachable(&self) -> HashSet<UserId> {
+        self.routing
+            .dump()
+            .await
+            .into_iter()
+            .map(|route| route.destination)
+            .collect()
+    }
+
+    async fn exchange_routes(&self) {
+        let Some(forwarder) = &self.forwarder else {
+            return;
+        };
+        for neighbor in self.neighbors.list().await {
+            if let Err(e) = forwarder.send_routes(neighbor.peer, &self.routing).await {
+                tracing::warn!("route exchange with {:?} failed: {e:#}", neighbor.peer);
+            }
+        }
+    }
+
+    /// Check every `interval` until the task is aborted.
+    pub fn spawn(&self, interval: Duration) -> JoinHandle<()> {
+        let detector = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                detector.check().await;
+            }
+        })
+    }
+}
+
+fn sorted(users: impl Iterator<Item = UserId>) -> Vec<UserId> {
+    let mut users: Vec<_> = users.collect();
+    users.sort_by_key(|user| user.0);
+    users
+}
@@ -0,0 +1,196 @@
+use crate::clock::{Clock, SystemClock};
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::routing_control::RoutingControl;
+use crate::types::{Timestamp, UserId};
+use crate::wire::WireFormat;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Frame sizes tried by a probe round, largest first.
+pub const DEFAULT_PROBE_SIZES: [usize; 6] = [1500, 1200, 1024, 512, 256, 128];
+
+/// How long a discovered path MTU is trusted before it must be re-probed.
+pub const DEFAULT_PATH_MTU_TTL: Duration = Duration::from_secs(600);
+
+/// Outstanding probe: destination, encoded size when sent, and expiry.
+type PendingProbe = (UserId, usize, Timestamp);
+
+/// Per-destination path-MTU discovery. The sender emits
+/// [`RoutingControl::MtuProbe`]s padded to several frame sizes; relays drop
+/// probes their link cannot carry (see
+/// [`Forwarder::handle_incoming`](crate::Forwarder::handle_incoming)), and
+/// the destination acknowledges each one that arrives. The largest
+/// acknowledged size is the path MTU, used in place of the local link MTU
+/// when fragmenting (see
+/// [`FragmentSender::with_path_mtu`](crate::FragmentSender::with_path_mtu)).
+#[derive(Clone)]
+pub struct PathMtu {
+    identity: Identity,
+    /// Largest acknowledged frame size per destination, and its expiry.
+    paths: Arc<Mutex<HashMap<UserId, (usize, Timestamp)>>>,
+    pending: Arc<Mutex<HashMap<u32, PendingProbe>>>,
+    next_probe_id: Arc<AtomicU32>,
+    ttl: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl PathMtu {
+    pub fn new(identity: Identity) -> Self {
+        Self::with_clock(identity, DEFAULT_PATH_MTU_TTL, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(identity: Identity, ttl: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            identity,
+            paths: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            next_probe_id: Arc::new(AtomicU32::new(0)),
+            ttl,
+            clock,
+        }
+    }
+
+    /// Signed probes to `destination`, one per entry of `sizes`, padded so
+    /// each encodes to that many bytes in `format` (exact for bincode, at
+    /// least that many for text formats).
+    pub fn probes(
+        &self,
+        destination: UserId,
+        sizes: &[usize],
+        format: WireFormat,
+    ) -> Result<Vec<Message>> {
+        let mut probes = Vec::with_capacity(sizes.len());
+        for &size in sizes {
+            let probe_id = self.next_probe_id.fetch_add(1, Ordering::Relaxed);
+            let mut msg = self.probe(destination, probe_id, Vec::new())?;
+            let overhead = format.encode(&msg)?.len();
+            msg = self.probe(
+                destination,
+                probe_id,
+                vec![0; size.saturating_sub(overhead)],
+            )?;
+            let sent = format.encode(&msg)?.len();
+            let expires = self.clock.now() + self.ttl;
+            self.pending
+                .lock()
+                .unwrap()
+                .insert(probe_id, (destination, sent, expires));
+            probes.push(msg);
+        }
+        Ok(probes)
+    }
+
+    fn probe(&self, destination: UserId, probe_id: u32, padding: Vec<u8>) -> Result<Message> {
+        let control = RoutingControl::MtuProbe {
+            origin: self.identity.user_id(),
+            destination,
+            probe_id,
+            padding,
+        };
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            Some(destination),
+            MessageContent::Routing(control),
+        );
+        msg.sign(&self.identity)?;
+        Ok(msg)
+    }
+
+    /// At the destination: acknowledge a probe. Returns `None` for anything
+    /// but a probe for us.
+    pub fn handle_probe(&self, msg: &Message) -> Result<Option<Message>> {
+        let MessageContent::Routing(RoutingControl::MtuProbe {
+            origin,
+            destination,
+            probe_id,
+            ..
+        }) = &msg.content
+        else {
+            return Ok(None);
+        };
+        if *destination != self.identity.user_id() {
+            return Ok(None);
+        }
+        let ack = RoutingControl::MtuAck {
+            origin: *origin,
+            destination: *destination,
+            probe_id: *probe_id,
+        };
+        let mut reply = Message::new(
+            self.identity.user_id(),
+            Some(*origin),
+            MessageContent::Routing(ack),
+        );
+        reply.sign(&self.identity)?;
+        Ok(Some(reply))
+    }
+
+    /// At the sender: record the size of an acknowledged probe. Returns
+    /// false if `msg` is not an ack for one of our outstanding probes.
+    pub fn handle_ack(&self, msg: &Message) -> Result<bool> {
+        let MessageContent::Routing(RoutingControl::MtuAck {
+            origin,
+            destination,
+            probe_id,
+        }) = &msg.content
+        else {
+            return Ok(false);
+        };
+        if *origin != self.identity.user_id() {
+            return Ok(false);
+        }
+        msg.verify_signature()?;
+        if msg.sender != *destination {
+            anyhow::bail!("MTU ack not signed by the probed destination");
+        }
+        let now = self.clock.now();
+        let Some((probed, size, _)) = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(probe_id)
+            .filter(|(probed, _, expires)| probed == destination && *expires > now)
+        else {
+            return Ok(false);
+        };
+        let mut paths = self.paths.lock().unwrap();
+        let current = paths
+            .get(&probed)
+            .filter(|(_, expires)| *expires > now)
+            .map_or(0, |(mtu, _)| *mtu);
+        paths.insert(probed, (current.max(size), now + self.ttl));
+        Ok(true)
+    }
+
+    /// Discovered path MTU to `destination`, if a probe succeeded recently.
+    pub fn path_mtu(&self, destination: &UserId) -> Option<usize> {
+        let now = self.clock.now();
+        let paths = self.paths.lock().unwrap();
+        let (mtu, expires) = paths.get(destination)?;
+        (*expires > now).then_some(*mtu)
+    }
+
+    /// Frame size to fragment for when sending to `destination` over a link
+    /// with `link_mtu`: the smaller of the two once the path is known.
+    pub fn effective_mtu(&self, destination: &UserId, link_mtu: usize) -> usize {
+        self.path_mtu(destination)
+            .map_or(link_mtu, |mtu| mtu.min(link_mtu))
+    }
+
+    /// Forget expired path MTUs and probes that were never acknowledged.
+    pub fn purge_expired(&self) {
+        let now = self.clock.now();
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|_, (_, _, expires)| *expires > now);
+        self.paths
+            .lock()
+            .unwrap()
+            .retain(|_, (_, expires)| *expires > now);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::clock::{Clock, SystemClock};
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::transport::Transport;
+use crate::types::{Timestamp, UserId};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Users not heard from for this long are shown as offline by default.
+pub const DEFAULT_PRESENCE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Availability a user announces in [`MessageContent::Presence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresenceStatus {
+    Online,
+    Busy,
+    Offline,
+}
+
+/// Roster of users and their last announced status, for a contacts list.
+#[derive(Clone)]
+pub struct PresenceTracker {
+    last_seen: Arc<Mutex<HashMap<UserId, (PresenceStatus, Timestamp)>>>,
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl PresenceTracker {
+    /// Users silent for longer than `timeout` are reported as offline.
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_clock(timeout, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(timeout: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            last_seen: Arc::new(Mutex::new(HashMap::new())),
+            timeout,
+            clock,
+        }
+    }
+
+    /// Record the presence announcement in `msg`. Returns false if `msg` is
+    /// not a presence message.
+    pub fn ingest(&self, msg: &Message) -> bool {
+        let MessageContent::Presence { status } = msg.content else {
+            return false;
+        };
+        self.record(msg.sender, status);
+        true
+    }
+
+    /// Record that `user` announced `status` just now.
+    pub fn record(&self, user: UserId, status: PresenceStatus) {
+        let now = self.clock.now();
+        self.last_seen.lock().unwrap().insert(user, (status, now));
+    }
+
+    /// Current status of `user`, or `None` if never heard from.
+    pub fn status(&self, user: &UserId) -> Option<PresenceStatus> {
+        let (status, seen) = *self.last_seen.lock().unwrap().get(user)?;
+        Some(self.effective(status, seen))
+    }
+
+    /// Every known user with their current status and last-seen time.
+    pub fn roster(&self) -> Vec<(UserId, PresenceStatus, Timestamp)> {
+        self.last_seen
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(user, &(status, seen))| (*user, self.effective(status, seen), seen))
+            .collect()
+    }
+
+    fn effective(&self, status: PresenceStatus, seen: Timestamp) -> PresenceStatus {
+        let stale = self
+            .clock
+            .now()
+            .duration_since(seen)
+            .is_ok_and(|age| age > self.timeout);
+        if stale {
+            PresenceStatus::Offline
+        } else {
+            status
+        }
+    }
+}
+
+impl Default for PresenceTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_PRESENCE_TIMEOUT)
+    }
+}
+
+/// Periodically broadcasts our own signed presence announcement.
+#[derive(Clone)]
+pub struct PresenceBeacon {
+    identity: Identity,
+    transport: Arc<dyn Transport>,
+    status: Arc<Mutex<PresenceStatus>>,
+    interval: Duration,
+}
+
+impl PresenceBeacon {
+    /// Announce every `interval`, starting as [`PresenceStatus::Online`].
+    /// Keep `interval` well below the receivers' presence timeout.
+    pub fn new(identity: Identity, transport: Arc<dyn Transport>, interval: Duration) -> Self {
+        Self {
+            identity,
+            transport,
+            status: Arc::new(Mutex::new(PresenceStatus::Online)),
+            interval,
+        }
+    }
+
+    /// Change the announced status; takes effect at the next announcement.
+    pub fn set_status(&self, status: PresenceStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    /// Broadcast one presence announcement.
+    pub async fn announce_once(&self) -> Result<()> {
+        let status = *self.status.lock().unwrap();
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Presence { status },
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await
+    }
+
+    /// Announce every `interval` until the task is aborted.
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let beacon = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(beacon.interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = beacon.announce_once().await {
+                    tracing::warn!("presence announcement failed: {e:#}");
+                }
+            }
+        })
+    }
+}
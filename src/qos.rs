@@ -0,0 +1,131 @@
+use crate::message::MessagePriority;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Lifetime given to [`QosClass::RealTime`] messages: stale real-time data
+/// is worthless, so it is not kept around.
+pub const REALTIME_TTL: Duration = Duration::from_secs(30);
+
+/// Retransmission schedule of [`QosClass::Reliable`] messages.
+pub const DEFAULT_RETRANSMIT: RetransmitPolicy = RetransmitPolicy {
+    interval: Duration::from_secs(30),
+    max_attempts: 5,
+};
+
+/// Named quality-of-service class attached to a message, bundling the
+/// individual delivery knobs (see [`QosClass::policy`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QosClass {
+    /// Tracked until a delivery receipt arrives and retransmitted meanwhile.
+    Reliable,
+    /// Latency-sensitive: short TTL, queued ahead of normal traffic, never
+    /// retransmitted or carried for later delivery.
+    RealTime,
+    /// Fire-and-forget: sent once and shed first under congestion.
+    BestEffort,
+}
+
+/// When to resend a message that has not been acknowledged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetransmitPolicy {
+    /// Wait between attempts.
+    pub interval: Duration,
+    /// Total transmissions, including the first.
+    pub max_attempts: u32,
+}
+
+impl RetransmitPolicy {
+    /// This schedule adjusted to `priority`: Emergency messages are retried
+    /// four times as often and twice as many times, Urgent ones twice as
+    /// often, Background ones half as often. Normal keeps it unchanged.
+    pub fn for_priority(self, priority: MessagePriority) -> Self {
+        let (interval, max_attempts) = match priority {
+            MessagePriority::Emergency => (self.interval / 4, self.max_attempts.saturating_mul(2)),
+            MessagePriority::Urgent => (self.interval / 2, self.max_attempts),
+            MessagePriority::Normal => (self.interval, self.max_attempts),
+            MessagePriority::Background => (self.interval.saturating_mul(2), self.max_attempts),
+        };
+        Self {
+            interval,
+            max_attempts,
+        }
+    }
+}
+
+/// The concrete behaviour a [`QosClass`] stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QosPolicy {
+    /// `None` means the message is sent once and not tracked for receipts.
+    pub retransmit: Option<RetransmitPolicy>,
+    /// Priority used for queueing and admission control.
+    pub priority: MessagePriority,
+    /// Overrides the message TTL when set.
+    pub ttl: Option<Duration>,
+    /// Whether relays may hold the message for store-and-forward delivery.
+    pub store_and_forward: bool,
+}
+
+impl QosClass {
+    pub fn policy(self) -> QosPolicy {
+        match self {
+            QosClass::Reliable => QosPolicy {
+                retransmit: Some(DEFAULT_RETRANSMIT),
+                priority: MessagePriority::Normal,
+                ttl: None,
+                store_and_forward: true,
+            },
+            QosClass::RealTime => QosPolicy {
+                retransmit: None,
+                priority: MessagePriority::Urgent,
+                ttl: Some(REALTIME_TTL),
+                store_and_forward: false,
+            },
+            QosClass::BestEffort => QosPolicy {
+                retransmit: None,
+                priority: MessagePriority::Background,
+                ttl: None,
+                store_and_forward: true,
+            },
+        }
+    }
+}
+
+/// Per-recipient cap on retransmissions, so a flaky peer cannot spend the
+/// link retrying low-priority traffic. Within each `window` a recipient
+/// gets `per_peer` retransmissions in total, of which each priority may use
+/// at most its share; retries beyond that are deferred to a later window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetransmitBudget {
+    pub window: Duration,
+    pub per_peer: u32,
+    pub emergency: f32,
+    pub urgent: f32,
+    pub normal: f32,
+    pub background: f32,
+}
+
+impl Default for RetransmitBudget {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            per_peer: 20,
+            emergency: 1.0,
+            urgent: 0.5,
+            normal: 0.3,
+            background: 0.1,
+        }
+    }
+}
+
+impl RetransmitBudget {
+    /// Retransmissions a recipient may spend on `priority` per window.
+    pub fn allowance(&self, priority: MessagePriority) -> u32 {
+        let share = match priority {
+            MessagePriority::Emergency => self.emergency,
+            MessagePriority::Urgent => self.urgent,
+            MessagePriority::Normal => self.normal,
+            MessagePriority::Background => self.background,
+        };
+        (self.per_peer as f32 * share.clamp(0.0, 1.0)).floor() as u32
+    }
+}
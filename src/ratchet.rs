@@ -0,0 +1,322 @@
+use crate::identity::{Identity, SessionKey};
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{Context, Result};
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+/// Most message keys kept for out-of-order delivery, and most keys skipped
+/// in one step, so a forged counter cannot make us derive millions of keys.
+pub const MAX_SKIPPED_KEYS: usize = 256;
+
+/// Sessions kept per pair of users. More than one exists briefly when both
+/// open a session at once; the one last heard from is used to send.
+pub const MAX_SESSIONS_PER_PEER: usize = 4;
+
+type Key = [u8; 32];
+
+/// Opens a session: the initiator's session key and the ephemeral key it
+/// agreed the session with, which is also its first ratchet key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub identity: SessionKey,
+    pub ephemeral: Key,
+}
+
+/// Header sent in clear (but authenticated) with each session message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RatchetHeader {
+    /// Sender's current ratchet public key.
+    pub ratchet_key: Key,
+    /// Index of this message in the sender's current chain.
+    pub n: u32,
+    /// Length of the sender's previous chain, so skipped keys can be kept.
+    pub previous_n: u32,
+    /// Carried by the initiator's messages until the peer answers, so any
+    /// of them can set up the session at the other end.
+    pub handshake: Option<Handshake>,
+}
+
+/// Encrypted session message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RatchetEnvelope {
+    pub header: RatchetHeader,
+    pub ciphertext: Vec<u8>,
+}
+
+/// One side of a Double-Ratchet-lite session between two users.
+///
+/// Every message is encrypted with its own key from a hash chain, and the
+/// chains are re-seeded by a fresh Diffie-Hellman exchange whenever the
+/// conversation changes direction, so compromising one message key exposes
+/// neither earlier nor later messages. Sessions use the users' X25519
+/// [`SessionKey`]s, never their signing keys. The initiator, which must know
+/// the peer's session key, agrees the root with a fresh ephemeral key and
+/// sends a [`Handshake`] until it hears back, so no prekey server or extra
+/// round trip is needed; the responder can send once the first message
+/// arrives.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RatchetSession {
+    root: Key,
+    dh_self: Key,
+    dh_remote: Option<Key>,
+    send_chain: Option<Key>,
+    send_n: u32,
+    previous_n: u32,
+    recv_chain: Option<Key>,
+    recv_n: u32,
+    skipped: Vec<(Key, u32, Key)>,
+    /// Sent until the peer answers; set on the initiator only.
+    handshake: Option<Handshake>,
+    /// Ephemeral key of the handshake a responder session was opened by.
+    opened_by: Option<Key>,
+}
+
+impl RatchetSession {
+    /// Open a session from `local` to the owner of `peer`.
+    pub fn initiate(local: &Identity, peer: &SessionKey) -> Result<Self> {
+        peer.verify()?;
+        let ephemeral: Key = rand::random();
+        let root = handshake_root(
+            &x25519(local.x25519_secret(), peer.public),
+            &x25519(ephemeral, peer.public),
+        );
+        let (root, send_chain) = kdf_root(&root, &x25519(ephemeral, peer.public));
+        Ok(Self {
+            root,
+            dh_self: ephemeral,
+            dh_remote: Some(peer.public),
+            send_chain: Some(send_chain),
+            send_n: 0,
+            previous_n: 0,
+            recv_chain: None,
+            recv_n: 0,
+            skipped: Vec::new(),
+            handshake: Some(Handshake {
+                identity: local.session_key(),
+                ephemeral: x25519(ephemeral, X25519_BASEPOINT_BYTES),
+            }),
+            opened_by: None,
+        })
+    }
+
+    /// Accept a session opened to `local` by `handshake`. Our session key is
+    /// the first ratchet key; the initiator's first message ratchets past it.
+    pub fn respond(local: &Identity, handshake: &Handshake) -> Result<Self> {
+        handshake.identity.verify()?;
+        let secret = local.x25519_secret();
+        let root = handshake_root(
+            &x25519(secret, handshake.identity.public),
+            &x25519(secret, handshake.ephemeral),
+        );
+        Ok(Self {
+            root,
+            dh_self: secret,
+            dh_remote: None,
+            send_chain: None,
+            send_n: 0,
+            previous_n: 0,
+            recv_chain: None,
+            recv_n: 0,
+            skipped: Vec::new(),
+            handshake: None,
+            opened_by: Some(handshake.ephemeral),
+        })
+    }
+
+    /// Ephemeral key of the handshake that opened this session at the peer.
+    pub fn opened_by(&self) -> Option<Key> {
+        self.opened_by
+    }
+
+    /// Encrypt `plaintext` with the next message key.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<RatchetEnvelope> {
+        let chain = self.send_chain.context("session has no sending chain")?;
+        let (next, message_key) = kdf_chain(&chain);
+        let header = RatchetHeader {
+            ratchet_key: x25519(self.dh_self, X25519_BASEPOINT_BYTES),
+            n: self.send_n,
+            previous_n: self.previous_n,
+            handshake: self.handshake.clone(),
+        };
+        self.send_chain = Some(next);
+        self.send_n += 1;
+        let ciphertext = seal(&message_key, &header, plaintext)?;
+        Ok(RatchetEnvelope { header, ciphertext })
+    }
+
+    /// Decrypt `envelope`, advancing the ratchet. On error the session is
+    /// left unchanged.
+    pub fn decrypt(&mut self, envelope: &RatchetEnvelope) -> Result<Vec<u8>> {
+        let mut next = self.clone();
+        let plaintext = next.decrypt_inner(envelope)?;
+        // The peer has the session; no need to keep opening it.
+        next.handshake = None;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    fn decrypt_inner(&mut self, envelope: &RatchetEnvelope) -> Result<Vec<u8>> {
+        let header = &envelope.header;
+        if let Some(pos) = self
+            .skipped
+            .iter()
+            .position(|(key, n, _)| *key == header.ratchet_key && *n == header.n)
+        {
+            let (_, _, message_key) = self.skipped.remove(pos);
+            return open(&message_key, header, &envelope.ciphertext);
+        }
+        if Some(header.ratchet_key) != self.dh_remote {
+            self.skip_until(header.previous_n)?;
+            self.dh_ratchet(header.ratchet_key);
+        }
+        self.skip_until(header.n)?;
+        let chain = self.recv_chain.context("no receiving chain for message")?;
+        let (next, message_key) = kdf_chain(&chain);
+        self.recv_chain = Some(next);
+        self.recv_n += 1;
+        open(&message_key, header, &envelope.ciphertext)
+    }
+
+    /// Store keys for messages `recv_n..until` of the current receiving
+    /// chain, which have not arrived yet.
+    fn skip_until(&mut self, until: u32) -> Result<()> {
+        let (Some(mut chain), Some(remote)) = (self.recv_chain, self.dh_remote) else {
+            return Ok(());
+        };
+        if until.saturating_sub(self.recv_n) as usize > MAX_SKIPPED_KEYS {
+            anyhow::bail!("too many skipped messages");
+        }
+        while self.recv_n < until {
+            let (next, message_key) = kdf_chain(&chain);
+            self.skipped.push((remote, self.recv_n, message_key));
+            chain = next;
+            self.recv_n += 1;
+        }
+        self.recv_chain = Some(chain);
+        if self.skipped.len() > MAX_SKIPPED_KEYS {
+            let excess = self.skipped.len() - MAX_SKIPPED_KEYS;
+            self.skipped.drain(..excess);
+        }
+        Ok(())
+    }
+
+    fn dh_ratchet(&mut self, remote: Key) {
+        self.previous_n = self.send_n;
+        self.send_n = 0;
+        self.recv_n = 0;
+        self.dh_remote = Some(remote);
+        let (root, recv_chain) = kdf_root(&self.root, &x25519(self.dh_self, remote));
+        self.dh_self = rand::random();
+        let (root, send_chain) = kdf_root(&root, &x25519(self.dh_self, remote));
+        self.root = root;
+        self.recv_chain = Some(recv_chain);
+        self.send_chain = Some(send_chain);
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Key {
+    let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data);
+    tag.as_ref().try_into().expect("SHA-256 output is 32 bytes")
+}
+
+/// Initial root key from the static and ephemeral agreements.
+fn handshake_root(static_dh: &Key, ephemeral_dh: &Key) -> Key {
+    let mut ikm = static_dh.to_vec();
+    ikm.extend_from_slice(ephemeral_dh);
+    hmac_sha256(&ikm, b"DisasterMesh session root")
+}
+
+/// Seal `local`'s stored sessions with a key only it can derive, so a copy
+/// of the store alone does not reveal them. `aad` binds them to their slot.
+pub(crate) fn seal_sessions(
+    local: &Identity,
+    aad: &[u8],
+    sessions: &[RatchetSession],
+) -> Result<Vec<u8>> {
+    let nonce: [u8; 12] = rand::random();
+    let mut sealed = nonce.to_vec();
+    sealed.extend(
+        storage_cipher(local)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &bincode::serialize(sessions)?,
+                    aad,
+                },
+            )
+            .map_err(|_| anyhow::anyhow!("encryption failed"))?,
+    );
+    Ok(sealed)
+}
+
+/// Inverse of [`seal_sessions`].
+pub(crate) fn open_sessions(
+    local: &Identity,
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<RatchetSession>> {
+    if sealed.len() < 12 {
+        anyhow::bail!("stored sessions too short");
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let plain = storage_cipher(local)
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("cannot decrypt stored sessions"))?;
+    Ok(bincode::deserialize(&plain)?)
+}
+
+fn storage_cipher(local: &Identity) -> Aes256Gcm {
+    let key = hmac_sha256(&local.x25519_secret(), b"DisasterMesh session storage");
+    Aes256Gcm::new_from_slice(&key).expect("32-byte key")
+}
+
+/// Root KDF (HKDF-SHA256 with the root key as salt): new root and chain key.
+fn kdf_root(root: &Key, dh: &Key) -> (Key, Key) {
+    let prk = hmac_sha256(root, dh);
+    let new_root = hmac_sha256(&prk, b"DisasterMesh ratchet\x01");
+    let mut info = new_root.to_vec();
+    info.extend_from_slice(b"DisasterMesh ratchet\x02");
+    (new_root, hmac_sha256(&prk, &info))
+}
+
+/// Chain KDF: next chain key and this step's message key.
+fn kdf_chain(chain: &Key) -> (Key, Key) {
+    (hmac_sha256(chain, &[0x02]), hmac_sha256(chain, &[0x01]))
+}
+
+fn seal(message_key: &Key, header: &RatchetHeader, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let aad = bincode::serialize(header)?;
+    // Each key encrypts exactly one message, so a fixed nonce is safe.
+    Aes256Gcm::new_from_slice(message_key)
+        .expect("32-byte key")
+        .encrypt(
+            Nonce::from_slice(&[0; 12]),
+            Payload {
+                msg: plaintext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("encryption failed"))
+}
+
+fn open(message_key: &Key, header: &RatchetHeader, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    let aad = bincode::serialize(header)?;
+    Aes256Gcm::new_from_slice(message_key)
+        .expect("32-byte key")
+        .decrypt(
+            Nonce::from_slice(&[0; 12]),
+            Payload {
+                msg: ciphertext,
+                aad: &aad,
+            },
+        )
+        .map_err(|_| anyhow::anyhow!("decryption failed"))
+}
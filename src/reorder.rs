@@ -0,0 +1,182 @@
+use crate::clock::{Clock, SystemClock};
+use crate::message::Message;
+use crate::types::{Timestamp, UserId};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+/// Default time a [`ReorderBuffer`] waits for a missing message before
+/// skipping past it.
+pub const DEFAULT_REORDER_HOLD: Duration = Duration::from_secs(5);
+
+/// One sender's stream to one recipient (`None` for its broadcasts).
+type ChannelKey = (UserId, Option<UserId>);
+
+struct Channel {
+    /// Sequence number to release next.
+    next: u64,
+    held: BTreeMap<u64, Message>,
+    /// When the current gap was first noticed.
+    gap_since: Option<Timestamp>,
+}
+
+impl Channel {
+    fn starting_at(next: u64) -> Self {
+        Self {
+            next,
+            held: BTreeMap::new(),
+            gap_since: None,
+        }
+    }
+
+    /// Pop held messages that continue the sequence.
+    fn release(&mut self, now: Timestamp) -> Vec<Message> {
+        let mut ready = Vec::new();
+        while let Some(msg) = self.held.remove(&self.next) {
+            ready.push(msg);
+            self.next += 1;
+        }
+        self.gap_since = match (self.held.is_empty(), ready.is_empty()) {
+            (true, _) => None,
+            (false, true) => self.gap_since.or(Some(now)),
+            // Progress was made; the remaining gap is a new one.
+            (false, false) => Some(now),
+        };
+        ready
+    }
+}
+
+/// Receiver-side buffer restoring each sender's order using
+/// [`Message::seq`]. Messages are released in sequence; after a gap they
+/// are held for up to the hold time, then the buffer gives up on the
+/// missing ones and moves on. Unsequenced messages pass straight through,
+/// and anything at or below what was already released is dropped.
+///
+/// A stream first seen mid-way (message 1 lost, or sent before this buffer
+/// existed) waits out one hold time and then starts at its lowest held
+/// message; give the buffer a store to resume streams across restarts.
+#[derive(Clone)]
+pub struct ReorderBuffer {
+    channels: Arc<RwLock<HashMap<ChannelKey, Channel>>>,
+    hold: Duration,
+    clock: Arc<dyn Clock>,
+    /// Next sequence number per stream, if persisted.
+    store: Option<sled::Tree>,
+}
+
+impl ReorderBuffer {
+    pub fn new(hold: Duration) -> Self {
+        Self::with_clock(hold, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(hold: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            channels: Arc::new(RwLock::new(HashMap::new())),
+            hold,
+            clock,
+            store: None,
+        }
+    }
+
+    /// Remember each stream's position in `tree` so a restarted receiver
+    /// carries on where it left off instead of holding every stream.
+    pub fn with_store(mut self, tree: sled::Tree) -> Self {
+        self.store = Some(tree);
+        self
+    }
+
+    /// Position of a stream not seen since this buffer was created.
+    fn stored_next(&self, key: &ChannelKey) -> u64 {
+        let Some(store) = &self.store else {
+            return 1;
+        };
+        match store.get(store_key(key)) {
+            Ok(Some(raw)) => raw.as_ref().try_into().map(u64::from_be_bytes).unwrap_or(1),
+            Ok(None) => 1,
+            Err(e) => {
+                tracing::warn!("reading reorder position failed: {e}");
+                1
+            }
+        }
+    }
+
+    fn persist(&self, key: &ChannelKey, next: u64) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.insert(store_key(key), &next.to_be_bytes()) {
+                tracing::warn!("saving reorder position failed: {e}");
+            }
+        }
+    }
+
+    /// Accept `msg`, returning the messages now deliverable in order.
+    pub async fn push(&self, msg: Message) -> Vec<Message> {
+        let Some(seq) = msg.seq else {
+            return vec![msg];
+        };
+        let now = self.clock.now();
+        let key = (msg.sender, msg.recipient);
+        let mut channels = self.channels.write().await;
+        let channel = channels
+            .entry(key)
+            .or_insert_with(|| Channel::starting_at(self.stored_next(&key)));
+        if seq < channel.next || channel.held.contains_key(&seq) {
+            return Vec::new();
+        }
+        channel.held.insert(seq, msg);
+        let ready = channel.release(now);
+        if !ready.is_empty() {
+            self.persist(&key, channel.next);
+        }
+        ready
+    }
+
+    /// Give up on gaps held longer than the hold time, returning the
+    /// messages that were waiting behind them, in order. Call periodically.
+    pub async fn flush_expired(&self) -> Vec<Message> {
+        let now = self.clock.now();
+        let mut ready = Vec::new();
+        for (key, channel) in self.channels.write().await.iter_mut() {
+            let timed_out = channel
+                .gap_since
+                .is_some_and(|since| now.duration_since(since).unwrap_or_default() >= self.hold);
+            if !timed_out {
+                continue;
+            }
+            if let Some(&first) = channel.held.keys().next() {
+                channel.next = first;
+            }
+            ready.extend(channel.release(now));
+            self.persist(key, channel.next);
+        }
+        ready
+    }
+
+    /// How long a gap is waited out before being skipped.
+    pub fn hold(&self) -> Duration {
+        self.hold
+    }
+
+    /// Messages waiting for an earlier one to arrive.
+    pub async fn held(&self) -> usize {
+        self.channels
+            .read()
+            .await
+            .values()
+            .map(|channel| channel.held.len())
+            .sum()
+    }
+}
+
+/// Same layout as the sender's sequence counter key.
+fn store_key((sender, recipient): &ChannelKey) -> Vec<u8> {
+    let mut key = sender.0.to_vec();
+    key.extend_from_slice(&recipient.map_or([0; 32], |user| user.0));
+    key
+}
+
+impl Default for ReorderBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_REORDER_HOLD)
+    }
+}
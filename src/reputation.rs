@@ -0,0 +1,191 @@
+use crate::clock::{Clock, SystemClock};
+use crate::types::{PeerId, Timestamp};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Behaviour observed from a neighbour that moves its reputation score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReputationEvent {
+    /// Relayed a message whose signature verified.
+    ValidSignature,
+    /// Relayed a message with a forged or corrupted signature.
+    InvalidSignature,
+    /// Exceeded a rate limit.
+    RateLimited,
+    /// Handed us a message we had already relayed (our id on its path).
+    /// Flooding makes this routine, so the forwarder does not record it;
+    /// callers that route unicast traffic may.
+    LoopContribution,
+}
+
+impl ReputationEvent {
+    fn delta(self) -> f64 {
+        match self {
+            ReputationEvent::ValidSignature => 0.1,
+            ReputationEvent::InvalidSignature => -2.0,
+            ReputationEvent::RateLimited => -1.0,
+            ReputationEvent::LoopContribution => -0.5,
+        }
+    }
+}
+
+/// Tuning for [`Reputation`].
+#[derive(Debug, Clone, Copy)]
+pub struct ReputationConfig {
+    /// Peers scoring below this are blacklisted.
+    pub threshold: f64,
+    /// Time for a score to decay halfway back to neutral (zero).
+    pub half_life: Duration,
+    /// Scores are clamped to `[-max_score, max_score]`.
+    pub max_score: f64,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            threshold: -5.0,
+            half_life: Duration::from_secs(600),
+            max_score: 10.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Score {
+    value: f64,
+    updated: Timestamp,
+}
+
+/// Per-peer reputation with a threshold blacklist. Scores decay toward
+/// neutral over time, so a peer that stops misbehaving is eventually let back
+/// in.
+#[derive(Clone)]
+pub struct Reputation {
+    config: ReputationConfig,
+    scores: Arc<RwLock<HashMap<PeerId, Score>>>,
+    clock: Arc<dyn Clock>,
+    store: Option<sled::Tree>,
+}
+
+impl Reputation {
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            scores: Arc::new(RwLock::new(HashMap::new())),
+            clock: Arc::new(SystemClock),
+            store: None,
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Persist blacklisted peers in `tree` so they stay blocked across
+    /// restarts. Entries already in the tree are loaded.
+    pub fn with_store(mut self, tree: sled::Tree) -> Result<Self> {
+        {
+            let mut scores = self.scores.write().unwrap();
+            for entry in tree.iter() {
+                let (key, raw) = entry?;
+                let Ok(peer) = <[u8; 32]>::try_from(key.as_ref()) else {
+                    continue;
+                };
+                let (value, updated) = bincode::deserialize(&raw)?;
+                scores.insert(PeerId(peer), Score { value, updated });
+            }
+        }
+        self.store = Some(tree);
+        Ok(self)
+    }
+
+    /// Record `event` for `peer` and return its new score.
+    pub fn record(&self, peer: PeerId, event: ReputationEvent) -> f64 {
+        let now = self.clock.now();
+        let mut scores = self.scores.write().unwrap();
+        let current = scores
+            .get(&peer)
+            .map(|s| self.decayed(*s, now))
+            .unwrap_or(0.0);
+        let value = (current + event.delta()).clamp(-self.config.max_score, self.config.max_score);
+        let score = Score {
+            value,
+            updated: now,
+        };
+        scores.insert(peer, score);
+        self.persist(peer, score);
+        value
+    }
+
+    /// Current (decayed) score of `peer`; unknown peers are neutral.
+    pub fn score(&self, peer: &PeerId) -> f64 {
+        let now = self.clock.now();
+        self.scores
+            .read()
+            .unwrap()
+            .get(peer)
+            .map(|s| self.decayed(*s, now))
+            .unwrap_or(0.0)
+    }
+
+    /// Score of `peer` scaled to `[-1, 1]`.
+    pub fn standing(&self, peer: &PeerId) -> f64 {
+        self.score(peer) / self.config.max_score
+    }
+
+    /// Whether all traffic from `peer` should be dropped.
+    pub fn is_blacklisted(&self, peer: &PeerId) -> bool {
+        self.score(peer) < self.config.threshold
+    }
+
+    /// Peers currently below the threshold.
+    pub fn blacklist(&self) -> Vec<PeerId> {
+        let now = self.clock.now();
+        self.scores
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, s)| self.decayed(**s, now) < self.config.threshold)
+            .map(|(peer, _)| *peer)
+            .collect()
+    }
+
+    /// Reset `peer` to neutral, lifting any blacklisting.
+    pub fn forgive(&self, peer: &PeerId) {
+        self.scores.write().unwrap().remove(peer);
+        if let Some(store) = &self.store {
+            let _ = store.remove(peer.0);
+        }
+    }
+
+    fn decayed(&self, score: Score, now: Timestamp) -> f64 {
+        let elapsed = now.duration_since(score.updated).unwrap_or_default();
+        let half_lives = elapsed.as_secs_f64() / self.config.half_life.as_secs_f64().max(1e-9);
+        score.value * 0.5f64.powf(half_lives)
+    }
+
+    fn persist(&self, peer: PeerId, score: Score) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let result = if score.value < self.config.threshold {
+            bincode::serialize(&(score.value, score.updated))
+                .map_err(anyhow::Error::from)
+                .and_then(|raw| Ok(store.insert(peer.0, raw).map(|_| ())?))
+        } else {
+            store.remove(peer.0).map(|_| ()).map_err(Into::into)
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to persist reputation: {e:#}");
+        }
+    }
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Self::new(ReputationConfig::default())
+    }
+}
@@ -1,44 +1,361 @@
+use crate::clock::{Clock, SystemClock};
+use crate::config::MeshConfig;
+use crate::message::{Message, MessageContent};
+use crate::reputation::Reputation;
+use crate::routing_control::RoutingControl;
+use crate::service::ServiceId;
+use crate::stats::MeshStats;
 use crate::types::{PeerId, UserId};
-use std::collections::HashMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
-use tokio::sync::RwLock;
-use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+
+/// How long a destination stays marked unreachable by default.
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(30);
+
+/// Assumed latency of one hop when nothing better is known.
+pub const DEFAULT_HOP_LATENCY: Duration = Duration::from_millis(500);
 
 /// Routing information for a single destination
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RouteInfo {
     pub destination: UserId,
     pub next_hop: PeerId,
     pub hop_count: u8,
     pub last_updated: SystemTime,
     pub link_quality: f32,
+    /// Estimated latency of each hop along the route. Missing from routes
+    /// sent by older peers, which get [`DEFAULT_HOP_LATENCY`].
+    #[serde(default = "default_hop_latency")]
+    pub hop_latency: Duration,
+}
+
+fn default_hop_latency() -> Duration {
+    DEFAULT_HOP_LATENCY
 }
 
 impl RouteInfo {
-    fn is_expired(&self, max_age: Duration) -> bool {
-        self.last_updated
-            .elapsed()
+    /// Estimated end-to-end latency: `hop_count` hops of `hop_latency` each.
+    pub fn estimated_latency(&self) -> Duration {
+        self.hop_latency * u32::from(self.hop_count.max(1))
+    }
+
+    /// Order routes from best to worst: fewer hops, then better link
+    /// quality, then fresher.
+    fn rank(&self, other: &RouteInfo) -> std::cmp::Ordering {
+        self.hop_count
+            .cmp(&other.hop_count)
+            .then(other.link_quality.total_cmp(&self.link_quality))
+            .then(other.last_updated.cmp(&self.last_updated))
+    }
+
+    fn is_expired(&self, now: SystemTime, max_age: Duration) -> bool {
+        now.duration_since(self.last_updated)
             .map(|e| e > max_age)
             .unwrap_or(true)
     }
 }
 
+/// How a route's link quality fades while it goes unrefreshed; see
+/// [`RoutingEngine::with_quality_decay`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QualityDecay {
+    /// Falls in a straight line, reaching zero after `over`.
+    Linear { over: Duration },
+    /// Halves every `half_life`.
+    Exponential { half_life: Duration },
+}
+
+impl QualityDecay {
+    /// `quality` after `age` without a refresh.
+    pub fn apply(&self, quality: f32, age: Duration) -> f32 {
+        let age = age.as_secs_f32();
+        match *self {
+            QualityDecay::Linear { over } => {
+                quality * (1.0 - age / over.as_secs_f32().max(f32::EPSILON)).max(0.0)
+            }
+            QualityDecay::Exponential { half_life } => {
+                quality * 0.5f32.powf(age / half_life.as_secs_f32().max(f32::EPSILON))
+            }
+        }
+    }
+}
+
+/// Notifications about routing-table changes, published on
+/// [`RoutingEngine::subscribe`].
+#[derive(Debug, Clone)]
+pub enum RouteEvent {
+    /// A route to a previously unknown destination was learned.
+    Added {
+        destination: UserId,
+        route: RouteInfo,
+    },
+    /// An existing route was replaced by a better one.
+    Updated {
+        destination: UserId,
+        route: RouteInfo,
+    },
+    /// A route aged out during [`RoutingEngine::cleanup`], or was evicted to
+    /// stay within [`RoutingEngine::with_max_routes`].
+    Removed {
+        destination: UserId,
+        route: RouteInfo,
+    },
+    /// A route was explicitly invalidated (e.g. on RERR or link break).
+    Invalidated {
+        destination: UserId,
+        route: RouteInfo,
+    },
+    /// Route discovery gave up after exhausting its retries.
+    RouteDiscoveryFailed { destination: UserId },
+}
+
+/// Result of [`RoutingEngine::lookup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteLookup {
+    /// A live route exists via this neighbour.
+    Route(PeerId),
+    /// The destination recently proved unreachable; do not rediscover yet.
+    Unreachable,
+    /// Nothing is known about the destination.
+    Unknown,
+}
+
 /// A minimal routing engine maintaining a table of the best-known routes.
 #[derive(Clone)]
 pub struct RoutingEngine {
     routes: Arc<RwLock<HashMap<UserId, RouteInfo>>>,
+    /// Destinations confirmed unreachable, with the time the entry lapses.
+    unreachable: Arc<Mutex<HashMap<UserId, SystemTime>>>,
+    negative_ttl: Duration,
+    /// When each destination's route was last handed out by `next_hop`.
+    last_used: Arc<Mutex<HashMap<UserId, SystemTime>>>,
+    max_routes: Option<usize>,
+    /// Quality margin that lets a route one hop longer win; see
+    /// [`with_quality_preference`](Self::with_quality_preference).
+    quality_threshold: Option<f32>,
+    /// Reputation of advertising neighbours and how much it counts; see
+    /// [`with_reputation`](Self::with_reputation).
+    reputation: Option<(Reputation, f32)>,
+    quality_decay: Option<QualityDecay>,
     max_age: Duration,
+    hop_latency: Duration,
+    /// Measured latency to individual neighbours.
+    link_latency: Arc<Mutex<HashMap<PeerId, Duration>>>,
+    /// Advertised forwarding willingness of neighbours; see
+    /// [`set_willingness`](Self::set_willingness).
+    willingness: Arc<Mutex<HashMap<PeerId, f32>>>,
+    /// Advertised providers of each service.
+    services: Arc<Mutex<HashMap<ServiceId, HashSet<UserId>>>>,
+    /// This node's peer and user id; see [`with_local`](Self::with_local).
+    local: Option<(PeerId, UserId)>,
+    clock: Arc<dyn Clock>,
+    events: broadcast::Sender<RouteEvent>,
+    stats: Arc<MeshStats>,
 }
 
 impl RoutingEngine {
     /// Create a new routing engine.
     pub fn new(max_age: Duration) -> Self {
+        Self::with_clock(max_age, Arc::new(SystemClock))
+    }
+
+    /// Create a routing engine with the route lifetimes from `config`.
+    pub fn from_config(config: &MeshConfig) -> Self {
+        let mut engine =
+            Self::new(config.route_max_age()).with_negative_ttl(config.negative_route_ttl());
+        if let Some(max) = config.max_routes {
+            engine = engine.with_max_routes(max);
+        }
+        if let Some(threshold) = config.route_quality_threshold {
+            engine = engine.with_quality_preference(threshold);
+        }
+        if let Some(half_life) = config.route_quality_half_life() {
+            engine = engine.with_quality_decay(QualityDecay::Exponential { half_life });
+        }
+        engine
+    }
+
+    /// Create a routing engine driven by a custom clock (e.g. `MockClock`).
+    pub fn with_clock(max_age: Duration, clock: Arc<dyn Clock>) -> Self {
+        let (events, _) = broadcast::channel(1024);
         Self {
             routes: Arc::new(RwLock::new(HashMap::new())),
+            unreachable: Arc::new(Mutex::new(HashMap::new())),
+            negative_ttl: DEFAULT_NEGATIVE_TTL,
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+            max_routes: None,
+            quality_threshold: None,
+            reputation: None,
+            quality_decay: None,
             max_age,
+            hop_latency: DEFAULT_HOP_LATENCY,
+            link_latency: Arc::new(Mutex::new(HashMap::new())),
+            willingness: Arc::new(Mutex::new(HashMap::new())),
+            services: Arc::new(Mutex::new(HashMap::new())),
+            local: None,
+            clock,
+            events,
+            stats: Arc::new(MeshStats::default()),
         }
     }
 
+    /// Run on the node reachable as `peer` and speaking as `user`, so
+    /// imported tables cannot route us through or to ourselves.
+    pub fn with_local(mut self, peer: PeerId, user: UserId) -> Self {
+        self.local = Some((peer, user));
+        self
+    }
+
+    /// Hold at most `max` routes. A new destination beyond that evicts the
+    /// worst route (see [`update_route`](Self::update_route)) unless it is
+    /// in active use, or is dropped if it would itself be the worst.
+    pub fn with_max_routes(mut self, max: usize) -> Self {
+        self.max_routes = Some(max);
+        self
+    }
+
+    /// Age after which an unrefreshed route is dropped by
+    /// [`cleanup`](Self::cleanup).
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    /// Share counters with other components instead of using a private set.
+    pub fn with_stats(mut self, stats: Arc<MeshStats>) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    pub fn stats(&self) -> Arc<MeshStats> {
+        self.stats.clone()
+    }
+
+    /// Keep destinations marked unreachable for `ttl` (default
+    /// [`DEFAULT_NEGATIVE_TTL`]).
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Trade hops for link quality: a route one hop longer replaces (or
+    /// keeps its place against) a shorter one when its link quality is
+    /// higher by at least `threshold`. Without this, fewer hops always win
+    /// and quality only breaks ties.
+    pub fn with_quality_preference(mut self, threshold: f32) -> Self {
+        self.quality_threshold = Some(threshold);
+        self
+    }
+
+    /// Compare routes by link quality faded by `decay` since their last
+    /// update, so an unrefreshed route loses out to fresher ones well before
+    /// it expires. Stored qualities are left untouched.
+    pub fn with_quality_decay(mut self, decay: QualityDecay) -> Self {
+        self.quality_decay = Some(decay);
+        self
+    }
+
+    /// `route`'s link quality as of now, after any configured decay.
+    pub fn effective_quality(&self, route: &RouteInfo) -> f32 {
+        match &self.quality_decay {
+            Some(decay) => {
+                let age = self
+                    .clock
+                    .now()
+                    .duration_since(route.last_updated)
+                    .unwrap_or_default();
+                decay.apply(route.link_quality, age)
+            }
+            None => route.link_quality,
+        }
+    }
+
+    /// Judge routes by who advertised them as well: the next hop's
+    /// [`Reputation::standing`] times `weight` is added to a route's link
+    /// quality when comparing it against another, so reliable neighbours
+    /// win ties and flaky ones need a clearly better link to be used.
+    pub fn with_reputation(mut self, reputation: Reputation, weight: f32) -> Self {
+        self.reputation = Some((reputation, weight));
+        self
+    }
+
+    /// Assume `latency` per hop (default [`DEFAULT_HOP_LATENCY`]), e.g. the
+    /// transport's [`latency_hint`](crate::Transport::latency_hint).
+    pub fn with_hop_latency(mut self, latency: Duration) -> Self {
+        self.hop_latency = latency;
+        self
+    }
+
+    /// Record the measured latency to neighbour `peer`. Routes learned via
+    /// `peer` from now on use it as their per-hop estimate.
+    pub fn set_link_latency(&self, peer: PeerId, latency: Duration) {
+        self.link_latency.lock().unwrap().insert(peer, latency);
+    }
+
+    /// Record how willing neighbour `peer` is to relay, from 0 (leaf only)
+    /// to 1 (the default). Routes relayed through a neighbour at 0 lose to
+    /// any alternative; between 0 and 1 the route's link quality is scaled
+    /// by the willingness when comparing it against another. Routes to the
+    /// neighbour itself are unaffected.
+    pub fn set_willingness(&self, peer: PeerId, willingness: f32) {
+        self.willingness
+            .lock()
+            .unwrap()
+            .insert(peer, willingness.clamp(0.0, 1.0));
+    }
+
+    /// Advertised forwarding willingness of neighbour `peer`.
+    pub fn willingness(&self, peer: &PeerId) -> f32 {
+        self.willingness
+            .lock()
+            .unwrap()
+            .get(peer)
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// Willingness of `next_hop` to relay a route of `hop_count` hops; a
+    /// one-hop route needs no relaying.
+    fn relay_willingness(&self, next_hop: &PeerId, hop_count: u8) -> f32 {
+        if hop_count <= 1 {
+            1.0
+        } else {
+            self.willingness(next_hop)
+        }
+    }
+
+    fn hop_latency_via(&self, peer: &PeerId) -> Duration {
+        self.link_latency
+            .lock()
+            .unwrap()
+            .get(peer)
+            .copied()
+            .unwrap_or(self.hop_latency)
+    }
+
+    /// Estimated time for a message to reach `destination`, if routed.
+    pub async fn estimated_latency(&self, destination: &UserId) -> Option<Duration> {
+        let routes = self.routes.read().await;
+        routes.get(destination).map(RouteInfo::estimated_latency)
+    }
+
+    /// Subscribe to route additions, updates and removals.
+    pub fn subscribe(&self) -> broadcast::Receiver<RouteEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publish an event on behalf of a companion component (e.g. discovery).
+    pub(crate) fn publish(&self, event: RouteEvent) {
+        let _ = self.events.send(event);
+    }
+
+    pub(crate) fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
     /// Update or insert a route. If an existing route has a worse hop count or
     /// link quality, it will be replaced.
     pub async fn update_route(
@@ -49,38 +366,416 @@ impl RoutingEngine {
         link_quality: f32,
     ) {
         let mut routes = self.routes.write().await;
+        self.apply_update(&mut routes, destination, next_hop, hop_count, link_quality);
+    }
+
+    /// Apply many `(destination, next_hop, hop_count, link_quality)` updates
+    /// with the same scoring as [`update_route`](Self::update_route), taking
+    /// the write lock only once. Use this for large merges, e.g. after a
+    /// partition heals.
+    pub async fn update_routes_bulk(&self, updates: Vec<(UserId, PeerId, u8, f32)>) {
+        let mut routes = self.routes.write().await;
+        for (destination, next_hop, hop_count, link_quality) in updates {
+            self.apply_update(&mut routes, destination, next_hop, hop_count, link_quality);
+        }
+    }
+
+    fn apply_update(
+        &self,
+        routes: &mut HashMap<UserId, RouteInfo>,
+        destination: UserId,
+        next_hop: PeerId,
+        hop_count: u8,
+        link_quality: f32,
+    ) {
         let should_replace = routes
             .get(&destination)
-            .map(|existing| {
-                hop_count < existing.hop_count
-                    || (hop_count == existing.hop_count && link_quality > existing.link_quality)
-            })
+            .map(|existing| self.prefers(next_hop, hop_count, link_quality, existing))
             .unwrap_or(true);
 
         if should_replace {
-            routes.insert(
+            let route = RouteInfo {
                 destination,
-                RouteInfo {
-                    destination,
-                    next_hop,
-                    hop_count,
-                    last_updated: SystemTime::now(),
-                    link_quality,
-                },
-            );
+                next_hop,
+                hop_count,
+                last_updated: self.clock.now(),
+                link_quality,
+                hop_latency: self.hop_latency_via(&next_hop),
+            };
+            if !routes.contains_key(&destination) && !self.make_room(routes, &route) {
+                return;
+            }
+            self.unreachable.lock().unwrap().remove(&destination);
+            let event = match routes.insert(destination, route.clone()) {
+                Some(_) => RouteEvent::Updated { destination, route },
+                None => RouteEvent::Added { destination, route },
+            };
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Whether a route via `next_hop` of `hop_count` hops and
+    /// `link_quality` is better than `existing`.
+    fn prefers(
+        &self,
+        next_hop: PeerId,
+        hop_count: u8,
+        link_quality: f32,
+        existing: &RouteInfo,
+    ) -> bool {
+        let willing = self.relay_willingness(&next_hop, hop_count);
+        let existing_willing = self.relay_willingness(&existing.next_hop, existing.hop_count);
+        if (willing == 0.0) != (existing_willing == 0.0) {
+            return existing_willing == 0.0;
+        }
+        let link_quality = self.score(&next_hop, willing * link_quality);
+        let existing_quality = self.score(
+            &existing.next_hop,
+            existing_willing * self.effective_quality(existing),
+        );
+        match (
+            hop_count.abs_diff(existing.hop_count),
+            self.quality_threshold,
+        ) {
+            (0, _) => link_quality > existing_quality,
+            (1, Some(margin)) if hop_count > existing.hop_count => {
+                link_quality >= existing_quality + margin
+            }
+            // One hop shorter: wins unless the existing route is better by
+            // the margin.
+            (1, Some(margin)) => existing_quality < link_quality + margin,
+            _ => hop_count < existing.hop_count,
+        }
+    }
+
+    /// `link_quality` adjusted for the reputation of `next_hop`.
+    fn score(&self, next_hop: &PeerId, link_quality: f32) -> f32 {
+        match &self.reputation {
+            Some((reputation, weight)) => {
+                link_quality + weight * reputation.standing(next_hop) as f32
+            }
+            None => link_quality,
+        }
+    }
+
+    /// [`RouteInfo::rank`] with decayed link qualities.
+    fn rank(&self, a: &RouteInfo, b: &RouteInfo) -> std::cmp::Ordering {
+        a.hop_count
+            .cmp(&b.hop_count)
+            .then(
+                self.effective_quality(b)
+                    .total_cmp(&self.effective_quality(a)),
+            )
+            .then(a.rank(b))
+    }
+
+    /// Evict the worst route not in active use if the table is full and it
+    /// ranks below `candidate`. Returns whether `candidate` fits.
+    fn make_room(&self, routes: &mut HashMap<UserId, RouteInfo>, candidate: &RouteInfo) -> bool {
+        let Some(max) = self.max_routes else {
+            return true;
+        };
+        if routes.len() < max {
+            return true;
+        }
+        let now = self.clock.now();
+        let last_used = self.last_used.lock().unwrap();
+        let in_use = |dest: &UserId| {
+            last_used
+                .get(dest)
+                .and_then(|used| now.duration_since(*used).ok())
+                .is_some_and(|idle| idle <= self.max_age)
+        };
+        let victim = routes
+            .values()
+            .filter(|r| !in_use(&r.destination))
+            .max_by(|a, b| self.rank(a, b))
+            .filter(|worst| self.rank(worst, candidate).is_gt())
+            .map(|worst| worst.destination);
+        let Some(victim) = victim else {
+            return false;
+        };
+        let route = routes.remove(&victim).expect("victim is in the table");
+        let _ = self.events.send(RouteEvent::Removed {
+            destination: victim,
+            route,
+        });
+        true
+    }
+
+    /// Like [`update_route`](Self::update_route), but a route that already
+    /// goes via `next_hop` is always overwritten, so periodic advertisements
+    /// keep it fresh and report worsening metrics.
+    pub async fn refresh_route(
+        &self,
+        destination: UserId,
+        next_hop: PeerId,
+        hop_count: u8,
+        link_quality: f32,
+    ) {
+        let mut routes = self.routes.write().await;
+        if routes.get(&destination).map(|r| r.next_hop) == Some(next_hop) {
+            let route = RouteInfo {
+                destination,
+                next_hop,
+                hop_count,
+                last_updated: self.clock.now(),
+                link_quality,
+                hop_latency: self.hop_latency_via(&next_hop),
+            };
+            self.unreachable.lock().unwrap().remove(&destination);
+            routes.insert(destination, route.clone());
+            let _ = self.events.send(RouteEvent::Updated { destination, route });
+            return;
+        }
+        drop(routes);
+        self.update_route(destination, next_hop, hop_count, link_quality)
+            .await
+    }
+
+    /// Learn routes from a routing control message received from `from`.
+    ///
+    /// The message must carry a valid signature from its sender, and the
+    /// sender must be the node the packet speaks for (RREQ origin, RREP
+    /// destination); otherwise it is dropped and counted in
+    /// `MeshStats::rejected_control`.
+    pub async fn apply_control(
+        &self,
+        msg: &Message,
+        from: PeerId,
+        link_quality: f32,
+    ) -> Result<()> {
+        let MessageContent::Routing(control) = &msg.content else {
+            anyhow::bail!("not a routing control message");
+        };
+        if let Err(e) = self.check_control(msg, control) {
+            MeshStats::incr(&self.stats.rejected_control);
+            return Err(e.context("rejected routing control"));
+        }
+
+        // The envelope hop count is incremented by each relay; the node that
+        // handed it to us is one hop further.
+        let hops = msg.hop_count.saturating_add(1);
+        for packet in control.clone().expand() {
+            match packet {
+                RoutingControl::Rreq { origin, .. } => {
+                    self.update_route(origin, from, hops, link_quality).await
+                }
+                RoutingControl::Rrep { destination, .. } => {
+                    self.update_route(destination, from, hops, link_quality)
+                        .await
+                }
+                RoutingControl::Rerr { unreachable } => {
+                    for dest in unreachable {
+                        if self.next_hop(&dest).await == Some(from) {
+                            self.invalidate(&dest).await;
+                            self.mark_unreachable(dest);
+                        }
+                    }
+                }
+                RoutingControl::ServiceAdvert { provider, services } => {
+                    self.update_route(provider, from, hops, link_quality).await;
+                    self.learn_services(provider, &services);
+                }
+                RoutingControl::RouteExchange(routes) => self.import(&routes, from).await,
+                // Only the neighbour itself can say how willing it is.
+                RoutingControl::Willingness { .. } if msg.hop_count > 0 => {}
+                RoutingControl::Willingness { willingness, .. } => {
+                    self.set_willingness(from, willingness)
+                }
+                // Epidemic exchange and fragment ARQ carry no route information.
+                RoutingControl::Summary { .. }
+                | RoutingControl::Request { .. }
+                | RoutingControl::FragNack { .. } => {}
+                // Path-MTU state lives in `PathMtu`.
+                RoutingControl::MtuProbe { .. } | RoutingControl::MtuAck { .. } => {}
+                // Served by the forwarder's `ContentCache`.
+                RoutingControl::ContentRequest { .. } => {}
+                // Beacons are sequence-checked by `ProactiveBeacon`.
+                RoutingControl::Beacon { .. } => {}
+                RoutingControl::Batch(_) => unreachable!("expand flattens batches"),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_control(&self, msg: &Message, control: &RoutingControl) -> Result<()> {
+        msg.verify_signature()?;
+        for packet in control.clone().expand() {
+            let speaker = match &packet {
+                RoutingControl::Rreq { origin, .. } => Some(origin),
+                RoutingControl::Rrep { destination, .. } => Some(destination),
+                RoutingControl::Beacon { origin, .. } => Some(origin),
+                RoutingControl::MtuProbe { origin, .. } => Some(origin),
+                RoutingControl::MtuAck { destination, .. } => Some(destination),
+                RoutingControl::ServiceAdvert { provider, .. } => Some(provider),
+                RoutingControl::Willingness { origin, .. } => Some(origin),
+                _ => None,
+            };
+            if speaker.is_some_and(|speaker| *speaker != msg.sender) {
+                anyhow::bail!("control packet not signed by the node it speaks for");
+            }
+            // NaN would win every comparison it takes part in.
+            if let RoutingControl::Willingness { willingness, .. } = &packet {
+                if !willingness.is_finite() {
+                    anyhow::bail!("willingness is not a finite number");
+                }
+            }
+            // A table only gives distances from the neighbour that sent it;
+            // a relayed copy would make far routes look near.
+            if matches!(packet, RoutingControl::RouteExchange(_)) && msg.hop_count != 0 {
+                anyhow::bail!("route exchange not sent by a neighbour");
+            }
         }
+        Ok(())
     }
 
     /// Retrieve the next hop for a destination, if a valid route exists.
     pub async fn next_hop(&self, destination: &UserId) -> Option<PeerId> {
         let routes = self.routes.read().await;
-        routes.get(destination).map(|r| r.next_hop)
+        let next_hop = routes.get(destination).map(|r| r.next_hop)?;
+        if self.max_routes.is_some() {
+            let now = self.clock.now();
+            self.last_used.lock().unwrap().insert(*destination, now);
+        }
+        Some(next_hop)
+    }
+
+    /// Wait until a route to `destination` is installed and return its
+    /// next hop, e.g. after starting discovery. Fails after `timeout`, or
+    /// early if discovery for `destination` gives up.
+    pub async fn wait_for_route(&self, destination: &UserId, timeout: Duration) -> Result<PeerId> {
+        // Subscribe before checking so a route installed in between is not missed.
+        let mut events = self.subscribe();
+        let wait = async {
+            loop {
+                if let Some(peer) = self.next_hop(destination).await {
+                    return Ok(peer);
+                }
+                // Any event, or a lag, may mean the table changed; recheck.
+                if let Ok(RouteEvent::RouteDiscoveryFailed {
+                    destination: failed,
+                }) = events.recv().await
+                {
+                    if failed == *destination {
+                        anyhow::bail!("route discovery for {destination} failed");
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| anyhow::anyhow!("no route to {destination} within {timeout:?}"))?
+    }
+
+    /// Record that `provider` offers `services`.
+    pub fn learn_services(&self, provider: UserId, services: &[ServiceId]) {
+        let mut known = self.services.lock().unwrap();
+        for service in services {
+            known.entry(*service).or_default().insert(provider);
+        }
+    }
+
+    /// Known providers of `service`, reachable or not.
+    pub fn providers(&self, service: &ServiceId) -> Vec<UserId> {
+        self.services
+            .lock()
+            .unwrap()
+            .get(service)
+            .map(|providers| providers.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Best route to any provider of `service`: fewest hops, ties broken
+    /// like other routes. Anycast messages follow it.
+    pub async fn nearest_provider(&self, service: &ServiceId) -> Option<RouteInfo> {
+        let providers = self.providers(service);
+        let route = {
+            let routes = self.routes.read().await;
+            providers
+                .iter()
+                .filter_map(|provider| routes.get(provider))
+                .min_by(|a, b| self.rank(a, b))
+                .cloned()?
+        };
+        if self.max_routes.is_some() {
+            let now = self.clock.now();
+            self.last_used
+                .lock()
+                .unwrap()
+                .insert(route.destination, now);
+        }
+        Some(route)
     }
 
-    /// Remove expired routes.
+    /// Live route, known-unreachable marker, or nothing, for `destination`.
+    pub async fn lookup(&self, destination: &UserId) -> RouteLookup {
+        if let Some(peer) = self.next_hop(destination).await {
+            return RouteLookup::Route(peer);
+        }
+        if self.is_unreachable(destination) {
+            RouteLookup::Unreachable
+        } else {
+            RouteLookup::Unknown
+        }
+    }
+
+    /// Record that `destination` cannot currently be reached (RERR or failed
+    /// discovery). Cleared when the negative TTL lapses or a route is learned.
+    pub fn mark_unreachable(&self, destination: UserId) {
+        let until = self.clock.now() + self.negative_ttl;
+        self.unreachable.lock().unwrap().insert(destination, until);
+    }
+
+    /// Whether `destination` is marked unreachable and the mark is current.
+    pub fn is_unreachable(&self, destination: &UserId) -> bool {
+        let now = self.clock.now();
+        self.unreachable
+            .lock()
+            .unwrap()
+            .get(destination)
+            .is_some_and(|until| *until > now)
+    }
+
+    /// Remove expired routes and lapsed unreachable markers.
     pub async fn cleanup(&self) {
+        let now = self.clock.now();
+        self.unreachable
+            .lock()
+            .unwrap()
+            .retain(|_, until| *until > now);
+        self.last_used.lock().unwrap().retain(|_, used| {
+            now.duration_since(*used)
+                .is_ok_and(|idle| idle <= self.max_age)
+        });
         let mut routes = self.routes.write().await;
-        routes.retain(|_, route| !route.is_expired(self.max_age));
+        routes.retain(|destination, route| {
+            let expired = route.is_expired(now, self.max_age);
+            if expired {
+                let _ = self.events.send(RouteEvent::Removed {
+                    destination: *destination,
+                    route: route.clone(),
+                });
+            }
+            !expired
+        });
+        // A provider we can no longer reach must re-advertise.
+        self.services.lock().unwrap().retain(|_, providers| {
+            providers.retain(|provider| routes.contains_key(provider));
+            !providers.is_empty()
+        });
+    }
+
+    /// Drop the route to `destination`, e.g. after a RERR. Returns the removed
+    /// entry, if any.
+    pub async fn invalidate(&self, destination: &UserId) -> Option<RouteInfo> {
+        let mut routes = self.routes.write().await;
+        let route = routes.remove(destination)?;
+        let _ = self.events.send(RouteEvent::Invalidated {
+            destination: *destination,
+            route: route.clone(),
+        });
+        Some(route)
     }
 
     /// For testing and diagnostics: return a snapshot of current table.
@@ -88,4 +783,54 @@ impl RoutingEngine {
         let routes = self.routes.read().await;
         routes.values().cloned().collect()
     }
-} 
\ No newline at end of file
+
+    /// Routes to share with a newly connected neighbour.
+    pub async fn export(&self) -> Vec<RouteInfo> {
+        self.dump().await
+    }
+
+    /// Reinstate routes saved in a snapshot, keeping their original
+    /// timestamps so ones that went stale in transit still age out. Existing
+    /// routes win.
+    pub(crate) async fn restore(&self, saved: &[RouteInfo]) {
+        let mut routes = self.routes.write().await;
+        for route in saved {
+            if routes.contains_key(&route.destination) || !self.make_room(&mut routes, route) {
+                continue;
+            }
+            routes.insert(route.destination, route.clone());
+            let _ = self.events.send(RouteEvent::Added {
+                destination: route.destination,
+                route: route.clone(),
+            });
+        }
+    }
+
+    /// Merge a neighbour's exported routes. Each route is re-rooted at `via`
+    /// and one hop longer, then scored like any other update, so existing
+    /// better routes are kept.
+    ///
+    /// Routes that lead back through us or to us (split horizon, once
+    /// [`with_local`](Self::with_local) is set) and routes with a non-finite
+    /// quality are skipped; other qualities are clamped to `0.0..=1.0`.
+    pub async fn import(&self, routes: &[RouteInfo], via: PeerId) {
+        let updates = routes
+            .iter()
+            .filter(|route| route.link_quality.is_finite())
+            .filter(|route| {
+                self.local.map_or(true, |(peer, user)| {
+                    route.next_hop != peer && route.destination != user
+                })
+            })
+            .map(|route| {
+                (
+                    route.destination,
+                    via,
+                    route.hop_count.saturating_add(1),
+                    route.link_quality.clamp(0.0, 1.0),
+                )
+            })
+            .collect();
+        self.update_routes_bulk(updates).await;
+    }
+}
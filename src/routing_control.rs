@@ -1,5 +1,15 @@
-use crate::types::UserId;
-use serde::{Deserialize, Serialize};
+use crate::content_cache::ContentHash;
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::service::ServiceId;
+use crate::transport::Transport;
+use crate::types::{MessageId, UserId};
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::Cell;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
 
 /// Control packets for the routing protocol (AODV-inspired)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -21,7 +31,169 @@ pub enum RoutingControl {
     },
 
     /// Route Error – notifies that given destinations are unreachable.
-    Rerr {
-        unreachable: Vec<UserId>,
+    Rerr { unreachable: Vec<UserId> },
+
+    /// Epidemic summary vector: ids of messages the sender is carrying.
+    Summary { ids: Vec<MessageId> },
+
+    /// Epidemic request for carried messages the sender lacks.
+    Request { ids: Vec<MessageId> },
+
+    /// Selective retransmission request for a chunked transfer: the
+    /// fragment indices the receiver still lacks. Empty means complete.
+    FragNack {
+        msg_id: MessageId,
+        missing: Vec<u32>,
+    },
+
+    /// Periodic proactive advertisement of `origin`'s routing table.
+    /// `seq` increases with every beacon so stale copies can be ignored.
+    Beacon {
+        origin: UserId,
+        seq: u32,
+        entries: Vec<crate::beacon::BeaconEntry>,
     },
-} 
\ No newline at end of file
+
+    /// Path-MTU probe padded to a chosen size. Relays whose link cannot
+    /// carry it drop it, so only probes that fit the whole path arrive.
+    MtuProbe {
+        origin: UserId,
+        destination: UserId,
+        probe_id: u32,
+        padding: Vec<u8>,
+    },
+
+    /// Reply from a probe's `destination`: probe `probe_id` made it.
+    MtuAck {
+        origin: UserId,
+        destination: UserId,
+        probe_id: u32,
+    },
+
+    /// Ask nearby peers for cached content (see
+    /// [`ContentCache`](crate::ContentCache)).
+    ContentRequest { hash: ContentHash },
+
+    /// `provider` offers `services` to anycast senders; flooded so every
+    /// node learns a route to its nearest provider.
+    ServiceAdvert {
+        provider: UserId,
+        services: Vec<ServiceId>,
+    },
+
+    /// Routing table export sent to a newly connected neighbour.
+    RouteExchange(Vec<crate::routing::RouteInfo>),
+
+    /// How willing `origin` is to relay for its neighbours, from 0 (leaf
+    /// only) to 1 (always); see
+    /// [`RoutingEngine::set_willingness`](crate::RoutingEngine::set_willingness).
+    Willingness { origin: UserId, willingness: f32 },
+
+    /// Several control packets aggregated into one transmission.
+    Batch(#[serde(deserialize_with = "deserialize_batch")] Vec<RoutingControl>),
+}
+
+/// Deepest `Batch` nesting accepted from the wire. Batches are only ever one
+/// level deep in practice; the cap stops crafted input from recursing until
+/// the stack overflows.
+pub const MAX_BATCH_DEPTH: usize = 8;
+
+thread_local! {
+    static BATCH_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+fn deserialize_batch<'de, D>(deserializer: D) -> Result<Vec<RoutingControl>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DepthGuard;
+    impl Drop for DepthGuard {
+        fn drop(&mut self) {
+            BATCH_DEPTH.with(|depth| depth.set(depth.get() - 1));
+        }
+    }
+
+    let depth = BATCH_DEPTH.with(|depth| {
+        depth.set(depth.get() + 1);
+        depth.get()
+    });
+    let _guard = DepthGuard;
+    if depth > MAX_BATCH_DEPTH {
+        return Err(serde::de::Error::custom(
+            "routing control batches nested too deeply",
+        ));
+    }
+    Vec::deserialize(deserializer)
+}
+
+impl RoutingControl {
+    /// Flatten (possibly nested) batches into individual packets, in order.
+    pub fn expand(self) -> Vec<RoutingControl> {
+        match self {
+            RoutingControl::Batch(packets) => packets
+                .into_iter()
+                .flat_map(RoutingControl::expand)
+                .collect(),
+            packet => vec![packet],
+        }
+    }
+}
+
+/// Aggregates broadcast control packets queued within a short window into a
+/// single [`RoutingControl::Batch`] transmission to save airtime during route
+/// discovery storms.
+#[derive(Clone)]
+pub struct ControlBatcher {
+    identity: Identity,
+    transport: Arc<dyn Transport>,
+    window: Duration,
+    pending: Arc<Mutex<Vec<RoutingControl>>>,
+}
+
+impl ControlBatcher {
+    pub fn new(identity: Identity, transport: Arc<dyn Transport>, window: Duration) -> Self {
+        Self {
+            identity,
+            transport,
+            window,
+            pending: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Queue a packet for broadcast. The first packet in an empty queue opens
+    /// the window; everything queued before it closes goes out together.
+    pub async fn enqueue(&self, packet: RoutingControl) {
+        let mut pending = self.pending.lock().await;
+        pending.push(packet);
+        if pending.len() == 1 {
+            let batcher = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(batcher.window).await;
+                if let Err(e) = batcher.flush().await {
+                    tracing::warn!("control batch flush failed: {e:#}");
+                }
+            });
+        }
+    }
+
+    /// Broadcast everything queued right now. Returns false if the queue was
+    /// empty.
+    pub async fn flush(&self) -> Result<bool> {
+        let mut packets = std::mem::take(&mut *self.pending.lock().await);
+        let control = match packets.len() {
+            0 => return Ok(false),
+            1 => packets.remove(0),
+            _ => RoutingControl::Batch(packets),
+        };
+        let mut msg = Message::new(
+            self.identity.user_id(),
+            None,
+            MessageContent::Routing(control),
+        );
+        msg.sign(&self.identity)?;
+        self.transport
+            .broadcast(self.transport.encode_message(&msg)?)
+            .await?;
+        Ok(true)
+    }
+}
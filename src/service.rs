@@ -0,0 +1,35 @@
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::routing_control::RoutingControl;
+use anyhow::Result;
+use ring::digest;
+use serde::{Deserialize, Serialize};
+
+/// Role a node can provide to the mesh ("medic", "water", ...), for anycast
+/// addressing. Derived from the role name so every node agrees on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ServiceId(pub [u8; 16]);
+
+impl ServiceId {
+    /// Truncated SHA-256 of `name`.
+    pub fn named(name: &str) -> Self {
+        let hash = digest::digest(&digest::SHA256, name.as_bytes());
+        Self(hash.as_ref()[..16].try_into().expect("SHA-256 is 32 bytes"))
+    }
+}
+
+/// Signed broadcast announcing that `identity` provides `services`. Relays
+/// flood it like any routing control, and each hop learns a route to the
+/// provider from it.
+pub fn service_advert(identity: &Identity, services: Vec<ServiceId>) -> Result<Message> {
+    let mut msg = Message::new(
+        identity.user_id(),
+        None,
+        MessageContent::Routing(RoutingControl::ServiceAdvert {
+            provider: identity.user_id(),
+            services,
+        }),
+    );
+    msg.sign(identity)?;
+    Ok(msg)
+}
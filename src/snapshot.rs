@@ -0,0 +1,130 @@
+use crate::identity::Identity;
+use crate::message::Message;
+use crate::routing::RouteInfo;
+use crate::store_crypto::{StoreCipher, STORE_SALT_LEN};
+use crate::types::{MessageId, Timestamp, UserId};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Format version written by this build; [`NodeStateSnapshot::from_bytes`]
+/// refuses anything else. Version 2 added per-route hop latency, version 3
+/// the sender sequence counters, version 4 sealed identities the way the
+/// encrypted store does.
+pub const SNAPSHOT_VERSION: u32 = 4;
+
+const SNAPSHOT_MAGIC: &[u8; 6] = b"DMSNAP";
+
+/// Associated data binding the sealed secrets to their role.
+const IDENTITIES_AAD: &[u8] = b"disaster-mesh snapshot identities";
+
+/// Everything needed to move a node to new hardware: its identities
+/// (encrypted under a passphrase), routing table, unacknowledged outgoing
+/// messages, dedup history and sequence counters. Produced by
+/// [`MeshNode::export_state`](crate::MeshNode::export_state).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeStateSnapshot {
+    pub version: u32,
+    pub created: Timestamp,
+    /// Identity the exporting node ran as; build the replacement node with
+    /// [`primary_identity`](Self::primary_identity) to keep its user id.
+    pub primary: UserId,
+    pub default_identity: Option<UserId>,
+    pub identities: SealedIdentities,
+    pub routes: Vec<RouteInfo>,
+    /// Sent messages still awaiting a delivery receipt.
+    pub pending: Vec<Message>,
+    /// Message ids seen, with their first-seen time.
+    pub seen: Vec<(MessageId, Timestamp)>,
+    /// Last sequence number used per stream and named counter, so the new
+    /// device does not reuse any.
+    pub sequences: Vec<(Vec<u8>, u64)>,
+}
+
+/// Identity secret keys sealed under a passphrase with [`StoreCipher`]:
+/// an Argon2id-derived key and ChaCha20-Poly1305, so a leaked snapshot is
+/// as hard to brute-force as a seized encrypted store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealedIdentities {
+    salt: [u8; STORE_SALT_LEN],
+    sealed: Vec<u8>,
+}
+
+impl SealedIdentities {
+    pub fn seal(identities: &[Identity], passphrase: &str) -> Result<Self> {
+        let salt: [u8; STORE_SALT_LEN] = rand::random();
+        let secrets: Vec<[u8; 32]> = identities.iter().map(Identity::secret_bytes).collect();
+        let sealed = StoreCipher::derive(passphrase, &salt)?
+            .seal(IDENTITIES_AAD, &bincode::serialize(&secrets)?)?;
+        Ok(Self { salt, sealed })
+    }
+
+    /// Decrypt the identities; fails on a wrong passphrase.
+    pub fn open(&self, passphrase: &str) -> Result<Vec<Identity>> {
+        let plain = StoreCipher::derive(passphrase, &self.salt)?
+            .open(IDENTITIES_AAD, &self.sealed)
+            .map_err(|_| anyhow::anyhow!("wrong passphrase or corrupted snapshot"))?;
+        let secrets: Vec<[u8; 32]> = bincode::deserialize(&plain)?;
+        Ok(secrets.iter().map(Identity::from_secret_bytes).collect())
+    }
+}
+
+impl NodeStateSnapshot {
+    /// Fail unless the snapshot was written in [`SNAPSHOT_VERSION`].
+    pub fn check_version(&self) -> Result<()> {
+        if self.version != SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "snapshot version {} is not supported (expected {SNAPSHOT_VERSION})",
+                self.version
+            );
+        }
+        Ok(())
+    }
+
+    /// Decrypt the identity the exporting node ran as.
+    pub fn primary_identity(&self, passphrase: &str) -> Result<Identity> {
+        self.identities
+            .open(passphrase)?
+            .into_iter()
+            .find(|identity| identity.user_id() == self.primary)
+            .context("snapshot does not contain its primary identity")
+    }
+
+    /// Encode as a self-describing file: magic, version, then the body.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend(bincode::serialize(self)?);
+        Ok(bytes)
+    }
+
+    /// Decode bytes from [`to_bytes`](Self::to_bytes), checking the version
+    /// before touching the body.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let body = bytes
+            .strip_prefix(SNAPSHOT_MAGIC.as_slice())
+            .context("not a node state snapshot")?;
+        anyhow::ensure!(body.len() >= 4, "truncated snapshot");
+        let (version, body) = body.split_at(4);
+        let version = u32::from_le_bytes(version.try_into()?);
+        if version != SNAPSHOT_VERSION {
+            anyhow::bail!(
+                "snapshot version {version} is not supported (expected {SNAPSHOT_VERSION})"
+            );
+        }
+        let snapshot: Self = bincode::deserialize(body).context("decode snapshot")?;
+        snapshot.check_version()?;
+        Ok(snapshot)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes()?).with_context(|| format!("write {}", path.display()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+        Self::from_bytes(&bytes)
+    }
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Node-wide counters shared (via `Arc`) between pipeline components.
+#[derive(Debug, Default)]
+pub struct MeshStats {
+    /// Messages dropped because our own peer id was already on their path.
+    pub loop_drops: AtomicU64,
+    /// Routing control packets dropped for a missing or invalid signature.
+    pub rejected_control: AtomicU64,
+    /// Events lost because a subscriber lagged behind its broadcast channel.
+    pub dropped_events: AtomicU64,
+    /// Messages dropped because the relaying peer is blacklisted or their
+    /// signature failed to verify.
+    pub reputation_drops: AtomicU64,
+    /// Messages dropped for reaching the forwarder's hop limit.
+    pub hop_limit_drops: AtomicU64,
+    /// Relative-TTL messages dropped after relays used up their lifetime.
+    pub expired_drops: AtomicU64,
+    /// Geo-scoped messages not relayed because we are outside their region.
+    pub geo_drops: AtomicU64,
+    /// RREQs not relayed because their `(origin, request_id)` was already seen.
+    pub rreq_duplicates: AtomicU64,
+    /// Relayed messages shed by admission control while congested.
+    pub congestion_drops: AtomicU64,
+    /// Relayed messages refused because our forwarding willingness is 0.
+    pub unwilling_drops: AtomicU64,
+    /// Content requests answered from our content cache.
+    pub content_cache_hits: AtomicU64,
+    /// Rebroadcasts dropped because enough neighbours were overheard
+    /// relaying the same message.
+    pub suppressed_rebroadcasts: AtomicU64,
+    /// Bytes handed to the link by a
+    /// [`MeteredTransport`](crate::MeteredTransport).
+    pub bytes_sent: AtomicU64,
+    /// Bytes received on a metered link.
+    pub bytes_received: AtomicU64,
+    /// Transmissions held back because they would exceed the byte budget.
+    pub deferred_sends: AtomicU64,
+    /// Deferred transmissions dropped because the deferred queue was full.
+    pub budget_drops: AtomicU64,
+    /// Incomplete reassemblies evicted to stay within the
+    /// [`Reassembler`](crate::Reassembler) limits.
+    pub reassembly_evictions: AtomicU64,
+    /// Received frames dropped by a
+    /// [`ChecksummedTransport`](crate::ChecksummedTransport) for a bad CRC.
+    pub checksum_failures: AtomicU64,
+}
+
+/// Plain-value copy of [`MeshStats`] for reporting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    pub loop_drops: u64,
+    pub rejected_control: u64,
+    pub dropped_events: u64,
+    pub reputation_drops: u64,
+    pub hop_limit_drops: u64,
+    pub expired_drops: u64,
+    pub geo_drops: u64,
+    pub rreq_duplicates: u64,
+    pub congestion_drops: u64,
+    pub unwilling_drops: u64,
+    pub content_cache_hits: u64,
+    pub suppressed_rebroadcasts: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    pub deferred_sends: u64,
+    pub budget_drops: u64,
+    pub reassembly_evictions: u64,
+    pub checksum_failures: u64,
+}
+
+/// Snapshot of how full an event channel is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelOccupancy {
+    /// Events still queued for the slowest subscriber.
+    pub queued: usize,
+    pub capacity: usize,
+}
+
+impl ChannelOccupancy {
+    pub fn of<T>(tx: &broadcast::Sender<T>, capacity: usize) -> Self {
+        Self {
+            queued: tx.len(),
+            capacity,
+        }
+    }
+}
+
+impl MeshStats {
+    /// Bump `counter` by one.
+    pub(crate) fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Read `counter`.
+    pub fn get(counter: &AtomicU64) -> u64 {
+        counter.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            loop_drops: Self::get(&self.loop_drops),
+            rejected_control: Self::get(&self.rejected_control),
+            dropped_events: Self::get(&self.dropped_events),
+            reputation_drops: Self::get(&self.reputation_drops),
+            hop_limit_drops: Self::get(&self.hop_limit_drops),
+            expired_drops: Self::get(&self.expired_drops),
+            geo_drops: Self::get(&self.geo_drops),
+            rreq_duplicates: Self::get(&self.rreq_duplicates),
+            congestion_drops: Self::get(&self.congestion_drops),
+            unwilling_drops: Self::get(&self.unwilling_drops),
+            content_cache_hits: Self::get(&self.content_cache_hits),
+            suppressed_rebroadcasts: Self::get(&self.suppressed_rebroadcasts),
+            bytes_sent: Self::get(&self.bytes_sent),
+            bytes_received: Self::get(&self.bytes_received),
+            deferred_sends: Self::get(&self.deferred_sends),
+            budget_drops: Self::get(&self.budget_drops),
+            reassembly_evictions: Self::get(&self.reassembly_evictions),
+            checksum_failures: Self::get(&self.checksum_failures),
+        }
+    }
+
+    /// Receive the next event, treating `Lagged` as recoverable: the skipped
+    /// events are logged and added to `dropped_events`. Returns `None` once
+    /// the channel is closed.
+    pub async fn recv_event<T: Clone>(&self, rx: &mut broadcast::Receiver<T>) -> Option<T> {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("event subscriber lagged, {skipped} events dropped");
+                    self.dropped_events.fetch_add(skipped, Ordering::Relaxed);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use argon2::{Algorithm, Argon2, Params, Version};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::{hkdf, hmac};
+use std::sync::Arc;
+
+/// Argon2id memory cost, in KiB, of deriving a [`StoreCipher`] key. Run
+/// once when the store is opened.
+pub const STORE_KDF_MEMORY_KIB: u32 = 19 * 1024;
+
+/// Argon2id passes over that memory.
+pub const STORE_KDF_PASSES: u32 = 2;
+
+/// Length of the random salt kept alongside an encrypted store.
+pub const STORE_SALT_LEN: usize = 16;
+
+/// Encryption of stored values under a device passphrase: ChaCha20-Poly1305
+/// with a key derived by Argon2id, which makes guessing the passphrase of a
+/// seized device memory-hard. Each value is bound to its sled key, so
+/// ciphertexts cannot be swapped between entries.
+#[derive(Clone)]
+pub struct StoreCipher {
+    key: Arc<LessSafeKey>,
+    /// Keys content addresses, derived from the same secret.
+    ids: hmac::Key,
+}
+
+impl StoreCipher {
+    pub fn derive(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let params = Params::new(STORE_KDF_MEMORY_KIB, STORE_KDF_PASSES, 1, Some(32))
+            .map_err(|e| anyhow::anyhow!("store key parameters: {e}"))?;
+        let mut key = [0; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("store key derivation failed: {e}"))?;
+        let ids = hkdf::Salt::new(hkdf::HKDF_SHA256, &[])
+            .extract(&key)
+            .expand(&[b"disaster-mesh store ids"], hmac::HMAC_SHA256)
+            .map_err(|_| anyhow::anyhow!("store id key derivation failed"))?
+            .into();
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key).expect("32-byte key");
+        Ok(Self {
+            key: Arc::new(LessSafeKey::new(key)),
+            ids,
+        })
+    }
+
+    /// Encrypt `plain` for storage under `key`: a random nonce followed by
+    /// the ciphertext and tag.
+    pub fn seal(&self, key: &[u8], plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
+        let mut sealed = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(key),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("store encryption failed"))?;
+        sealed.splice(0..0, nonce);
+        Ok(sealed)
+    }
+
+    /// Inverse of [`seal`](Self::seal). Fails for a wrong passphrase, a
+    /// value moved from another key, or tampering.
+    pub fn open(&self, key: &[u8], sealed: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("stored value too short to be encrypted");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).expect("nonce length checked");
+        let mut plain = ciphertext.to_vec();
+        let len = self
+            .key
+            .open_in_place(nonce, Aad::from(key), &mut plain)
+            .map_err(|_| anyhow::anyhow!("cannot decrypt stored value: wrong key or corrupted"))?
+            .len();
+        plain.truncate(len);
+        Ok(plain)
+    }
+
+    /// Content address for `data` under this store's key, so stored keys do
+    /// not reveal which known content the store holds.
+    pub(crate) fn content_id(&self, data: &[u8]) -> Vec<u8> {
+        hmac::sign(&self.ids, data).as_ref().to_vec()
+    }
+}
@@ -0,0 +1,117 @@
+use crate::clock::{Clock, SystemClock};
+use crate::message::MessagePriority;
+use crate::types::{MessageId, Timestamp, UserId};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default number of overheard copies after which a held rebroadcast is
+/// considered redundant.
+pub const DEFAULT_SUPPRESSION_THRESHOLD: u32 = 3;
+
+/// Default time a rebroadcast is held while counting overheard copies.
+pub const DEFAULT_ASSESSMENT_DELAY: Duration = Duration::from_millis(250);
+
+/// A rebroadcast waiting out its assessment.
+#[derive(Debug, Clone)]
+pub struct HeldRebroadcast {
+    /// Fair-queue flow the rebroadcast is relayed on: the message's sender.
+    pub flow: UserId,
+    pub priority: MessagePriority,
+    /// Our encoded rebroadcast.
+    pub data: Vec<u8>,
+}
+
+struct Assessment {
+    first_heard: Timestamp,
+    /// Copies heard since the first one.
+    duplicates: u32,
+    rebroadcast: HeldRebroadcast,
+}
+
+/// Counter-based broadcast-storm mitigation: a rebroadcast is held for an
+/// assessment delay after the message is first heard, and dropped if
+/// `threshold` or more further copies were overheard meanwhile, since the
+/// neighbours have evidently covered the area already.
+#[derive(Clone)]
+pub struct BroadcastSuppression {
+    pending: Arc<Mutex<HashMap<MessageId, Assessment>>>,
+    threshold: u32,
+    delay: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl BroadcastSuppression {
+    pub fn new(threshold: u32, delay: Duration) -> Self {
+        Self::with_clock(threshold, delay, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(threshold: u32, delay: Duration, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            threshold,
+            delay,
+            clock,
+        }
+    }
+
+    /// Record overhearing a copy of `id`. Only counts while a rebroadcast
+    /// of `id` is held.
+    pub fn overheard(&self, id: MessageId) {
+        if let Some(assessment) = self.pending.lock().unwrap().get_mut(&id) {
+            assessment.duplicates += 1;
+        }
+    }
+
+    /// Hold our rebroadcast of `id`, first heard now, until its assessment
+    /// ends.
+    pub fn hold(&self, id: MessageId, rebroadcast: HeldRebroadcast) {
+        let first_heard = self.clock.now();
+        self.pending.lock().unwrap().insert(
+            id,
+            Assessment {
+                first_heard,
+                duplicates: 0,
+                rebroadcast,
+            },
+        );
+    }
+
+    /// End assessments whose delay has elapsed. Returns the rebroadcasts
+    /// still worth sending and how many were suppressed. Call periodically.
+    pub fn release(&self) -> (Vec<HeldRebroadcast>, usize) {
+        let now = self.clock.now();
+        let mut pending = self.pending.lock().unwrap();
+        let due: Vec<_> = pending
+            .iter()
+            .filter(|(_, a)| now.duration_since(a.first_heard).unwrap_or_default() >= self.delay)
+            .map(|(id, _)| *id)
+            .collect();
+        let (mut send, mut suppressed) = (Vec::new(), 0);
+        for id in due {
+            let assessment = pending.remove(&id).expect("collected above");
+            if assessment.duplicates >= self.threshold {
+                suppressed += 1;
+            } else {
+                send.push(assessment.rebroadcast);
+            }
+        }
+        (send, suppressed)
+    }
+
+    /// How long a rebroadcast is held while copies are counted.
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Rebroadcasts waiting for their assessment to end.
+    pub fn held(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+}
+
+impl Default for BroadcastSuppression {
+    fn default() -> Self {
+        Self::new(DEFAULT_SUPPRESSION_THRESHOLD, DEFAULT_ASSESSMENT_DELAY)
+    }
+}
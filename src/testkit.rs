@@ -0,0 +1,402 @@
+//! Test helpers, compiled only with the `testkit` feature.
+
+use crate::forwarding::{AodvReactive, Forwarder};
+use crate::identity::Identity;
+use crate::message::{Message, MessageContent};
+use crate::message_manager::{MessageFilter, MessageManager};
+use crate::node::MeshNode;
+use crate::routing::RoutingEngine;
+use crate::routing_control::RoutingControl;
+use crate::transport::{Transport, TransportEvent, DEFAULT_EVENT_CAPACITY};
+use crate::types::{PeerId, UserId};
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+thread_local! {
+    static ID_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// Make [`MessageId::new`](crate::MessageId::new) and
+/// [`UserId::random`](crate::UserId::random) on this thread draw from an RNG
+/// seeded with `seed`, so the same seed yields the same ids. Ids made on
+/// other threads (e.g. multi-threaded runtime workers) stay random.
+pub fn seed_ids(seed: u64) {
+    ID_RNG.with(|rng| *rng.borrow_mut() = Some(StdRng::seed_from_u64(seed)));
+}
+
+/// Return this thread to random ids.
+pub fn unseed_ids() {
+    ID_RNG.with(|rng| *rng.borrow_mut() = None);
+}
+
+/// Run `f` with ids seeded by `seed`, restoring random ids afterwards.
+pub fn with_seeded_ids<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            unseed_ids();
+        }
+    }
+    seed_ids(seed);
+    let _reset = Reset;
+    f()
+}
+
+/// Next value from the seeded RNG, if this thread has one.
+pub(crate) fn seeded_bytes<const N: usize>() -> Option<[u8; N]> {
+    ID_RNG.with(|rng| {
+        let mut rng = rng.borrow_mut();
+        let rng = rng.as_mut()?;
+        let mut bytes = [0; N];
+        rng.fill_bytes(&mut bytes);
+        Some(bytes)
+    })
+}
+
+/// Upper bound on events [`MeshSimulator::run_until_idle`] processes before
+/// assuming a forwarding storm.
+pub const DEFAULT_MAX_SIM_STEPS: usize = 100_000;
+
+/// Topology and in-flight events shared by a simulation's transports.
+struct SimState {
+    /// `links[a][b]`: whether `b` hears `a`.
+    links: Vec<Vec<bool>>,
+    /// Events waiting to be handed to node `.0`, oldest first.
+    queue: VecDeque<(usize, TransportEvent)>,
+}
+
+/// Transport of one simulated node. Frames go into the simulation's queue
+/// and reach a neighbour only when [`MeshSimulator::step`] hands them over,
+/// and only if the link is still up then.
+pub struct SimTransport {
+    index: usize,
+    peers: Vec<PeerId>,
+    state: Arc<Mutex<SimState>>,
+    events: broadcast::Sender<TransportEvent>,
+}
+
+impl SimTransport {
+    fn neighbours(&self) -> Vec<usize> {
+        let state = self.state.lock().unwrap();
+        (0..self.peers.len())
+            .filter(|&to| to != self.index && state.links[self.index][to])
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Transport for SimTransport {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        let Some(to) = self.peers.iter().position(|p| *p == peer) else {
+            anyhow::bail!("unknown simulated peer");
+        };
+        let mut state = self.state.lock().unwrap();
+        if to == self.index || !state.links[self.index][to] {
+            anyhow::bail!("no simulated link to {peer}");
+        }
+        let event = TransportEvent::DataReceived {
+            peer: self.peers[self.index],
+            data,
+        };
+        state.queue.push_back((to, event));
+        Ok(())
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        for to in self.neighbours() {
+            self.send(self.peers[to], data.clone()).await?;
+        }
+        Ok(())
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.neighbours()
+            .into_iter()
+            .map(|to| self.peers[to])
+            .collect()
+    }
+
+    /// Events are handed to nodes by the simulator, never through here.
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events.subscribe()
+    }
+
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    fn link_quality(&self) -> f32 {
+        1.0
+    }
+}
+
+/// Deterministic multi-node mesh for integration tests. Every node is a
+/// [`MeshNode`] forwarding with [`AodvReactive`]; who hears whom is an
+/// adjacency matrix that can change mid-run. Nothing runs in the background:
+/// transmissions queue up and are delivered one at a time, in order, by
+/// [`step`](Self::step) or [`run_until_idle`](Self::run_until_idle), so a
+/// flood spreads breadth-first and every run of a test behaves the same.
+pub struct MeshSimulator {
+    nodes: Vec<MeshNode>,
+    transports: Vec<Arc<dyn Transport>>,
+    peers: Vec<PeerId>,
+    state: Arc<Mutex<SimState>>,
+    max_steps: usize,
+    /// Request id of the next announcement round.
+    round: AtomicU32,
+}
+
+impl MeshSimulator {
+    /// Nodes `0..adjacency.len()` where `adjacency[a][b]` means `b` hears
+    /// `a`. Node `i`'s keys and peer id are derived from `i`.
+    pub async fn new(adjacency: Vec<Vec<bool>>) -> Result<Self> {
+        let n = adjacency.len();
+        if adjacency.iter().any(|row| row.len() != n) {
+            anyhow::bail!("adjacency matrix must be square");
+        }
+        let peers: Vec<PeerId> = (0..n).map(sim_peer).collect();
+        let state = Arc::new(Mutex::new(SimState {
+            links: adjacency,
+            queue: VecDeque::new(),
+        }));
+        let mut nodes = Vec::with_capacity(n);
+        let mut transports = Vec::with_capacity(n);
+        for (index, peer) in peers.iter().enumerate() {
+            let (events, _) = broadcast::channel(DEFAULT_EVENT_CAPACITY);
+            let transport: Arc<dyn Transport> = Arc::new(SimTransport {
+                index,
+                peers: peers.clone(),
+                state: state.clone(),
+                events,
+            });
+            let identity = sim_identity(index);
+            let routing = RoutingEngine::new(Duration::from_secs(3600));
+            let forwarder = Forwarder::new(
+                identity.clone(),
+                *peer,
+                Arc::new(AodvReactive::new(routing.clone())),
+                transport.clone(),
+            );
+            let messages = MessageManager::in_memory().await?;
+            let node = MeshNode::new(identity, *peer, transport.clone(), messages)
+                .with_routing(routing)
+                .with_forwarder(forwarder);
+            nodes.push(node);
+            transports.push(transport);
+        }
+        Ok(Self {
+            nodes,
+            transports,
+            peers,
+            state,
+            max_steps: DEFAULT_MAX_SIM_STEPS,
+            round: AtomicU32::new(0),
+        })
+    }
+
+    /// `n` nodes in a line, each hearing only its direct neighbours.
+    pub async fn line(n: usize) -> Result<Self> {
+        let adjacency = (0..n)
+            .map(|a| (0..n).map(|b| a.abs_diff(b) == 1).collect())
+            .collect();
+        Self::new(adjacency).await
+    }
+
+    /// Give up in [`run_until_idle`](Self::run_until_idle) after `steps`
+    /// events (default [`DEFAULT_MAX_SIM_STEPS`]).
+    pub fn with_max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = steps;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, index: usize) -> &MeshNode {
+        &self.nodes[index]
+    }
+
+    pub fn peer(&self, index: usize) -> PeerId {
+        self.peers[index]
+    }
+
+    pub fn user(&self, index: usize) -> UserId {
+        self.nodes[index].user_id()
+    }
+
+    /// Bring the link between `a` and `b` up or down in both directions,
+    /// reporting the change to both ends as `PeerConnected` or
+    /// `PeerDisconnected`. Frames in flight over a link that goes down are
+    /// lost.
+    pub fn set_link(&self, a: usize, b: usize, up: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.links[a][b] == up && state.links[b][a] == up {
+            return;
+        }
+        state.links[a][b] = up;
+        state.links[b][a] = up;
+        let event = |peer| match up {
+            true => TransportEvent::PeerConnected(peer),
+            false => TransportEvent::PeerDisconnected(peer),
+        };
+        state.queue.push_back((a, event(self.peers[b])));
+        state.queue.push_back((b, event(self.peers[a])));
+    }
+
+    pub fn is_linked(&self, a: usize, b: usize) -> bool {
+        self.state.lock().unwrap().links[a][b]
+    }
+
+    /// Deliver the oldest queued event. Returns `false` once nothing is
+    /// queued.
+    pub async fn step(&self) -> bool {
+        let next = {
+            let mut state = self.state.lock().unwrap();
+            let Some((to, event)) = state.queue.pop_front() else {
+                return false;
+            };
+            let live = match &event {
+                TransportEvent::DataReceived { peer, .. } => {
+                    let from = self.index_of(peer);
+                    state.links[from][to]
+                }
+                _ => true,
+            };
+            live.then_some((to, event))
+        };
+        if let Some((to, event)) = next {
+            self.nodes[to].handle_event(event).await;
+        }
+        true
+    }
+
+    /// Deliver events until none are left, returning how many were
+    /// processed. Fails if that takes more than the step limit.
+    pub async fn run_until_idle(&self) -> Result<usize> {
+        let mut steps = 0;
+        while self.step().await {
+            steps += 1;
+            if steps > self.max_steps {
+                anyhow::bail!("simulation still busy after {steps} steps");
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Have every node flood a route request, so all others learn a route
+    /// back to it, and run until the floods die out.
+    pub async fn announce_all(&self) -> Result<()> {
+        let request_id = self.round.fetch_add(1, Ordering::Relaxed);
+        for (index, transport) in self.transports.iter().enumerate() {
+            let identity = sim_identity(index);
+            let origin = identity.user_id();
+            let rreq = RoutingControl::Rreq {
+                origin,
+                destination: self.user((index + 1) % self.len()),
+                request_id,
+                hop_count: 0,
+            };
+            let mut msg = Message::new(origin, None, MessageContent::Routing(rreq));
+            msg.sign(&identity)?;
+            // Our own flood echoing back must not teach us a route to
+            // ourselves.
+            self.nodes[index]
+                .messages()
+                .mark_message_seen(&msg.id)
+                .await?;
+            transport.broadcast(transport.encode_message(&msg)?).await?;
+            self.run_until_idle().await?;
+        }
+        Ok(())
+    }
+
+    /// Shortest hop count from `from` to `to` over links that are up now.
+    pub fn distance(&self, from: usize, to: usize) -> Option<u8> {
+        let state = self.state.lock().unwrap();
+        let mut hops = vec![None; self.len()];
+        hops[from] = Some(0u8);
+        let mut frontier = VecDeque::from([from]);
+        while let Some(at) = frontier.pop_front() {
+            for next in 0..self.len() {
+                if state.links[at][next] && hops[next].is_none() {
+                    hops[next] = hops[at].map(|h| h.saturating_add(1));
+                    frontier.push_back(next);
+                }
+            }
+        }
+        hops[to]
+    }
+
+    /// Whether every node's routing table matches the current topology: a
+    /// shortest-path route to each reachable node, through a neighbour it
+    /// can still hear, and none to unreachable ones.
+    pub async fn converged(&self) -> bool {
+        for from in 0..self.len() {
+            let routes = self.nodes[from].routing().dump().await;
+            for to in (0..self.len()).filter(|&to| to != from) {
+                let route = routes.iter().find(|r| r.destination == self.user(to));
+                let ok = match (route, self.distance(from, to)) {
+                    (None, None) => true,
+                    (Some(route), Some(hops)) => {
+                        let via = self.index_of(&route.next_hop);
+                        route.hop_count == hops
+                            && self.is_linked(from, via)
+                            && self.distance(via, to) == Some(hops - 1)
+                    }
+                    _ => false,
+                };
+                if !ok {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Messages addressed to node `index` that reached its store.
+    pub async fn delivered(&self, index: usize) -> Result<Vec<Message>> {
+        self.nodes[index]
+            .messages()
+            .list_messages(&MessageFilter {
+                recipient: Some(self.user(index)),
+                ..MessageFilter::default()
+            })
+            .await
+    }
+
+    fn index_of(&self, peer: &PeerId) -> usize {
+        self.peers
+            .iter()
+            .position(|p| p == peer)
+            .expect("peer belongs to the simulation")
+    }
+}
+
+fn sim_peer(index: usize) -> PeerId {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(&(index as u64 + 1).to_be_bytes());
+    PeerId(bytes)
+}
+
+fn sim_identity(index: usize) -> Identity {
+    let mut secret = [0x5a; 32];
+    secret[..8].copy_from_slice(&(index as u64).to_be_bytes());
+    Identity::from_secret_bytes(&secret)
+}
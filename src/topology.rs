@@ -0,0 +1,59 @@
+use crate::neighbor::NeighborTable;
+use crate::routing::RoutingEngine;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Render the local view of the mesh as a Graphviz DOT digraph, suitable for
+/// piping into `dot -Tsvg`. The local node links to each neighbour (edge
+/// labelled with link quality), and each neighbour links to the destinations
+/// routed through it (labelled with hop count and link quality).
+pub async fn topology_dot(routing: &RoutingEngine, neighbors: &NeighborTable) -> String {
+    let mut neighbours = neighbors.list().await;
+    neighbours.sort_by_key(|n| n.peer.0);
+    let mut routes = routing.dump().await;
+    routes.sort_by_key(|r| r.destination.0);
+
+    let mut out = String::from("digraph mesh {\n");
+    out.push_str("    \"self\" [shape=doublecircle];\n");
+
+    let peers: BTreeSet<_> = neighbours
+        .iter()
+        .map(|n| n.peer.0)
+        .chain(routes.iter().map(|r| r.next_hop.0))
+        .collect();
+    for peer in &peers {
+        let _ = writeln!(out, "    \"peer:{}\" [shape=box];", short_hex(peer));
+    }
+    for route in &routes {
+        let _ = writeln!(
+            out,
+            "    \"user:{}\" [shape=ellipse];",
+            short_hex(&route.destination.0)
+        );
+    }
+    for n in &neighbours {
+        let _ = writeln!(
+            out,
+            "    \"self\" -> \"peer:{}\" [label=\"q={:.2}\"];",
+            short_hex(&n.peer.0),
+            n.link_quality
+        );
+    }
+    for route in &routes {
+        let _ = writeln!(
+            out,
+            "    \"peer:{}\" -> \"user:{}\" [label=\"hops={} q={:.2}\"];",
+            short_hex(&route.next_hop.0),
+            short_hex(&route.destination.0),
+            route.hop_count,
+            route.link_quality
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// First four bytes in hex; enough to tell nodes apart on a diagram.
+fn short_hex(bytes: &[u8; 32]) -> String {
+    bytes[..4].iter().map(|b| format!("{b:02x}")).collect()
+}
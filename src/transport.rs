@@ -1,10 +1,22 @@
 use async_trait::async_trait;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
 
+use crate::address_book::PeerAddress;
+use crate::compression::CompressionAlgorithm;
+use crate::framing::Framing;
+use crate::message::Message;
+use crate::stats::ChannelOccupancy;
 use crate::types::PeerId;
+use crate::wire::WireFormat;
 use anyhow::Result;
 
+/// Default capacity of a transport's event channels.
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone)]
 pub enum TransportEvent {
     PeerConnected(PeerId),
@@ -16,10 +28,37 @@ pub enum TransportEvent {
 #[async_trait]
 pub trait Transport: Send + Sync {
     async fn start(&mut self) -> Result<()>;
+
+    /// Stop background tasks, close sockets and emit a final
+    /// `PeerDisconnected` for each connected peer. No-op by default.
+    async fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Send `data` to one neighbour. The result is that link's outcome, so
+    /// callers can retry or report the peer as unreachable.
     async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()>;
     async fn broadcast(&self, data: Vec<u8>) -> Result<()>;
+
+    /// Like [`broadcast`](Self::broadcast), but reports the outcome for each
+    /// peer, e.g. to raise a RERR for neighbours that have gone away. By
+    /// default this is a [`send`](Self::send) to every connected peer.
+    async fn broadcast_detailed(&self, data: Vec<u8>) -> Vec<(PeerId, Result<()>)> {
+        let mut results = Vec::new();
+        for peer in self.get_peers() {
+            results.push((peer, self.send(peer, data.clone()).await));
+        }
+        results
+    }
     fn get_peers(&self) -> Vec<PeerId>;
     fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent>;
+
+    /// Lossless alternative to [`subscribe_events`](Self::subscribe_events):
+    /// events are never dropped, and a consumer that falls behind slows the
+    /// transport down instead. `None` if the transport does not support it.
+    fn subscribe_events_reliable(&self) -> Option<mpsc::Receiver<TransportEvent>> {
+        None
+    }
     /// Maximum Transmission Unit (bytes) supported by this transport. Used for
     /// message fragmentation decisions at higher layers.
     fn mtu(&self) -> usize;
@@ -28,23 +67,240 @@ pub trait Transport: Send + Sync {
     /// an instantaneous metric that routing algorithms can leverage when
     /// selecting paths.
     fn link_quality(&self) -> f32;
+
+    /// Typical one-hop latency of this link, if the transport knows it
+    /// (e.g. from airtime or ping measurements). Used for route latency
+    /// estimates.
+    fn latency_hint(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Whether the link itself already provides confidentiality (e.g. an
+    /// encrypted BLE link or TLS). When true the message pipeline may skip
+    /// application-layer encryption to save work on constrained hardware.
+    fn is_secure(&self) -> bool {
+        false
+    }
+
+    /// Encoding used for messages framed onto this link.
+    fn wire_format(&self) -> WireFormat {
+        WireFormat::Bincode
+    }
+
+    /// How encoded messages are delimited on this link. Stream transports
+    /// wrap outgoing payloads with [`Framing::frame`] and split what they
+    /// read with a [`FrameDecoder`](crate::FrameDecoder) per connection.
+    fn framing(&self) -> Framing {
+        Framing::Datagram
+    }
+
+    /// Compression this link applies to outgoing payloads in
+    /// [`encode_message`](Self::encode_message). Both ends must agree on
+    /// whether the link compresses, but not on the algorithm: receivers
+    /// follow the one named in each frame's header.
+    fn compression(&self) -> CompressionAlgorithm {
+        CompressionAlgorithm::None
+    }
+
+    /// Serialize `msg` for this link in its [`wire_format`](Self::wire_format),
+    /// compressed behind a one-byte algorithm header unless
+    /// [`compression`](Self::compression) is `None`.
+    fn encode_message(&self, msg: &Message) -> Result<Vec<u8>> {
+        match self.compression() {
+            CompressionAlgorithm::None => self.wire_format().encode(msg),
+            algorithm => self
+                .wire_format()
+                .encode_compressed(msg, algorithm.compressor().as_ref()),
+        }
+    }
+
+    /// Inverse of [`encode_message`](Self::encode_message).
+    fn decode_message(&self, data: &[u8]) -> Result<Message> {
+        match self.compression() {
+            CompressionAlgorithm::None => self.wire_format().decode(data),
+            _ => self.wire_format().decode_compressed(data),
+        }
+    }
+
+    /// Open a link to a peer last seen at `address`, e.g. one remembered
+    /// in an [`AddressBook`](crate::AddressBook). Fails for address kinds
+    /// the transport does not dial, which by default is all of them.
+    async fn connect(&self, address: &PeerAddress) -> Result<()> {
+        anyhow::bail!("transport cannot dial {address:?}")
+    }
+
+    /// Address `peer` can be dialed at again with [`connect`](Self::connect),
+    /// if the transport knows one. None by default.
+    fn peer_address(&self, _peer: PeerId) -> Option<PeerAddress> {
+        None
+    }
+
+    /// How full the event channel currently is, if the transport tracks it.
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        None
+    }
+
+    /// Whether the transport's background loop is still running. Transports
+    /// that can detect their own failure (e.g. an unplugged serial device)
+    /// report it here for [`TransportWatchdog`](crate::TransportWatchdog).
+    fn is_alive(&self) -> bool {
+        true
+    }
+}
+
+/// Transport event fan-out supporting both subscription styles: broadcast
+/// receivers, which skip events when they lag (fine for telemetry), and
+/// bounded mpsc receivers, which never lose an event but make
+/// [`publish`](Self::publish) wait while they are full.
+#[derive(Clone)]
+pub struct EventFanout {
+    tx: broadcast::Sender<TransportEvent>,
+    reliable: Arc<std::sync::Mutex<Vec<mpsc::Sender<TransportEvent>>>>,
+    capacity: usize,
+}
+
+impl EventFanout {
+    /// Fan-out whose channels each hold `capacity` events (at least one;
+    /// the channels cannot be empty).
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            tx: broadcast::channel(capacity).0,
+            reliable: Arc::new(std::sync::Mutex::new(Vec::new())),
+            capacity,
+        }
+    }
+
+    /// Hand `event` to every subscriber, waiting for room in each reliable
+    /// subscriber's queue. Reliable subscribers that went away are pruned.
+    pub async fn publish(&self, event: TransportEvent) {
+        let _ = self.tx.send(event.clone());
+        let reliable = self.reliable.lock().unwrap().clone();
+        let mut closed = false;
+        for sub in &reliable {
+            closed |= sub.send(event.clone()).await.is_err();
+        }
+        if closed {
+            self.reliable.lock().unwrap().retain(|sub| !sub.is_closed());
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TransportEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn subscribe_reliable(&self) -> mpsc::Receiver<TransportEvent> {
+        let (tx, rx) = mpsc::channel(self.capacity);
+        self.reliable.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// Fill level of the broadcast side.
+    pub fn occupancy(&self) -> ChannelOccupancy {
+        ChannelOccupancy::of(&self.tx, self.capacity)
+    }
 }
 
 /// A basic in-memory mock transport useful for early tests
 #[derive(Clone)]
 pub struct MockTransport {
     peers: Arc<RwLock<Vec<PeerId>>>,
-    tx: broadcast::Sender<TransportEvent>,
+    /// Connected peers whose link is down: sends to them fail.
+    unreachable: Arc<RwLock<HashSet<PeerId>>>,
+    events: EventFanout,
+    format: WireFormat,
+    compression: CompressionAlgorithm,
+    mtu: usize,
+    latency: Option<Duration>,
+    alive: Arc<AtomicBool>,
 }
 
 impl MockTransport {
     pub fn new() -> Self {
-        let (tx, _) = broadcast::channel(1024);
+        Self::with_capacity(DEFAULT_EVENT_CAPACITY)
+    }
+
+    /// Mock transport whose event channel holds `capacity` events.
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
             peers: Arc::new(RwLock::new(Vec::new())),
-            tx,
+            unreachable: Arc::new(RwLock::new(HashSet::new())),
+            events: EventFanout::new(capacity),
+            format: WireFormat::default(),
+            compression: CompressionAlgorithm::None,
+            mtu: 1500,
+            latency: None,
+            alive: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Frame messages on this mock link with `format`.
+    pub fn with_wire_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compress messages on this mock link with `compression`.
+    pub fn with_compression(mut self, compression: CompressionAlgorithm) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Report `mtu` as this link's MTU (default 1500).
+    pub fn with_mtu(mut self, mtu: usize) -> Self {
+        self.mtu = mtu;
+        self
+    }
+
+    /// Report `latency` as this link's latency hint.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Connect `peer`, emitting `PeerConnected`. Already connected peers are
+    /// left alone.
+    pub async fn add_peer(&self, peer: PeerId) {
+        let mut peers = self.peers.write().await;
+        if peers.contains(&peer) {
+            return;
+        }
+        peers.push(peer);
+        drop(peers);
+        self.events
+            .publish(TransportEvent::PeerConnected(peer))
+            .await;
+    }
+
+    /// Simulate `peer`'s link failing (or recovering) without disconnecting
+    /// it: sends to an unreachable peer return an error.
+    pub async fn set_reachable(&self, peer: PeerId, reachable: bool) {
+        let mut unreachable = self.unreachable.write().await;
+        if reachable {
+            unreachable.remove(&peer);
+        } else {
+            unreachable.insert(peer);
         }
     }
+
+    /// Simulate the transport's background loop dying (or coming back), as
+    /// reported by [`Transport::is_alive`]. `start` revives it.
+    pub fn set_alive(&self, alive: bool) {
+        self.alive.store(alive, Ordering::Relaxed);
+    }
+
+    /// Disconnect `peer`, emitting `PeerDisconnected` if it was connected.
+    pub async fn remove_peer(&self, peer: PeerId) {
+        let mut peers = self.peers.write().await;
+        let Some(pos) = peers.iter().position(|p| *p == peer) else {
+            return;
+        };
+        peers.remove(pos);
+        drop(peers);
+        self.events
+            .publish(TransportEvent::PeerDisconnected(peer))
+            .await;
+    }
 }
 
 impl Default for MockTransport {
@@ -56,24 +312,37 @@ impl Default for MockTransport {
 #[async_trait]
 impl Transport for MockTransport {
     async fn start(&mut self) -> Result<()> {
-        // No-op for mock
+        // Nothing to open for the mock; just mark it running.
+        self.alive.store(true, Ordering::Relaxed);
         Ok(())
     }
 
     async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
-        let _ = self.tx.send(TransportEvent::DataReceived { peer, data });
+        if self.unreachable.read().await.contains(&peer) {
+            anyhow::bail!("mock peer unreachable");
+        }
+        self.events
+            .publish(TransportEvent::DataReceived { peer, data })
+            .await;
         Ok(())
     }
 
     async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        for (peer, result) in self.broadcast_detailed(data).await {
+            if let Err(e) = result {
+                tracing::warn!("broadcast to {peer:?} failed: {e:#}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn broadcast_detailed(&self, data: Vec<u8>) -> Vec<(PeerId, Result<()>)> {
         let peers = self.peers.read().await.clone();
+        let mut results = Vec::with_capacity(peers.len());
         for peer in peers {
-            let _ = self.tx.send(TransportEvent::DataReceived {
-                peer,
-                data: data.clone(),
-            });
+            results.push((peer, self.send(peer, data.clone()).await));
         }
-        Ok(())
+        results
     }
 
     fn get_peers(&self) -> Vec<PeerId> {
@@ -81,17 +350,47 @@ impl Transport for MockTransport {
     }
 
     fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
-        self.tx.subscribe()
+        self.events.subscribe()
+    }
+
+    fn subscribe_events_reliable(&self) -> Option<mpsc::Receiver<TransportEvent>> {
+        Some(self.events.subscribe_reliable())
     }
 
     fn mtu(&self) -> usize {
-        // In-memory mock – effectively unlimited but return a large common
-        // Ethernet MTU to exercise fragmentation logic in higher layers.
-        1500
+        // In-memory mock – effectively unlimited but report a common
+        // Ethernet MTU (or the configured one) to exercise fragmentation
+        // logic in higher layers.
+        self.mtu
     }
 
     fn link_quality(&self) -> f32 {
         // Perfect connection for mock transport.
         1.0
     }
+
+    fn latency_hint(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    fn is_secure(&self) -> bool {
+        // Plain in-memory channel – never skip encryption.
+        false
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.format
+    }
+
+    fn compression(&self) -> CompressionAlgorithm {
+        self.compression
+    }
+
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        Some(self.events.occupancy())
+    }
+
+    fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Relaxed)
+    }
 }
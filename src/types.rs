@@ -1,5 +1,8 @@
+use crate::message::MessageContent;
 use serde::{Deserialize, Serialize};
-use std::time::{Duration, SystemTime};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 /// Unique identifier for a message (UUID v4)
@@ -8,7 +11,17 @@ pub struct MessageId(Uuid);
 
 impl MessageId {
     pub fn new() -> Self {
-        Self(Uuid::new_v4())
+        Self(uuid::Builder::from_random_bytes(random_bytes()).into_uuid())
+    }
+
+    /// Content-addressed id: identical `content` from the same `sender` in
+    /// the same `timestamp_bucket` always yields the same id, so re-sends of
+    /// one bulletin from different relays are deduplicated by seen-sets.
+    pub fn from_content(sender: &UserId, content: &MessageContent, timestamp_bucket: u64) -> Self {
+        let mut name = sender.0.to_vec();
+        name.extend_from_slice(&timestamp_bucket.to_le_bytes());
+        name.extend(bincode::serialize(content).unwrap_or_default());
+        Self(Uuid::new_v5(&Uuid::NAMESPACE_OID, &name))
     }
 
     pub fn to_bytes(self) -> [u8; 16] {
@@ -25,28 +38,143 @@ impl MessageId {
     }
 }
 
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 impl Default for MessageId {
     fn default() -> Self {
         Self::new()
     }
 }
 
-/// Public-key hash identifying a user
+/// Ed25519 public key identifying a user
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct UserId(pub [u8; 32]);
 
 impl UserId {
     pub fn random() -> Self {
-        Self(rand::random())
+        Self(random_bytes())
+    }
+
+    /// First [`SHORT_ID_LEN`] hex digits, for logs and UIs.
+    pub fn to_short(&self) -> String {
+        short_hex(&self.0)
+    }
+}
+
+/// Lowercase hex of the public key; parsed back by [`FromStr`].
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl FromStr for UserId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_hex(s).map(Self)
+    }
+}
+
+/// Number of hex digits shown by `to_short`.
+pub const SHORT_ID_LEN: usize = 8;
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|b| write!(f, "{b:02x}"))
+}
+
+fn short_hex(bytes: &[u8]) -> String {
+    bytes[..SHORT_ID_LEN / 2]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Parse exactly 64 hex digits (either case) into a 32-byte id.
+fn parse_hex(s: &str) -> anyhow::Result<[u8; 32]> {
+    let s = s.trim();
+    if s.len() != 64 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("expected 64 hex digits, got {s:?}");
+    }
+    let mut bytes = [0; 32];
+    for (byte, pair) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).expect("checked hex digits");
+        *byte = u8::from_str_radix(pair, 16).expect("checked hex digits");
     }
+    Ok(bytes)
+}
+
+/// Randomness for generated ids; seedable per thread under `testkit`.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    #[cfg(feature = "testkit")]
+    if let Some(bytes) = crate::testkit::seeded_bytes() {
+        return bytes;
+    }
+    let mut bytes = [0; N];
+    rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut bytes);
+    bytes
 }
 
 /// Identifier for a peer device (transport-specific)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PeerId(pub [u8; 32]);
 
+impl PeerId {
+    /// First [`SHORT_ID_LEN`] hex digits, for logs and UIs.
+    pub fn to_short(&self) -> String {
+        short_hex(&self.0)
+    }
+}
+
+/// Lowercase hex; parsed back by [`FromStr`].
+impl fmt::Display for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_hex(f, &self.0)
+    }
+}
+
+impl FromStr for PeerId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_hex(s).map(Self)
+    }
+}
+
 /// Helper alias used in several structs
 pub type Timestamp = SystemTime;
 
+/// Milliseconds since the Unix epoch, the wire representation of a
+/// [`Timestamp`]. Times before the epoch clamp to 0.
+pub fn timestamp_to_millis(t: Timestamp) -> u64 {
+    t.duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Inverse of [`timestamp_to_millis`].
+pub fn timestamp_from_millis(millis: u64) -> Timestamp {
+    UNIX_EPOCH + Duration::from_millis(millis)
+}
+
+/// Serde adapter storing a [`Timestamp`] as `u64` milliseconds since the
+/// epoch, for `#[serde(with = "timestamp_millis")]`. Unlike serde's default
+/// `SystemTime` encoding it is identical on every platform.
+pub mod timestamp_millis {
+    use super::{timestamp_from_millis, timestamp_to_millis, Timestamp};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(t: &Timestamp, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(timestamp_to_millis(*t))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Timestamp, D::Error> {
+        u64::deserialize(deserializer).map(timestamp_from_millis)
+    }
+}
+
 /// Standard TTL used when none specified
 pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
@@ -0,0 +1,38 @@
+use crate::message::Message;
+use std::fmt;
+
+/// Why a [`MessageValidator`] refused a message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RejectReason {
+    /// The sender may not post under this policy.
+    Sender,
+    /// The content exceeds a size ceiling.
+    TooLarge,
+    /// The content failed a policy check (keywords, format, ...).
+    Content(String),
+    /// A signature was required but is missing or invalid.
+    Unauthenticated,
+    /// An unsequenced, replayed or out-of-date broadcast.
+    Replay,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::Sender => write!(f, "sender not allowed"),
+            RejectReason::TooLarge => write!(f, "content too large"),
+            RejectReason::Content(why) => write!(f, "content rejected: {why}"),
+            RejectReason::Unauthenticated => write!(f, "missing or invalid signature"),
+            RejectReason::Replay => write!(f, "replayed or stale broadcast"),
+        }
+    }
+}
+
+impl std::error::Error for RejectReason {}
+
+/// Deployment-specific acceptance policy, run by
+/// [`MessageManager::validate_message`](crate::MessageManager::validate_message)
+/// after the built-in TTL and signature checks.
+pub trait MessageValidator: Send + Sync {
+    fn validate(&self, msg: &Message) -> Result<(), RejectReason>;
+}
@@ -0,0 +1,173 @@
+use crate::clock::{Clock, SystemClock};
+use crate::transport::Transport;
+use crate::types::Timestamp;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How often [`TransportWatchdog::spawn`] checks the transport by default.
+pub const DEFAULT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Health of a watched transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportHealth {
+    Healthy,
+    /// No events for longer than the silence timeout.
+    Silent,
+    /// The transport reports its background loop has stopped.
+    Dead,
+}
+
+/// Reinitializes a failed transport, e.g. by reopening a serial device.
+pub type RestartFn = Arc<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+/// Watches a transport for a dead background loop (via
+/// [`Transport::is_alive`]) or, optionally, for too long without any event,
+/// publishes its [`TransportHealth`], and calls a restart hook when it
+/// fails.
+#[derive(Clone)]
+pub struct TransportWatchdog {
+    transport: Arc<dyn Transport>,
+    interval: Duration,
+    silence_timeout: Option<Duration>,
+    restart: Option<RestartFn>,
+    last_activity: Arc<Mutex<Timestamp>>,
+    restarts: Arc<Mutex<u64>>,
+    health: watch::Sender<TransportHealth>,
+    clock: Arc<dyn Clock>,
+}
+
+impl TransportWatchdog {
+    pub fn new(transport: Arc<dyn Transport>) -> Self {
+        Self::with_clock(transport, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(transport: Arc<dyn Transport>, clock: Arc<dyn Clock>) -> Self {
+        let (health, _) = watch::channel(TransportHealth::Healthy);
+        Self {
+            transport,
+            interval: DEFAULT_WATCHDOG_INTERVAL,
+            silence_timeout: None,
+            restart: None,
+            last_activity: Arc::new(Mutex::new(clock.now())),
+            restarts: Arc::new(Mutex::new(0)),
+            health,
+            clock,
+        }
+    }
+
+    /// Check every `interval` when spawned (default
+    /// [`DEFAULT_WATCHDOG_INTERVAL`]).
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Also treat the link as failed after `timeout` without any transport
+    /// event. Only useful on links that are never legitimately quiet that
+    /// long, e.g. ones carrying periodic beacons.
+    pub fn with_silence_timeout(mut self, timeout: Duration) -> Self {
+        self.silence_timeout = Some(timeout);
+        self
+    }
+
+    /// Call `restart` whenever a check finds the transport unhealthy.
+    pub fn with_restart<F>(mut self, restart: F) -> Self
+    where
+        F: Fn() -> BoxFuture<'static, Result<()>> + Send + Sync + 'static,
+    {
+        self.restart = Some(Arc::new(restart));
+        self
+    }
+
+    /// Health as of the last check.
+    pub fn health(&self) -> TransportHealth {
+        *self.health.borrow()
+    }
+
+    /// Watch health changes.
+    pub fn subscribe(&self) -> watch::Receiver<TransportHealth> {
+        self.health.subscribe()
+    }
+
+    /// Successful restarts so far.
+    pub fn restarts(&self) -> u64 {
+        *self.restarts.lock().unwrap()
+    }
+
+    /// Note that the transport showed signs of life just now.
+    pub fn record_activity(&self) {
+        *self.last_activity.lock().unwrap() = self.clock.now();
+    }
+
+    /// Assess the transport once, publish the result and, if it is
+    /// unhealthy, run the restart hook. Returns the health found before
+    /// any restart.
+    pub async fn check(&self) -> TransportHealth {
+        let health = self.assess();
+        self.health.send_replace(health);
+        if health == TransportHealth::Healthy {
+            return health;
+        }
+        tracing::warn!("transport unhealthy: {health:?}");
+        if let Some(restart) = &self.restart {
+            match restart().await {
+                Ok(()) => {
+                    *self.restarts.lock().unwrap() += 1;
+                    self.record_activity();
+                    self.health.send_replace(self.assess());
+                }
+                Err(e) => tracing::warn!("transport restart failed: {e:#}"),
+            }
+        }
+        health
+    }
+
+    fn assess(&self) -> TransportHealth {
+        if !self.transport.is_alive() {
+            return TransportHealth::Dead;
+        }
+        let last = *self.last_activity.lock().unwrap();
+        let silent = self.silence_timeout.is_some_and(|timeout| {
+            self.clock
+                .now()
+                .duration_since(last)
+                .is_ok_and(|idle| idle > timeout)
+        });
+        if silent {
+            TransportHealth::Silent
+        } else {
+            TransportHealth::Healthy
+        }
+    }
+
+    /// Record transport events and check every interval until the task is
+    /// aborted.
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let watchdog = self.clone();
+        tokio::spawn(async move {
+            let mut events = watchdog.transport.subscribe_events();
+            let mut ticker = tokio::time::interval(watchdog.interval);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        watchdog.check().await;
+                    }
+                    event = events.recv() => match event {
+                        Ok(_) | Err(RecvError::Lagged(_)) => watchdog.record_activity(),
+                        // The old channel is gone; wait a tick, then follow
+                        // the restarted one.
+                        Err(RecvError::Closed) => {
+                            ticker.tick().await;
+                            events = watchdog.transport.subscribe_events();
+                        }
+                    },
+                }
+            }
+        })
+    }
+}
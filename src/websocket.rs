@@ -0,0 +1,335 @@
+use crate::address_book::PeerAddress;
+use crate::stats::ChannelOccupancy;
+use crate::transport::{EventFanout, Transport, TransportEvent, DEFAULT_EVENT_CAPACITY};
+use crate::types::PeerId;
+use crate::wire::WireFormat;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::WebSocketStream;
+
+/// Gateway transport bridging browser clients into the mesh. Each WebSocket
+/// connection is a peer; every frame carries one encoded message, JSON by
+/// default. Gateways can also [`connect`](Transport::connect) to each other.
+#[derive(Clone)]
+pub struct WebSocketTransport {
+    bind_addr: SocketAddr,
+    local_addr: Arc<RwLock<Option<SocketAddr>>>,
+    peers: Arc<RwLock<HashMap<PeerId, mpsc::UnboundedSender<Vec<u8>>>>>,
+    /// Listening address of each gateway this side dialed.
+    dialed: Arc<RwLock<HashMap<PeerId, SocketAddr>>>,
+    accept: Arc<Mutex<Option<JoinHandle<()>>>>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// Background tasks whose futures have not yet completed or been
+    /// dropped.
+    running: Arc<AtomicUsize>,
+    events: EventFanout,
+    format: WireFormat,
+}
+
+impl WebSocketTransport {
+    pub fn new(bind_addr: SocketAddr) -> Self {
+        Self::with_capacity(bind_addr, DEFAULT_EVENT_CAPACITY)
+    }
+
+    /// Gateway whose event channel holds `capacity` events.
+    pub fn with_capacity(bind_addr: SocketAddr, capacity: usize) -> Self {
+        Self {
+            bind_addr,
+            local_addr: Arc::new(RwLock::new(None)),
+            peers: Arc::new(RwLock::new(HashMap::new())),
+            dialed: Arc::new(RwLock::new(HashMap::new())),
+            accept: Arc::new(Mutex::new(None)),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            running: Arc::new(AtomicUsize::new(0)),
+            events: EventFanout::new(capacity),
+            format: WireFormat::Json,
+        }
+    }
+
+    /// Frame messages with `format` instead of the browser-friendly JSON
+    /// default.
+    pub fn with_wire_format(mut self, format: WireFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Number of background tasks (accept loop, per-connection readers and
+    /// writers) still running.
+    pub fn active_tasks(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Spawn `task`, counting it in [`active_tasks`](Self::active_tasks)
+    /// until its future finishes or is dropped by an abort.
+    fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) -> JoinHandle<()> {
+        let running = RunningTask::start(&self.running);
+        tokio::spawn(async move {
+            let _running = running;
+            task.await
+        })
+    }
+
+    /// [`spawn`](Self::spawn) a task that [`shutdown`](Transport::shutdown)
+    /// must stop.
+    fn track(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let task = self.spawn(task);
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+    }
+
+    /// Address the server is listening on once started (useful with port 0).
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *self.local_addr.read().unwrap()
+    }
+
+    async fn handle_connection(self, stream: TcpStream) -> Result<()> {
+        let ws = tokio_tungstenite::accept_async(stream)
+            .await
+            .context("websocket handshake")?;
+        self.serve(ws, None).await
+    }
+
+    /// Run one established connection as a new peer until either side
+    /// closes it. `dialed` is the address it was opened to, if this side
+    /// opened it.
+    async fn serve(self, ws: WebSocketStream<TcpStream>, dialed: Option<SocketAddr>) -> Result<()> {
+        let (mut sink, mut stream) = ws.split();
+        let peer = PeerId(rand::random());
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        self.peers.write().unwrap().insert(peer, out_tx);
+        if let Some(addr) = dialed {
+            self.dialed.write().unwrap().insert(peer, addr);
+        }
+        self.events
+            .publish(TransportEvent::PeerConnected(peer))
+            .await;
+
+        let writer = self.spawn(async move {
+            while let Some(data) = out_rx.recv().await {
+                if sink.send(WsMessage::Binary(data)).await.is_err() {
+                    break;
+                }
+            }
+            let _ = sink.close().await;
+        });
+        let writer_abort = writer.abort_handle();
+        self.tasks.lock().unwrap().push(writer);
+
+        while let Some(frame) = stream.next().await {
+            let data = match frame {
+                Ok(WsMessage::Binary(data)) => data,
+                Ok(WsMessage::Text(text)) => text.into_bytes(),
+                Ok(WsMessage::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    self.events
+                        .publish(TransportEvent::Error(e.to_string()))
+                        .await;
+                    break;
+                }
+            };
+            // Waits while a reliable subscriber is full, pausing this read
+            // loop and so applying backpressure to the client.
+            self.events
+                .publish(TransportEvent::DataReceived { peer, data })
+                .await;
+        }
+
+        writer_abort.abort();
+        self.dialed.write().unwrap().remove(&peer);
+        let removed = self.peers.write().unwrap().remove(&peer).is_some();
+        if removed {
+            self.events
+                .publish(TransportEvent::PeerDisconnected(peer))
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn start(&mut self) -> Result<()> {
+        let listener = TcpListener::bind(self.bind_addr)
+            .await
+            .with_context(|| format!("bind websocket gateway on {}", self.bind_addr))?;
+        *self.local_addr.write().unwrap() = Some(listener.local_addr()?);
+
+        let transport = self.clone();
+        let accept = self.spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        transport
+                            .events
+                            .publish(TransportEvent::Error(e.to_string()))
+                            .await;
+                        continue;
+                    }
+                };
+                let conn = transport.clone();
+                transport.track(async move {
+                    if let Err(e) = conn.clone().handle_connection(stream).await {
+                        conn.events
+                            .publish(TransportEvent::Error(format!("{e:#}")))
+                            .await;
+                    }
+                });
+            }
+        });
+        *self.accept.lock().unwrap() = Some(accept);
+        Ok(())
+    }
+
+    async fn shutdown(&mut self) -> Result<()> {
+        // Close the listener first so no connection task starts while the
+        // others are torn down.
+        let accept = self.accept.lock().unwrap().take();
+        if let Some(accept) = accept {
+            accept.abort();
+            let _ = accept.await;
+        }
+        // A connection task may have spawned its writer in the meantime;
+        // repeat until nothing is left.
+        loop {
+            let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+            if tasks.is_empty() {
+                break;
+            }
+            for task in &tasks {
+                task.abort();
+            }
+            for task in tasks {
+                let _ = task.await;
+            }
+        }
+        *self.local_addr.write().unwrap() = None;
+
+        let peers: Vec<PeerId> = self
+            .peers
+            .write()
+            .unwrap()
+            .drain()
+            .map(|(p, _)| p)
+            .collect();
+        self.dialed.write().unwrap().clear();
+        for peer in peers {
+            self.events
+                .publish(TransportEvent::PeerDisconnected(peer))
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        let peers = self.peers.read().unwrap();
+        let out = peers.get(&peer).context("unknown websocket peer")?;
+        out.send(data)
+            .map_err(|_| anyhow::anyhow!("websocket peer disconnected"))
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        for (peer, out) in self.peers.read().unwrap().iter() {
+            if out.send(data.clone()).is_err() {
+                tracing::warn!("broadcast to {peer:?} failed: websocket peer disconnected");
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a connection to the gateway listening at a
+    /// [`PeerAddress::Socket`]. An address already connected is left alone.
+    async fn connect(&self, address: &PeerAddress) -> Result<()> {
+        let PeerAddress::Socket(addr) = *address else {
+            anyhow::bail!("websocket transport cannot dial {address:?}");
+        };
+        if self
+            .dialed
+            .read()
+            .unwrap()
+            .values()
+            .any(|known| *known == addr)
+        {
+            return Ok(());
+        }
+        let stream = TcpStream::connect(addr)
+            .await
+            .with_context(|| format!("connect to websocket gateway at {addr}"))?;
+        let (ws, _) = tokio_tungstenite::client_async(format!("ws://{addr}/"), stream)
+            .await
+            .context("websocket handshake")?;
+        let conn = self.clone();
+        self.track(async move {
+            if let Err(e) = conn.clone().serve(ws, Some(addr)).await {
+                conn.events
+                    .publish(TransportEvent::Error(format!("{e:#}")))
+                    .await;
+            }
+        });
+        Ok(())
+    }
+
+    fn peer_address(&self, peer: PeerId) -> Option<PeerAddress> {
+        let addr = self.dialed.read().unwrap().get(&peer).copied()?;
+        Some(PeerAddress::Socket(addr))
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.peers.read().unwrap().keys().copied().collect()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events.subscribe()
+    }
+
+    fn subscribe_events_reliable(&self) -> Option<mpsc::Receiver<TransportEvent>> {
+        Some(self.events.subscribe_reliable())
+    }
+
+    fn mtu(&self) -> usize {
+        // WebSocket frames carry their own length; keep messages unfragmented.
+        1024 * 1024
+    }
+
+    fn link_quality(&self) -> f32 {
+        // Wired/IP hop to the gateway – treat as reliable.
+        1.0
+    }
+
+    fn wire_format(&self) -> WireFormat {
+        self.format
+    }
+
+    fn event_occupancy(&self) -> Option<ChannelOccupancy> {
+        Some(self.events.occupancy())
+    }
+}
+
+/// Counts one task in [`WebSocketTransport::active_tasks`] for as long as
+/// it is alive.
+struct RunningTask(Arc<AtomicUsize>);
+
+impl RunningTask {
+    fn start(running: &Arc<AtomicUsize>) -> Self {
+        running.fetch_add(1, Ordering::SeqCst);
+        Self(running.clone())
+    }
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
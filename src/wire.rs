@@ -0,0 +1,130 @@
+use crate::message::Message;
+use anyhow::Result;
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Upper bound on an encoded message accepted from a peer. Declared lengths
+/// inside the payload are checked against it before anything is allocated.
+pub const MAX_MESSAGE_BYTES: u64 = 1024 * 1024;
+
+/// Bincode options matching `bincode::serialize`, plus a size limit.
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_MESSAGE_BYTES)
+}
+
+/// Decode a bincode [`Message`] from untrusted peer data, rejecting oversized
+/// input and oversized declared lengths (e.g. a `File` claiming gigabytes).
+pub fn decode_message(bytes: &[u8]) -> Result<Message> {
+    WireFormat::Bincode.decode(bytes)
+}
+
+/// Bytes of CRC-32 trailer added by [`WireFormat::encode_checked`] and
+/// [`ChecksummedTransport`](crate::ChecksummedTransport).
+pub const CHECKSUM_LEN: usize = 4;
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3) of `data`. Detects transmission errors only; it is
+/// no defence against tampering, which is what signatures are for.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Append the CRC-32 of `frame` to it, as checked by [`strip_crc32`].
+pub fn append_crc32(frame: &mut Vec<u8>) {
+    let crc = crc32(frame);
+    frame.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Verify and remove the trailer written by [`append_crc32`], returning
+/// the payload.
+pub fn strip_crc32(frame: &[u8]) -> Result<&[u8]> {
+    if frame.len() < CHECKSUM_LEN {
+        anyhow::bail!("frame too short for checksum");
+    }
+    let (payload, trailer) = frame.split_at(frame.len() - CHECKSUM_LEN);
+    let expected = u32::from_le_bytes(trailer.try_into().expect("4-byte trailer"));
+    if crc32(payload) != expected {
+        anyhow::bail!("frame checksum mismatch");
+    }
+    Ok(payload)
+}
+
+/// Serialization used for bytes handed to a transport.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WireFormat {
+    /// Compact binary encoding used between mesh nodes.
+    #[default]
+    Bincode,
+    /// Human-readable JSON, e.g. for browser clients behind a gateway.
+    Json,
+    /// Compact, self-describing CBOR for interop with embedded/C peers.
+    Cbor,
+}
+
+impl WireFormat {
+    pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        Ok(match self {
+            WireFormat::Bincode => bincode::serialize(value)?,
+            WireFormat::Json => serde_json::to_vec(value)?,
+            WireFormat::Cbor => {
+                let mut out = Vec::new();
+                ciborium::into_writer(value, &mut out)?;
+                out
+            }
+        })
+    }
+
+    /// Decode untrusted input. Anything over [`MAX_MESSAGE_BYTES`] is rejected,
+    /// and bincode also checks declared lengths against that limit.
+    pub fn decode<T: DeserializeOwned>(self, data: &[u8]) -> Result<T> {
+        if data.len() as u64 > MAX_MESSAGE_BYTES {
+            anyhow::bail!("payload of {} bytes exceeds limit", data.len());
+        }
+        Ok(match self {
+            WireFormat::Bincode => bincode_options().deserialize(data)?,
+            WireFormat::Json => serde_json::from_slice(data)?,
+            WireFormat::Cbor => ciborium::from_reader(data)?,
+        })
+    }
+
+    /// [`encode`](Self::encode) followed by a CRC-32 trailer, for links with
+    /// no integrity checking of their own (e.g. raw LoRa frames).
+    pub fn encode_checked<T: Serialize>(self, value: &T) -> Result<Vec<u8>> {
+        let mut frame = self.encode(value)?;
+        append_crc32(&mut frame);
+        Ok(frame)
+    }
+
+    /// Verify the CRC-32 trailer written by
+    /// [`encode_checked`](Self::encode_checked), then [`decode`](Self::decode).
+    /// Corrupted frames are rejected before any deserialization or signature
+    /// work is done.
+    pub fn decode_checked<T: DeserializeOwned>(self, frame: &[u8]) -> Result<T> {
+        self.decode(strip_crc32(frame)?)
+    }
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use common::when_unlocked;
+use disaster_mesh::{AddressBook, MockClock, PeerAddress, PeerId, UserId};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_addresses_survive_reopen() {
+    let path = std::env::temp_dir().join(format!("dm-addresses-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let clock = MockClock::default();
+    let (alice, bob) = (PeerId([1; 32]), PeerId([2; 32]));
+    let alice_user = UserId::random();
+
+    let book = AddressBook::open(&path)
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+    book.record(
+        alice,
+        Some(alice_user),
+        PeerAddress::Serial("/dev/ttyUSB0".into()),
+    )
+    .unwrap();
+    clock.advance(Duration::from_secs(1));
+    book.record(bob, None, PeerAddress::Ble([0xaa, 0xbb, 0xcc, 0, 1, 2]))
+        .unwrap();
+    clock.advance(Duration::from_secs(1));
+    let socket = PeerAddress::Socket("192.168.1.20:9000".parse().unwrap());
+    book.record(alice, None, socket.clone()).unwrap();
+    book.flush().await.unwrap();
+    drop(book);
+
+    let book = when_unlocked(|| async { AddressBook::open(&path) })
+        .await
+        .unwrap();
+    let entry = book.get(&alice).unwrap().unwrap();
+    assert_eq!(
+        entry.user,
+        Some(alice_user),
+        "a later record keeps the user"
+    );
+    assert_eq!(
+        entry.addresses,
+        vec![socket, PeerAddress::Serial("/dev/ttyUSB0".into())],
+        "most recently used address first"
+    );
+    assert_eq!(book.by_user(&alice_user).unwrap().unwrap().peer, alice);
+    let order: Vec<_> = book.entries().unwrap().iter().map(|e| e.peer).collect();
+    assert_eq!(order, vec![alice, bob]);
+
+    assert!(book.remove(&bob).unwrap().is_some());
+    assert!(book.get(&bob).unwrap().is_none());
+    drop(book);
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[cfg(feature = "websocket")]
+#[tokio::test]
+async fn test_node_records_dialable_peers_for_reconnect() {
+    use disaster_mesh::{Identity, MeshNode, MessageManager, Transport, WebSocketTransport};
+
+    let mut neighbour = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    neighbour.start().await.unwrap();
+    let neighbour_addr = PeerAddress::Socket(neighbour.local_addr().unwrap());
+
+    let mut transport = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    transport.start().await.unwrap();
+    let book = AddressBook::in_memory().unwrap();
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    )
+    .with_address_book(book.clone());
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    transport.connect(&neighbour_addr).await.unwrap();
+    let entry = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(peer) = transport.get_peers().first() {
+                if let Some(entry) = book.get(peer).unwrap() {
+                    return entry;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(entry.addresses, vec![neighbour_addr]);
+    node.shutdown();
+    task.await.unwrap().unwrap();
+    transport.shutdown().await.unwrap();
+
+    // After a restart the book dials the neighbour again.
+    let mut restarted = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    restarted.start().await.unwrap();
+    assert_eq!(book.reconnect(&restarted).await.unwrap(), 1);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while restarted.get_peers().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    restarted.shutdown().await.unwrap();
+    neighbour.shutdown().await.unwrap();
+}
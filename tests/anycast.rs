@@ -0,0 +1,72 @@
+use disaster_mesh::{
+    service_advert, AodvReactive, ForwardDecision, ForwardingStrategy, Identity, Message,
+    MessageContent, PeerId, RoutingEngine, ServiceId, UserId,
+};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_anycast_routes_to_closer_provider() {
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let medic = ServiceId::named("medic");
+    let (far, near) = (Identity::generate(), Identity::generate());
+    let (via_far, via_near) = (PeerId([1; 32]), PeerId([2; 32]));
+
+    // The far medic's advert has been relayed three times; the near one is a
+    // direct neighbour.
+    let mut far_advert = service_advert(&far, vec![medic]).unwrap();
+    far_advert.hop_count = 3;
+    routing
+        .apply_control(&far_advert, via_far, 1.0)
+        .await
+        .unwrap();
+    let near_advert = service_advert(&near, vec![medic, ServiceId::named("water")]).unwrap();
+    routing
+        .apply_control(&near_advert, via_near, 1.0)
+        .await
+        .unwrap();
+
+    let mut providers = routing.providers(&medic);
+    providers.sort_by_key(|u| u.0);
+    let mut expected = vec![far.user_id(), near.user_id()];
+    expected.sort_by_key(|u| u.0);
+    assert_eq!(providers, expected);
+    let nearest = routing.nearest_provider(&medic).await.unwrap();
+    assert_eq!(nearest.destination, near.user_id());
+
+    let request = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("injured at the north gate".into()))
+        .anycast(medic)
+        .build()
+        .unwrap();
+    let strategy = AodvReactive::new(routing.clone());
+    assert_eq!(
+        strategy.decide(&request, PeerId([9; 32])).await,
+        ForwardDecision::Unicast(via_near)
+    );
+
+    // Nobody advertises this one, so a normal-priority anycast goes nowhere.
+    let unknown = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("need a boat".into()))
+        .anycast(ServiceId::named("boat"))
+        .build()
+        .unwrap();
+    assert_eq!(
+        strategy.decide(&unknown, PeerId([9; 32])).await,
+        ForwardDecision::Drop
+    );
+}
+
+#[tokio::test]
+async fn test_advert_must_be_signed_by_provider() {
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let provider = Identity::generate();
+    let mut forged = service_advert(&provider, vec![ServiceId::named("medic")]).unwrap();
+    forged.sender = UserId::random();
+    assert!(routing
+        .apply_control(&forged, PeerId([1; 32]), 1.0)
+        .await
+        .is_err());
+    assert!(routing.providers(&ServiceId::named("medic")).is_empty());
+}
@@ -0,0 +1,114 @@
+use disaster_mesh::{
+    AuthorityBroadcasts, Identity, Message, MessageContent, MessageManager, MessagePriority,
+    RejectReason,
+};
+
+#[tokio::test]
+async fn test_authority_broadcast_accepted_once_and_replay_rejected() {
+    let authority = MessageManager::in_memory().await.unwrap();
+    let agency = authority.add_identity(Identity::generate());
+    let mut alerts = Vec::new();
+    for text in ["evacuate zone A", "zone A all clear"] {
+        alerts.push(
+            authority
+                .create_message(agency, None, MessageContent::Text(text.into()))
+                .await
+                .unwrap(),
+        );
+    }
+
+    let policy = AuthorityBroadcasts::new([agency]);
+    let receiver = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_validator(Box::new(policy.clone()));
+    assert!(receiver.deliver(alerts[0].clone()).await.unwrap());
+    assert!(receiver.deliver(alerts[1].clone()).await.unwrap());
+    assert_eq!(policy.highest_seq(&agency), alerts[1].seq);
+
+    // Another node (or this one after its seen-set forgot the id) gets the
+    // old evacuation order re-flooded.
+    let other = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_validator(Box::new(policy.clone()));
+    let err = other.deliver(alerts[0].clone()).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RejectReason>(),
+        Some(&RejectReason::Replay)
+    );
+}
+
+#[tokio::test]
+async fn test_authority_broadcast_must_be_signed() {
+    let agency = Identity::generate();
+    let policy = AuthorityBroadcasts::new([agency.user_id()]);
+    let receiver = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages()
+        .with_validator(Box::new(policy));
+
+    let forged = Message::builder()
+        .sender(agency.user_id())
+        .content(MessageContent::Text("evacuate now".into()))
+        .priority(MessagePriority::Emergency)
+        .seq(99)
+        .build()
+        .unwrap();
+    let err = receiver.deliver(forged).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RejectReason>(),
+        Some(&RejectReason::Unauthenticated)
+    );
+}
+
+#[tokio::test]
+async fn test_authority_high_water_mark_survives_restart() {
+    let authority = MessageManager::in_memory().await.unwrap();
+    let agency = authority.add_identity(Identity::generate());
+    let mut alerts = Vec::new();
+    for text in ["evacuate zone A", "zone A all clear"] {
+        alerts.push(
+            authority
+                .create_message(agency, None, MessageContent::Text(text.into()))
+                .await
+                .unwrap(),
+        );
+    }
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let tree = db.open_tree("authority_seqs").unwrap();
+    let receiver = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_validator(Box::new(
+            AuthorityBroadcasts::new([agency])
+                .with_store(tree.clone())
+                .unwrap(),
+        ));
+    assert!(receiver.deliver(alerts[1].clone()).await.unwrap());
+
+    // After a reboot the seen-set is empty, but the stored mark still
+    // refuses the older alert.
+    let policy = AuthorityBroadcasts::new([agency]).with_store(tree).unwrap();
+    assert_eq!(policy.highest_seq(&agency), alerts[1].seq);
+    let rebooted = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_validator(Box::new(policy));
+    let err = rebooted.deliver(alerts[0].clone()).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RejectReason>(),
+        Some(&RejectReason::Replay)
+    );
+
+    // The manager keeps the marks in its own store.
+    let receiver = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_authority_broadcasts(AuthorityBroadcasts::new([agency]))
+        .unwrap();
+    assert!(receiver.deliver(alerts[0].clone()).await.unwrap());
+    assert!(receiver.deliver(alerts[1].clone()).await.unwrap());
+}
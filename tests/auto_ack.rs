@@ -0,0 +1,77 @@
+use disaster_mesh::{
+    decode_message, AckSuppression, Identity, MeshNode, MessageContent, MessageManager, MockClock,
+    MockTransport, PeerId, Transport, TransportEvent, WireFormat,
+};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_duplicate_copies_are_acked_once_per_window() {
+    let transport = MockTransport::new();
+    let (alice_peer, bob_peer) = (PeerId([1; 32]), PeerId([2; 32]));
+    transport.add_peer(alice_peer).await;
+    transport.add_peer(bob_peer).await;
+    let shared: Arc<dyn Transport> = Arc::new(transport);
+
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let bob = MeshNode::new(
+        Identity::generate(),
+        bob_peer,
+        shared.clone(),
+        MessageManager::in_memory().await.unwrap(),
+    )
+    .with_auto_ack(AckSuppression::with_clock(
+        Duration::from_secs(30),
+        clock.clone(),
+    ));
+    let task = tokio::spawn({
+        let bob = bob.clone();
+        async move { bob.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let alice = MessageManager::in_memory().await.unwrap();
+    let user = alice.add_identity(Identity::generate());
+    let msg = alice
+        .create_message(
+            user,
+            Some(bob.user_id()),
+            MessageContent::Text("ping".into()),
+        )
+        .await
+        .unwrap();
+    let data = WireFormat::default().encode(&msg).unwrap();
+
+    let mut on_air = shared.subscribe_events();
+    let mut acks = |expected: usize| {
+        let mut receipts = HashSet::new();
+        while let Ok(event) = on_air.try_recv() {
+            if let TransportEvent::DataReceived { data, .. } = event {
+                let copy = decode_message(&data).unwrap();
+                if let MessageContent::Receipt { original_id, .. } = copy.content {
+                    assert_eq!(original_id, msg.id);
+                    copy.verify_signature().unwrap();
+                    receipts.insert(copy.id);
+                }
+            }
+        }
+        assert_eq!(receipts.len(), expected);
+    };
+
+    // The sender retransmits aggressively: three copies, one ack.
+    for _ in 0..3 {
+        shared.send(bob_peer, data.clone()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    acks(1);
+
+    // Still retransmitting after the window: the ack may have been lost.
+    clock.advance(Duration::from_secs(30));
+    shared.send(bob_peer, data.clone()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    acks(1);
+
+    bob.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,174 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use disaster_mesh::{
+    decode_message, BeaconConfig, Identity, MessageManager, MockTransport, PeerId, ProactiveBeacon,
+    RoutingEngine, Transport, TransportEvent, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+
+/// Transport whose broadcasts land in a channel the test delivers by hand.
+struct Outbox {
+    tx: mpsc::UnboundedSender<Vec<u8>>,
+    events: MockTransport,
+}
+
+#[async_trait]
+impl Transport for Outbox {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, _peer: PeerId, data: Vec<u8>) -> Result<()> {
+        Ok(self.tx.send(data)?)
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        Ok(self.tx.send(data)?)
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        Vec::new()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events.subscribe_events()
+    }
+
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    fn link_quality(&self) -> f32 {
+        1.0
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_line_converges_through_periodic_beacons() {
+    // A - B - C; node `i` is heard as `PeerId([i; 32])`.
+    let neighbours: [&[usize]; 3] = [&[1], &[0, 2], &[1]];
+    let identities: Vec<_> = (0..3).map(|_| Identity::generate()).collect();
+    let config = BeaconConfig {
+        interval: Duration::from_secs(5),
+        max_entries: 16,
+    };
+
+    let mut nodes = Vec::new();
+    let mut outboxes = Vec::new();
+    for (i, identity) in identities.iter().enumerate() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let transport = Arc::new(Outbox {
+            tx,
+            events: MockTransport::new(),
+        });
+        let routing = RoutingEngine::new(Duration::from_secs(60));
+        nodes.push(ProactiveBeacon::new(
+            identity.clone(),
+            PeerId([i as u8; 32]),
+            routing,
+            transport,
+            config,
+        ));
+        outboxes.push(rx);
+    }
+    let tasks: Vec<_> = nodes.iter().map(|n| n.spawn()).collect();
+
+    for _ in 0..3 {
+        tokio::time::sleep(config.interval).await;
+        for (i, outbox) in outboxes.iter_mut().enumerate() {
+            while let Ok(frame) = outbox.try_recv() {
+                let msg = decode_message(&frame).unwrap();
+                for &n in neighbours[i] {
+                    nodes[n]
+                        .handle_beacon(&msg, PeerId([i as u8; 32]), 1.0)
+                        .await
+                        .unwrap();
+                }
+            }
+        }
+    }
+    for task in tasks {
+        task.abort();
+    }
+
+    for (i, node) in nodes.iter().enumerate() {
+        let routing = node.routing();
+        for (j, identity) in identities.iter().enumerate() {
+            if i == j {
+                continue;
+            }
+            let next = if neighbours[i].contains(&j) { j } else { 1 };
+            assert_eq!(
+                routing.next_hop(&identity.user_id()).await,
+                Some(PeerId([next as u8; 32])),
+                "node {i} lacks a route to node {j}"
+            );
+        }
+        assert_eq!(routing.dump().await.len(), 2);
+    }
+}
+
+fn beacon_node(
+    identity: &Identity,
+    peer: u8,
+    store: Option<&MessageManager>,
+) -> (ProactiveBeacon, mpsc::UnboundedReceiver<Vec<u8>>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let transport = Arc::new(Outbox {
+        tx,
+        events: MockTransport::new(),
+    });
+    let mut node = ProactiveBeacon::new(
+        identity.clone(),
+        PeerId([peer; 32]),
+        RoutingEngine::new(Duration::from_secs(60)),
+        transport,
+        BeaconConfig::default(),
+    );
+    if let Some(store) = store {
+        node = node.with_store(store.clone());
+    }
+    (node, rx)
+}
+
+#[tokio::test]
+async fn test_beacons_skip_routes_through_the_listener() {
+    let (a, mut a_out) = beacon_node(&Identity::generate(), 0, None);
+    let (b, _) = beacon_node(&Identity::generate(), 1, None);
+    // A reaches X through B, and Y through someone else.
+    let (x, y) = (UserId::random(), UserId::random());
+    a.routing().update_route(x, PeerId([1; 32]), 1, 1.0).await;
+    a.routing().update_route(y, PeerId([2; 32]), 1, 1.0).await;
+
+    a.beacon_once().await.unwrap();
+    let msg = decode_message(&a_out.try_recv().unwrap()).unwrap();
+    assert!(b.handle_beacon(&msg, PeerId([0; 32]), 1.0).await.unwrap());
+    assert_eq!(b.routing().next_hop(&x).await, None);
+    assert_eq!(b.routing().next_hop(&y).await, Some(PeerId([0; 32])));
+}
+
+#[tokio::test]
+async fn test_beacon_sequence_survives_restart() {
+    let identity = Identity::generate();
+    let store = MessageManager::in_memory().await.unwrap();
+    let (listener, _) = beacon_node(&Identity::generate(), 1, None);
+
+    let (before, mut out) = beacon_node(&identity, 0, Some(&store));
+    before.beacon_once().await.unwrap();
+    let msg = decode_message(&out.try_recv().unwrap()).unwrap();
+    assert!(listener
+        .handle_beacon(&msg, PeerId([0; 32]), 1.0)
+        .await
+        .unwrap());
+
+    // A restarted node continues the sequence instead of starting over.
+    let (after, mut out) = beacon_node(&identity, 0, Some(&store));
+    after.beacon_once().await.unwrap();
+    let msg = decode_message(&out.try_recv().unwrap()).unwrap();
+    assert!(listener
+        .handle_beacon(&msg, PeerId([0; 32]), 1.0)
+        .await
+        .unwrap());
+}
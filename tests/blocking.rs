@@ -0,0 +1,25 @@
+use disaster_mesh::blocking::{MessageManager, RoutingEngine};
+use disaster_mesh::{Identity, MessageContent, MessageFilter, PeerId, UserId};
+use std::time::Duration;
+
+#[test]
+fn test_blocking_api_without_runtime() {
+    assert!(tokio::runtime::Handle::try_current().is_err());
+
+    let manager = MessageManager::in_memory().unwrap();
+    let me = manager.add_identity(Identity::generate());
+    let msg = manager
+        .create_message(me, None, MessageContent::Text("hello".into()))
+        .unwrap();
+    assert!(manager.validate_message(&msg).is_ok());
+    let stored = manager.list_messages(&MessageFilter::default()).unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, msg.id);
+
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let dest = UserId::random();
+    routing.update_route(dest, PeerId([3; 32]), 2, 0.9);
+    assert_eq!(routing.next_hop(&dest), Some(PeerId([3; 32])));
+    assert!(routing.invalidate(&dest).is_some());
+    assert_eq!(routing.next_hop(&dest), None);
+}
@@ -0,0 +1,113 @@
+use disaster_mesh::{
+    Clock, Message, MessageContent, MessageId, MessageManager, MockClock, PeerId, RoutingEngine,
+    TtlMode, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_mock_clock_expires_routes() {
+    let clock = MockClock::default();
+    let engine = RoutingEngine::with_clock(Duration::from_secs(60), Arc::new(clock.clone()));
+    let dest = UserId::random();
+    engine.update_route(dest, PeerId([1; 32]), 1, 1.0).await;
+
+    clock.advance(Duration::from_secs(30));
+    engine.cleanup().await;
+    assert!(engine.next_hop(&dest).await.is_some());
+
+    clock.advance(Duration::from_secs(31));
+    engine.cleanup().await;
+    assert!(engine.next_hop(&dest).await.is_none());
+}
+
+#[tokio::test]
+async fn test_mock_clock_expires_messages() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages()
+        .with_clock(Arc::new(clock.clone()));
+    let mut message = manager
+        .create_message(UserId::random(), None, MessageContent::Text("tick".into()))
+        .await
+        .unwrap();
+    assert_eq!(message.timestamp, clock.now());
+    message.ttl = Duration::from_secs(10);
+
+    assert!(manager.validate_message(&message).await.is_ok());
+    clock.advance(Duration::from_secs(11));
+    assert!(manager.validate_message(&message).await.is_err());
+}
+
+#[tokio::test]
+async fn test_seen_ids_expire_after_dedup_window() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()))
+        .with_dedup_window(Duration::from_secs(600));
+    let id = MessageId::new();
+
+    manager.mark_message_seen(&id).await.unwrap();
+    clock.advance(Duration::from_secs(300));
+    assert!(!manager.is_new_message(&id).await);
+    // A repeat sighting does not extend the window.
+    manager.mark_message_seen(&id).await.unwrap();
+    assert_eq!(manager.prune_seen().await.unwrap(), 0);
+
+    clock.advance(Duration::from_secs(301));
+    assert!(manager.is_new_message(&id).await);
+    assert_eq!(manager.prune_seen().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_default_dedup_window_covers_the_longest_ttl() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages()
+        .with_clock(Arc::new(clock.clone()))
+        .with_max_ttl(Duration::from_secs(7200))
+        .with_max_future_skew(Duration::from_secs(60));
+
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("t".into()));
+    msg.timestamp = clock.now();
+    msg.ttl = Duration::from_secs(7201);
+    assert!(manager.validate_message(&msg).await.is_err());
+
+    let id = MessageId::new();
+    manager.mark_message_seen(&id).await.unwrap();
+    clock.advance(Duration::from_secs(7200));
+    assert!(!manager.is_new_message(&id).await);
+    clock.advance(Duration::from_secs(61));
+    assert!(manager.is_new_message(&id).await);
+}
+
+#[tokio::test]
+async fn test_future_timestamps_beyond_skew_are_rejected() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages()
+        .with_clock(Arc::new(clock.clone()))
+        .with_max_future_skew(Duration::from_secs(60));
+    let at = |ahead: Duration, mode: TtlMode| {
+        let mut msg = Message::new(UserId::random(), None, MessageContent::Text("t".into()));
+        msg.timestamp = clock.now() + ahead;
+        msg.ttl_mode = mode;
+        msg
+    };
+
+    let slightly = at(Duration::from_secs(30), TtlMode::Absolute);
+    assert!(manager.validate_message(&slightly).await.is_ok());
+    let far = at(Duration::from_secs(3600 * 24 * 365), TtlMode::Absolute);
+    assert!(manager.validate_message(&far).await.is_err());
+    // Relative TTLs ignore the timestamp, so skew is harmless there.
+    let relative = at(Duration::from_secs(3600 * 24 * 365), TtlMode::Relative);
+    assert!(manager.validate_message(&relative).await.is_ok());
+}
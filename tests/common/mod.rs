@@ -0,0 +1,38 @@
+use std::future::Future;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// Run `open` until the store's file lock is free. sled's background threads
+/// can hold the lock briefly after the last handle is dropped; a real restart
+/// never sees that. Any other error is returned at once.
+pub async fn when_unlocked<T, F, Fut>(open: F) -> anyhow::Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    for _ in 0..50 {
+        match open().await {
+            Err(err) if is_lock_contention(&err) => {
+                tokio::time::sleep(Duration::from_millis(10)).await
+            }
+            result => return result,
+        }
+    }
+    open().await
+}
+
+/// sled reports a held lock as an `Other` I/O error wrapping the
+/// `WouldBlock` from the non-blocking lock attempt.
+fn is_lock_contention(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let io = match cause.downcast_ref::<sled::Error>() {
+            Some(sled::Error::Io(io)) => io,
+            _ => match cause.downcast_ref::<std::io::Error>() {
+                Some(io) => io,
+                None => return false,
+            },
+        };
+        io.kind() == ErrorKind::WouldBlock
+            || (io.kind() == ErrorKind::Other && io.to_string().contains("WouldBlock"))
+    })
+}
@@ -0,0 +1,134 @@
+use disaster_mesh::{
+    CompressionAlgorithm, Compressor, Deflate, Gzip, Identity, MeshNode, Message, MessageContent,
+    MessageManager, MockTransport, NoCompression, PeerId, Transport, TransportEvent, UserId,
+    WireFormat,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn bulletin() -> Message {
+    Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("Water distribution at the school gym. ".repeat(20)),
+    )
+}
+
+#[test]
+fn test_message_round_trips_through_each_compressor() {
+    let msg = bulletin();
+    let plain = WireFormat::Bincode.encode(&msg).unwrap();
+    let mut compressors: Vec<Box<dyn Compressor>> = vec![
+        Box::new(NoCompression),
+        Box::new(Deflate::default()),
+        Box::new(Gzip::default()),
+    ];
+    #[cfg(feature = "zstd")]
+    compressors.push(Box::new(disaster_mesh::Zstd::default()));
+    for compressor in compressors {
+        let compressor = compressor.as_ref();
+        let frame = WireFormat::Bincode
+            .encode_compressed(&msg, compressor)
+            .unwrap();
+        assert_eq!(frame[0], compressor.algorithm() as u8);
+        if compressor.algorithm() != CompressionAlgorithm::None {
+            assert!(
+                frame.len() < plain.len() / 2,
+                "{:?}",
+                compressor.algorithm()
+            );
+        }
+        let decoded: Message = WireFormat::Bincode.decode_compressed(&frame).unwrap();
+        assert_eq!(decoded.id, msg.id);
+        assert_eq!(decoded.content, msg.content);
+    }
+}
+
+#[test]
+fn test_header_selects_decompressor() {
+    let msg = bulletin();
+    let gzip = WireFormat::Bincode
+        .encode_compressed(&msg, &Gzip::default())
+        .unwrap();
+    // Real gzip magic after our header byte.
+    assert_eq!(&gzip[1..3], &[0x1f, 0x8b]);
+
+    // Relabelling the payload as another algorithm makes it undecodable.
+    let mut relabelled = gzip.clone();
+    relabelled[0] = CompressionAlgorithm::Deflate as u8;
+    assert!(WireFormat::Bincode
+        .decode_compressed::<Message>(&relabelled)
+        .is_err());
+    relabelled[0] = 9;
+    assert!(WireFormat::Bincode
+        .decode_compressed::<Message>(&relabelled)
+        .is_err());
+
+    // A corrupted body fails the gzip checksum.
+    let mut corrupted = gzip;
+    let last = corrupted.len() - 12;
+    corrupted[last] ^= 0xff;
+    assert!(WireFormat::Bincode
+        .decode_compressed::<Message>(&corrupted)
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_node_compresses_on_a_compressing_link() {
+    let transport = MockTransport::new().with_compression(CompressionAlgorithm::Gzip);
+    let neighbour = PeerId([2; 32]);
+    transport.add_peer(neighbour).await;
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    );
+    let mut inbox = Box::pin(node.inbound());
+    let mut on_air = transport.subscribe_events();
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // Outgoing frames carry the algorithm header and a gzip body.
+    let sent = node
+        .send(MessageContent::Text("shelter ".repeat(40)), None)
+        .await
+        .unwrap();
+    let data = match on_air.recv().await {
+        Ok(TransportEvent::DataReceived { data, .. }) => data,
+        other => panic!("expected the broadcast, got {other:?}"),
+    };
+    assert_eq!(data[0], CompressionAlgorithm::Gzip as u8);
+    assert_eq!(transport.decode_message(&data).unwrap().id, sent.id);
+
+    // Incoming frames are decompressed whatever algorithm they name.
+    let origin = Identity::generate();
+    let mut bulletin = Message::new(
+        origin.user_id(),
+        None,
+        MessageContent::Text("water at the gym".into()),
+    );
+    bulletin.sign(&origin).unwrap();
+    let frame = WireFormat::Bincode
+        .encode_compressed(&bulletin, &Deflate::default())
+        .unwrap();
+    transport.send(neighbour, frame).await.unwrap();
+    let delivered = tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            let msg = inbox.next().await.unwrap();
+            if msg.id == bulletin.id {
+                return msg;
+            }
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(delivered.content, bulletin.content);
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,69 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, Identity, MeshConfig, MessageContent,
+    MessageFilter, MessageManager, MockTransport, PeerId, RoutingEngine, TtlMode, UserId,
+    DEFAULT_HOP_LIMIT,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+const SAMPLE: &str = r#"
+ttl_mode = "Relative"
+retention_max_messages = 2
+route_max_age_secs = 90
+negative_route_ttl_secs = 5
+max_hops = 4
+"#;
+
+#[tokio::test]
+async fn test_toml_config_propagates() {
+    let config = MeshConfig::from_toml_str(SAMPLE).unwrap();
+    assert_eq!(config.ttl_mode, TtlMode::Relative);
+    // Unlisted keys keep their defaults.
+    assert_eq!(config.chunk_size, MeshConfig::default().chunk_size);
+    assert_eq!(MeshConfig::default().max_hops, DEFAULT_HOP_LIMIT);
+    assert_eq!(
+        MeshConfig::from_toml_str(&config.to_toml_string().unwrap()).unwrap(),
+        config
+    );
+
+    let routing = RoutingEngine::from_config(&config);
+    assert_eq!(routing.max_age(), Duration::from_secs(90));
+
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_config(&config);
+    let sender = UserId::random();
+    for i in 0..3 {
+        let msg = manager
+            .create_message(sender, None, MessageContent::Text(format!("#{i}")))
+            .await
+            .unwrap();
+        assert_eq!(msg.ttl_mode, TtlMode::Relative);
+    }
+    let stored = manager
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 2);
+
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+    .with_config(&config);
+    let mut far = manager
+        .create_message(sender, None, MessageContent::Text("far".into()))
+        .await
+        .unwrap();
+    far.hop_count = 4;
+    assert_eq!(
+        forwarder
+            .handle_incoming(&far, PeerId([2; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Drop
+    );
+}
@@ -0,0 +1,109 @@
+use disaster_mesh::{
+    content_request, decode_message, ContentCache, ContentHash, ControlledFlood, ForwardDecision,
+    Forwarder, Identity, MeshStats, Message, MessageContent, MockClock, MockTransport, PeerId,
+    Transport, TransportEvent, DEFAULT_MAX_HOPS,
+};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+fn peer(i: u8) -> PeerId {
+    PeerId([i; 32])
+}
+
+fn bulletin(origin: &Identity, text: &str) -> Message {
+    let mut msg = Message::new(origin.user_id(), None, MessageContent::Text(text.into()));
+    msg.sign(origin).unwrap();
+    msg
+}
+
+#[tokio::test]
+async fn test_relay_answers_content_request_from_cache() {
+    let origin = Identity::generate();
+    let requester = Identity::generate();
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(peer(2)).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        peer(1),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        transport.clone(),
+    )
+    .with_content_cache(ContentCache::default());
+
+    // A forged copy arrives first; it must not take the genuine one's slot.
+    let msg = bulletin(&origin, "water at the school gym");
+    let mut forged = msg.clone();
+    let forger = Identity::generate();
+    forged.id = disaster_mesh::MessageId::new();
+    forged.sender = forger.user_id();
+    forged.sign(&forger).unwrap();
+    forged.sender = origin.user_id();
+    forwarder.handle_incoming(&forged, peer(0)).await.unwrap();
+    // The relay hears the origin's bulletin and passes it on.
+    forwarder.handle_incoming(&msg, peer(0)).await.unwrap();
+
+    // A late joiner asks for it by hash; the relay answers and stops the request.
+    let mut events = transport.subscribe_events();
+    let request = content_request(&requester, ContentHash::of(&msg.content)).unwrap();
+    assert_eq!(
+        forwarder.handle_incoming(&request, peer(2)).await.unwrap(),
+        ForwardDecision::Drop
+    );
+    let Ok(TransportEvent::DataReceived { peer: to, data }) = events.recv().await else {
+        panic!("cached bulletin not sent");
+    };
+    assert_eq!(to, peer(2));
+    let served = decode_message(&data).unwrap();
+    assert_eq!(served.id, msg.id);
+    served.verify_signature().unwrap();
+    assert_eq!(MeshStats::get(&forwarder.stats().content_cache_hits), 1);
+
+    // A repeat of the request is a duplicate and gets no second answer, and
+    // one relayed from further away is not ours to answer.
+    assert_eq!(
+        forwarder.handle_incoming(&request, peer(2)).await.unwrap(),
+        ForwardDecision::Drop
+    );
+    let mut relayed = content_request(&requester, ContentHash::of(&msg.content)).unwrap();
+    relayed.hop_count = 1;
+    relayed.path.push(peer(3));
+    assert_ne!(
+        forwarder.handle_incoming(&relayed, peer(2)).await.unwrap(),
+        ForwardDecision::Drop
+    );
+    assert_eq!(MeshStats::get(&forwarder.stats().content_cache_hits), 1);
+
+    // Unknown content is not answered here and floods on as usual.
+    let unknown = content_request(&requester, ContentHash([7; 32])).unwrap();
+    assert_ne!(
+        forwarder.handle_incoming(&unknown, peer(2)).await.unwrap(),
+        ForwardDecision::Drop
+    );
+}
+
+#[tokio::test]
+async fn test_cache_entries_expire_and_evict() {
+    let clock = Arc::new(MockClock::new(SystemTime::now()));
+    let cache = ContentCache::with_clock(2, Duration::from_secs(60), clock.clone());
+    let origin = Identity::generate();
+    let first = bulletin(&origin, "first");
+    let second = bulletin(&origin, "second");
+    let third = bulletin(&origin, "third");
+
+    assert!(cache.store(&first).await);
+    assert!(!cache.store(&first).await);
+    assert!(cache.store(&second).await);
+    assert!(cache.store(&third).await);
+    assert_eq!(cache.len().await, 2);
+    assert!(cache.get(&ContentHash::of(&first.content)).await.is_none());
+
+    // Unsigned messages are never cached.
+    let mut unsigned = bulletin(&origin, "unsigned");
+    unsigned.signature.clear();
+    assert!(!cache.store(&unsigned).await);
+
+    clock.advance(Duration::from_secs(61));
+    assert!(cache.get(&ContentHash::of(&third.content)).await.is_none());
+    cache.purge_expired().await;
+    assert!(cache.is_empty().await);
+}
@@ -0,0 +1,85 @@
+use disaster_mesh::{
+    DeadLetterReason, Identity, Message, MessageContent, MessageManager, MockClock, TtlMode, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_expired_pending_message_is_dead_lettered() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let me = manager.add_identity(Identity::generate());
+    let doomed = manager
+        .create_from(
+            Message::builder()
+                .sender(me)
+                .to(UserId::random())
+                .content(MessageContent::Text("are you safe?".into()))
+                .ttl(Duration::from_secs(60)),
+        )
+        .await
+        .unwrap();
+    let lasting = manager
+        .create_message(
+            me,
+            Some(UserId::random()),
+            MessageContent::Text("hi".into()),
+        )
+        .await
+        .unwrap();
+    let mut dead = manager.subscribe_dead_letters();
+
+    assert_eq!(manager.purge_expired().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(manager.purge_expired().await.unwrap(), 1);
+
+    let letter = dead.try_recv().unwrap();
+    assert_eq!(letter.message.id, doomed.id);
+    assert_eq!(letter.message.content, doomed.content);
+    assert_eq!(letter.reason, DeadLetterReason::Expired);
+    assert!(dead.try_recv().is_err());
+
+    let pending: Vec<_> = manager
+        .pending_messages()
+        .await
+        .unwrap()
+        .iter()
+        .map(|msg| msg.id)
+        .collect();
+    assert_eq!(pending, [lasting.id]);
+    assert_eq!(manager.message_status(&doomed.id).await, None);
+
+    assert_eq!(manager.dead_letters().await.unwrap().len(), 1);
+    let taken = manager.remove_dead_letter(&doomed.id).await.unwrap();
+    assert_eq!(taken.unwrap().message.id, doomed.id);
+    assert!(manager.dead_letters().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_relative_ttl_pending_message_is_dead_lettered() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let me = manager.add_identity(Identity::generate());
+    let doomed = manager
+        .create_from(
+            Message::builder()
+                .sender(me)
+                .to(UserId::random())
+                .content(MessageContent::Text("still there?".into()))
+                .ttl(Duration::from_secs(60))
+                .ttl_mode(TtlMode::Relative),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(manager.purge_expired().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(manager.purge_expired().await.unwrap(), 1);
+    assert_eq!(manager.message_status(&doomed.id).await, None);
+}
@@ -0,0 +1,76 @@
+use disaster_mesh::{
+    DeliveryStatus, Identity, Message, MessageContent, MessageManager, ReceiptKind, UserId,
+};
+
+#[tokio::test]
+async fn test_delivered_receipt_round_trip() {
+    let manager = MessageManager::new().await.unwrap();
+    let sender = UserId::random();
+    let recipient = Identity::generate();
+
+    let sent = manager
+        .create_message(
+            sender,
+            Some(recipient.user_id()),
+            MessageContent::Text("ping".into()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        manager.message_status(&sent.id).await,
+        Some(DeliveryStatus::Sent)
+    );
+
+    // Recipient side: acknowledge delivery, then read.
+    let mut delivered = Message::receipt(recipient.user_id(), &sent, ReceiptKind::Delivered);
+    delivered.sign(&recipient).unwrap();
+    assert_eq!(delivered.recipient, Some(sender));
+    let status = manager.handle_receipt(&delivered).await.unwrap();
+    assert_eq!(status, Some(DeliveryStatus::Delivered));
+
+    let mut read = Message::receipt(recipient.user_id(), &sent, ReceiptKind::Read);
+    read.sign(&recipient).unwrap();
+    manager.handle_receipt(&read).await.unwrap();
+    // A late Delivered receipt must not downgrade Read.
+    manager.handle_receipt(&delivered).await.unwrap();
+    assert_eq!(
+        manager.message_status(&sent.id).await,
+        Some(DeliveryStatus::Read)
+    );
+
+    let unknown = Message::receipt(
+        recipient.user_id(),
+        &Message::new(sender, None, MessageContent::Text("x".into())),
+        ReceiptKind::Read,
+    );
+    assert_eq!(manager.handle_receipt(&unknown).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_receipt_must_come_from_the_recipient() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let sender = manager.add_identity(Identity::generate());
+    let recipient = Identity::generate();
+    let sent = manager
+        .create_message(
+            sender,
+            Some(recipient.user_id()),
+            MessageContent::Text("ping".into()),
+        )
+        .await
+        .unwrap();
+
+    // Signed, but by a bystander rather than the recipient.
+    let bystander = Identity::generate();
+    let mut forged = Message::receipt(bystander.user_id(), &sent, ReceiptKind::Delivered);
+    forged.sign(&bystander).unwrap();
+    assert!(manager.handle_receipt(&forged).await.is_err());
+
+    // Claims to be the recipient, but carries no valid signature.
+    let unsigned = Message::receipt(recipient.user_id(), &sent, ReceiptKind::Delivered);
+    assert!(manager.handle_receipt(&unsigned).await.is_err());
+    assert_eq!(
+        manager.message_status(&sent.id).await,
+        Some(DeliveryStatus::Sent)
+    );
+}
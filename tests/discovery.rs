@@ -0,0 +1,94 @@
+use disaster_mesh::{
+    ControlledFlood, DiscoveryConfig, DiscoveryDecision, Forwarder, Identity, MockClock,
+    MockTransport, PeerId, RouteDiscovery, RouteEvent, RoutingEngine, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_rreq_backoff_then_give_up() {
+    let clock = MockClock::default();
+    let routing = RoutingEngine::with_clock(Duration::from_secs(60), Arc::new(clock.clone()));
+    let mut events = routing.subscribe();
+    let discovery = RouteDiscovery::new(
+        routing,
+        DiscoveryConfig {
+            min_interval: Duration::from_secs(1),
+            max_retries: 2,
+        },
+    );
+    let unreachable = UserId::random();
+
+    // Poll every 500ms for 10s; RREQs may only go out at t=0, 1s and 3s.
+    let mut sent_at = Vec::new();
+    let mut failed = 0;
+    for tick in 0..20u64 {
+        match discovery.request(unreachable).await {
+            DiscoveryDecision::Send => sent_at.push(tick * 500),
+            DiscoveryDecision::Failed => failed += 1,
+            _ => {}
+        }
+        clock.advance(Duration::from_millis(500));
+    }
+    assert_eq!(sent_at, vec![0, 1000, 3000]);
+    assert!(failed > 0);
+
+    // The failure is reported exactly once.
+    assert!(matches!(
+        events.try_recv(),
+        Ok(RouteEvent::RouteDiscoveryFailed { destination }) if destination == unreachable
+    ));
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_known_route_skips_discovery() {
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let discovery = RouteDiscovery::new(routing.clone(), DiscoveryConfig::default());
+    let dest = UserId::random();
+
+    assert_eq!(discovery.request(dest).await, DiscoveryDecision::Send);
+    assert_eq!(discovery.request(dest).await, DiscoveryDecision::Throttled);
+    routing.update_route(dest, PeerId([1; 32]), 2, 1.0).await;
+    assert_eq!(discovery.request(dest).await, DiscoveryDecision::RouteKnown);
+}
+
+#[tokio::test]
+async fn test_cancelled_discovery_drops_retry_state() {
+    let clock = MockClock::default();
+    let routing = RoutingEngine::with_clock(Duration::from_secs(60), Arc::new(clock.clone()));
+    let discovery = RouteDiscovery::new(routing.clone(), DiscoveryConfig::default());
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+    .with_discovery(discovery.clone());
+    let (dest, other) = (UserId::random(), UserId::random());
+
+    assert!(forwarder.discover(dest).await.unwrap());
+    assert!(forwarder.discover(other).await.unwrap());
+    let mut pending = discovery.pending_discoveries().await;
+    pending.sort_by_key(|user| user.0);
+    let mut expected = vec![dest, other];
+    expected.sort_by_key(|user| user.0);
+    assert_eq!(pending, expected);
+
+    // Retry up to the last attempt before giving up.
+    for _ in 0..4 {
+        clock.advance(Duration::from_secs(60));
+        assert!(forwarder.discover(dest).await.unwrap());
+    }
+    discovery.cancel_discovery(dest).await;
+    assert_eq!(discovery.pending_discoveries().await, vec![other]);
+
+    // The retries no longer count against the destination: a new send
+    // starts over instead of abandoning it, and nothing marks it
+    // unreachable.
+    clock.advance(Duration::from_secs(60));
+    assert!(forwarder.discover(dest).await.unwrap());
+    assert_eq!(discovery.request(dest).await, DiscoveryDecision::Throttled);
+    assert!(!routing.is_unreachable(&dest));
+    assert!(discovery.pending_discoveries().await.contains(&dest));
+}
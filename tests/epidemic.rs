@@ -0,0 +1,90 @@
+use disaster_mesh::{
+    decode_message, AodvReactive, Epidemic, EpidemicBuffer, ForwardDecision, Forwarder, Identity,
+    Message, MessageContent, MockClock, MockTransport, PeerId, RoutingEngine, Transport,
+    TransportEvent, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Pop the next message a node put on its mock transport.
+fn sent(rx: &mut broadcast::Receiver<TransportEvent>) -> Message {
+    match rx.try_recv() {
+        Ok(TransportEvent::DataReceived { data, .. }) => decode_message(&data).unwrap(),
+        other => panic!("expected a sent message, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_carried_message_spreads_on_contact() {
+    let (peer_a, peer_b) = (PeerId([1; 32]), PeerId([2; 32]));
+    let transport_a = Arc::new(MockTransport::new());
+    let transport_b = Arc::new(MockTransport::new());
+    let mut out_a = transport_a.subscribe_events();
+    let mut out_b = transport_b.subscribe_events();
+
+    let identity_a = Identity::generate();
+    let epidemic_a = Epidemic::new(
+        EpidemicBuffer::new(16),
+        identity_a.clone(),
+        transport_a.clone(),
+    );
+    let epidemic_b = Epidemic::new(EpidemicBuffer::new(16), Identity::generate(), transport_b);
+    let forwarder_a = Forwarder::new(
+        identity_a,
+        peer_a,
+        Arc::new(AodvReactive::new(RoutingEngine::new(Duration::from_secs(
+            60,
+        )))),
+        transport_a,
+    )
+    .with_epidemic(epidemic_a.clone());
+
+    // A has no route to the destination, so it carries the message.
+    let far_away = UserId::random();
+    let msg = Message::new(
+        UserId::random(),
+        Some(far_away),
+        MessageContent::Text("hold".into()),
+    );
+    let decision = forwarder_a
+        .handle_incoming(&msg, PeerId([9; 32]))
+        .await
+        .unwrap();
+    assert_eq!(decision, ForwardDecision::Discover(far_away));
+    assert!(epidemic_a.buffer().contains(&msg.id).await);
+
+    // Contact: A offers its summary, B requests what it lacks, A delivers.
+    epidemic_a.on_peer_connected(peer_b).await.unwrap();
+    let summary = sent(&mut out_a);
+    assert!(epidemic_b.handle_control(&summary, peer_a).await.unwrap());
+    let request = sent(&mut out_b);
+    assert!(epidemic_a.handle_control(&request, peer_b).await.unwrap());
+    let delivered = sent(&mut out_a);
+    assert_eq!(delivered.id, msg.id);
+    assert!(epidemic_b.carry(delivered).await);
+
+    // A second contact finds nothing missing and sends no request.
+    epidemic_a.on_peer_connected(peer_b).await.unwrap();
+    let summary = sent(&mut out_a);
+    epidemic_b.handle_control(&summary, peer_a).await.unwrap();
+    assert!(out_b.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_buffer_is_bounded_and_honours_ttl() {
+    let clock = MockClock::default();
+    let buffer = EpidemicBuffer::with_clock(2, Arc::new(clock.clone()));
+    let msgs: Vec<Message> = (0..3)
+        .map(|i| Message::new(UserId::random(), None, MessageContent::Text(i.to_string())))
+        .collect();
+    for msg in &msgs {
+        assert!(buffer.store(msg.clone()).await);
+    }
+    // Oldest evicted to make room.
+    assert_eq!(buffer.summary().await, vec![msgs[1].id, msgs[2].id]);
+
+    clock.advance(msgs[0].ttl + Duration::from_secs(1));
+    assert!(buffer.summary().await.is_empty());
+    assert!(!buffer.store(msgs[0].clone()).await);
+}
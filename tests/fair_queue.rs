@@ -0,0 +1,89 @@
+use disaster_mesh::{
+    decode_message, ControlledFlood, FairQueue, Forwarder, Identity, Message, MessageContent,
+    MessagePriority, MockTransport, PeerId, Transport, TransportEvent, UserId, DEFAULT_MAX_HOPS,
+};
+use std::sync::Arc;
+
+#[test]
+fn test_quiet_destination_is_not_starved_by_chatty_one() {
+    let (chatty, quiet, urgent) = (UserId::random(), UserId::random(), UserId::random());
+    let mut queue = FairQueue::new(64);
+    for i in 0..20 {
+        assert!(queue.push(chatty, MessagePriority::Normal, format!("chatty {i}")));
+    }
+    for i in 0..3 {
+        assert!(queue.push(quiet, MessagePriority::Normal, format!("quiet {i}")));
+    }
+    assert!(queue.push(urgent, MessagePriority::Emergency, "mayday".to_string()));
+
+    let first: Vec<_> = (0..7).map(|_| queue.pop().unwrap()).collect();
+    assert_eq!(
+        first,
+        ["mayday", "chatty 0", "quiet 0", "chatty 1", "quiet 1", "chatty 2", "quiet 2"]
+    );
+    // The rest belongs to the chatty flow, still in order.
+    assert_eq!(queue.len(), 17);
+    assert_eq!(queue.pop().unwrap(), "chatty 3");
+
+    // A full flow refuses more without affecting others.
+    let mut small = FairQueue::new(1);
+    assert!(small.push(chatty, MessagePriority::Normal, 1));
+    assert!(!small.push(chatty, MessagePriority::Normal, 2));
+    assert!(small.push(quiet, MessagePriority::Normal, 3));
+}
+
+#[test]
+fn test_new_flows_are_refused_at_the_flow_limit() {
+    let mut queue = FairQueue::new(8).with_max_flows(2);
+    let (first, second, third) = (UserId::random(), UserId::random(), UserId::random());
+    assert!(queue.push(first, MessagePriority::Normal, 1));
+    assert!(queue.push(second, MessagePriority::Normal, 2));
+    // Existing flows still take items; a third key does not.
+    assert!(queue.push(first, MessagePriority::Normal, 3));
+    assert!(!queue.push(third, MessagePriority::Normal, 4));
+    assert!(queue.push(third, MessagePriority::Emergency, 5));
+
+    // Once a flow drains, its slot is free again.
+    assert_eq!(queue.pop(), Some(5));
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    assert!(queue.push(third, MessagePriority::Normal, 6));
+}
+
+#[tokio::test]
+async fn test_forwarder_relays_queued_traffic_fairly() {
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(PeerId([2; 32])).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        transport.clone(),
+    )
+    .with_fair_queue(64);
+
+    // Both senders are reached through the same neighbour, as on a line.
+    let (chatty, quiet, link) = (UserId::random(), UserId::random(), PeerId([9; 32]));
+    let bulletin =
+        |sender: UserId, text: &str| Message::new(sender, None, MessageContent::Text(text.into()));
+    for i in 0..10 {
+        forwarder
+            .handle_incoming(&bulletin(chatty, &format!("spam {i}")), link)
+            .await
+            .unwrap();
+    }
+    forwarder
+        .handle_incoming(&bulletin(quiet, "road closed"), link)
+        .await
+        .unwrap();
+    assert_eq!(forwarder.queued(), 11);
+
+    let mut events = transport.subscribe_events();
+    assert_eq!(forwarder.send_queued(2).await.unwrap(), 2);
+    let mut senders = Vec::new();
+    while let Ok(TransportEvent::DataReceived { data, .. }) = events.try_recv() {
+        senders.push(decode_message(&data).unwrap().sender);
+    }
+    assert_eq!(senders, [chatty, quiet]);
+    assert_eq!(forwarder.queued(), 9);
+}
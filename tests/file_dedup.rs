@@ -0,0 +1,125 @@
+use disaster_mesh::{
+    Identity, Message, MessageContent, MessageFilter, MessageManager, MockClock, RetentionPolicy,
+    UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_identical_files_share_one_blob() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let me = manager.add_identity(Identity::generate());
+    let map = MessageContent::File {
+        name: "evacuation-map.png".into(),
+        data: vec![7; 4096],
+    };
+    for _ in 0..2 {
+        let msg = Message::builder()
+            .sender(me)
+            .to(UserId::random())
+            .content(map.clone())
+            .ttl(Duration::from_secs(60));
+        manager.create_from(msg).await.unwrap();
+    }
+    assert_eq!(manager.file_blob_count(), 1);
+    let stored = manager
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 2);
+    assert!(stored.iter().all(|msg| msg.content == map));
+
+    // Both copies expire; the blob goes with the last reference, while the
+    // dead letters keep the full file.
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(manager.purge_expired().await.unwrap(), 2);
+    assert_eq!(manager.file_blob_count(), 0);
+    let letters = manager.dead_letters().await.unwrap();
+    assert!(letters.iter().all(|letter| letter.message.content == map));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_copies_keep_their_blob() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let me = manager.add_identity(Identity::generate());
+    let map = MessageContent::File {
+        name: "evacuation-map.png".into(),
+        data: vec![7; 4096],
+    };
+    let tasks: Vec<_> = (0..32)
+        .map(|i| {
+            let (manager, map) = (manager.clone(), map.clone());
+            tokio::spawn(async move {
+                let ttl = if i % 2 == 0 { 60 } else { 600 };
+                let msg = Message::builder()
+                    .sender(me)
+                    .to(UserId::random())
+                    .content(map)
+                    .ttl(Duration::from_secs(ttl));
+                manager.create_from(msg).await.unwrap();
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(manager.file_blob_count(), 1);
+
+    // Half the references go; the body stays for the other half.
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(manager.purge_expired().await.unwrap(), 16);
+    assert_eq!(manager.file_blob_count(), 1);
+    let stored = manager
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 16);
+    assert!(stored.iter().all(|msg| msg.content == map));
+}
+
+#[tokio::test]
+async fn test_retention_counts_file_bodies() {
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_retention(RetentionPolicy {
+            max_messages: None,
+            max_bytes: Some(10_000),
+        });
+    let me = manager.add_identity(Identity::generate());
+    let origin = Identity::generate();
+    for i in 0..3u8 {
+        let mut photo = Message::builder()
+            .sender(origin.user_id())
+            .to(me)
+            .content(MessageContent::File {
+                name: format!("photo-{i}.jpg"),
+                data: vec![i; 8192],
+            })
+            .build()
+            .unwrap();
+        photo.sign(&origin).unwrap();
+        assert!(manager.deliver(photo).await.unwrap());
+    }
+    assert_eq!(manager.file_blob_count(), 3);
+
+    // The records alone are tiny; the bodies put the store over budget.
+    assert_eq!(manager.enforce_retention().await.unwrap(), 2);
+    assert_eq!(manager.file_blob_count(), 1);
+    assert_eq!(
+        manager
+            .list_messages(&MessageFilter::default())
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
@@ -0,0 +1,166 @@
+use disaster_mesh::{
+    FileReceiver, FileTransfer, Identity, Message, MessageContent, MessageId, MessageManager,
+    MockClock, RoutingControl, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_multi_chunk_file_transfer() {
+    let dir = std::env::temp_dir().join(format!("dm-transfer-{}", std::process::id()));
+    let inbox = dir.join("inbox");
+    tokio::fs::create_dir_all(&inbox).await.unwrap();
+    let original: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let source = dir.join("map.bin");
+    tokio::fs::write(&source, &original).await.unwrap();
+
+    let sender_manager = MessageManager::in_memory().await.unwrap();
+    let sender = sender_manager.add_identity(Identity::generate());
+    let receiver_manager = MessageManager::in_memory().await.unwrap();
+    let recipient = UserId::random();
+
+    let mut outgoing = FileTransfer::new(sender_manager)
+        .with_chunk_size(1024)
+        .open(sender, recipient, &source)
+        .await
+        .unwrap();
+    assert_eq!(outgoing.total_chunks(), 10);
+    let receiver = FileReceiver::new(&inbox);
+
+    // Chunk 3 is lost in transit.
+    let mut last = None;
+    while let Some(chunk) = outgoing.next_chunk().await.unwrap() {
+        if let MessageContent::FileChunk { index: 3, .. } = chunk.content {
+            continue;
+        }
+        assert!(receiver_manager.deliver(chunk.clone()).await.unwrap());
+        last = receiver.receive(&chunk).await.unwrap();
+    }
+    let progress = last.take().unwrap();
+    assert!(!progress.complete);
+    assert_eq!(progress.received_bytes, 10_000 - 1024);
+
+    let id = outgoing.transfer_id();
+    let Some(RoutingControl::FragNack { missing, .. }) = receiver.nack(&sender, &id).await else {
+        panic!("transfer should be in progress");
+    };
+    assert_eq!(missing, vec![3]);
+    for chunk in outgoing.resend(&missing).await.unwrap() {
+        last = receiver.receive(&chunk).await.unwrap();
+    }
+    let progress = last.take().unwrap();
+    assert!(progress.complete);
+    assert_eq!(progress.path, inbox.join("map.bin"));
+    assert_eq!(tokio::fs::read(&progress.path).await.unwrap(), original);
+    assert!(receiver.missing(&sender, &id).await.is_none());
+
+    tokio::fs::remove_dir_all(&dir).await.unwrap();
+}
+
+fn chunk(sender: UserId, transfer_id: MessageId, total_chunks: u32, total_size: u64) -> Message {
+    Message::new(
+        sender,
+        None,
+        MessageContent::FileChunk {
+            transfer_id,
+            name: "report.txt".into(),
+            index: 0,
+            total_chunks,
+            offset: 0,
+            total_size,
+            data: b"hi".to_vec(),
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_receiver_rejects_oversized_and_clobbering_transfers() {
+    let inbox = std::env::temp_dir().join(format!("dm-transfer-limits-{}", std::process::id()));
+    tokio::fs::create_dir_all(&inbox).await.unwrap();
+    let receiver = FileReceiver::new(&inbox).with_max_file_size(1024);
+    let sender = UserId::random();
+
+    // Too large, or more chunks than bytes.
+    assert!(receiver
+        .receive(&chunk(sender, MessageId::new(), 1, 4096))
+        .await
+        .is_err());
+    assert!(receiver
+        .receive(&chunk(sender, MessageId::new(), u32::MAX, 2))
+        .await
+        .is_err());
+
+    // Transfers are keyed by sender: another sender reusing the id does not
+    // join this transfer, and cannot take over its file either.
+    let id = MessageId::new();
+    let progress = receiver
+        .receive(&chunk(sender, id, 2, 4))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!progress.complete);
+    assert!(receiver
+        .receive(&chunk(UserId::random(), id, 2, 4))
+        .await
+        .is_err());
+    assert_eq!(receiver.missing(&sender, &id).await, Some(vec![1]));
+
+    // A finished file is never overwritten.
+    tokio::fs::write(inbox.join("notes.txt"), b"keep")
+        .await
+        .unwrap();
+    let mut notes = chunk(sender, MessageId::new(), 1, 2);
+    if let MessageContent::FileChunk { name, .. } = &mut notes.content {
+        *name = "notes.txt".into();
+    }
+    assert!(receiver.receive(&notes).await.is_err());
+    assert_eq!(
+        tokio::fs::read(inbox.join("notes.txt")).await.unwrap(),
+        b"keep"
+    );
+
+    tokio::fs::remove_dir_all(&inbox).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_receiver_caps_transfers_and_abandons_idle_ones() {
+    let inbox = std::env::temp_dir().join(format!("dm-transfer-idle-{}", std::process::id()));
+    tokio::fs::create_dir_all(&inbox).await.unwrap();
+    let clock = MockClock::default();
+    let receiver = FileReceiver::with_clock(&inbox, Arc::new(clock.clone()))
+        .with_max_concurrent_transfers(1)
+        .with_idle_timeout(Duration::from_secs(60));
+    let (alice, bob) = (UserId::random(), UserId::random());
+    let (stalled, next) = (MessageId::new(), MessageId::new());
+
+    receiver
+        .receive(&chunk(alice, stalled, 2, 4))
+        .await
+        .unwrap();
+    let part = inbox.join("report.txt.part");
+    assert!(tokio::fs::try_exists(&part).await.unwrap());
+    assert!(receiver.receive(&chunk(bob, next, 2, 4)).await.is_err());
+
+    // Once the first transfer goes quiet it is dropped with its partial file
+    // and the slot goes to the next one.
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(receiver.purge_idle().await, 1);
+    assert!(!tokio::fs::try_exists(&part).await.unwrap());
+    assert!(receiver.missing(&alice, &stalled).await.is_none());
+    let progress = receiver
+        .receive(&chunk(bob, next, 2, 4))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(progress.path, inbox.join("report.txt"));
+
+    // New transfers also sweep out idle ones on their own.
+    clock.advance(Duration::from_secs(61));
+    assert!(receiver
+        .receive(&chunk(alice, MessageId::new(), 2, 4))
+        .await
+        .is_ok());
+    assert!(receiver.missing(&bob, &next).await.is_none());
+
+    tokio::fs::remove_dir_all(&inbox).await.unwrap();
+}
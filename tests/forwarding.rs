@@ -0,0 +1,410 @@
+use disaster_mesh::{
+    decode_message, AdmissionConfig, AodvReactive, Clock, ControlledFlood, ForwardDecision,
+    Forwarder, ForwardingStrategy, Identity, MeshStats, Message, MessageContent, MessagePriority,
+    MockClock, MockTransport, PeerId, RoutingControl, RoutingEngine, Transport, TransportEvent,
+    TtlMode, UserId, DEFAULT_MAX_HOPS,
+};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Line 0 - 1 - 2 - 3 with a dead-end branch 1 - 4; node `i` is reachable as
+/// `PeerId([i; 32])`.
+const NEIGHBOURS: [&[usize]; 5] = [&[1], &[0, 2, 4], &[1, 3], &[2], &[1]];
+
+fn peer(i: usize) -> PeerId {
+    PeerId([i as u8; 32])
+}
+
+/// Inject `msg` at node 0 and propagate it through the topology, returning the
+/// number of transmissions made by relays.
+async fn simulate(nodes: &[Arc<dyn ForwardingStrategy>], msg: Message, dest: usize) -> usize {
+    // The origin has seen its own message.
+    nodes[0].decide(&msg, peer(0)).await;
+    let mut transmissions = 0;
+    let mut queue: VecDeque<(usize, usize, Message)> =
+        NEIGHBOURS[0].iter().map(|&n| (n, 0, msg.clone())).collect();
+    while let Some((node, from, msg)) = queue.pop_front() {
+        if node == dest {
+            continue;
+        }
+        let mut forwarded = msg.clone();
+        forwarded.hop_count += 1;
+        match nodes[node].decide(&msg, peer(from)).await {
+            ForwardDecision::Broadcast => {
+                transmissions += 1;
+                for &n in NEIGHBOURS[node] {
+                    queue.push_back((n, node, forwarded.clone()));
+                }
+            }
+            ForwardDecision::Unicast(next) => {
+                transmissions += 1;
+                queue.push_back((next.0[0] as usize, node, forwarded));
+            }
+            ForwardDecision::Drop | ForwardDecision::Discover(_) => {}
+        }
+    }
+    transmissions
+}
+
+#[tokio::test]
+async fn test_controlled_flood_suppresses_duplicates() {
+    let flood = ControlledFlood::default();
+    let msg = Message::new(UserId::random(), None, MessageContent::Text("all".into()));
+    assert_eq!(
+        flood.decide(&msg, peer(1)).await,
+        ForwardDecision::Broadcast
+    );
+    assert_eq!(flood.decide(&msg, peer(2)).await, ForwardDecision::Drop);
+
+    let mut tired = Message::new(UserId::random(), None, MessageContent::Text("far".into()));
+    tired.hop_count = 16;
+    assert_eq!(flood.decide(&tired, peer(1)).await, ForwardDecision::Drop);
+}
+
+#[tokio::test]
+async fn test_flood_vs_aodv_on_line_topology() {
+    let origin = UserId::random();
+    let dest_user = UserId::random();
+    let msg = Message::new(origin, Some(dest_user), MessageContent::Text("hi".into()));
+
+    let flood: Vec<Arc<dyn ForwardingStrategy>> = (0..5)
+        .map(|_| Arc::new(ControlledFlood::default()) as Arc<dyn ForwardingStrategy>)
+        .collect();
+    // Relays 1, 2 and 4 each rebroadcast exactly once despite hearing echoes.
+    assert_eq!(simulate(&flood, msg.clone(), 3).await, 3);
+
+    let mut aodv: Vec<Arc<dyn ForwardingStrategy>> = Vec::new();
+    for i in 0..5 {
+        let routing = RoutingEngine::new(Duration::from_secs(60));
+        if i < 3 {
+            routing
+                .update_route(dest_user, peer(i + 1), (3 - i) as u8, 1.0)
+                .await;
+        }
+        aodv.push(Arc::new(AodvReactive::new(routing)));
+    }
+    // Only the on-path relays transmit; the branch never hears the message.
+    assert_eq!(simulate(&aodv, msg.clone(), 3).await, 2);
+
+    // Without a route the relay asks for discovery instead of flooding.
+    let lost = AodvReactive::new(RoutingEngine::new(Duration::from_secs(60)));
+    assert_eq!(
+        lost.decide(&msg, peer(0)).await,
+        ForwardDecision::Discover(dest_user)
+    );
+}
+
+#[tokio::test]
+async fn test_strategy_forgets_ids_after_the_dedup_window() {
+    let clock = MockClock::default();
+    let flood = ControlledFlood::with_clock(DEFAULT_MAX_HOPS, Arc::new(clock.clone()))
+        .with_dedup_window(Duration::from_secs(60));
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("hi".into()));
+    msg.ttl = Duration::from_secs(3600);
+
+    assert_eq!(
+        flood.decide(&msg, peer(1)).await,
+        ForwardDecision::Broadcast
+    );
+    assert_eq!(flood.decide(&msg, peer(2)).await, ForwardDecision::Drop);
+
+    clock.advance(Duration::from_secs(61));
+    let other = Message::new(UserId::random(), None, MessageContent::Text("yo".into()));
+    assert_eq!(
+        flood.decide(&other, peer(1)).await,
+        ForwardDecision::Broadcast
+    );
+    assert_eq!(flood.seen_count().await, 1);
+    assert_eq!(
+        flood.decide(&msg, peer(2)).await,
+        ForwardDecision::Broadcast
+    );
+}
+
+#[tokio::test]
+async fn test_message_with_own_peer_on_path_is_dropped_as_loop() {
+    let local = peer(7);
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        local,
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    );
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("echo".into()));
+    msg.path = vec![peer(1), local, peer(2)];
+
+    let decision = forwarder.handle_incoming(&msg, peer(2)).await.unwrap();
+    assert_eq!(decision, ForwardDecision::Drop);
+    assert_eq!(MeshStats::get(&forwarder.stats().loop_drops), 1);
+
+    // A fresh message without us on its path is still forwarded.
+    let fresh = Message::new(UserId::random(), None, MessageContent::Text("new".into()));
+    let decision = forwarder.handle_incoming(&fresh, peer(2)).await.unwrap();
+    assert_eq!(decision, ForwardDecision::Broadcast);
+    assert_eq!(MeshStats::get(&forwarder.stats().loop_drops), 1);
+}
+
+#[tokio::test]
+async fn test_emergency_without_route_is_flooded() {
+    let aodv = AodvReactive::new(RoutingEngine::new(Duration::from_secs(60)));
+    let dest = UserId::random();
+    let mut sos = Message::new(
+        UserId::random(),
+        Some(dest),
+        MessageContent::Text("SOS".into()),
+    );
+    sos.priority = MessagePriority::Emergency;
+    assert_eq!(aodv.decide(&sos, peer(0)).await, ForwardDecision::Broadcast);
+
+    let chat = Message::new(
+        UserId::random(),
+        Some(dest),
+        MessageContent::Text("hi".into()),
+    );
+    assert_eq!(
+        aodv.decide(&chat, peer(0)).await,
+        ForwardDecision::Discover(dest)
+    );
+}
+
+#[tokio::test]
+async fn test_message_is_dropped_at_hop_limit() {
+    let stats = Arc::new(MeshStats::default());
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("far".into()));
+    // Relay the message along a chain of fresh forwarders, each with a
+    // permissive strategy so only the forwarder's own limit applies.
+    let mut relays = 0;
+    loop {
+        let forwarder = Forwarder::new(
+            Identity::generate(),
+            peer(relays),
+            Arc::new(ControlledFlood::new(u8::MAX)),
+            Arc::new(MockTransport::new()),
+        )
+        .with_max_hops(5)
+        .with_stats(stats.clone());
+        if forwarder.handle_incoming(&msg, peer(relays)).await.unwrap() == ForwardDecision::Drop {
+            break;
+        }
+        msg.hop_count += 1;
+        relays += 1;
+    }
+    // Hops 0..=3 are relayed; the copy that would arrive with 5 is never sent.
+    assert_eq!(relays, 4);
+    assert_eq!(msg.hop_count, 4);
+    assert_eq!(MeshStats::get(&stats.hop_limit_drops), 1);
+
+    let arrived_at_limit = Forwarder::new(
+        Identity::generate(),
+        peer(9),
+        Arc::new(ControlledFlood::new(u8::MAX)),
+        Arc::new(MockTransport::new()),
+    )
+    .with_max_hops(5)
+    .with_stats(stats.clone());
+    msg.hop_count = 5;
+    assert_eq!(
+        arrived_at_limit
+            .handle_incoming(&msg, peer(1))
+            .await
+            .unwrap(),
+        ForwardDecision::Drop
+    );
+    assert_eq!(MeshStats::get(&stats.hop_limit_drops), 2);
+}
+
+#[tokio::test]
+async fn test_relative_ttl_expires_after_budget_regardless_of_clock() {
+    // Relays whose clocks are a day ahead of the sender's.
+    let skewed = Arc::new(MockClock::new(
+        std::time::SystemTime::now() + Duration::from_secs(86_400),
+    ));
+    let stats = Arc::new(MeshStats::default());
+    let mut msg = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("short-lived".into()))
+        .ttl(Duration::from_secs(3))
+        .ttl_mode(TtlMode::Relative)
+        .build()
+        .unwrap();
+
+    let mut relays = 0;
+    for i in 0..10 {
+        let transport = Arc::new(MockTransport::new());
+        transport.add_peer(peer(i + 1)).await;
+        let mut events = transport.subscribe_events();
+        let forwarder = Forwarder::new(
+            Identity::generate(),
+            peer(i),
+            Arc::new(ControlledFlood::with_clock(
+                DEFAULT_MAX_HOPS,
+                skewed.clone(),
+            )),
+            transport,
+        )
+        .with_ttl_decrement(Duration::from_secs(1))
+        .with_stats(stats.clone());
+        if forwarder.handle_incoming(&msg, peer(i + 1)).await.unwrap() == ForwardDecision::Drop {
+            break;
+        }
+        let Ok(TransportEvent::DataReceived { data, .. }) = events.recv().await else {
+            panic!("relayed copy not broadcast");
+        };
+        msg = decode_message(&data).unwrap();
+        relays += 1;
+    }
+    // 3s budget, 1s per hop: two relays, the third would hand on zero.
+    assert_eq!(relays, 2);
+    assert_eq!(msg.ttl, Duration::from_secs(1));
+    assert_eq!(MeshStats::get(&stats.expired_drops), 1);
+
+    // The same message in absolute mode is already stale on the skewed relays.
+    let mut absolute = msg.clone();
+    absolute.ttl_mode = TtlMode::Absolute;
+    let flood = ControlledFlood::with_clock(DEFAULT_MAX_HOPS, skewed);
+    assert_eq!(
+        flood.decide(&absolute, peer(1)).await,
+        ForwardDecision::Drop
+    );
+}
+
+#[tokio::test]
+async fn test_relative_ttl_ages_while_held() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(peer(2)).await;
+    let mut events = transport.subscribe_events();
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        peer(1),
+        Arc::new(ControlledFlood::with_clock(DEFAULT_MAX_HOPS, clock.clone())),
+        transport,
+    )
+    .with_clock(clock.clone())
+    .with_ttl_decrement(Duration::from_secs(1));
+    let mut msg = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("queued".into()))
+        .ttl(Duration::from_secs(10))
+        .ttl_mode(TtlMode::Relative)
+        .build()
+        .unwrap();
+    msg.received_at = Some(clock.now());
+
+    // Four seconds in our inbound queue count against the budget too.
+    clock.advance(Duration::from_secs(4));
+    assert!(!msg.is_expired_at(clock.now()));
+    forwarder.handle_incoming(&msg, peer(2)).await.unwrap();
+    let Ok(TransportEvent::DataReceived { data, .. }) = events.recv().await else {
+        panic!("relayed copy not broadcast");
+    };
+    assert_eq!(decode_message(&data).unwrap().ttl, Duration::from_secs(5));
+
+    clock.advance(Duration::from_secs(6));
+    assert!(msg.is_expired_at(clock.now()));
+}
+
+#[tokio::test]
+async fn test_duplicate_rreq_is_forwarded_once() {
+    let origin = Identity::generate();
+    let rreq = RoutingControl::Rreq {
+        origin: origin.user_id(),
+        destination: UserId::random(),
+        request_id: 7,
+        hop_count: 0,
+    };
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        peer(1),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    );
+    // Two envelopes for the same request, so the message-id seen set alone
+    // would let both through.
+    let mut decisions = Vec::new();
+    for from in [peer(2), peer(3)] {
+        let mut msg = Message::new(
+            origin.user_id(),
+            None,
+            MessageContent::Routing(rreq.clone()),
+        );
+        msg.sign(&origin).unwrap();
+        decisions.push(forwarder.handle_incoming(&msg, from).await.unwrap());
+    }
+    assert_eq!(
+        decisions,
+        [ForwardDecision::Broadcast, ForwardDecision::Drop]
+    );
+    assert_eq!(MeshStats::get(&forwarder.stats().rreq_duplicates), 1);
+}
+
+#[tokio::test]
+async fn test_admission_sets_up_the_relay_queue_it_measures() {
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        peer(1),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+    .with_admission(AdmissionConfig {
+        queue_capacity: 2,
+        ..AdmissionConfig::default()
+    });
+
+    for _ in 0..3 {
+        let mut msg = Message::new(UserId::random(), None, MessageContent::Text("x".into()));
+        msg.priority = MessagePriority::Background;
+        forwarder.handle_incoming(&msg, peer(2)).await.unwrap();
+    }
+    assert_eq!(forwarder.queued(), 1);
+    assert_eq!(MeshStats::get(&forwarder.stats().congestion_drops), 2);
+}
+
+#[tokio::test]
+async fn test_congested_forwarder_sheds_background_but_not_emergency() {
+    let transport = MockTransport::new();
+    transport.add_peer(peer(2)).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        peer(1),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(transport),
+    )
+    .with_fair_queue(8)
+    .with_admission(AdmissionConfig {
+        queue_capacity: 4,
+        ..AdmissionConfig::default()
+    });
+
+    let relay = |priority| {
+        let mut msg = Message::new(UserId::random(), None, MessageContent::Text("x".into()));
+        msg.priority = priority;
+        msg
+    };
+    // Relays that are never sent back the outbound queue up to capacity.
+    for _ in 0..4 {
+        let msg = relay(MessagePriority::Urgent);
+        forwarder.handle_incoming(&msg, peer(2)).await.unwrap();
+    }
+    assert_eq!(forwarder.queued(), 4);
+
+    let mut decisions = Vec::new();
+    for priority in [MessagePriority::Background, MessagePriority::Emergency] {
+        let msg = relay(priority);
+        decisions.push(forwarder.handle_incoming(&msg, peer(2)).await.unwrap());
+    }
+    assert_eq!(
+        decisions,
+        [ForwardDecision::Drop, ForwardDecision::Broadcast]
+    );
+    assert_eq!(MeshStats::get(&forwarder.stats().congestion_drops), 1);
+
+    // Draining the queue lifts the congestion.
+    forwarder.send_queued(usize::MAX).await.unwrap();
+    let msg = relay(MessagePriority::Background);
+    assert_eq!(
+        forwarder.handle_incoming(&msg, peer(2)).await.unwrap(),
+        ForwardDecision::Broadcast
+    );
+}
@@ -0,0 +1,191 @@
+use disaster_mesh::{
+    fragment, Fragment, FragmentPolicy, FragmentSender, MeshStats, Message, MessageContent,
+    MessageId, MockClock, Outbound, Reassembler, RoutingControl, UserId, WireFormat,
+    MAX_FRAGMENT_RETENTION,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_only_missing_fragments_are_retransmitted() {
+    let sender = FragmentSender::new();
+    let receiver = Reassembler::new(Duration::from_secs(60));
+    let msg_id = MessageId::new();
+    let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+    let fragments = sender.split(msg_id, &payload, 100, Duration::from_secs(60));
+    assert_eq!(fragments.len(), 10);
+
+    // Fragments 2, 5 and 9 are lost on the first pass.
+    for fragment in fragments
+        .into_iter()
+        .filter(|f| ![2, 5, 9].contains(&f.index))
+    {
+        assert!(receiver.insert(fragment).is_none());
+    }
+    let Some(RoutingControl::FragNack {
+        msg_id: id,
+        missing,
+    }) = receiver.nack(&msg_id)
+    else {
+        panic!("expected a FragNack");
+    };
+    assert_eq!(id, msg_id);
+    assert_eq!(missing, vec![2, 5, 9]);
+
+    let resent = sender.handle_nack(&msg_id, &missing);
+    assert_eq!(
+        resent.iter().map(|f| f.index).collect::<Vec<_>>(),
+        vec![2, 5, 9]
+    );
+    let mut reassembled = None;
+    for fragment in resent {
+        reassembled = receiver.insert(fragment);
+    }
+    assert_eq!(reassembled, Some(payload));
+    assert_eq!(receiver.missing(&msg_id), None);
+
+    // The final (empty) nack releases the sender's copy.
+    assert!(sender.is_retained(&msg_id));
+    assert!(sender.handle_nack(&msg_id, &[]).is_empty());
+    assert!(!sender.is_retained(&msg_id));
+}
+
+#[test]
+fn test_unbounded_ttl_is_capped_instead_of_overflowing() {
+    let clock = MockClock::default();
+    let sender = FragmentSender::with_clock(Arc::new(clock.clone()));
+    let receiver = Reassembler::with_clock(Duration::MAX, Arc::new(clock.clone()));
+    let mut msg = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("x".repeat(300)),
+    );
+    msg.ttl = Duration::MAX;
+
+    let Outbound::Fragments(fragments) = sender
+        .prepare(&msg, WireFormat::Bincode, 100, FragmentPolicy::Auto)
+        .unwrap()
+    else {
+        panic!("expected fragments");
+    };
+    assert!(receiver.insert(fragments[0].clone()).is_none());
+    assert!(receiver.missing(&msg.id).is_some());
+    assert!(!sender.handle_nack(&msg.id, &[1]).is_empty());
+
+    clock.advance(MAX_FRAGMENT_RETENTION);
+    assert!(sender.handle_nack(&msg.id, &[1]).is_empty());
+    assert!(!sender.is_retained(&msg.id));
+}
+
+#[test]
+fn test_oldest_partial_reassembly_is_evicted() {
+    let stats = Arc::new(MeshStats::default());
+    let receiver = Reassembler::new(Duration::from_secs(60))
+        .with_limits(3, 1024)
+        .with_stats(stats.clone());
+    let ids: Vec<MessageId> = (0..4).map(|_| MessageId::new()).collect();
+    let payloads: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 40]).collect();
+    let transfers: Vec<Vec<Fragment>> = ids
+        .iter()
+        .zip(&payloads)
+        .map(|(id, payload)| fragment(*id, payload, 20))
+        .collect();
+
+    // Start four transfers with a cap of three: the first is evicted.
+    for fragments in &transfers {
+        assert!(receiver.insert(fragments[0].clone()).is_none());
+    }
+    assert_eq!(receiver.missing(&ids[0]), None);
+    assert_eq!(MeshStats::get(&stats.reassembly_evictions), 1);
+    assert_eq!(
+        receiver.insert(transfers[3][1].clone()),
+        Some(payloads[3].clone())
+    );
+
+    // A fragment that would exceed the byte budget pushes out the oldest.
+    let big_id = MessageId::new();
+    let big = fragment(big_id, &[7; 2000], 1000);
+    assert!(receiver.insert(big[0].clone()).is_none());
+    assert_eq!(receiver.missing(&ids[1]), None);
+    assert!(receiver.missing(&ids[2]).is_some());
+    assert_eq!(receiver.missing(&big_id), Some(vec![1]));
+    assert_eq!(MeshStats::get(&stats.reassembly_evictions), 2);
+}
+
+#[test]
+fn test_oversized_transfer_does_not_flush_others() {
+    let stats = Arc::new(MeshStats::default());
+    let receiver = Reassembler::new(Duration::from_secs(60))
+        .with_limits(8, 1024)
+        .with_stats(stats.clone());
+    let ids: Vec<MessageId> = (0..3).map(|_| MessageId::new()).collect();
+    for id in &ids {
+        assert!(receiver
+            .insert(fragment(*id, &[1; 200], 100)[0].clone())
+            .is_none());
+    }
+
+    // A transfer growing past the whole budget is dropped on its own.
+    let hostile = MessageId::new();
+    let chunks = fragment(hostile, &[9; 1400], 700);
+    assert!(receiver.insert(chunks[0].clone()).is_none());
+    assert!(receiver.insert(chunks[1].clone()).is_none());
+    assert_eq!(receiver.missing(&hostile), None);
+    for id in &ids {
+        assert_eq!(receiver.missing(id), Some(vec![1]));
+    }
+    assert_eq!(MeshStats::get(&stats.reassembly_evictions), 1);
+
+    // A claimed total the budget could never hold is ignored outright.
+    let claim = Fragment {
+        msg_id: MessageId::new(),
+        index: 0,
+        total: u32::MAX,
+        data: vec![0; 16],
+    };
+    let claimed = claim.msg_id;
+    assert!(receiver.insert(claim).is_none());
+    assert_eq!(receiver.missing(&claimed), None);
+}
+
+#[test]
+fn test_no_fragment_control_errors_over_mtu() {
+    let sender = FragmentSender::new();
+    let rerr = RoutingControl::Rerr {
+        unreachable: (0..20).map(|_| UserId::random()).collect(),
+    };
+    let control = Message::new(UserId::random(), None, MessageContent::Routing(rerr));
+    let format = WireFormat::Bincode;
+    assert!(format.encode(&control).unwrap().len() > 256);
+
+    assert!(sender
+        .prepare(&control, format, 256, FragmentPolicy::Never)
+        .is_err());
+    assert!(!sender.is_retained(&control.id));
+
+    let Outbound::Fragments(fragments) = sender
+        .prepare(&control, format, 256, FragmentPolicy::Auto)
+        .unwrap()
+    else {
+        panic!("expected fragments");
+    };
+    assert!(fragments.len() > 1);
+    let receiver = Reassembler::new(Duration::from_secs(60));
+    let whole = fragments
+        .into_iter()
+        .find_map(|fragment| receiver.insert(fragment))
+        .unwrap();
+    assert_eq!(format.decode::<Message>(&whole).unwrap().id, control.id);
+
+    // A small message goes whole unless fragments are forced.
+    let text = Message::new(UserId::random(), None, MessageContent::Text("ok".into()));
+    assert!(matches!(
+        sender.prepare(&text, format, 256, FragmentPolicy::Auto),
+        Ok(Outbound::Whole(_))
+    ));
+    assert!(matches!(
+        sender.prepare(&text, format, 256, FragmentPolicy::Always),
+        Ok(Outbound::Fragments(f)) if f.len() == 1
+    ));
+}
@@ -0,0 +1,81 @@
+use disaster_mesh::{
+    decode_message, Framing, Message, MessageContent, UserId, WireFormat, FRAME_END,
+};
+
+/// Two encoded messages, the second containing bytes that collide with the
+/// delimiter framing's special values.
+fn two_messages() -> (Message, Message) {
+    let first = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("bridge out on route 9".into()),
+    );
+    let second = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::File {
+            name: "map.bin".into(),
+            data: vec![FRAME_END, 0xDB, 0xDC, 0xDD, FRAME_END],
+        },
+    );
+    (first, second)
+}
+
+fn framed_stream(framing: Framing, messages: &[&Message]) -> Vec<u8> {
+    messages
+        .iter()
+        .flat_map(|msg| {
+            framing
+                .frame(&WireFormat::Bincode.encode(msg).unwrap())
+                .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_stream_framings_split_concatenated_messages() {
+    let (first, second) = two_messages();
+    for framing in [Framing::LengthPrefixed, Framing::Delimited] {
+        let stream = framed_stream(framing, &[&first, &second]);
+
+        // All at once.
+        let payloads = framing.decoder().push(&stream).unwrap();
+        assert_eq!(payloads.len(), 2, "{framing:?}");
+        assert_eq!(decode_message(&payloads[0]).unwrap().id, first.id);
+        assert_eq!(
+            decode_message(&payloads[1]).unwrap().content,
+            second.content
+        );
+
+        // Byte by byte, as a slow serial line would deliver it.
+        let mut decoder = framing.decoder();
+        let mut trickled = Vec::new();
+        for byte in &stream {
+            trickled.extend(decoder.push(std::slice::from_ref(byte)).unwrap());
+        }
+        assert_eq!(trickled, payloads, "{framing:?}");
+        assert_eq!(decoder.buffered(), 0);
+    }
+}
+
+#[test]
+fn test_datagram_framing_keeps_each_read_whole() {
+    let (first, second) = two_messages();
+    let mut decoder = Framing::Datagram.decoder();
+    let a = Framing::Datagram
+        .frame(&WireFormat::Bincode.encode(&first).unwrap())
+        .unwrap();
+    let b = Framing::Datagram
+        .frame(&WireFormat::Bincode.encode(&second).unwrap())
+        .unwrap();
+    assert_eq!(decoder.push(&a).unwrap(), vec![a]);
+    assert_eq!(decoder.push(&b).unwrap(), vec![b.clone()]);
+    assert_eq!(decode_message(&b).unwrap().id, second.id);
+}
+
+#[test]
+fn test_oversized_length_prefix_is_rejected() {
+    let mut decoder = Framing::LengthPrefixed.decoder();
+    assert!(decoder.push(&u32::MAX.to_be_bytes()).is_err());
+    assert_eq!(decoder.buffered(), 0);
+}
@@ -0,0 +1,68 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, GeoHint, GeoPoint, Identity, MeshStats, Message,
+    MessageContent, MockTransport, PeerId, UserId,
+};
+use std::sync::Arc;
+
+fn forwarder() -> Forwarder {
+    Forwarder::new(
+        Identity::generate(),
+        PeerId([0; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+}
+
+#[tokio::test]
+async fn test_geo_scoped_message_only_relayed_inside_region() {
+    // 2 km around a point in Valencia.
+    let sector = GeoHint {
+        lat: 39.4699,
+        lon: -0.3763,
+        radius_m: 2_000.0,
+    };
+    let alert = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("evacuate sector 4".into()))
+        .geo(sector)
+        .build()
+        .unwrap();
+
+    // ~1 km north of the centre.
+    let inside = forwarder().with_position(GeoPoint::new(39.4789, -0.3763));
+    assert_eq!(
+        inside
+            .handle_incoming(&alert, PeerId([1; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Broadcast
+    );
+
+    // ~11 km north: clearly outside.
+    let outside = forwarder().with_position(GeoPoint::new(39.5699, -0.3763));
+    assert_eq!(
+        outside
+            .handle_incoming(&alert, PeerId([1; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Drop
+    );
+    assert_eq!(MeshStats::get(&outside.stats().geo_drops), 1);
+
+    // Unscoped traffic and nodes without a position are unaffected.
+    let chat = Message::new(UserId::random(), None, MessageContent::Text("hi".into()));
+    assert_eq!(
+        outside
+            .handle_incoming(&chat, PeerId([1; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Broadcast
+    );
+    assert_eq!(
+        forwarder()
+            .handle_incoming(&alert, PeerId([1; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Broadcast
+    );
+}
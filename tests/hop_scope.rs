@@ -0,0 +1,62 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, HopScope, Identity, Message, MessageContent,
+    MessagePriority, MockTransport, PeerId, UserId, DEFAULT_HOP_LIMIT,
+};
+use std::sync::Arc;
+
+/// Relay `msg` along a chain of fresh relays until one drops it, returning
+/// how many hops it made. `max_hops` overrides the forwarder's default.
+async fn reach(scope: HopScope, max_hops: Option<u8>, priority: MessagePriority) -> u8 {
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("hi".into()));
+    msg.priority = priority;
+    for relay in 1..=u8::MAX {
+        let transport = MockTransport::new();
+        transport
+            .add_peer(PeerId([relay.wrapping_add(1); 32]))
+            .await;
+        let mut forwarder = Forwarder::new(
+            Identity::generate(),
+            PeerId([relay; 32]),
+            Arc::new(ControlledFlood::new(u8::MAX)),
+            Arc::new(transport),
+        )
+        .with_hop_scope(scope);
+        if let Some(max_hops) = max_hops {
+            forwarder = forwarder.with_max_hops(max_hops);
+        }
+        let from = PeerId([relay - 1; 32]);
+        if forwarder.handle_incoming(&msg, from).await.unwrap() == ForwardDecision::Drop {
+            return msg.hop_count;
+        }
+        msg.hop_count += 1;
+        msg.path.push(PeerId([relay; 32]));
+    }
+    unreachable!("hop limit never hit")
+}
+
+#[tokio::test]
+async fn test_background_floods_less_far_than_emergency() {
+    let scope = HopScope {
+        urgent: 10,
+        normal: 6,
+        background: 3,
+    };
+    assert_eq!(reach(scope, Some(12), MessagePriority::Emergency).await, 11);
+    assert_eq!(reach(scope, Some(12), MessagePriority::Urgent).await, 9);
+    assert_eq!(reach(scope, Some(12), MessagePriority::Background).await, 2);
+    assert_eq!(scope.limit(MessagePriority::Normal, 4), 4);
+}
+
+#[tokio::test]
+async fn test_default_scope_fits_under_default_hop_limit() {
+    let scope = HopScope::default();
+    assert_eq!(DEFAULT_HOP_LIMIT, 32);
+    assert_eq!(
+        reach(scope, None, MessagePriority::Emergency).await,
+        DEFAULT_HOP_LIMIT - 1
+    );
+    assert_eq!(
+        reach(scope, None, MessagePriority::Urgent).await,
+        scope.urgent - 1
+    );
+}
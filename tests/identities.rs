@@ -0,0 +1,67 @@
+use disaster_mesh::{Identity, MessageContent, MessageManager, UserId};
+
+#[tokio::test]
+async fn test_messages_under_two_identities_verify_independently() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let work = manager.add_identity(Identity::generate());
+    let personal = manager.add_identity(Identity::generate());
+    assert_eq!(manager.default_identity().unwrap().user_id(), work);
+
+    let a = manager
+        .create_message_as(None, None, MessageContent::Text("team".into()))
+        .await
+        .unwrap();
+    let b = manager
+        .create_message_as(Some(&personal), None, MessageContent::Text("family".into()))
+        .await
+        .unwrap();
+    assert_eq!(a.sender, work);
+    assert_eq!(b.sender, personal);
+    a.verify_signature().unwrap();
+    b.verify_signature().unwrap();
+
+    // A receiver using a different identity still verifies both by sender.
+    let receiver = MessageManager::in_memory().await.unwrap();
+    receiver.add_identity(Identity::generate());
+    assert!(receiver.validate_message(&a).await.is_ok());
+    assert!(receiver.validate_message(&b).await.is_ok());
+
+    // Swapping senders breaks verification.
+    let mut forged = b.clone();
+    forged.sender = work;
+    assert!(receiver.validate_message(&forged).await.is_err());
+
+    // Stripping the signature does not help either, unless the receiver
+    // explicitly accepts unsigned traffic.
+    let mut stripped = a.clone();
+    stripped.signature.clear();
+    assert!(receiver.validate_message(&stripped).await.is_err());
+    let lenient = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages();
+    assert!(lenient.validate_message(&stripped).await.is_ok());
+    assert!(lenient.validate_message(&forged).await.is_err());
+}
+
+#[tokio::test]
+async fn test_rotating_the_default_identity() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let old = manager.add_identity(Identity::generate());
+    let new = manager.add_identity(Identity::generate());
+
+    assert!(manager.set_default_identity(&UserId::random()).is_err());
+    assert!(manager.remove_identity(&old).is_some());
+    assert_eq!(manager.default_identity().unwrap().user_id(), new);
+    assert!(manager
+        .create_message_as(Some(&old), None, MessageContent::Text("x".into()))
+        .await
+        .is_err());
+
+    manager.remove_identity(&new);
+    assert!(manager.default_identity().is_none());
+    assert!(manager
+        .create_message_as(None, None, MessageContent::Text("x".into()))
+        .await
+        .is_err());
+}
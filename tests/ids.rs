@@ -0,0 +1,36 @@
+use disaster_mesh::{Identity, PeerId, UserId};
+
+#[test]
+fn test_ids_round_trip_through_text() {
+    let user = Identity::generate().user_id();
+    let text = user.to_string();
+    assert_eq!(text.len(), 64);
+    assert_eq!(text.parse::<UserId>().unwrap(), user);
+    assert_eq!(text.to_uppercase().parse::<UserId>().unwrap(), user);
+
+    let peer = PeerId([0xab; 32]);
+    assert_eq!(peer.to_string(), "ab".repeat(32));
+    assert_eq!(peer.to_string().parse::<PeerId>().unwrap(), peer);
+
+    for bad in [
+        "",
+        "abcd",
+        &"zz".repeat(32),
+        &"+f".repeat(32),
+        &"ab".repeat(33),
+    ] {
+        assert!(bad.parse::<UserId>().is_err(), "{bad:?} parsed");
+    }
+}
+
+#[test]
+fn test_to_short_is_stable_prefix() {
+    let mut bytes = [0; 32];
+    bytes[..4].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+    assert_eq!(UserId(bytes).to_short(), "deadbeef");
+    assert_eq!(PeerId(bytes).to_short(), "deadbeef");
+
+    let user = UserId::random();
+    assert_eq!(user.to_short(), user.to_short());
+    assert!(user.to_string().starts_with(&user.to_short()));
+}
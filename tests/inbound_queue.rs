@@ -0,0 +1,74 @@
+use disaster_mesh::{
+    InboundQueue, Message, MessageContent, MessagePriority, PeerId, TtlMode, UserId,
+};
+use std::time::Duration;
+
+fn inbound(text: &str, priority: MessagePriority, ttl_secs: u64) -> Message {
+    Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text(text.into()))
+        .priority(priority)
+        .ttl(Duration::from_secs(ttl_secs))
+        .build()
+        .unwrap()
+}
+
+fn text(msg: &Message) -> &str {
+    match &msg.content {
+        MessageContent::Text(text) => text,
+        other => panic!("unexpected content {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_inbound_order_respects_priority_then_deadline() {
+    let queue = InboundQueue::new(16);
+    let from = PeerId([1; 32]);
+    for msg in [
+        inbound("bulk", MessagePriority::Background, 60),
+        inbound("chat", MessagePriority::Normal, 3600),
+        inbound("chat expiring", MessagePriority::Normal, 30),
+        inbound("mayday", MessagePriority::Emergency, 3600),
+        inbound("status", MessagePriority::Normal, 600),
+    ] {
+        assert!(queue.push(from, msg));
+    }
+
+    let mut order = Vec::new();
+    while !queue.is_empty() {
+        let (_, msg) = queue.next().await;
+        order.push(text(&msg).to_string());
+    }
+    assert_eq!(order, ["mayday", "chat expiring", "status", "chat", "bulk"]);
+}
+
+#[test]
+fn test_full_queue_drops_least_urgent() {
+    let queue = InboundQueue::new(2);
+    let from = PeerId([1; 32]);
+    assert!(queue.push(from, inbound("bulk", MessagePriority::Background, 60)));
+    assert!(queue.push(from, inbound("chat", MessagePriority::Normal, 60)));
+    assert!(!queue.push(from, inbound("more bulk", MessagePriority::Background, 60)));
+    assert!(queue.push(from, inbound("mayday", MessagePriority::Emergency, 60)));
+
+    assert_eq!(queue.len(), 2);
+    assert_eq!(text(&queue.pop().unwrap().1), "mayday");
+    assert_eq!(text(&queue.pop().unwrap().1), "chat");
+}
+
+#[tokio::test]
+async fn test_huge_ttl_does_not_overflow() {
+    let queue = InboundQueue::new(16);
+    let from = PeerId([1; 32]);
+    let mut forever = inbound("forever", MessagePriority::Normal, 60);
+    forever.ttl = Duration::MAX;
+    let mut relative = forever.clone();
+    relative.ttl_mode = TtlMode::Relative;
+    assert!(queue.push(from, forever));
+    assert!(queue.push(from, relative));
+    assert!(queue.push(from, inbound("soon", MessagePriority::Normal, 60)));
+
+    // Sorted as never expiring, behind anything with a real deadline.
+    assert_eq!(text(&queue.next().await.1), "soon");
+    assert_eq!(queue.len(), 2);
+}
@@ -0,0 +1,49 @@
+use disaster_mesh::{Message, MessageContent, MessageManager, UserId};
+use futures::StreamExt;
+
+#[tokio::test]
+async fn test_typed_subscribers_see_only_their_variant() {
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages();
+    let texts = manager.subscribe_text();
+    let telemetry = manager.subscribe_telemetry();
+    let me = UserId::random();
+
+    let mixed = vec![
+        MessageContent::Text("hello".into()),
+        MessageContent::Telemetry {
+            kind: "battery".into(),
+            readings: vec![("level".into(), 0.82)],
+            unit: Some("ratio".into()),
+        },
+        MessageContent::File {
+            name: "map.png".into(),
+            data: vec![1, 2, 3],
+        },
+        MessageContent::Text("world".into()),
+    ];
+    for content in mixed {
+        let msg = Message::new(UserId::random(), Some(me), content);
+        assert!(manager.deliver(msg.clone()).await.unwrap());
+        // Duplicates are not handed out again.
+        assert!(!manager.deliver(msg).await.unwrap());
+    }
+    drop(manager);
+
+    let texts: Vec<_> = texts.map(|m| m.content).collect().await;
+    assert_eq!(
+        texts,
+        vec![
+            MessageContent::Text("hello".into()),
+            MessageContent::Text("world".into())
+        ]
+    );
+    let telemetry: Vec<_> = telemetry.collect().await;
+    assert_eq!(telemetry.len(), 1);
+    assert!(matches!(
+        telemetry[0].content,
+        MessageContent::Telemetry { ref kind, .. } if kind == "battery"
+    ));
+}
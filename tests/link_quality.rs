@@ -0,0 +1,41 @@
+use disaster_mesh::{LinkQualityTracker, PeerId};
+
+#[test]
+fn test_ewma_smooths_noisy_samples() {
+    let tracker = LinkQualityTracker::new(0.2);
+    let peer = PeerId([1; 32]);
+
+    // Flapping link: alternating perfect and dead samples.
+    let mut outputs = Vec::new();
+    for i in 0..50 {
+        let sample = if i % 2 == 0 { 1.0 } else { 0.0 };
+        outputs.push(tracker.record(peer, sample));
+    }
+    // After warm-up the average hovers near 0.5 with far less swing than
+    // the raw 0/1 samples.
+    for value in &outputs[10..] {
+        assert!((0.35..=0.65).contains(value), "unsmoothed value {value}");
+    }
+
+    // Out-of-range samples never push the estimate outside [0, 1].
+    for sample in [5.0, -3.0, f32::NAN, 1.5] {
+        let value = tracker.record(peer, sample);
+        assert!((0.0..=1.0).contains(&value));
+    }
+    assert!(tracker.quality(&peer).is_some());
+    assert!(tracker.quality(&PeerId([2; 32])).is_none());
+}
+
+#[test]
+fn test_alpha_controls_responsiveness() {
+    let slow = LinkQualityTracker::new(0.1);
+    let fast = LinkQualityTracker::new(0.9);
+    let peer = PeerId([1; 32]);
+    slow.record(peer, 1.0);
+    fast.record(peer, 1.0);
+    let slow_after = slow.record(peer, 0.0);
+    let fast_after = fast.record(peer, 0.0);
+    assert!((slow_after - 0.9).abs() < 1e-6);
+    assert!((fast_after - 0.1).abs() < 1e-6);
+    assert!((slow.overall() - 0.9).abs() < 1e-6);
+}
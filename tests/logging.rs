@@ -0,0 +1,155 @@
+use disaster_mesh::{
+    ControlledFlood, Forwarder, Identity, MessageContent, MessageManager, MockTransport, PeerId,
+    DEFAULT_MAX_HOPS,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+type Fields = HashMap<String, String>;
+
+#[derive(Default)]
+struct Recorded {
+    /// Span name and fields, indexed by id - 1.
+    spans: Vec<(&'static str, Fields)>,
+    /// Enclosing span name and event fields.
+    events: Vec<(Option<&'static str>, Fields)>,
+    stack: Vec<Id>,
+}
+
+/// Minimal subscriber recording every span and event, standing in for
+/// whatever a consumer would install.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Recorded>>);
+
+struct FieldVisitor<'a>(&'a mut Fields);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().into(), format!("{value:?}"));
+    }
+}
+
+impl Subscriber for Capture {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Fields::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        let mut recorded = self.0.lock().unwrap();
+        recorded.spans.push((span.metadata().name(), fields));
+        Id::from_u64(recorded.spans.len() as u64)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut recorded = self.0.lock().unwrap();
+        let (_, fields) = &mut recorded.spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(fields));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        let mut recorded = self.0.lock().unwrap();
+        let span = recorded
+            .stack
+            .last()
+            .map(|id| recorded.spans[id.into_u64() as usize - 1].0);
+        recorded.events.push((span, fields));
+    }
+
+    fn enter(&self, span: &Id) {
+        self.0.lock().unwrap().stack.push(span.clone());
+    }
+
+    fn exit(&self, _: &Id) {
+        self.0.lock().unwrap().stack.pop();
+    }
+}
+
+impl Capture {
+    fn span(&self, name: &str) -> Option<Fields> {
+        let recorded = self.0.lock().unwrap();
+        recorded
+            .spans
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, fields)| fields.clone())
+    }
+
+    fn decisions_in(&self, span: &str) -> Vec<String> {
+        let recorded = self.0.lock().unwrap();
+        recorded
+            .events
+            .iter()
+            .filter(|(s, _)| *s == Some(span))
+            .filter_map(|(_, fields)| fields.get("decision").cloned())
+            .collect()
+    }
+}
+
+#[tokio::test]
+async fn test_forwarded_message_span_carries_message_fields() {
+    let capture = Capture::default();
+    let _guard = tracing::subscriber::set_default(capture.clone());
+
+    let origin = Identity::generate();
+    let manager = MessageManager::in_memory().await.unwrap();
+    manager.add_identity(origin.clone());
+    let msg = manager
+        .create_message(
+            origin.user_id(),
+            None,
+            MessageContent::Text("road to the clinic is open".into()),
+        )
+        .await
+        .unwrap();
+    let created = capture.span("create").expect("no create span");
+    assert_eq!(created["message.id"], msg.id.to_string());
+    assert_eq!(created["sender"], origin.user_id().to_string());
+
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(PeerId([2; 32])).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        transport,
+    );
+    forwarder
+        .handle_incoming(&msg, PeerId([0; 32]))
+        .await
+        .unwrap();
+    forwarder
+        .handle_incoming(&msg, PeerId([3; 32]))
+        .await
+        .unwrap();
+
+    let forward = capture.span("forward").expect("no forward span");
+    assert_eq!(forward["message.id"], msg.id.to_string());
+    assert_eq!(forward["sender"], msg.sender.to_string());
+    assert_eq!(forward["hop_count"], "0");
+    assert_eq!(
+        capture.decisions_in("forward"),
+        ["forwarded", "dropped-duplicate"]
+    );
+
+    let receiver = MessageManager::in_memory().await.unwrap();
+    assert!(receiver.deliver(msg.clone()).await.unwrap());
+    assert!(!receiver.deliver(msg).await.unwrap());
+    assert_eq!(
+        capture.decisions_in("deliver"),
+        ["delivered", "dropped-duplicate"]
+    );
+}
@@ -0,0 +1,123 @@
+use disaster_mesh::{
+    Identity, Message, MessageContent, MessageId, MessageManager, MessagePriority, UserId,
+    WireFormat, DEFAULT_TTL,
+};
+use std::time::Duration;
+
+#[test]
+fn test_builder_fully_specified() {
+    let sender = UserId::random();
+    let recipient = UserId::random();
+    let content = MessageContent::Text("SOS".into());
+
+    let message = Message::builder()
+        .sender(sender)
+        .to(recipient)
+        .content(content.clone())
+        .ttl(Duration::from_secs(60))
+        .priority(MessagePriority::Emergency)
+        .build()
+        .unwrap();
+
+    assert_eq!(message.sender, sender);
+    assert_eq!(message.recipient, Some(recipient));
+    assert_eq!(message.content, content);
+    assert_eq!(message.ttl, Duration::from_secs(60));
+    assert_eq!(message.priority, MessagePriority::Emergency);
+    assert_eq!(message.hop_count, 0);
+}
+
+#[test]
+fn test_builder_defaults_and_missing_field() {
+    let sender = UserId::random();
+    let message = Message::builder()
+        .sender(sender)
+        .content(MessageContent::Text("hi".into()))
+        .build()
+        .unwrap();
+    assert_eq!(message.recipient, None);
+    assert_eq!(message.ttl, DEFAULT_TTL);
+    assert_eq!(message.priority, MessagePriority::Normal);
+
+    let missing_content = Message::builder().sender(sender).build();
+    assert!(missing_content.is_err());
+}
+
+#[tokio::test]
+async fn test_telemetry_round_trip_and_store() {
+    let content = MessageContent::Telemetry {
+        kind: "air_quality".into(),
+        readings: vec![("pm2_5".into(), 35.2), ("pm10".into(), 51.0)],
+        unit: Some("µg/m³".into()),
+    };
+    for format in [WireFormat::Bincode, WireFormat::Json] {
+        let encoded = format.encode(&content).unwrap();
+        let decoded: MessageContent = format.decode(&encoded).unwrap();
+        assert_eq!(decoded, content);
+    }
+
+    let manager = MessageManager::new().await.unwrap();
+    let sender = manager.add_identity(Identity::generate());
+    let message = manager
+        .create_message(sender, None, content.clone())
+        .await
+        .unwrap();
+    assert!(manager.validate_message(&message).await.is_ok());
+    assert!(!manager.is_new_message(&message.id).await);
+    let decoded = manager
+        .decrypt_message(
+            &manager
+                .encrypt_message(&content, &message.sender)
+                .await
+                .unwrap(),
+            &message.sender,
+        )
+        .await
+        .unwrap();
+    assert_eq!(decoded, content);
+}
+
+#[test]
+fn test_priority_default_and_round_trip() {
+    let message = Message::new(UserId::random(), None, MessageContent::Text("x".into()));
+    assert_eq!(message.priority, MessagePriority::Normal);
+
+    let urgent = Message::builder()
+        .sender(UserId::random())
+        .content(MessageContent::Text("now".into()))
+        .priority(MessagePriority::Urgent)
+        .build()
+        .unwrap();
+    for format in [WireFormat::Bincode, WireFormat::Json] {
+        let decoded: Message = format.decode(&format.encode(&urgent).unwrap()).unwrap();
+        assert_eq!(decoded.priority, MessagePriority::Urgent);
+    }
+}
+
+#[test]
+fn test_content_addressed_ids() {
+    let sender = UserId::random();
+    let bulletin = MessageContent::Text("Bridge on route 9 is out".into());
+
+    let a = MessageId::from_content(&sender, &bulletin, 42);
+    let b = MessageId::from_content(&sender, &bulletin, 42);
+    assert_eq!(a, b);
+
+    let other = MessageContent::Text("Bridge on route 9 is open".into());
+    assert_ne!(a, MessageId::from_content(&sender, &other, 42));
+    assert_ne!(a, MessageId::from_content(&sender, &bulletin, 43));
+    assert_ne!(a, MessageId::from_content(&UserId::random(), &bulletin, 42));
+
+    // Opt-in via the builder; random ids remain the default.
+    let build = |content_id: bool| {
+        let builder = Message::builder().sender(sender).content(bulletin.clone());
+        let builder = if content_id {
+            builder.content_id(Duration::from_secs(3600))
+        } else {
+            builder
+        };
+        builder.build().unwrap().id
+    };
+    assert_eq!(build(true), build(true));
+    assert_ne!(build(false), build(false));
+}
@@ -1,4 +1,7 @@
-use disaster_mesh::{MessageContent, MessageManager, UserId};
+use disaster_mesh::{Identity, MessageContent, MessageFilter, MessageManager, MockClock, UserId};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_message_creation() {
@@ -14,3 +17,143 @@ async fn test_message_creation() {
     assert_eq!(message.sender, sender);
     assert_eq!(message.content, content);
 }
+
+#[tokio::test]
+async fn test_in_memory_managers_are_isolated() {
+    let a = MessageManager::in_memory().await.unwrap();
+    let b = MessageManager::in_memory().await.unwrap();
+
+    let message = a
+        .create_message(
+            UserId::random(),
+            None,
+            MessageContent::Text("a only".into()),
+        )
+        .await
+        .unwrap();
+
+    assert!(!a.is_new_message(&message.id).await);
+    assert!(b.is_new_message(&message.id).await);
+}
+
+#[tokio::test]
+async fn test_broadcast_file_is_rejected() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let sender = UserId::random();
+    let file = MessageContent::File {
+        name: "medical.pdf".into(),
+        data: vec![1, 2, 3],
+    };
+
+    assert!(manager
+        .create_message(sender, None, file.clone())
+        .await
+        .is_err());
+    assert!(manager
+        .create_message(sender, Some(UserId::random()), file)
+        .await
+        .is_ok());
+    assert!(manager
+        .create_message(sender, None, MessageContent::Text("shelter open".into()))
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_thread_reconstruction() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+    let alice = manager.add_identity(Identity::generate());
+    let bob = manager.add_identity(Identity::generate());
+    let text = |s: &str| MessageContent::Text(s.into());
+
+    let parent = manager
+        .create_message(alice, Some(bob), text("water at the school?"))
+        .await
+        .unwrap();
+    clock.advance(Duration::from_secs(1));
+    let first = manager
+        .create_reply(bob, &parent, text("yes, 40 litres"))
+        .await
+        .unwrap();
+    clock.advance(Duration::from_secs(1));
+    let second = manager
+        .create_reply(alice, &first, text("on my way"))
+        .await
+        .unwrap();
+    manager
+        .create_message(alice, Some(bob), text("unrelated"))
+        .await
+        .unwrap();
+
+    assert_eq!(first.recipient, Some(alice));
+    assert_eq!(first.in_reply_to, Some(parent.id));
+    let ids: Vec<_> = manager
+        .thread(parent.id)
+        .await
+        .unwrap()
+        .iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(ids, vec![parent.id, first.id, second.id]);
+
+    // The parent reference is covered by the signature.
+    let mut forged = first.clone();
+    forged.in_reply_to = None;
+    assert!(forged.verify_signature().is_err());
+}
+
+/// Copy the store's files as they are on disk right now, as a power cut
+/// would leave them. Dropping the store instead would flush it cleanly.
+fn crash_copy(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            crash_copy(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_flush_on_write_survives_reopen() {
+    let path = std::env::temp_dir().join(format!("dm-durable-{}", std::process::id()));
+    let crashed = path.with_extension("crashed");
+    let _ = std::fs::remove_dir_all(&path);
+    let _ = std::fs::remove_dir_all(&crashed);
+
+    let manager = MessageManager::open(&path)
+        .await
+        .unwrap()
+        .with_flush_on_write(true);
+    let me = manager.add_identity(Identity::generate());
+    let last = manager
+        .create_message(
+            me,
+            Some(UserId::random()),
+            MessageContent::Text("last".into()),
+        )
+        .await
+        .unwrap();
+    // The store is still open, so nothing has flushed it on the way out.
+    crash_copy(&path, &crashed);
+
+    let reopened = MessageManager::open(&crashed).await.unwrap();
+    let stored = reopened
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, last.id);
+    assert!(!reopened.is_new_message(&last.id).await);
+    assert_eq!(reopened.pending_count().await, 1);
+    drop((manager, reopened));
+    std::fs::remove_dir_all(&path).unwrap();
+    std::fs::remove_dir_all(&crashed).unwrap();
+}
@@ -0,0 +1,69 @@
+use disaster_mesh::{
+    Clock, MessageContent, MessageFilter, MessageId, MessageManager, MockClock, UserId,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_stream_matches_eager_list() {
+    let clock = Arc::new(MockClock::default());
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let sender = UserId::random();
+    let alice = UserId::random();
+    let start = clock.now();
+
+    for i in 0..20 {
+        let recipient = (i % 2 == 0).then_some(alice);
+        manager
+            .create_message(sender, recipient, MessageContent::Text(format!("#{i}")))
+            .await
+            .unwrap();
+        clock.advance(Duration::from_secs(60));
+    }
+    // Seen markers are not messages.
+    manager.mark_message_seen(&MessageId::new()).await.unwrap();
+
+    let all = MessageFilter::default();
+    let streamed: Vec<_> = manager
+        .stream_messages(all.clone())
+        .map(|m| m.unwrap().id)
+        .collect()
+        .await;
+    let listed: Vec<_> = manager
+        .list_messages(&all)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(streamed.len(), 20);
+    assert_eq!(streamed, listed);
+
+    // Messages to alice within the first ten minutes: #0, #2, ..., #8.
+    let filter = MessageFilter {
+        recipient: Some(alice),
+        since: Some(start),
+        until: Some(start + Duration::from_secs(600)),
+    };
+    let page: Vec<_> = manager
+        .stream_messages(filter.clone())
+        .map(|m| m.unwrap())
+        .collect()
+        .await;
+    assert_eq!(page.len(), 5);
+    assert!(page.iter().all(|m| m.recipient == Some(alice)));
+    assert_eq!(
+        page.iter().map(|m| m.id).collect::<Vec<_>>(),
+        manager
+            .list_messages(&filter)
+            .await
+            .unwrap()
+            .iter()
+            .map(|m| m.id)
+            .collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,103 @@
+use disaster_mesh::{
+    ByteBudget, Message, MessageContent, MessagePriority, MeteredTransport, MockClock,
+    MockTransport, PeerId, StatsSnapshot, Transport, TransportEvent, UserId, WireFormat,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn frame(priority: MessagePriority) -> Vec<u8> {
+    let mut msg = Message::new(UserId::random(), None, MessageContent::Text("x".repeat(40)));
+    msg.priority = priority;
+    WireFormat::default().encode(&msg).unwrap()
+}
+
+#[tokio::test]
+async fn test_budget_defers_background_sends_and_counts_bytes() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let link = MockTransport::new();
+    let peer = PeerId([2; 32]);
+    link.add_peer(peer).await;
+    let mut on_air = link.subscribe_events();
+
+    let background = frame(MessagePriority::Background);
+    let size = background.len() as u64;
+    let metered = MeteredTransport::new(Box::new(link))
+        .with_budget(ByteBudget {
+            bytes: size * 2,
+            interval: Duration::from_secs(60),
+        })
+        .with_clock(clock.clone());
+
+    for _ in 0..4 {
+        metered.send(peer, background.clone()).await.unwrap();
+    }
+    // Emergency traffic goes out regardless of the budget.
+    let mayday = frame(MessagePriority::Emergency);
+    metered.send(peer, mayday.clone()).await.unwrap();
+
+    let mut sent = 0;
+    while let Ok(TransportEvent::DataReceived { data, .. }) = on_air.try_recv() {
+        sent += data.len() as u64;
+    }
+    let actual = size * 2 + mayday.len() as u64;
+    assert_eq!(sent, actual);
+    assert_eq!(metered.deferred(), 2);
+    assert_eq!(metered.budget_used(), actual);
+    assert_eq!(
+        metered.stats().snapshot(),
+        StatsSnapshot {
+            bytes_sent: actual,
+            deferred_sends: 2,
+            ..Default::default()
+        }
+    );
+
+    // Nothing fits until the next interval.
+    assert_eq!(metered.flush_deferred().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(metered.flush_deferred().await.unwrap(), 2);
+    assert_eq!(metered.deferred(), 0);
+    assert_eq!(metered.stats().snapshot().bytes_sent, actual + size * 2);
+}
+
+#[tokio::test]
+async fn test_oversize_deferred_frame_does_not_block_the_queue() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let link = MockTransport::new();
+    let peer = PeerId([2; 32]);
+    link.add_peer(peer).await;
+    let mut on_air = link.subscribe_events();
+
+    let background = frame(MessagePriority::Background);
+    let size = background.len() as u64;
+    let mut big = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("x".repeat(400)),
+    );
+    big.priority = MessagePriority::Urgent;
+    let big = WireFormat::default().encode(&big).unwrap();
+    let metered = MeteredTransport::new(Box::new(link))
+        .with_budget(ByteBudget {
+            bytes: size,
+            interval: Duration::from_secs(60),
+        })
+        .with_clock(clock.clone());
+
+    metered.send(peer, background.clone()).await.unwrap();
+    // Over the whole budget, and ahead of the small frame by priority.
+    metered.send(peer, big.clone()).await.unwrap();
+    metered.send(peer, background.clone()).await.unwrap();
+    assert_eq!(metered.deferred(), 2);
+    while on_air.try_recv().is_ok() {}
+
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(metered.flush_deferred().await.unwrap(), 1);
+    match on_air.try_recv() {
+        Ok(TransportEvent::DataReceived { data, .. }) => assert_eq!(data, big),
+        other => panic!("expected the oversize frame, got {other:?}"),
+    }
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(metered.flush_deferred().await.unwrap(), 1);
+    assert_eq!(metered.deferred(), 0);
+}
@@ -0,0 +1,101 @@
+use disaster_mesh::{
+    decode_message, Identity, MeshNode, Message, MessageContent, MessageManager, MockTransport,
+    PeerId, Transport, TransportEvent, UserId,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_pruning_keeps_signature_valid() {
+    let alice = Identity::generate();
+    let (bob, carol) = (UserId::random(), UserId::random());
+    let mut msg = Message::builder()
+        .sender(alice.user_id())
+        .content(MessageContent::Text("meet at the depot".into()))
+        .to_many(vec![bob, carol])
+        .build()
+        .unwrap();
+    msg.sign(&alice).unwrap();
+    assert_eq!(msg.pending_recipients(), [bob, carol]);
+
+    msg.delivered_to.push(bob);
+    assert_eq!(msg.pending_recipients(), [carol]);
+    msg.verify_signature().unwrap();
+
+    // The recipient list itself is signed.
+    msg.recipients.push(UserId::random());
+    assert!(msg.verify_signature().is_err());
+
+    assert!(Message::builder()
+        .sender(alice.user_id())
+        .content(MessageContent::Text("?".into()))
+        .to(bob)
+        .to_many(vec![carol])
+        .build()
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_message_delivered_at_each_recipient_and_pruned_in_transit() {
+    let transport = MockTransport::new();
+    for i in 1..=3 {
+        transport.add_peer(PeerId([i; 32])).await;
+    }
+    let shared: Arc<dyn Transport> = Arc::new(transport);
+    let mut nodes = Vec::new();
+    for i in 1..=3 {
+        nodes.push(MeshNode::new(
+            Identity::generate(),
+            PeerId([i; 32]),
+            shared.clone(),
+            MessageManager::in_memory().await.unwrap(),
+        ));
+    }
+    let (alice, bob, carol) = (&nodes[0], &nodes[1], &nodes[2]);
+    let mut inboxes = [Box::pin(bob.inbound()), Box::pin(carol.inbound())];
+    let mut on_air = shared.subscribe_events();
+    let tasks: Vec<_> = nodes
+        .iter()
+        .cloned()
+        .map(|node| tokio::spawn(async move { node.run().await }))
+        .collect();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let sent = alice
+        .send_to_many(
+            MessageContent::Text("convoy leaves at dawn".into()),
+            vec![bob.user_id(), carol.user_id()],
+        )
+        .await
+        .unwrap();
+    for inbox in &mut inboxes {
+        let received = tokio::time::timeout(Duration::from_secs(2), inbox.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.id, sent.id);
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Each recipient relayed the message with itself pruned.
+    let mut relays = Vec::new();
+    while let Ok(event) = on_air.try_recv() {
+        if let TransportEvent::DataReceived { data, .. } = event {
+            let copy = decode_message(&data).unwrap();
+            if let Some(relay) = copy.path.last() {
+                relays.push((*relay, copy.pending_recipients()));
+            }
+        }
+    }
+    assert!(relays.contains(&(PeerId([2; 32]), vec![carol.user_id()])));
+    assert!(relays.contains(&(PeerId([3; 32]), vec![bob.user_id()])));
+    assert!(relays.iter().all(|(_, pending)| pending.len() == 1));
+
+    for node in &nodes {
+        node.shutdown();
+    }
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+}
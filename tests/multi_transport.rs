@@ -0,0 +1,96 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use disaster_mesh::{MockTransport, MultiTransport, PeerId, Transport, TransportEvent};
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+
+/// Radio with a fixed neighbour set; every transmission shows up as a
+/// `DataReceived` event for the addressed peer(s).
+struct Radio {
+    inner: MockTransport,
+    peers: Vec<PeerId>,
+    mtu: usize,
+    quality: f32,
+}
+
+#[async_trait]
+impl Transport for Radio {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        self.inner.send(peer, data).await
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        for peer in &self.peers {
+            self.inner.send(*peer, data.clone()).await?;
+        }
+        Ok(())
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.peers.clone()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.inner.subscribe_events()
+    }
+
+    fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    fn link_quality(&self) -> f32 {
+        self.quality
+    }
+}
+
+#[tokio::test]
+async fn test_multi_transport_merges_links() {
+    let ble = Radio {
+        inner: MockTransport::new(),
+        peers: vec![PeerId([1; 32]), PeerId([2; 32])],
+        mtu: 244,
+        quality: 0.9,
+    };
+    let lora = Radio {
+        inner: MockTransport::new(),
+        peers: vec![PeerId([2; 32]), PeerId([3; 32])],
+        mtu: 222,
+        quality: 0.4,
+    };
+    let mut multi = MultiTransport::new(vec![Box::new(ble), Box::new(lora)]);
+    multi.start().await.unwrap();
+    let mut events = multi.subscribe_events();
+
+    let peers: HashSet<_> = multi.get_peers().into_iter().collect();
+    assert_eq!(peers.len(), 3);
+    assert_eq!(multi.mtu(), 222);
+    assert_eq!(multi.link_quality(), 0.9);
+
+    // Broadcast fans out on both links: two BLE peers plus two LoRa peers.
+    multi.broadcast(b"all".to_vec()).await.unwrap();
+    let mut heard = Vec::new();
+    for _ in 0..4 {
+        match events.recv().await.unwrap() {
+            TransportEvent::DataReceived { peer, data } => {
+                assert_eq!(data, b"all");
+                heard.push(peer.0[0]);
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+    heard.sort();
+    assert_eq!(heard, vec![1, 2, 2, 3]);
+
+    // Unicast uses a link that knows the peer; unknown peers are an error.
+    multi.send(PeerId([3; 32]), b"one".to_vec()).await.unwrap();
+    match events.recv().await.unwrap() {
+        TransportEvent::DataReceived { peer, .. } => assert_eq!(peer, PeerId([3; 32])),
+        other => panic!("unexpected event {other:?}"),
+    }
+    assert!(multi.send(PeerId([9; 32]), vec![]).await.is_err());
+    multi.shutdown().await.unwrap();
+}
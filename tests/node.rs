@@ -0,0 +1,179 @@
+use disaster_mesh::{
+    Identity, MeshNode, Message, MessageContent, MessageManager, MessageValidator, MockTransport,
+    PeerId, RejectReason, Transport, TransportEvent,
+};
+use futures::StreamExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_two_nodes_exchange_message() {
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([1; 32])).await;
+    transport.add_peer(PeerId([2; 32])).await;
+    let shared: Arc<dyn Transport> = Arc::new(transport);
+
+    let alice = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        shared.clone(),
+        MessageManager::in_memory().await.unwrap(),
+    );
+    let bob = MeshNode::new(
+        Identity::generate(),
+        PeerId([2; 32]),
+        shared,
+        MessageManager::in_memory().await.unwrap(),
+    );
+    let mut bob_inbox = Box::pin(bob.inbound());
+    let tasks =
+        [alice.clone(), bob.clone()].map(|node| tokio::spawn(async move { node.run().await }));
+    // Let both event loops subscribe before anything is sent.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let sent = alice
+        .send(
+            MessageContent::Text("water at the school".into()),
+            Some(bob.user_id()),
+        )
+        .await
+        .unwrap();
+    let received = tokio::time::timeout(Duration::from_secs(2), bob_inbox.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(received.id, sent.id);
+    assert_eq!(received.sender, alice.user_id());
+    assert_eq!(received.content, sent.content);
+
+    alice.shutdown();
+    bob.shutdown();
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_message_for_local_identity_is_delivered_not_relayed() {
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([2; 32])).await;
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    );
+    // A second persona held by the same node.
+    let persona = node.messages().add_identity(Identity::generate());
+    let mut inbox = Box::pin(node.inbound());
+    let mut wire = transport.subscribe_events();
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let author = Identity::generate();
+    for recipient in [node.user_id(), persona] {
+        let mut msg = Message::new(
+            author.user_id(),
+            Some(recipient),
+            MessageContent::Text("hi".into()),
+        );
+        msg.sign(&author).unwrap();
+        let data = transport.wire_format().encode(&msg).unwrap();
+        transport.send(PeerId([2; 32]), data).await.unwrap();
+        let delivered = tokio::time::timeout(Duration::from_secs(2), inbox.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(delivered.id, msg.id);
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    // Only the two injected frames went over the air; nothing was relayed.
+    let mut frames = 0;
+    while let Ok(event) = wire.try_recv() {
+        if let TransportEvent::DataReceived { .. } = event {
+            frames += 1;
+        }
+    }
+    assert_eq!(frames, 2);
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
+
+/// Counts the messages it checks and rejects "spam".
+struct CountChecks(Arc<AtomicUsize>);
+
+impl MessageValidator for CountChecks {
+    fn validate(&self, msg: &Message) -> Result<(), RejectReason> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        match &msg.content {
+            MessageContent::Text(text) if text == "spam" => {
+                Err(RejectReason::Content("spam".into()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_each_message_is_validated_once() {
+    let checks = Arc::new(AtomicUsize::new(0));
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([2; 32])).await;
+    let messages = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_validator(Box::new(CountChecks(checks.clone())));
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        messages,
+    );
+    let mut inbox = Box::pin(node.inbound());
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let author = Identity::generate();
+    let signed = |text: &str| {
+        let mut msg = Message::new(
+            author.user_id(),
+            Some(node.user_id()),
+            MessageContent::Text(text.into()),
+        );
+        msg.sign(&author).unwrap();
+        msg
+    };
+    let inject = |msg: &Message| transport.wire_format().encode(msg).unwrap();
+
+    let msg = signed("hi");
+    transport.send(PeerId([2; 32]), inject(&msg)).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(2), inbox.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(checks.load(Ordering::SeqCst), 1);
+
+    // Re-flooded copies of a rejected message are not checked again.
+    let mut spam = signed("spam");
+    for hop_count in 0..3 {
+        spam.hop_count = hop_count;
+        transport
+            .send(PeerId([2; 32]), inject(&spam))
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(checks.load(Ordering::SeqCst), 2);
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use disaster_mesh::{
+    MeshStats, MessageContent, MessageManager, MockTransport, NodeControl, PeerId, RoutingEngine,
+    StatsSnapshot, Transport, TransportEvent, UserId,
+};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Transport with a fixed neighbour set.
+struct FixedPeers(Vec<PeerId>, MockTransport);
+
+#[async_trait]
+impl Transport for FixedPeers {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, _peer: PeerId, _data: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn broadcast(&self, _data: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.0.clone()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.1.subscribe_events()
+    }
+
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    fn link_quality(&self) -> f32 {
+        1.0
+    }
+}
+
+#[tokio::test]
+async fn test_node_control_queries_and_commands() {
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let messages = MessageManager::in_memory().await.unwrap();
+    let stats = Arc::new(MeshStats::default());
+    let neighbours = vec![PeerId([1; 32]), PeerId([2; 32])];
+    let control = NodeControl::new(
+        routing.clone(),
+        messages.clone(),
+        Arc::new(FixedPeers(neighbours.clone(), MockTransport::new())),
+        stats.clone(),
+    );
+
+    let dest = UserId::random();
+    routing.update_route(dest, PeerId([1; 32]), 2, 0.9).await;
+    for _ in 0..3 {
+        messages
+            .create_message(
+                UserId::random(),
+                Some(dest),
+                MessageContent::Text("q".into()),
+            )
+            .await
+            .unwrap();
+    }
+    stats.loop_drops.fetch_add(2, Ordering::Relaxed);
+
+    // Clones share the same underlying state.
+    let view = control.clone();
+    assert_eq!(view.routes().await.len(), 1);
+    assert_eq!(view.neighbors().await, neighbours);
+    assert_eq!(view.pending_count().await, 3);
+    assert_eq!(
+        view.stats_snapshot().await,
+        StatsSnapshot {
+            loop_drops: 2,
+            ..Default::default()
+        }
+    );
+
+    assert!(control.clear_route(&dest).await);
+    assert!(!control.clear_route(&dest).await);
+    assert!(view.routes().await.is_empty());
+}
@@ -0,0 +1,72 @@
+use disaster_mesh::{
+    ControlledFlood, Forwarder, Identity, MockTransport, NeighborTable, PartitionDetector,
+    PartitionEvent, PeerId, RoutingEngine, Transport, TransportEvent, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_partition_and_heal_are_detected() {
+    let routing = RoutingEngine::new(Duration::from_secs(300));
+    let neighbors = NeighborTable::new();
+    let (near, bridge) = (PeerId([1; 32]), PeerId([2; 32]));
+    neighbors.update(near, 1.0).await;
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(near).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([9; 32]),
+        Arc::new(ControlledFlood::default()),
+        transport.clone(),
+    );
+    let detector =
+        PartitionDetector::new(routing.clone(), neighbors).with_route_exchange(forwarder);
+    let mut events = detector.subscribe();
+
+    let local: Vec<_> = (0..2).map(|_| UserId::random()).collect();
+    let cluster: Vec<_> = (0..4).map(|_| UserId::random()).collect();
+    for user in &local {
+        routing.update_route(*user, near, 1, 1.0).await;
+    }
+    for user in &cluster {
+        routing.update_route(*user, bridge, 2, 0.9).await;
+    }
+    assert_eq!(detector.check().await, None);
+
+    // The bridge to the far cluster goes down.
+    for user in &cluster {
+        routing.invalidate(user).await;
+    }
+    let Some(PartitionEvent::Partitioned { lost, reachable }) = detector.check().await else {
+        panic!("expected a partition");
+    };
+    assert_eq!(lost.len(), 4);
+    assert!(cluster.iter().all(|user| lost.contains(user)));
+    assert_eq!(reachable, 2);
+    assert_eq!(detector.report().await.partitioned, lost);
+    assert!(matches!(
+        events.try_recv(),
+        Ok(PartitionEvent::Partitioned { .. })
+    ));
+    assert_eq!(detector.check().await, None);
+
+    // It comes back; the heal triggers a route exchange with neighbours.
+    let mut wire = transport.subscribe_events();
+    for user in &cluster {
+        routing.update_route(*user, bridge, 2, 0.9).await;
+    }
+    let Some(PartitionEvent::Healed {
+        regained,
+        reachable,
+    }) = detector.check().await
+    else {
+        panic!("expected healing");
+    };
+    assert_eq!(regained, lost);
+    assert_eq!(reachable, 6);
+    assert!(detector.report().await.partitioned.is_empty());
+    assert!(matches!(
+        wire.try_recv(),
+        Ok(TransportEvent::DataReceived { peer, .. }) if peer == near
+    ));
+}
@@ -0,0 +1,79 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, FragmentPolicy, FragmentSender, Identity, Message,
+    MessageContent, MockClock, MockTransport, Outbound, PathMtu, PeerId, WireFormat,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_probing_finds_mid_path_bottleneck() {
+    // A --(1500)--> B --(600)--> C
+    let clock = MockClock::default();
+    let (a, c) = (Identity::generate(), Identity::generate());
+    let prober = PathMtu::with_clock(a.clone(), Duration::from_secs(60), Arc::new(clock.clone()));
+    let responder = PathMtu::new(c.clone());
+    let relay = Forwarder::new(
+        Identity::generate(),
+        PeerId([2; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new().with_mtu(600)),
+    );
+
+    let probes = prober
+        .probes(c.user_id(), &[1400, 1024, 512, 256], WireFormat::Bincode)
+        .unwrap();
+    let mut relayed = 0;
+    for probe in probes {
+        assert!(WireFormat::Bincode.encode(&probe).unwrap().len() <= 1500);
+        if relay
+            .handle_incoming(&probe, PeerId([1; 32]))
+            .await
+            .unwrap()
+            == ForwardDecision::Drop
+        {
+            continue;
+        }
+        relayed += 1;
+        let ack = responder.handle_probe(&probe).unwrap().unwrap();
+        assert!(prober.handle_ack(&ack).unwrap());
+    }
+    assert_eq!(relayed, 2);
+    assert_eq!(prober.path_mtu(&c.user_id()), Some(512));
+    assert_eq!(prober.effective_mtu(&c.user_id(), 1500), 512);
+    assert_eq!(prober.effective_mtu(&c.user_id(), 300), 300);
+
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(prober.path_mtu(&c.user_id()), None);
+    assert_eq!(prober.effective_mtu(&c.user_id(), 1500), 1500);
+}
+
+#[test]
+fn test_fragment_sender_uses_discovered_path_mtu() {
+    let (a, c) = (Identity::generate(), Identity::generate());
+    let prober = PathMtu::new(a.clone());
+    let responder = PathMtu::new(c.clone());
+    let probe = prober
+        .probes(c.user_id(), &[512], WireFormat::Bincode)
+        .unwrap()
+        .remove(0);
+    let ack = responder.handle_probe(&probe).unwrap().unwrap();
+    prober.handle_ack(&ack).unwrap();
+
+    let sender = FragmentSender::new().with_path_mtu(prober);
+    let text = MessageContent::Text("x".repeat(1000));
+    let unicast = Message::new(a.user_id(), Some(c.user_id()), text.clone());
+    let Outbound::Fragments(fragments) = sender
+        .prepare(&unicast, WireFormat::Bincode, 1500, FragmentPolicy::Auto)
+        .unwrap()
+    else {
+        panic!("unicast not fragmented for the 512-byte path");
+    };
+    assert!(fragments.iter().all(|f| f.data.len() <= 512));
+
+    // Broadcasts have no single path and still use the link MTU.
+    let broadcast = Message::new(a.user_id(), None, text);
+    assert!(matches!(
+        sender.prepare(&broadcast, WireFormat::Bincode, 1500, FragmentPolicy::Auto),
+        Ok(Outbound::Whole(_))
+    ));
+}
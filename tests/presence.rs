@@ -0,0 +1,72 @@
+use disaster_mesh::{
+    Clock, Identity, MeshNode, Message, MessageContent, MessageManager, MockClock, MockTransport,
+    PeerId, PresenceStatus, PresenceTracker, Transport, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn presence(user: UserId, status: PresenceStatus) -> Message {
+    Message::new(user, None, MessageContent::Presence { status })
+}
+
+#[tokio::test]
+async fn test_roster_tracks_latest_status_and_ages_out() {
+    let clock = MockClock::default();
+    let tracker = PresenceTracker::with_clock(Duration::from_secs(60), Arc::new(clock.clone()));
+    let (alice, bob) = (UserId::random(), UserId::random());
+
+    assert!(tracker.ingest(&presence(alice, PresenceStatus::Online)));
+    assert!(tracker.ingest(&presence(bob, PresenceStatus::Online)));
+    assert!(!tracker.ingest(&Message::new(
+        alice,
+        None,
+        MessageContent::Text("hi".into())
+    )));
+
+    clock.advance(Duration::from_secs(40));
+    tracker.ingest(&presence(alice, PresenceStatus::Busy));
+    let alice_seen = clock.now();
+
+    clock.advance(Duration::from_secs(30));
+    let mut roster = tracker.roster();
+    roster.sort_by_key(|(user, ..)| *user != alice);
+    assert_eq!(roster.len(), 2);
+    assert_eq!(roster[0], (alice, PresenceStatus::Busy, alice_seen));
+    // Bob has been silent for 70s, past the 60s timeout.
+    assert_eq!(roster[1].1, PresenceStatus::Offline);
+
+    clock.advance(Duration::from_secs(31));
+    assert_eq!(tracker.status(&alice), Some(PresenceStatus::Offline));
+    assert_eq!(tracker.status(&UserId::random()), None);
+}
+
+#[tokio::test]
+async fn test_node_tracks_received_presence() {
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([2; 32])).await;
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    );
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let neighbour = Identity::generate();
+    let mut msg = presence(neighbour.user_id(), PresenceStatus::Busy);
+    msg.sign(&neighbour).unwrap();
+    let data = transport.wire_format().encode(&msg).unwrap();
+    transport.send(PeerId([2; 32]), data).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(
+        node.presence().status(&neighbour.user_id()),
+        Some(PresenceStatus::Busy)
+    );
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
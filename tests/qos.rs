@@ -0,0 +1,251 @@
+use disaster_mesh::{
+    AdmissionConfig, EpidemicBuffer, Identity, MeshNode, Message, MessageContent, MessageManager,
+    MessagePriority, MockClock, MockTransport, PeerId, QosClass, ReceiptKind, RetransmitBudget,
+    RetransmitPolicy, UserId, DEFAULT_RETRANSMIT, REALTIME_TTL,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn text(s: &str) -> MessageContent {
+    MessageContent::Text(s.into())
+}
+
+#[tokio::test]
+async fn test_only_reliable_messages_are_retransmitted() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+    let me = manager.add_identity(Identity::generate());
+    let peer = Identity::generate();
+
+    let reliable = manager
+        .create_message_with_qos(
+            me,
+            Some(peer.user_id()),
+            text("need insulin"),
+            QosClass::Reliable,
+        )
+        .await
+        .unwrap();
+    for class in [QosClass::RealTime, QosClass::BestEffort] {
+        manager
+            .create_message_with_qos(me, Some(peer.user_id()), text("fyi"), class)
+            .await
+            .unwrap();
+    }
+    assert_eq!(manager.pending_count().await, 1);
+    assert!(manager.due_retransmissions().await.unwrap().is_empty());
+
+    // One resend per interval until the attempt limit is reached.
+    let mut resent = 0;
+    for _ in 0..DEFAULT_RETRANSMIT.max_attempts + 2 {
+        clock.advance(DEFAULT_RETRANSMIT.interval);
+        let due = manager.due_retransmissions().await.unwrap();
+        if let Some(msg) = due.first() {
+            assert_eq!(due.len(), 1);
+            assert_eq!(msg.id, reliable.id);
+            manager.record_retransmission(&msg.id).await.unwrap();
+            resent += 1;
+        }
+    }
+    assert_eq!(resent, DEFAULT_RETRANSMIT.max_attempts - 1);
+
+    // A receipt stops retransmission for good.
+    let again = manager
+        .create_message_with_qos(me, Some(peer.user_id()), text("again"), QosClass::Reliable)
+        .await
+        .unwrap();
+    let mut receipt = Message::receipt(peer.user_id(), &again, ReceiptKind::Delivered);
+    receipt.sign(&peer).unwrap();
+    manager.handle_receipt(&receipt).await.unwrap();
+    clock.advance(DEFAULT_RETRANSMIT.interval);
+    assert!(manager.due_retransmissions().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_higher_priority_is_retransmitted_more_aggressively() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()))
+        .with_retransmit_budget(None);
+    let me = manager.add_identity(Identity::generate());
+    let peer = Identity::generate();
+    for priority in [MessagePriority::Emergency, MessagePriority::Background] {
+        let builder = Message::builder()
+            .sender(me)
+            .to(peer.user_id())
+            .content(text("status"))
+            .qos(QosClass::Reliable)
+            .priority(priority);
+        manager.create_from(builder).await.unwrap();
+    }
+
+    // Step a quarter interval at a time for twice the default schedule.
+    let step = DEFAULT_RETRANSMIT.interval / 4;
+    let (mut emergency, mut background) = (0, 0);
+    for _ in 0..8 * DEFAULT_RETRANSMIT.max_attempts {
+        clock.advance(step);
+        for msg in manager.due_retransmissions().await.unwrap() {
+            manager.record_retransmission(&msg.id).await.unwrap();
+            match msg.priority {
+                MessagePriority::Emergency => emergency += 1,
+                _ => background += 1,
+            }
+        }
+    }
+    assert_eq!(emergency, 2 * DEFAULT_RETRANSMIT.max_attempts - 1);
+    assert_eq!(background, DEFAULT_RETRANSMIT.max_attempts - 1);
+    assert_eq!(
+        DEFAULT_RETRANSMIT.for_priority(MessagePriority::Background),
+        RetransmitPolicy {
+            interval: 2 * DEFAULT_RETRANSMIT.interval,
+            max_attempts: DEFAULT_RETRANSMIT.max_attempts,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_failed_retransmission_keeps_its_attempt() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+    let transport = MockTransport::new();
+    let (down, up) = (PeerId([2; 32]), PeerId([3; 32]));
+    transport.add_peer(down).await;
+    transport.add_peer(up).await;
+    transport.set_reachable(down, false).await;
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        manager,
+    );
+    let (stranded, reachable) = (UserId::random(), UserId::random());
+    node.routing().update_route(stranded, down, 1, 1.0).await;
+    node.routing().update_route(reachable, up, 1, 1.0).await;
+    for dest in [stranded, reachable] {
+        node.messages()
+            .create_message_with_qos(node.user_id(), Some(dest), text("sos"), QosClass::Reliable)
+            .await
+            .unwrap();
+    }
+
+    // The failed send does not stop the other one going out.
+    clock.advance(DEFAULT_RETRANSMIT.interval);
+    assert_eq!(node.retransmit().await.unwrap(), 1);
+
+    // Nor does it use up an attempt: it is still due once the link is back.
+    transport.set_reachable(down, true).await;
+    assert_eq!(node.retransmit().await.unwrap(), 1);
+    assert_eq!(node.retransmit().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_classes_map_to_queue_and_expiry_behaviour() {
+    let sender = Identity::generate().user_id();
+    let build = |class| {
+        Message::builder()
+            .sender(sender)
+            .content(text("position"))
+            .qos(class)
+            .build()
+            .unwrap()
+    };
+    let (reliable, realtime, best_effort) = (
+        build(QosClass::Reliable),
+        build(QosClass::RealTime),
+        build(QosClass::BestEffort),
+    );
+    assert_eq!(realtime.priority, MessagePriority::Urgent);
+    assert_eq!(realtime.ttl, REALTIME_TTL);
+    assert_eq!(best_effort.priority, MessagePriority::Background);
+    assert_eq!(reliable.priority, MessagePriority::Normal);
+
+    // Under moderate congestion best-effort traffic is shed first.
+    let admission = AdmissionConfig::default();
+    assert!(admission.admits(realtime.priority, 0.6));
+    assert!(admission.admits(reliable.priority, 0.6));
+    assert!(!admission.admits(best_effort.priority, 0.6));
+
+    // Real-time traffic is never carried for later delivery.
+    let buffer = EpidemicBuffer::new(8);
+    assert!(buffer.store(reliable).await);
+    assert!(!buffer.store(realtime).await);
+    assert!(buffer.store(best_effort).await);
+
+    // An explicit priority still wins over the class default.
+    let pinned = Message::builder()
+        .sender(sender)
+        .content(text("sos"))
+        .qos(QosClass::BestEffort)
+        .priority(MessagePriority::Emergency)
+        .build()
+        .unwrap();
+    assert_eq!(pinned.priority, MessagePriority::Emergency);
+    assert_eq!(pinned.qos, Some(QosClass::BestEffort));
+}
+
+#[tokio::test]
+async fn test_retransmit_budget_favours_emergency() {
+    let clock = MockClock::default();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()))
+        .with_retransmit_budget(Some(RetransmitBudget {
+            window: Duration::from_secs(3600),
+            per_peer: 10,
+            ..RetransmitBudget::default()
+        }));
+    let me = manager.add_identity(Identity::generate());
+    let peer = Identity::generate();
+
+    let mut sent = Vec::new();
+    for priority in [MessagePriority::Background, MessagePriority::Emergency] {
+        for _ in 0..6 {
+            let builder = Message::builder()
+                .sender(me)
+                .to(peer.user_id())
+                .content(text("status"))
+                .qos(QosClass::Reliable)
+                .priority(priority);
+            sent.push(manager.create_from(builder).await.unwrap());
+        }
+    }
+
+    // The peer's link flaps: it acknowledges one message every other round.
+    // Rounds follow the slower Background schedule, so every message is due
+    // in each.
+    let round_length = DEFAULT_RETRANSMIT
+        .for_priority(MessagePriority::Background)
+        .interval;
+    let (mut emergency, mut background) = (0, 0);
+    for round in 0..DEFAULT_RETRANSMIT.max_attempts {
+        clock.advance(round_length);
+        for msg in manager.due_retransmissions().await.unwrap() {
+            manager.record_retransmission(&msg.id).await.unwrap();
+            match msg.priority {
+                MessagePriority::Emergency => emergency += 1,
+                _ => background += 1,
+            }
+        }
+        if round % 2 == 1 {
+            let mut receipt = Message::receipt(
+                peer.user_id(),
+                &sent[6 + round as usize],
+                ReceiptKind::Delivered,
+            );
+            receipt.sign(&peer).unwrap();
+            manager.handle_receipt(&receipt).await.unwrap();
+        }
+    }
+    assert_eq!(emergency + background, 10);
+    assert_eq!(background, 1);
+    assert_eq!(manager.pending_count().await, 10);
+}
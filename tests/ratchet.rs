@@ -0,0 +1,125 @@
+use disaster_mesh::{Identity, MessageContent, MessageManager, RatchetEnvelope};
+use std::collections::HashSet;
+
+#[tokio::test]
+async fn test_session_messages_use_distinct_keys() {
+    let alice_node = MessageManager::in_memory().await.unwrap();
+    let bob_node = MessageManager::in_memory().await.unwrap();
+    let alice = alice_node.add_identity(Identity::generate());
+    let bob = bob_node.add_identity(Identity::generate());
+    // Alice opens the session, so she needs Bob's session key.
+    alice_node
+        .add_session_key(&bob_node.session_key(&bob).unwrap())
+        .unwrap();
+
+    let mut seen_keys = HashSet::new();
+    let mut ciphertexts = HashSet::new();
+    let mut first_from_alice = None;
+    // Both sides send first, then alternate with bursts in each direction.
+    for round in 0..4 {
+        for i in 0..3 {
+            let content = MessageContent::Text("same words".into());
+            let data = alice_node
+                .session_encrypt(&alice, &bob, &content)
+                .await
+                .unwrap();
+            let envelope: RatchetEnvelope = bincode::deserialize(&data).unwrap();
+            assert!(seen_keys.insert((envelope.header.ratchet_key, envelope.header.n)));
+            assert!(ciphertexts.insert(envelope.ciphertext));
+            first_from_alice.get_or_insert(data.clone());
+            let decrypted = bob_node.session_decrypt(&bob, &alice, &data).await.unwrap();
+            assert_eq!(decrypted, content);
+
+            let reply = MessageContent::Text(format!("ack {round}.{i}"));
+            let data = bob_node
+                .session_encrypt(&bob, &alice, &reply)
+                .await
+                .unwrap();
+            let envelope: RatchetEnvelope = bincode::deserialize(&data).unwrap();
+            assert!(seen_keys.insert((envelope.header.ratchet_key, envelope.header.n)));
+            let decrypted = alice_node
+                .session_decrypt(&alice, &bob, &data)
+                .await
+                .unwrap();
+            assert_eq!(decrypted, reply);
+        }
+    }
+    // The ratchet moved on as the conversation changed direction.
+    let ratchet_keys: HashSet<_> = seen_keys.iter().map(|(key, _)| key).collect();
+    assert!(ratchet_keys.len() > 2);
+
+    // Used message keys are gone: a replay cannot be decrypted again.
+    assert!(bob_node
+        .session_decrypt(&bob, &alice, &first_from_alice.unwrap())
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_out_of_order_and_simultaneous_first_messages() {
+    let a_node = MessageManager::in_memory().await.unwrap();
+    let b_node = MessageManager::in_memory().await.unwrap();
+    let a = a_node.add_identity(Identity::generate());
+    let b = b_node.add_identity(Identity::generate());
+    a_node
+        .add_session_key(&b_node.session_key(&b).unwrap())
+        .unwrap();
+    b_node
+        .add_session_key(&a_node.session_key(&a).unwrap())
+        .unwrap();
+    let text = |s: &str| MessageContent::Text(s.into());
+
+    // Both open the session at once, each sending two messages.
+    let a1 = a_node.session_encrypt(&a, &b, &text("a1")).await.unwrap();
+    let a2 = a_node.session_encrypt(&a, &b, &text("a2")).await.unwrap();
+    let b1 = b_node.session_encrypt(&b, &a, &text("b1")).await.unwrap();
+    let b2 = b_node.session_encrypt(&b, &a, &text("b2")).await.unwrap();
+
+    // Delivered out of order.
+    assert_eq!(
+        b_node.session_decrypt(&b, &a, &a2).await.unwrap(),
+        text("a2")
+    );
+    assert_eq!(
+        a_node.session_decrypt(&a, &b, &b2).await.unwrap(),
+        text("b2")
+    );
+    assert_eq!(
+        b_node.session_decrypt(&b, &a, &a1).await.unwrap(),
+        text("a1")
+    );
+    assert_eq!(
+        a_node.session_decrypt(&a, &b, &b1).await.unwrap(),
+        text("b1")
+    );
+
+    let b3 = b_node.session_encrypt(&b, &a, &text("b3")).await.unwrap();
+    assert_eq!(
+        a_node.session_decrypt(&a, &b, &b3).await.unwrap(),
+        text("b3")
+    );
+}
+
+#[tokio::test]
+async fn test_session_needs_a_verified_session_key() {
+    let a_node = MessageManager::in_memory().await.unwrap();
+    let a = a_node.add_identity(Identity::generate());
+    let b = Identity::generate();
+    let text = MessageContent::Text("hi".into());
+
+    assert!(a_node
+        .session_encrypt(&a, &b.user_id(), &text)
+        .await
+        .is_err());
+
+    // A key claimed for B but signed by someone else is refused.
+    let mut forged = Identity::generate().session_key();
+    forged.user = b.user_id();
+    assert!(a_node.add_session_key(&forged).is_err());
+
+    a_node.add_session_key(&b.session_key()).unwrap();
+    a_node
+        .session_encrypt(&a, &b.user_id(), &text)
+        .await
+        .unwrap();
+}
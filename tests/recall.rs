@@ -0,0 +1,154 @@
+use disaster_mesh::{
+    AodvReactive, Epidemic, EpidemicBuffer, ForwardDecision, Forwarder, Identity, MeshNode,
+    Message, MessageContent, MessageManager, MockTransport, PeerId, QosClass, RoutingEngine,
+    Transport, UserId,
+};
+use futures::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn signed_recall(identity: &Identity, target: &Message) -> Message {
+    let mut recall = Message::new(
+        identity.user_id(),
+        None,
+        MessageContent::Recall { target: target.id },
+    );
+    recall.sign(identity).unwrap();
+    recall
+}
+
+#[tokio::test]
+async fn test_recall_removes_queued_message_before_it_is_sent() {
+    let transport = Arc::new(MockTransport::new());
+    let epidemic = Epidemic::new(
+        EpidemicBuffer::new(16),
+        Identity::generate(),
+        transport.clone(),
+    );
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(AodvReactive::new(RoutingEngine::new(Duration::from_secs(
+            60,
+        )))),
+        transport,
+    )
+    .with_epidemic(epidemic.clone());
+
+    // No route yet, so the alert waits in the carry queue.
+    let origin = Identity::generate();
+    let far_away = UserId::random();
+    let mut alert = Message::new(
+        origin.user_id(),
+        Some(far_away),
+        MessageContent::Text("evacuate sector 4".into()),
+    );
+    alert.sign(&origin).unwrap();
+    assert_eq!(
+        forwarder
+            .handle_incoming(&alert, PeerId([9; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Discover(far_away)
+    );
+    assert!(epidemic.buffer().contains(&alert.id).await);
+
+    // Only the original sender can recall it.
+    let forged = signed_recall(&Identity::generate(), &alert);
+    forwarder
+        .handle_incoming(&forged, PeerId([9; 32]))
+        .await
+        .unwrap();
+    assert!(epidemic.buffer().contains(&alert.id).await);
+
+    let recall = signed_recall(&origin, &alert);
+    forwarder
+        .handle_incoming(&recall, PeerId([9; 32]))
+        .await
+        .unwrap();
+    assert!(!epidemic.buffer().contains(&alert.id).await);
+    assert!(epidemic.buffer().summary().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_recalled_message_is_not_retransmitted_or_delivered() {
+    let sender = MessageManager::in_memory().await.unwrap();
+    let user = sender.add_identity(Identity::generate());
+    let alert = sender
+        .create_message_with_qos(
+            user,
+            Some(UserId::random()),
+            MessageContent::Text("wrong shelter".into()),
+            QosClass::Reliable,
+        )
+        .await
+        .unwrap();
+    assert_eq!(sender.pending_count().await, 1);
+
+    let recall = sender.create_recall(user, alert.id).await.unwrap();
+    assert!(sender.is_recalled(&alert).await);
+    assert_eq!(sender.pending_count().await, 0);
+    assert!(sender.due_retransmissions().await.unwrap().is_empty());
+    assert!(sender
+        .create_recall(UserId::random(), alert.id)
+        .await
+        .is_err());
+
+    // A node hearing the recall first drops the alert when it turns up,
+    // and a recall of it by anyone else changes nothing.
+    let receiver = MessageManager::in_memory().await.unwrap();
+    let censor = Identity::generate();
+    let forged = signed_recall(&censor, &alert);
+    assert_eq!(
+        receiver.handle_recall(&forged).await.unwrap(),
+        Some(alert.id)
+    );
+    assert!(!receiver.is_recalled(&alert).await);
+    assert_eq!(
+        receiver.handle_recall(&recall).await.unwrap(),
+        Some(alert.id)
+    );
+    assert!(!receiver.deliver(alert).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_foreign_recall_does_not_censor_message() {
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([2; 32])).await;
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    );
+    let mut inbox = Box::pin(
+        node.inbound()
+            .filter(|msg| std::future::ready(matches!(msg.content, MessageContent::Text(_)))),
+    );
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let origin = Identity::generate();
+    let mut alert = Message::new(
+        origin.user_id(),
+        Some(node.user_id()),
+        MessageContent::Text("shelter open".into()),
+    );
+    alert.sign(&origin).unwrap();
+    // Someone else recalls the alert before it arrives.
+    for msg in [signed_recall(&Identity::generate(), &alert), alert.clone()] {
+        let data = transport.wire_format().encode(&msg).unwrap();
+        transport.send(PeerId([2; 32]), data).await.unwrap();
+    }
+    let delivered = tokio::time::timeout(Duration::from_secs(2), inbox.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(delivered.id, alert.id);
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,111 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, Identity, MeshStats, Message, MessageContent,
+    MockClock, MockTransport, PeerId, Reputation, ReputationConfig, ReputationEvent, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn forged() -> Message {
+    let identity = Identity::generate();
+    let mut msg = Message::new(
+        identity.user_id(),
+        None,
+        MessageContent::Text("trust me".into()),
+    );
+    msg.sign(&identity).unwrap();
+    msg.content = MessageContent::Text("tampered".into());
+    msg
+}
+
+#[tokio::test]
+async fn test_misbehaving_peer_is_blacklisted_and_dropped() {
+    let clock = Arc::new(MockClock::default());
+    let reputation = Reputation::new(ReputationConfig {
+        threshold: -5.0,
+        half_life: Duration::from_secs(60),
+        max_score: 10.0,
+    })
+    .with_clock(clock.clone());
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([0; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+    .with_reputation(reputation.clone());
+    let bad = PeerId([6; 32]);
+
+    // Three forged signatures (-6) push the peer below the threshold.
+    for _ in 0..3 {
+        let decision = forwarder.handle_incoming(&forged(), bad).await.unwrap();
+        assert_eq!(decision, ForwardDecision::Drop);
+    }
+    assert!(reputation.is_blacklisted(&bad));
+    assert_eq!(reputation.blacklist(), vec![bad]);
+
+    // Even a perfectly valid message from it is now dropped.
+    let fresh = Message::new(UserId::random(), None, MessageContent::Text("ok".into()));
+    let decision = forwarder.handle_incoming(&fresh, bad).await.unwrap();
+    assert_eq!(decision, ForwardDecision::Drop);
+    assert_eq!(MeshStats::get(&forwarder.stats().reputation_drops), 4);
+
+    // A peer with no history is unaffected.
+    let decision = forwarder
+        .handle_incoming(&fresh, PeerId([7; 32]))
+        .await
+        .unwrap();
+    assert_eq!(decision, ForwardDecision::Broadcast);
+
+    // After a half-life the score has decayed to -3 and the peer is let back.
+    clock.advance(Duration::from_secs(60));
+    assert!(!reputation.is_blacklisted(&bad));
+}
+
+#[tokio::test]
+async fn test_echoed_floods_do_not_cost_reputation() {
+    let reputation = Reputation::default();
+    let local = PeerId([0; 32]);
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        local,
+        Arc::new(ControlledFlood::default()),
+        Arc::new(MockTransport::new()),
+    )
+    .with_reputation(reputation.clone());
+    let neighbour = PeerId([5; 32]);
+
+    // An honest neighbour rebroadcasting our floods hands every one back.
+    for _ in 0..50 {
+        let mut echo = Message::new(UserId::random(), None, MessageContent::Text("echo".into()));
+        echo.path = vec![local, neighbour];
+        let decision = forwarder.handle_incoming(&echo, neighbour).await.unwrap();
+        assert_eq!(decision, ForwardDecision::Drop);
+    }
+    assert_eq!(MeshStats::get(&forwarder.stats().loop_drops), 50);
+    assert!(!reputation.is_blacklisted(&neighbour));
+}
+
+#[test]
+fn test_blacklist_persists_in_store() {
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let tree = db.open_tree("blacklist").unwrap();
+    let clock = Arc::new(MockClock::default());
+    let bad = PeerId([9; 32]);
+
+    let reputation = Reputation::default()
+        .with_clock(clock.clone())
+        .with_store(tree.clone())
+        .unwrap();
+    for _ in 0..6 {
+        reputation.record(bad, ReputationEvent::RateLimited);
+    }
+    assert!(reputation.is_blacklisted(&bad));
+
+    let restarted = Reputation::default()
+        .with_clock(clock)
+        .with_store(tree)
+        .unwrap();
+    assert!(restarted.is_blacklisted(&bad));
+    restarted.forgive(&bad);
+    assert!(!restarted.is_blacklisted(&bad));
+}
@@ -0,0 +1,70 @@
+use disaster_mesh::{
+    MessageContent, MessageFilter, MessageManager, MockClock, RetentionPolicy, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_oldest_evictable_messages_are_removed() {
+    let clock = Arc::new(MockClock::default());
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone())
+        .with_retention(RetentionPolicy {
+            max_messages: Some(4),
+            max_bytes: None,
+        });
+    let sender = UserId::random();
+
+    // Two unacknowledged unicasts first: the oldest, but still pending.
+    let mut pending = Vec::new();
+    for i in 0..2 {
+        let msg = manager
+            .create_message(
+                sender,
+                Some(UserId::random()),
+                MessageContent::Text(format!("dm {i}")),
+            )
+            .await
+            .unwrap();
+        pending.push(msg.id);
+        clock.advance(Duration::from_secs(1));
+    }
+    let mut broadcasts = Vec::new();
+    for i in 0..5 {
+        let msg = manager
+            .create_message(sender, None, MessageContent::Text(format!("bc {i}")))
+            .await
+            .unwrap();
+        broadcasts.push(msg.id);
+        clock.advance(Duration::from_secs(1));
+    }
+
+    let kept: Vec<_> = manager
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| m.id)
+        .collect();
+    assert_eq!(kept.len(), 4);
+    assert!(pending.iter().all(|id| kept.contains(id)));
+    assert!(broadcasts[..3].iter().all(|id| !kept.contains(id)));
+    assert!(broadcasts[3..].iter().all(|id| kept.contains(id)));
+
+    // Once the pending messages expire they become evictable too.
+    clock.advance(Duration::from_secs(7200));
+    assert_eq!(manager.enforce_retention().await.unwrap(), 0);
+    let manager = manager.with_retention(RetentionPolicy {
+        max_messages: Some(1),
+        max_bytes: None,
+    });
+    assert_eq!(manager.enforce_retention().await.unwrap(), 3);
+    let kept = manager
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(kept.len(), 1);
+    assert_eq!(kept[0].id, broadcasts[4]);
+}
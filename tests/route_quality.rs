@@ -0,0 +1,104 @@
+use disaster_mesh::{
+    MeshConfig, MockClock, PeerId, QualityDecay, Reputation, ReputationConfig, ReputationEvent,
+    RoutingEngine, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_longer_high_quality_route_beats_short_poor_one() {
+    let (dest, poor, good) = (UserId::random(), PeerId([1; 32]), PeerId([2; 32]));
+
+    // Default: fewer hops always win.
+    let engine = RoutingEngine::new(Duration::from_secs(60));
+    engine.update_route(dest, poor, 2, 0.2).await;
+    engine.update_route(dest, good, 3, 0.95).await;
+    assert_eq!(engine.next_hop(&dest).await, Some(poor));
+
+    let config = MeshConfig {
+        route_quality_threshold: Some(0.3),
+        ..MeshConfig::default()
+    };
+    // Learned in either order, the reliable 3-hop route wins.
+    for (first, second) in [
+        ((poor, 2, 0.2), (good, 3, 0.95)),
+        ((good, 3, 0.95), (poor, 2, 0.2)),
+    ] {
+        let engine = RoutingEngine::from_config(&config);
+        engine.update_route(dest, first.0, first.1, first.2).await;
+        engine
+            .update_route(dest, second.0, second.1, second.2)
+            .await;
+        assert_eq!(engine.next_hop(&dest).await, Some(good));
+    }
+
+    // A gain below the threshold is not worth the extra hop...
+    let engine = RoutingEngine::from_config(&config);
+    engine.update_route(dest, poor, 2, 0.6).await;
+    engine.update_route(dest, good, 3, 0.8).await;
+    assert_eq!(engine.next_hop(&dest).await, Some(poor));
+    // ...and two extra hops never are.
+    engine.update_route(dest, good, 4, 1.0).await;
+    assert_eq!(engine.next_hop(&dest).await, Some(poor));
+}
+
+#[tokio::test]
+async fn test_reputable_neighbour_wins_equal_routes() {
+    let (dest, flaky, reliable) = (UserId::random(), PeerId([1; 32]), PeerId([2; 32]));
+    let reputation = Reputation::new(ReputationConfig::default());
+    for _ in 0..10 {
+        reputation.record(reliable, ReputationEvent::ValidSignature);
+    }
+    reputation.record(flaky, ReputationEvent::RateLimited);
+
+    // Learned in either order, the reliable neighbour's route is kept.
+    for (first, second) in [(flaky, reliable), (reliable, flaky)] {
+        let engine =
+            RoutingEngine::new(Duration::from_secs(60)).with_reputation(reputation.clone(), 0.5);
+        engine.update_route(dest, first, 2, 0.8).await;
+        engine.update_route(dest, second, 2, 0.8).await;
+        assert_eq!(engine.next_hop(&dest).await, Some(reliable));
+    }
+
+    // Reputation only nudges: a clearly better link still wins.
+    let engine =
+        RoutingEngine::new(Duration::from_secs(60)).with_reputation(reputation.clone(), 0.5);
+    engine.update_route(dest, reliable, 2, 0.3).await;
+    engine.update_route(dest, flaky, 2, 0.9).await;
+    assert_eq!(engine.next_hop(&dest).await, Some(flaky));
+}
+
+#[tokio::test]
+async fn test_unrefreshed_route_quality_decays() {
+    let clock = MockClock::default();
+    let engine = RoutingEngine::with_clock(Duration::from_secs(600), Arc::new(clock.clone()))
+        .with_quality_decay(QualityDecay::Exponential {
+            half_life: Duration::from_secs(60),
+        });
+    let (old_dest, fresh_dest, dest) = (UserId::random(), UserId::random(), UserId::random());
+    let (stale, fresh) = (PeerId([1; 32]), PeerId([2; 32]));
+
+    engine.update_route(old_dest, stale, 2, 0.9).await;
+    engine.update_route(dest, stale, 2, 0.9).await;
+    clock.advance(Duration::from_secs(120));
+    engine.update_route(fresh_dest, fresh, 2, 0.9).await;
+
+    let routes = engine.dump().await;
+    let quality = |d: UserId| {
+        let route = routes.iter().find(|r| r.destination == d).unwrap();
+        assert_eq!(route.link_quality, 0.9, "stored quality is untouched");
+        engine.effective_quality(route)
+    };
+    assert!((quality(old_dest) - 0.225).abs() < 1e-4);
+    assert_eq!(quality(fresh_dest), 0.9);
+
+    // A fresh route with a lower stored quality now beats the stale one.
+    engine.update_route(dest, fresh, 2, 0.5).await;
+    assert_eq!(engine.next_hop(&dest).await, Some(fresh));
+
+    let linear = QualityDecay::Linear {
+        over: Duration::from_secs(100),
+    };
+    assert_eq!(linear.apply(0.8, Duration::from_secs(25)), 0.6);
+    assert_eq!(linear.apply(0.8, Duration::from_secs(500)), 0.0);
+}
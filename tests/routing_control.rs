@@ -1,4 +1,12 @@
-use disaster_mesh::{routing_control::RoutingControl, UserId};
+use anyhow::Result;
+use async_trait::async_trait;
+use disaster_mesh::{
+    routing_control::RoutingControl, ControlBatcher, Identity, Message, MessageContent,
+    MockTransport, PeerId, Transport, TransportEvent, UserId,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
 
 #[test]
 fn test_routing_control_roundtrip() {
@@ -16,4 +24,77 @@ fn test_routing_control_roundtrip() {
     let decoded: RoutingControl = bincode::deserialize(&encoded).expect("deserialize");
 
     assert_eq!(packet, decoded);
-} 
\ No newline at end of file
+}
+/// Transport that records every broadcast payload.
+#[derive(Clone, Default)]
+struct RecordingTransport {
+    broadcasts: Arc<Mutex<Vec<Vec<u8>>>>,
+    events: MockTransport,
+}
+
+#[async_trait]
+impl Transport for RecordingTransport {
+    async fn start(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send(&self, _peer: PeerId, _data: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        self.broadcasts.lock().unwrap().push(data);
+        Ok(())
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        Vec::new()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.events.subscribe_events()
+    }
+
+    fn mtu(&self) -> usize {
+        1500
+    }
+
+    fn link_quality(&self) -> f32 {
+        1.0
+    }
+}
+
+#[tokio::test]
+async fn test_rreq_burst_sent_as_one_batch() {
+    let transport = RecordingTransport::default();
+    let identity = Identity::generate();
+    let origin = identity.user_id();
+    let batcher = ControlBatcher::new(
+        identity,
+        Arc::new(transport.clone()),
+        Duration::from_millis(20),
+    );
+
+    let rreqs: Vec<RoutingControl> = (0..3)
+        .map(|request_id| RoutingControl::Rreq {
+            origin,
+            destination: UserId::random(),
+            request_id,
+            hop_count: 0,
+        })
+        .collect();
+    for rreq in &rreqs {
+        batcher.enqueue(rreq.clone()).await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let sent = transport.broadcasts.lock().unwrap().clone();
+    assert_eq!(sent.len(), 1);
+    let msg: Message = bincode::deserialize(&sent[0]).unwrap();
+    assert!(msg.verify_signature().is_ok());
+    let MessageContent::Routing(control) = msg.content else {
+        panic!("expected routing content");
+    };
+    assert!(matches!(control, RoutingControl::Batch(_)));
+    assert_eq!(control.expand(), rreqs);
+}
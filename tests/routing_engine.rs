@@ -1,4 +1,8 @@
-use disaster_mesh::{routing::RoutingEngine, PeerId, UserId};
+use disaster_mesh::{
+    routing::RoutingEngine, Identity, MeshStats, Message, MessageContent, MockClock, PeerId,
+    RouteEvent, RouteInfo, RouteLookup, RoutingControl, UserId, DEFAULT_HOP_LATENCY,
+};
+use std::sync::Arc;
 use std::time::Duration;
 
 #[tokio::test]
@@ -8,9 +12,7 @@ async fn test_routing_update_and_query() {
     let next_hop = PeerId([1; 32]);
 
     // Insert route with hop_count 3
-    engine
-        .update_route(dest, next_hop, 3, 0.8)
-        .await;
+    engine.update_route(dest, next_hop, 3, 0.8).await;
 
     // Fetch next hop
     let retrieved = engine.next_hop(&dest).await;
@@ -18,9 +20,325 @@ async fn test_routing_update_and_query() {
 
     // Update with better hop_count
     let better_hop = PeerId([2; 32]);
-    engine
-        .update_route(dest, better_hop, 2, 0.8)
-        .await;
+    engine.update_route(dest, better_hop, 2, 0.8).await;
     let retrieved_better = engine.next_hop(&dest).await;
     assert_eq!(retrieved_better, Some(better_hop));
-} 
\ No newline at end of file
+}
+#[tokio::test]
+async fn test_route_events() {
+    let clock = MockClock::default();
+    let engine = RoutingEngine::with_clock(Duration::from_secs(60), Arc::new(clock.clone()));
+    let mut events = engine.subscribe();
+    let a = UserId::random();
+    let b = UserId::random();
+
+    engine.update_route(a, PeerId([1; 32]), 3, 0.5).await;
+    engine.update_route(a, PeerId([2; 32]), 2, 0.5).await;
+    // Worse route is ignored and must not publish anything.
+    engine.update_route(a, PeerId([3; 32]), 5, 0.5).await;
+    engine.update_route(b, PeerId([4; 32]), 1, 1.0).await;
+    engine.invalidate(&b).await;
+    clock.advance(Duration::from_secs(61));
+    engine.cleanup().await;
+
+    assert!(
+        matches!(events.try_recv(), Ok(RouteEvent::Added { destination, .. }) if destination == a)
+    );
+    assert!(matches!(
+        events.try_recv(),
+        Ok(RouteEvent::Updated { route, .. }) if route.next_hop == PeerId([2; 32])
+    ));
+    assert!(
+        matches!(events.try_recv(), Ok(RouteEvent::Added { destination, .. }) if destination == b)
+    );
+    assert!(
+        matches!(events.try_recv(), Ok(RouteEvent::Invalidated { destination, .. }) if destination == b)
+    );
+    assert!(
+        matches!(events.try_recv(), Ok(RouteEvent::Removed { destination, .. }) if destination == a)
+    );
+    assert!(events.try_recv().is_err());
+}
+
+fn signed_rrep(signer: &Identity, origin: UserId, destination: UserId) -> Message {
+    let mut msg = Message::new(
+        signer.user_id(),
+        Some(origin),
+        MessageContent::Routing(RoutingControl::Rrep {
+            origin,
+            destination,
+            hop_count: 0,
+        }),
+    );
+    msg.sign(signer).unwrap();
+    msg
+}
+
+#[tokio::test]
+async fn test_forged_rrep_does_not_install_route() {
+    let engine = RoutingEngine::new(Duration::from_secs(60));
+    let origin = UserId::random();
+    let victim = Identity::generate();
+    let attacker = Identity::generate();
+    let attacker_peer = PeerId([6; 32]);
+
+    // Attacker claims to be the victim but signs with its own key.
+    let mut forged = signed_rrep(&attacker, origin, victim.user_id());
+    forged.sender = victim.user_id();
+    assert!(engine
+        .apply_control(&forged, attacker_peer, 1.0)
+        .await
+        .is_err());
+
+    // Attacker signs honestly but speaks for a destination it is not.
+    let impersonation = signed_rrep(&attacker, origin, victim.user_id());
+    assert!(engine
+        .apply_control(&impersonation, attacker_peer, 1.0)
+        .await
+        .is_err());
+
+    // Unsigned packets are rejected as well.
+    let mut unsigned = signed_rrep(&victim, origin, victim.user_id());
+    unsigned.signature.clear();
+    assert!(engine
+        .apply_control(&unsigned, attacker_peer, 1.0)
+        .await
+        .is_err());
+
+    assert_eq!(engine.next_hop(&victim.user_id()).await, None);
+    assert_eq!(MeshStats::get(&engine.stats().rejected_control), 3);
+
+    // The genuine reply installs the route.
+    let genuine = signed_rrep(&victim, origin, victim.user_id());
+    let honest_peer = PeerId([7; 32]);
+    engine
+        .apply_control(&genuine, honest_peer, 1.0)
+        .await
+        .unwrap();
+    assert_eq!(engine.next_hop(&victim.user_id()).await, Some(honest_peer));
+}
+
+#[tokio::test]
+async fn test_route_exchange_merges_tables() {
+    let a = RoutingEngine::new(Duration::from_secs(60));
+    let b = RoutingEngine::new(Duration::from_secs(60));
+    let x = UserId::random();
+    let y = UserId::random();
+    let via_a = PeerId([10; 32]);
+    a.update_route(x, PeerId([1; 32]), 2, 0.9).await;
+    a.update_route(y, PeerId([2; 32]), 1, 0.8).await;
+    // B already knows a shorter way to X.
+    b.update_route(x, PeerId([3; 32]), 1, 0.5).await;
+
+    b.import(&a.export().await, via_a).await;
+
+    let routes = b.dump().await;
+    let to_y = routes.iter().find(|r| r.destination == y).unwrap();
+    assert_eq!((to_y.next_hop, to_y.hop_count), (via_a, 2));
+    let to_x = routes.iter().find(|r| r.destination == x).unwrap();
+    assert_eq!((to_x.next_hop, to_x.hop_count), (PeerId([3; 32]), 1));
+
+    // The same merge works when the export arrives as signed control.
+    let c = RoutingEngine::new(Duration::from_secs(60));
+    let exporter = Identity::generate();
+    let mut msg = Message::new(
+        exporter.user_id(),
+        None,
+        MessageContent::Routing(RoutingControl::RouteExchange(a.export().await)),
+    );
+    msg.sign(&exporter).unwrap();
+    c.apply_control(&msg, via_a, 1.0).await.unwrap();
+    assert_eq!(c.next_hop(&x).await, Some(via_a));
+    assert_eq!(c.next_hop(&y).await, Some(via_a));
+}
+
+#[tokio::test]
+async fn test_bulk_update_matches_sequential_updates() {
+    let clock = Arc::new(MockClock::default());
+    let sequential = RoutingEngine::with_clock(Duration::from_secs(60), clock.clone());
+    let bulk = RoutingEngine::with_clock(Duration::from_secs(60), clock);
+
+    // Overlapping updates so the scoring (fewer hops, then better quality)
+    // decides which survive.
+    let destinations: Vec<_> = (0..50).map(|_| UserId::random()).collect();
+    let mut updates = Vec::new();
+    for (i, dest) in destinations.iter().enumerate() {
+        for variant in 0..3u8 {
+            let hops = 1 + ((i as u8 + variant) % 4);
+            let quality = 0.2 * f32::from(variant + 1);
+            updates.push((*dest, PeerId([variant; 32]), hops, quality));
+        }
+    }
+    for &(dest, next_hop, hops, quality) in &updates {
+        sequential.update_route(dest, next_hop, hops, quality).await;
+    }
+    // One write-lock acquisition for the whole batch.
+    bulk.update_routes_bulk(updates).await;
+
+    let sort = |mut routes: Vec<RouteInfo>| {
+        routes.sort_by_key(|r| r.destination.0);
+        routes
+    };
+    let table = sort(bulk.dump().await);
+    assert_eq!(table.len(), destinations.len());
+    assert_eq!(table, sort(sequential.dump().await));
+}
+
+#[tokio::test]
+async fn test_unreachable_destination_expires() {
+    let clock = MockClock::default();
+    let engine = RoutingEngine::with_clock(Duration::from_secs(300), Arc::new(clock.clone()))
+        .with_negative_ttl(Duration::from_secs(30));
+    let dest = UserId::random();
+    assert_eq!(engine.lookup(&dest).await, RouteLookup::Unknown);
+
+    engine.mark_unreachable(dest);
+    assert_eq!(engine.lookup(&dest).await, RouteLookup::Unreachable);
+    clock.advance(Duration::from_secs(29));
+    assert_eq!(engine.lookup(&dest).await, RouteLookup::Unreachable);
+
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(engine.lookup(&dest).await, RouteLookup::Unknown);
+
+    // Learning a route clears the marker straight away.
+    engine.mark_unreachable(dest);
+    engine.update_route(dest, PeerId([3; 32]), 2, 0.8).await;
+    assert_eq!(
+        engine.lookup(&dest).await,
+        RouteLookup::Route(PeerId([3; 32]))
+    );
+}
+
+#[tokio::test]
+async fn test_full_table_evicts_weakest_route() {
+    let engine = RoutingEngine::new(Duration::from_secs(300)).with_max_routes(3);
+    let dests: Vec<UserId> = (0..5).map(|_| UserId::random()).collect();
+    engine.update_route(dests[0], PeerId([1; 32]), 1, 0.9).await;
+    engine.update_route(dests[1], PeerId([1; 32]), 6, 0.4).await;
+    engine.update_route(dests[2], PeerId([2; 32]), 2, 0.7).await;
+
+    engine.update_route(dests[3], PeerId([2; 32]), 3, 0.8).await;
+    let mut kept: Vec<UserId> = engine.dump().await.iter().map(|r| r.destination).collect();
+    kept.sort_by_key(|u| u.0);
+    let mut expected = vec![dests[0], dests[2], dests[3]];
+    expected.sort_by_key(|u| u.0);
+    assert_eq!(kept, expected);
+
+    // A route worse than everything in the full table is not admitted.
+    engine.update_route(dests[4], PeerId([3; 32]), 9, 0.1).await;
+    assert!(engine.next_hop(&dests[4]).await.is_none());
+    assert_eq!(engine.dump().await.len(), 3);
+}
+
+#[tokio::test]
+async fn test_latency_estimate_scales_with_hops() {
+    let engine =
+        RoutingEngine::new(Duration::from_secs(60)).with_hop_latency(Duration::from_secs(2));
+    let (near, far, unknown) = (UserId::random(), UserId::random(), UserId::random());
+    engine.update_route(near, PeerId([1; 32]), 1, 1.0).await;
+    engine.update_route(far, PeerId([1; 32]), 5, 1.0).await;
+    assert_eq!(
+        engine.estimated_latency(&near).await,
+        Some(Duration::from_secs(2))
+    );
+    assert_eq!(
+        engine.estimated_latency(&far).await,
+        Some(Duration::from_secs(10))
+    );
+    assert_eq!(engine.estimated_latency(&unknown).await, None);
+
+    // A measured neighbour latency refines routes learned through it.
+    let lora = PeerId([2; 32]);
+    engine.set_link_latency(lora, Duration::from_secs(6));
+    engine.update_route(unknown, lora, 3, 0.5).await;
+    assert_eq!(
+        engine.estimated_latency(&unknown).await,
+        Some(Duration::from_secs(18))
+    );
+}
+
+#[tokio::test]
+async fn test_import_applies_split_horizon_and_sanity_checks() {
+    let me = UserId::random();
+    let my_peer = PeerId([9; 32]);
+    let engine = RoutingEngine::new(Duration::from_secs(60)).with_local(my_peer, me);
+    let via = PeerId([10; 32]);
+    let route = |destination, next_hop, link_quality| RouteInfo {
+        destination,
+        next_hop,
+        hop_count: 1,
+        link_quality,
+        last_updated: std::time::SystemTime::now(),
+        hop_latency: Duration::from_millis(50),
+    };
+    let (back, nan, high, low) = (
+        UserId::random(),
+        UserId::random(),
+        UserId::random(),
+        UserId::random(),
+    );
+    engine
+        .import(
+            &[
+                route(back, my_peer, 1.0),
+                route(me, PeerId([1; 32]), 1.0),
+                route(nan, PeerId([1; 32]), f32::NAN),
+                route(high, PeerId([1; 32]), 7.0),
+                route(low, PeerId([1; 32]), -3.0),
+            ],
+            via,
+        )
+        .await;
+
+    let routes = engine.dump().await;
+    assert_eq!(routes.len(), 2);
+    let quality = |dest| {
+        routes
+            .iter()
+            .find(|r| r.destination == dest)
+            .unwrap()
+            .link_quality
+    };
+    assert_eq!(quality(high), 1.0);
+    assert_eq!(quality(low), 0.0);
+}
+
+#[tokio::test]
+async fn test_relayed_route_exchange_is_rejected() {
+    let a = RoutingEngine::new(Duration::from_secs(60));
+    let x = UserId::random();
+    a.update_route(x, PeerId([1; 32]), 1, 0.9).await;
+
+    let engine = RoutingEngine::new(Duration::from_secs(60));
+    let exporter = Identity::generate();
+    let mut msg = Message::new(
+        exporter.user_id(),
+        None,
+        MessageContent::Routing(RoutingControl::RouteExchange(a.export().await)),
+    );
+    msg.sign(&exporter).unwrap();
+    msg.hop_count = 1;
+    assert!(engine
+        .apply_control(&msg, PeerId([10; 32]), 1.0)
+        .await
+        .is_err());
+    assert_eq!(engine.next_hop(&x).await, None);
+}
+
+#[test]
+fn test_route_without_hop_latency_gets_default() {
+    let route = RouteInfo {
+        destination: UserId::random(),
+        next_hop: PeerId([1; 32]),
+        hop_count: 2,
+        link_quality: 0.5,
+        last_updated: std::time::SystemTime::now(),
+        hop_latency: Duration::from_millis(50),
+    };
+    // As sent by a peer that predates per-hop latency.
+    let mut old = serde_json::to_value(&route).unwrap();
+    old.as_object_mut().unwrap().remove("hop_latency");
+    let decoded: RouteInfo = serde_json::from_value(old).unwrap();
+    assert_eq!(decoded.hop_latency, DEFAULT_HOP_LATENCY);
+    assert_eq!(decoded.destination, route.destination);
+}
@@ -0,0 +1,46 @@
+use disaster_mesh::{Identity, MessageContent, MessageManager, Signer, UserId};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Stands in for a hardware token: the key never leaves it.
+struct TokenSigner {
+    key: Identity,
+    calls: AtomicUsize,
+}
+
+impl Signer for TokenSigner {
+    fn public_key(&self) -> UserId {
+        self.key.user_id()
+    }
+
+    fn sign(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(self.key.sign(data))
+    }
+}
+
+#[tokio::test]
+async fn test_messages_are_signed_through_external_signer() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let token = Arc::new(TokenSigner {
+        key: Identity::generate(),
+        calls: AtomicUsize::new(0),
+    });
+    let me = manager.add_signer(token.clone());
+    assert!(manager.is_local(&me));
+    assert!(manager.identity(&me).is_none(), "the key is not in process");
+
+    let msg = manager
+        .create_message_as(None, None, MessageContent::Text("from the token".into()))
+        .await
+        .unwrap();
+    assert_eq!(msg.sender, me);
+    assert_eq!(token.calls.load(Ordering::SeqCst), 1);
+    msg.verify_signature().unwrap();
+
+    assert!(manager.remove_signer(&me).is_some());
+    assert!(manager
+        .create_message_as(Some(&me), None, MessageContent::Text("gone".into()))
+        .await
+        .is_err());
+}
@@ -0,0 +1,27 @@
+use disaster_mesh::{signing_bytes, Identity, Message, MessageContent, TtlMode, WireFormat};
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn test_signature_survives_every_wire_format() {
+    let sender = Identity::generate();
+    let mut msg = Message::new(sender.user_id(), None, MessageContent::Text("hi".into()));
+    // Sub-millisecond precision is lost on the wire; signing must not see it.
+    msg.timestamp = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+    msg.ttl_mode = TtlMode::Relative;
+    msg.sign(&sender).unwrap();
+    let signed = signing_bytes(&msg).unwrap();
+
+    for format in [WireFormat::Bincode, WireFormat::Json, WireFormat::Cbor] {
+        let mut received: Message = format.decode(&format.encode(&msg).unwrap()).unwrap();
+        assert_ne!(received.timestamp, msg.timestamp);
+        assert_eq!(signing_bytes(&received).unwrap(), signed, "{format:?}");
+        received.verify_signature().unwrap();
+
+        // Relays may rewrite these without breaking the signature.
+        received.hop_count += 1;
+        received.ttl = Duration::from_secs(1);
+        received.verify_signature().unwrap();
+        received.content = MessageContent::Text("tampered".into());
+        assert!(received.verify_signature().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use disaster_mesh::{MeshSimulator, MessageContent};
+
+#[tokio::test]
+async fn test_line_delivers_end_to_end_and_heals() {
+    let sim = MeshSimulator::line(5).await.unwrap();
+    sim.announce_all().await.unwrap();
+    assert!(sim.converged().await);
+    assert_eq!(sim.distance(0, 4), Some(4));
+
+    let sent = sim
+        .node(0)
+        .send(
+            MessageContent::Text("end of the line".into()),
+            Some(sim.user(4)),
+        )
+        .await
+        .unwrap();
+    sim.run_until_idle().await.unwrap();
+    let delivered = sim.delivered(4).await.unwrap();
+    assert_eq!(delivered.len(), 1);
+    assert_eq!(delivered[0].id, sent.id);
+    // Relayed by nodes 1, 2 and 3.
+    assert_eq!(delivered[0].hop_count, 3);
+    for relay in 1..4 {
+        assert!(sim.delivered(relay).await.unwrap().is_empty());
+    }
+
+    // Cut the middle link: the routes across it lead nowhere now.
+    sim.set_link(2, 3, false);
+    assert_eq!(sim.distance(0, 4), None);
+    assert!(!sim.converged().await);
+    sim.node(0)
+        .send(MessageContent::Text("lost".into()), Some(sim.user(4)))
+        .await
+        .unwrap();
+    sim.run_until_idle().await.unwrap();
+    assert_eq!(sim.delivered(4).await.unwrap().len(), 1);
+
+    // Restore it and the same routes carry traffic again.
+    sim.set_link(2, 3, true);
+    sim.run_until_idle().await.unwrap();
+    assert!(sim.converged().await);
+    sim.node(0)
+        .send(MessageContent::Text("back".into()), Some(sim.user(4)))
+        .await
+        .unwrap();
+    sim.run_until_idle().await.unwrap();
+    assert_eq!(sim.delivered(4).await.unwrap().len(), 2);
+}
@@ -0,0 +1,71 @@
+use disaster_mesh::{
+    Identity, MeshNode, MessageContent, MessageId, MessageManager, MockTransport,
+    NodeStateSnapshot, PeerId, UserId,
+};
+use std::sync::Arc;
+
+async fn node(identity: Identity) -> MeshNode {
+    MeshNode::new(
+        identity,
+        PeerId([1; 32]),
+        Arc::new(MockTransport::new()),
+        MessageManager::in_memory().await.unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_export_and_import_node_state() {
+    let old = node(Identity::generate()).await;
+    let (far, relay) = (UserId::random(), PeerId([7; 32]));
+    old.routing().update_route(far, relay, 3, 0.8).await;
+    let pending = old
+        .send(MessageContent::Text("still waiting".into()), Some(far))
+        .await
+        .unwrap();
+    let relayed = MessageId::new();
+    old.messages().mark_message_seen(&relayed).await.unwrap();
+
+    let path = std::env::temp_dir().join(format!("dm-snapshot-{}.bin", std::process::id()));
+    old.export_state("correct horse")
+        .await
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let snapshot = NodeStateSnapshot::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(snapshot.primary_identity("wrong").is_err());
+    let new = node(snapshot.primary_identity("correct horse").unwrap()).await;
+    new.import_state(&snapshot, "correct horse").await.unwrap();
+
+    assert_eq!(new.user_id(), old.user_id());
+    assert_eq!(new.routing().next_hop(&far).await, Some(relay));
+    let restored = new.messages().pending_messages().await.unwrap();
+    assert_eq!(restored.len(), 1);
+    assert_eq!(restored[0].id, pending.id);
+    assert!(restored[0].verify_signature().is_ok());
+    assert!(!new.messages().is_new_message(&relayed).await);
+
+    // The stream to `far` carries on rather than restarting at 1.
+    let next = new
+        .send(MessageContent::Text("after the move".into()), Some(far))
+        .await
+        .unwrap();
+    assert_eq!(next.seq, pending.seq.map(|seq| seq + 1));
+}
+
+#[tokio::test]
+async fn test_snapshot_version_is_checked() {
+    let mut snapshot = node(Identity::generate())
+        .await
+        .export_state("pw")
+        .await
+        .unwrap();
+    snapshot.version += 1;
+    let bytes = snapshot.to_bytes().unwrap();
+    assert!(NodeStateSnapshot::from_bytes(&bytes).is_err());
+    assert!(NodeStateSnapshot::from_bytes(b"not a snapshot").is_err());
+
+    let fresh = node(Identity::generate()).await;
+    assert!(fresh.import_state(&snapshot, "pw").await.is_err());
+}
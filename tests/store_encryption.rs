@@ -0,0 +1,180 @@
+mod common;
+
+use common::when_unlocked;
+use disaster_mesh::{Identity, MessageContent, MessageFilter, MessageManager, StoreCipher, UserId};
+
+const SECRET: &str = "survivors sheltering at 14 Mill Lane";
+
+#[tokio::test]
+async fn test_encrypted_store_round_trips_but_hides_raw_values() {
+    let path = std::env::temp_dir().join(format!("dm-encrypted-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+
+    let manager = MessageManager::open(&path)
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    let me = manager.add_identity(Identity::generate());
+    let sent = manager
+        .create_message(
+            me,
+            Some(UserId::random()),
+            MessageContent::Text(SECRET.into()),
+        )
+        .await
+        .unwrap();
+    manager.sync().await.unwrap();
+    drop(manager);
+
+    // On disk: the key is the plain message id, the value is opaque.
+    let db = when_unlocked(|| async { Ok(sled::open(&path)?) })
+        .await
+        .unwrap();
+    let raw = db.get(sent.id.to_bytes()).unwrap().unwrap();
+    assert!(!raw
+        .windows(SECRET.len())
+        .any(|window| window == SECRET.as_bytes()));
+    assert!(bincode::deserialize::<disaster_mesh::Message>(&raw).is_err());
+    drop(db);
+
+    assert!(when_unlocked(|| MessageManager::open(&path))
+        .await
+        .unwrap()
+        .with_encryption("wrong horse")
+        .is_err());
+
+    let reopened = when_unlocked(|| MessageManager::open(&path))
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    let stored = reopened
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].content, MessageContent::Text(SECRET.into()));
+    assert_eq!(reopened.pending_messages().await.unwrap()[0].id, sent.id);
+    drop(reopened);
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[tokio::test]
+async fn test_encrypted_store_hides_file_content_addresses() {
+    let path = std::env::temp_dir().join(format!("dm-encrypted-files-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    let map = b"well-known evacuation map".repeat(100);
+
+    let manager = MessageManager::open(&path)
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    let me = manager.add_identity(Identity::generate());
+    let file = MessageContent::File {
+        name: "map.png".into(),
+        data: map.clone(),
+    };
+    manager
+        .create_message(me, Some(UserId::random()), file.clone())
+        .await
+        .unwrap();
+    manager.sync().await.unwrap();
+    drop(manager);
+
+    // Someone holding the well-known file cannot look it up by its hash.
+    let db = when_unlocked(|| async { Ok(sled::open(&path)?) })
+        .await
+        .unwrap();
+    let blobs = db.open_tree("file_blobs").unwrap();
+    assert_eq!(blobs.len(), 1);
+    let hash = ring::digest::digest(&ring::digest::SHA256, &map);
+    assert!(blobs.get(hash.as_ref()).unwrap().is_none());
+    drop(blobs);
+    drop(db);
+
+    let reopened = when_unlocked(|| MessageManager::open(&path))
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    let stored = reopened
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored[0].content, file);
+    drop(reopened);
+    let _ = std::fs::remove_dir_all(&path);
+}
+
+#[tokio::test]
+async fn test_encryption_refuses_existing_plaintext_store() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let me = manager.add_identity(Identity::generate());
+    manager
+        .create_message(me, None, MessageContent::Text("hello".into()))
+        .await
+        .unwrap();
+    assert!(manager.with_encryption("too late").is_err());
+}
+
+#[tokio::test]
+async fn test_encrypted_store_seals_ratchet_sessions() {
+    let path = std::env::temp_dir().join(format!("dm-encrypted-sessions-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+
+    let manager = MessageManager::open(&path)
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    let mine = Identity::generate();
+    let me = manager.add_identity(mine.clone());
+    let peer = Identity::generate();
+    manager.add_session_key(&peer.session_key()).unwrap();
+    manager
+        .session_encrypt(&me, &peer.user_id(), &MessageContent::Text(SECRET.into()))
+        .await
+        .unwrap();
+    manager.sync().await.unwrap();
+    drop(manager);
+
+    // The session record opens under the store key, so seizing the device
+    // also takes guessing the passphrase.
+    let db = when_unlocked(|| async { Ok(sled::open(&path)?) })
+        .await
+        .unwrap();
+    let salt = db
+        .open_tree("store_meta")
+        .unwrap()
+        .get("salt")
+        .unwrap()
+        .unwrap();
+    let key = [me.0, peer.user_id().0].concat();
+    let raw = db
+        .open_tree("sessions")
+        .unwrap()
+        .get(&key)
+        .unwrap()
+        .unwrap();
+    let cipher = StoreCipher::derive("correct horse", &salt).unwrap();
+    assert!(cipher.open(&key, &raw).is_ok());
+    let wrong = StoreCipher::derive("wrong horse", &salt).unwrap();
+    assert!(wrong.open(&key, &raw).is_err());
+    drop(db);
+
+    // And the session still works after a reopen.
+    let reopened = when_unlocked(|| MessageManager::open(&path))
+        .await
+        .unwrap()
+        .with_encryption("correct horse")
+        .unwrap();
+    reopened.add_identity(mine);
+    assert!(reopened
+        .session_encrypt(&me, &peer.user_id(), &MessageContent::Text("again".into()))
+        .await
+        .is_ok());
+    drop(reopened);
+    let _ = std::fs::remove_dir_all(&path);
+}
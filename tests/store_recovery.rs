@@ -0,0 +1,64 @@
+mod common;
+
+use common::when_unlocked;
+use disaster_mesh::{Identity, MessageContent, MessageFilter, MessageManager, StoreRecovery};
+
+#[tokio::test]
+async fn test_corrupt_store_fails_fast_or_self_heals() {
+    let parent = std::env::temp_dir().join(format!("dm-recovery-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&parent);
+    std::fs::create_dir_all(&parent).unwrap();
+    let path = parent.join("store");
+
+    let manager = MessageManager::open(&path).await.unwrap();
+    let me = manager.add_identity(Identity::generate());
+    manager
+        .create_message(me, None, MessageContent::Text("before".into()))
+        .await
+        .unwrap();
+    manager.sync().await.unwrap();
+    drop(manager);
+    std::fs::write(path.join("conf"), b"\xde\xad\xbe\xef not a sled config").unwrap();
+
+    assert!(
+        when_unlocked(|| MessageManager::open_with_recovery(&path, StoreRecovery::FailFast))
+            .await
+            .is_err()
+    );
+    assert!(
+        path.join("conf").exists(),
+        "fail-fast leaves the store alone"
+    );
+
+    let healed =
+        when_unlocked(|| MessageManager::open_with_recovery(&path, StoreRecovery::SelfHeal))
+            .await
+            .unwrap();
+    assert!(healed
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap()
+        .is_empty());
+    let me = healed.add_identity(Identity::generate());
+    healed
+        .create_message(me, None, MessageContent::Text("after".into()))
+        .await
+        .unwrap();
+
+    let quarantined: Vec<_> = std::fs::read_dir(&parent)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("store.corrupt-"))
+        .collect();
+    assert_eq!(quarantined.len(), 1);
+
+    // A store held by another handle is locked, not corrupt: never moved.
+    assert!(
+        MessageManager::open_with_recovery(&path, StoreRecovery::SelfHeal)
+            .await
+            .is_err()
+    );
+    drop(healed);
+    assert_eq!(std::fs::read_dir(&parent).unwrap().count(), 2);
+    let _ = std::fs::remove_dir_all(&parent);
+}
@@ -0,0 +1,156 @@
+use disaster_mesh::{
+    BroadcastSuppression, ControlledFlood, ForwardDecision, Forwarder, Identity, MeshNode,
+    MeshStats, Message, MessageContent, MessageManager, MessagePriority, MockClock, MockTransport,
+    PeerId, Transport, TransportEvent, UserId, DEFAULT_MAX_HOPS,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn transmissions(events: &mut tokio::sync::broadcast::Receiver<TransportEvent>) -> usize {
+    let mut count = 0;
+    while let Ok(TransportEvent::DataReceived { .. }) = events.try_recv() {
+        count += 1;
+    }
+    count
+}
+
+#[tokio::test]
+async fn test_rebroadcast_suppressed_after_overhearing_copies() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(PeerId([2; 32])).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        transport.clone(),
+    )
+    .with_suppression(BroadcastSuppression::with_clock(
+        3,
+        Duration::from_secs(1),
+        clock.clone(),
+    ));
+    let mut events = transport.subscribe_events();
+
+    let crowded = Message::new(UserId::random(), None, MessageContent::Text("flood".into()));
+    let quiet = Message::new(UserId::random(), None, MessageContent::Text("edge".into()));
+    for msg in [&crowded, &quiet] {
+        let decision = forwarder
+            .handle_incoming(msg, PeerId([9; 32]))
+            .await
+            .unwrap();
+        assert_eq!(decision, ForwardDecision::Broadcast);
+    }
+    // Three neighbours relay the crowded message while ours is held.
+    for neighbour in 10..13 {
+        forwarder
+            .handle_incoming(&crowded, PeerId([neighbour; 32]))
+            .await
+            .unwrap();
+    }
+    assert_eq!(transmissions(&mut events), 0);
+
+    assert_eq!(forwarder.release_suppressed().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(forwarder.release_suppressed().await.unwrap(), 1);
+    assert_eq!(transmissions(&mut events), 1);
+    assert_eq!(
+        MeshStats::get(&forwarder.stats().suppressed_rebroadcasts),
+        1
+    );
+
+    // Emergency traffic is never held back.
+    let mut mayday = Message::new(UserId::random(), None, MessageContent::Text("help".into()));
+    mayday.priority = MessagePriority::Emergency;
+    forwarder
+        .handle_incoming(&mayday, PeerId([9; 32]))
+        .await
+        .unwrap();
+    assert_eq!(transmissions(&mut events), 1);
+}
+
+#[tokio::test]
+async fn test_held_rebroadcast_goes_through_fair_queue() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let transport = Arc::new(MockTransport::new());
+    transport.add_peer(PeerId([2; 32])).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        transport.clone(),
+    )
+    .with_fair_queue(8)
+    .with_suppression(BroadcastSuppression::with_clock(
+        3,
+        Duration::from_secs(1),
+        clock.clone(),
+    ));
+    let mut events = transport.subscribe_events();
+
+    let bulletin = Message::new(UserId::random(), None, MessageContent::Text("flood".into()));
+    forwarder
+        .handle_incoming(&bulletin, PeerId([9; 32]))
+        .await
+        .unwrap();
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(forwarder.release_suppressed().await.unwrap(), 1);
+    // Released into the queue, not straight onto the link.
+    assert_eq!(transmissions(&mut events), 0);
+    assert_eq!(forwarder.queued(), 1);
+    assert_eq!(forwarder.send_queued(8).await.unwrap(), 1);
+    assert_eq!(transmissions(&mut events), 1);
+}
+
+#[tokio::test]
+async fn test_running_node_suppresses_overheard_rebroadcast() {
+    let transport = MockTransport::new();
+    for neighbour in 2..5 {
+        transport.add_peer(PeerId([neighbour; 32])).await;
+    }
+    let identity = Identity::generate();
+    let forwarder = Forwarder::new(
+        identity.clone(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::new(DEFAULT_MAX_HOPS)),
+        Arc::new(transport.clone()),
+    )
+    .with_suppression(BroadcastSuppression::new(2, Duration::from_millis(100)));
+    let node = MeshNode::new(
+        identity,
+        PeerId([1; 32]),
+        Arc::new(transport.clone()),
+        MessageManager::in_memory().await.unwrap(),
+    )
+    .with_forwarder(forwarder.clone());
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let origin = Identity::generate();
+    let mut bulletin = Message::new(origin.user_id(), None, MessageContent::Text("flood".into()));
+    bulletin.sign(&origin).unwrap();
+    let data = transport.wire_format().encode(&bulletin).unwrap();
+    // The first copy is held; the node drops the next two as duplicates
+    // but still counts them.
+    for neighbour in 2..5 {
+        transport
+            .send(PeerId([neighbour; 32]), data.clone())
+            .await
+            .unwrap();
+    }
+
+    let stats = forwarder.stats();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while MeshStats::get(&stats.suppressed_rebroadcasts) == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}
@@ -0,0 +1,28 @@
+use disaster_mesh::{seed_ids, unseed_ids, with_seeded_ids, MessageId, UserId};
+
+fn ids() -> (Vec<MessageId>, Vec<UserId>) {
+    (
+        (0..4).map(|_| MessageId::new()).collect(),
+        (0..4).map(|_| UserId::random()).collect(),
+    )
+}
+
+#[test]
+fn test_same_seed_yields_same_ids() {
+    let first = with_seeded_ids(42, ids);
+    let second = with_seeded_ids(42, ids);
+    assert_eq!(first, second);
+    assert_ne!(with_seeded_ids(43, ids), first);
+
+    // Seeded ids are still well-formed v4 UUIDs, and unseeded ones random.
+    seed_ids(42);
+    let id = MessageId::new();
+    unseed_ids();
+    assert_eq!(id, first.0[0]);
+    assert_eq!(uuid_version(id), 4);
+    assert_ne!(ids(), first);
+}
+
+fn uuid_version(id: MessageId) -> u8 {
+    id.to_bytes()[6] >> 4
+}
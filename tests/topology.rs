@@ -0,0 +1,32 @@
+use disaster_mesh::{topology_dot, NeighborTable, PeerId, RoutingEngine, UserId};
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_topology_dot_lists_nodes_and_edges() {
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let neighbors = NeighborTable::new();
+
+    let empty = topology_dot(&routing, &neighbors).await;
+    assert_eq!(
+        empty,
+        "digraph mesh {\n    \"self\" [shape=doublecircle];\n}\n"
+    );
+
+    let relay = PeerId([0xab; 32]);
+    neighbors.update(relay, 0.75).await;
+    routing
+        .update_route(UserId([0x01; 32]), relay, 1, 0.75)
+        .await;
+    routing
+        .update_route(UserId([0x02; 32]), relay, 3, 0.5)
+        .await;
+
+    let dot = topology_dot(&routing, &neighbors).await;
+    assert!(dot.starts_with("digraph mesh {"));
+    assert!(dot.contains("\"peer:abababab\" [shape=box];"));
+    assert!(dot.contains("\"user:01010101\" [shape=ellipse];"));
+    assert!(dot.contains("\"self\" -> \"peer:abababab\" [label=\"q=0.75\"];"));
+    assert!(dot.contains("\"peer:abababab\" -> \"user:01010101\" [label=\"hops=1 q=0.75\"];"));
+    assert!(dot.contains("\"peer:abababab\" -> \"user:02020202\" [label=\"hops=3 q=0.50\"];"));
+    assert!(dot.trim_end().ends_with('}'));
+}
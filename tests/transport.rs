@@ -0,0 +1,210 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use disaster_mesh::{
+    ChannelOccupancy, MeshStats, MessageContent, MessageManager, MockTransport, PeerId, Transport,
+    TransportEvent, UserId,
+};
+use tokio::sync::broadcast;
+
+/// Mock link that claims to be already encrypted (e.g. BLE with pairing).
+struct SecureMockTransport(MockTransport);
+
+#[async_trait]
+impl Transport for SecureMockTransport {
+    async fn start(&mut self) -> Result<()> {
+        self.0.start().await
+    }
+
+    async fn send(&self, peer: PeerId, data: Vec<u8>) -> Result<()> {
+        self.0.send(peer, data).await
+    }
+
+    async fn broadcast(&self, data: Vec<u8>) -> Result<()> {
+        self.0.broadcast(data).await
+    }
+
+    fn get_peers(&self) -> Vec<PeerId> {
+        self.0.get_peers()
+    }
+
+    fn subscribe_events(&self) -> broadcast::Receiver<TransportEvent> {
+        self.0.subscribe_events()
+    }
+
+    fn mtu(&self) -> usize {
+        self.0.mtu()
+    }
+
+    fn link_quality(&self) -> f32 {
+        self.0.link_quality()
+    }
+
+    fn is_secure(&self) -> bool {
+        true
+    }
+}
+
+#[tokio::test]
+async fn test_secure_link_skips_encryption() {
+    let manager = MessageManager::new().await.unwrap();
+    let plain = MockTransport::new();
+    let secure = SecureMockTransport(MockTransport::new());
+    assert!(!plain.is_secure());
+    assert!(manager.needs_encryption(&plain));
+    assert!(!manager.needs_encryption(&secure));
+
+    let peer = UserId::random();
+    let content = MessageContent::Text("over an encrypted link".into());
+    let encoded = manager
+        .encode_for_link(&content, &peer, &secure)
+        .await
+        .unwrap();
+    assert_eq!(encoded, bincode::serialize(&content).unwrap());
+
+    let decoded = manager
+        .decode_for_link(&encoded, &peer, &secure)
+        .await
+        .unwrap();
+    assert_eq!(decoded, content);
+}
+
+#[tokio::test]
+async fn test_lagged_subscriber_is_counted_not_fatal() {
+    let transport = MockTransport::with_capacity(4);
+    let stats = MeshStats::default();
+    let mut events = transport.subscribe_events();
+
+    for i in 0..10u8 {
+        transport.send(PeerId([i; 32]), vec![i]).await.unwrap();
+    }
+    assert_eq!(
+        transport.event_occupancy(),
+        Some(ChannelOccupancy {
+            queued: 4,
+            capacity: 4
+        })
+    );
+
+    // The six oldest events were overwritten; we resume at the seventh.
+    match stats.recv_event(&mut events).await {
+        Some(TransportEvent::DataReceived { data, .. }) => assert_eq!(data, vec![6]),
+        other => panic!("unexpected event {other:?}"),
+    }
+    assert_eq!(MeshStats::get(&stats.dropped_events), 6);
+}
+
+#[tokio::test]
+async fn test_mock_peers_receive_broadcast_and_disconnect() {
+    let transport = MockTransport::new();
+    let mut events = transport.subscribe_events();
+    let (a, b) = (PeerId([1; 32]), PeerId([2; 32]));
+
+    transport.add_peer(a).await;
+    transport.add_peer(b).await;
+    transport.add_peer(a).await;
+    assert_eq!(transport.get_peers(), vec![a, b]);
+    for peer in [a, b] {
+        match events.recv().await.unwrap() {
+            TransportEvent::PeerConnected(p) => assert_eq!(p, peer),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+
+    transport.broadcast(b"flood".to_vec()).await.unwrap();
+    for peer in [a, b] {
+        match events.recv().await.unwrap() {
+            TransportEvent::DataReceived { peer: p, data } => {
+                assert_eq!(p, peer);
+                assert_eq!(data, b"flood");
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+
+    transport.remove_peer(a).await;
+    match events.recv().await.unwrap() {
+        TransportEvent::PeerDisconnected(p) => assert_eq!(p, a),
+        other => panic!("unexpected event {other:?}"),
+    }
+    assert_eq!(transport.get_peers(), vec![b]);
+}
+
+#[tokio::test]
+async fn test_broadcast_detailed_reports_each_peer() {
+    let transport = MockTransport::new();
+    let peers = [PeerId([1; 32]), PeerId([2; 32]), PeerId([3; 32])];
+    for peer in peers {
+        transport.add_peer(peer).await;
+    }
+    transport.set_reachable(peers[1], false).await;
+    let mut events = transport.subscribe_events();
+
+    let results = transport.broadcast_detailed(b"ping".to_vec()).await;
+    let outcome: Vec<_> = results.iter().map(|(p, r)| (*p, r.is_ok())).collect();
+    assert_eq!(
+        outcome,
+        [(peers[0], true), (peers[1], false), (peers[2], true)]
+    );
+    for expected in [peers[0], peers[2]] {
+        match events.try_recv().unwrap() {
+            TransportEvent::DataReceived { peer, .. } => assert_eq!(peer, expected),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+    assert!(events.try_recv().is_err());
+
+    assert!(transport.send(peers[1], b"retry".to_vec()).await.is_err());
+    transport.set_reachable(peers[1], true).await;
+    assert!(transport.send(peers[1], b"retry".to_vec()).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_slow_reliable_subscriber_loses_no_payloads() {
+    let transport = MockTransport::with_capacity(4);
+    let mut reliable = transport.subscribe_events_reliable().unwrap();
+    let mut lossy = transport.subscribe_events();
+
+    let producer = {
+        let transport = transport.clone();
+        tokio::spawn(async move {
+            for i in 0..64u32 {
+                transport
+                    .send(PeerId([1; 32]), i.to_le_bytes().to_vec())
+                    .await
+                    .unwrap();
+            }
+        })
+    };
+    let mut received = Vec::new();
+    while received.len() < 64 {
+        // Slower than the producer, so the queue fills and it has to wait.
+        tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        match reliable.recv().await.unwrap() {
+            TransportEvent::DataReceived { data, .. } => {
+                received.push(u32::from_le_bytes(data.try_into().unwrap()))
+            }
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+    producer.await.unwrap();
+    assert_eq!(received, (0..64).collect::<Vec<_>>());
+
+    // The drop-on-lag subscriber, never drained, missed most of them.
+    assert!(matches!(
+        lossy.recv().await,
+        Err(broadcast::error::RecvError::Lagged(60))
+    ));
+}
+
+#[tokio::test]
+async fn test_zero_capacity_transport_still_delivers() {
+    let transport = MockTransport::with_capacity(0);
+    let mut events = transport.subscribe_events();
+    let mut reliable = transport.subscribe_events_reliable().unwrap();
+
+    transport.send(PeerId([1; 32]), vec![7]).await.unwrap();
+    for event in [events.recv().await.unwrap(), reliable.recv().await.unwrap()] {
+        assert!(matches!(event, TransportEvent::DataReceived { data, .. } if data == [7]));
+    }
+    assert_eq!(transport.event_occupancy().unwrap().capacity, 1);
+}
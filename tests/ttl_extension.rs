@@ -0,0 +1,53 @@
+use disaster_mesh::{
+    Identity, Message, MessageContent, MessageManager, MessagePriority, MockClock, QosClass, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_emergency_outlives_ttl_up_to_cap() {
+    let clock = Arc::new(MockClock::new(std::time::SystemTime::now()));
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone())
+        .with_retransmit_budget(None)
+        .with_ttl_extension(Some(Duration::from_secs(300)));
+    let me = manager.add_identity(Identity::generate());
+    let mut created = Vec::new();
+    for priority in [MessagePriority::Emergency, MessagePriority::Normal] {
+        let msg = Message::builder()
+            .sender(me)
+            .to(UserId::random())
+            .content(MessageContent::Text("trapped at the bridge".into()))
+            .qos(QosClass::Reliable)
+            .priority(priority)
+            .ttl(Duration::from_secs(60));
+        created.push(manager.create_from(msg).await.unwrap());
+    }
+    let pending = |manager: MessageManager| async move {
+        let pending = manager.pending_messages().await.unwrap();
+        pending.iter().map(|msg| msg.id).collect::<Vec<_>>()
+    };
+
+    // The normal message expires on schedule; the emergency one lives on
+    // and is retransmitted with a longer, validly signed TTL.
+    clock.advance(Duration::from_secs(61));
+    assert_eq!(manager.purge_expired().await.unwrap(), 1);
+    assert_eq!(pending(manager.clone()).await, [created[0].id]);
+    let retry = manager.due_retransmissions().await.unwrap();
+    assert_eq!(retry.len(), 1);
+    assert_eq!(retry[0].ttl.as_secs(), 121);
+    assert!(retry[0].verify_signature().is_ok());
+    manager.validate_message(&retry[0]).await.unwrap();
+
+    clock.advance(Duration::from_secs(238));
+    assert_eq!(manager.purge_expired().await.unwrap(), 0);
+    let retry = manager.due_retransmissions().await.unwrap();
+    assert_eq!(retry[0].ttl, Duration::from_secs(300));
+
+    // But not forever.
+    clock.advance(Duration::from_secs(2));
+    assert_eq!(manager.purge_expired().await.unwrap(), 1);
+    assert!(pending(manager.clone()).await.is_empty());
+}
@@ -0,0 +1,74 @@
+use disaster_mesh::{
+    Identity, Message, MessageContent, MessageManager, MessageValidator, RejectReason, UserId,
+};
+
+struct BlockSender(UserId);
+
+impl MessageValidator for BlockSender {
+    fn validate(&self, msg: &Message) -> Result<(), RejectReason> {
+        if msg.sender == self.0 {
+            return Err(RejectReason::Sender);
+        }
+        Ok(())
+    }
+}
+
+struct MaxTextLen(usize);
+
+impl MessageValidator for MaxTextLen {
+    fn validate(&self, msg: &Message) -> Result<(), RejectReason> {
+        match &msg.content {
+            MessageContent::Text(text) if text.len() > self.0 => Err(RejectReason::TooLarge),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_validators_reject_blocked_sender() {
+    let spammer = UserId::random();
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_unsigned_messages()
+        .with_validator(Box::new(BlockSender(spammer)))
+        .with_validator(Box::new(MaxTextLen(16)));
+
+    let text = |sender, s: &str| Message::new(sender, None, MessageContent::Text(s.into()));
+    let err = manager.deliver(text(spammer, "buy now")).await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RejectReason>(),
+        Some(&RejectReason::Sender)
+    );
+
+    let neighbour = UserId::random();
+    let err = manager
+        .validate_message(&text(neighbour, "a very long announcement"))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<RejectReason>(),
+        Some(&RejectReason::TooLarge)
+    );
+    assert!(manager.deliver(text(neighbour, "road open")).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_screened_forgery_does_not_shadow_the_genuine_message() {
+    let manager = MessageManager::in_memory().await.unwrap();
+    let author = Identity::generate();
+    let mut genuine = Message::new(
+        author.user_id(),
+        None,
+        MessageContent::Text("bridge closed".into()),
+    );
+    genuine.sign(&author).unwrap();
+    let mut forged = genuine.clone();
+    forged.content = MessageContent::Text("bridge open".into());
+
+    for _ in 0..2 {
+        assert!(manager.screen_message(&forged).await.is_err());
+    }
+    manager.screen_message(&genuine).await.unwrap();
+    assert!(manager.deliver_validated(genuine).await.unwrap());
+}
@@ -0,0 +1,58 @@
+use disaster_mesh::{DiscoveryConfig, PeerId, RouteDiscovery, RoutingEngine, UserId};
+use std::time::{Duration, Instant};
+
+#[tokio::test]
+async fn test_wait_for_route_returns_once_route_is_installed() {
+    let engine = RoutingEngine::new(Duration::from_secs(60));
+    let dest = UserId::random();
+    let next_hop = PeerId([7; 32]);
+
+    let installer = engine.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        installer.update_route(dest, next_hop, 2, 0.9).await;
+    });
+    let started = Instant::now();
+    let hop = engine
+        .wait_for_route(&dest, Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(hop, next_hop);
+    assert!(started.elapsed() < Duration::from_secs(1));
+
+    // Already known: immediate.
+    assert_eq!(
+        engine.wait_for_route(&dest, Duration::ZERO).await.unwrap(),
+        next_hop
+    );
+}
+
+#[tokio::test]
+async fn test_wait_for_route_times_out_or_fails_with_discovery() {
+    let engine = RoutingEngine::new(Duration::from_secs(60));
+    let dest = UserId::random();
+    assert!(engine
+        .wait_for_route(&dest, Duration::from_millis(20))
+        .await
+        .is_err());
+
+    // Discovery that gives up after its first RREQ.
+    let discovery = RouteDiscovery::new(
+        engine.clone(),
+        DiscoveryConfig {
+            min_interval: Duration::ZERO,
+            max_retries: 0,
+        },
+    );
+    tokio::spawn(async move {
+        discovery.request(dest).await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        discovery.request(dest).await;
+    });
+    let started = Instant::now();
+    assert!(engine
+        .wait_for_route(&dest, Duration::from_secs(5))
+        .await
+        .is_err());
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
@@ -0,0 +1,53 @@
+use disaster_mesh::{MockClock, MockTransport, PeerId, TransportHealth, TransportWatchdog};
+use futures::FutureExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_watchdog_restarts_dead_transport() {
+    let transport = MockTransport::new();
+    let restarted = transport.clone();
+    let watchdog = TransportWatchdog::new(Arc::new(transport.clone())).with_restart(move || {
+        let transport = restarted.clone();
+        async move {
+            transport.set_alive(true);
+            Ok(())
+        }
+        .boxed()
+    });
+    let health = watchdog.subscribe();
+
+    assert_eq!(watchdog.check().await, TransportHealth::Healthy);
+    assert_eq!(watchdog.restarts(), 0);
+
+    transport.set_alive(false);
+    assert_eq!(watchdog.check().await, TransportHealth::Dead);
+    assert_eq!(watchdog.restarts(), 1);
+    assert_eq!(watchdog.health(), TransportHealth::Healthy);
+    assert!(health.has_changed().unwrap());
+}
+
+#[tokio::test]
+async fn test_watchdog_flags_silent_transport() {
+    let clock = MockClock::default();
+    let transport = Arc::new(MockTransport::new());
+    let watchdog = TransportWatchdog::with_clock(transport.clone(), Arc::new(clock.clone()))
+        .with_silence_timeout(Duration::from_secs(30));
+
+    clock.advance(Duration::from_secs(20));
+    assert_eq!(watchdog.check().await, TransportHealth::Healthy);
+    clock.advance(Duration::from_secs(20));
+    assert_eq!(watchdog.check().await, TransportHealth::Silent);
+    assert_eq!(watchdog.health(), TransportHealth::Silent);
+
+    // Events seen by the spawned loop count as activity.
+    let task = watchdog
+        .clone()
+        .with_interval(Duration::from_secs(3600))
+        .spawn();
+    tokio::task::yield_now().await;
+    transport.add_peer(PeerId([1; 32])).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    task.abort();
+    assert_eq!(watchdog.check().await, TransportHealth::Healthy);
+}
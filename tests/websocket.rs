@@ -0,0 +1,142 @@
+#![cfg(feature = "websocket")]
+
+use disaster_mesh::{
+    Message, MessageContent, PeerAddress, Transport, TransportEvent, UserId, WebSocketTransport,
+    WireFormat,
+};
+use futures::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+#[tokio::test]
+async fn test_websocket_gateway_exchanges_message() {
+    let mut gateway = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    let mut events = gateway.subscribe_events();
+    gateway.start().await.unwrap();
+    let addr = gateway.local_addr().unwrap();
+
+    let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+        .await
+        .unwrap();
+    let peer = match timeout(Duration::from_secs(5), events.recv()).await {
+        Ok(Ok(TransportEvent::PeerConnected(peer))) => peer,
+        other => panic!("expected PeerConnected, got {other:?}"),
+    };
+    assert_eq!(gateway.get_peers(), vec![peer]);
+
+    // Browser -> gateway, JSON-encoded.
+    let outbound = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("from web".into()),
+    );
+    let json = WireFormat::Json.encode(&outbound).unwrap();
+    client
+        .send(WsMessage::Text(String::from_utf8(json).unwrap()))
+        .await
+        .unwrap();
+    let data = match timeout(Duration::from_secs(5), events.recv()).await {
+        Ok(Ok(TransportEvent::DataReceived { peer: from, data })) if from == peer => data,
+        other => panic!("expected DataReceived, got {other:?}"),
+    };
+    let received: Message = WireFormat::Json.decode(&data).unwrap();
+    assert_eq!(received.id, outbound.id);
+    assert_eq!(received.content, outbound.content);
+
+    // Gateway -> browser.
+    let reply = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Text("from mesh".into()),
+    );
+    gateway
+        .send(peer, WireFormat::Json.encode(&reply).unwrap())
+        .await
+        .unwrap();
+    let frame = timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let decoded: Message = WireFormat::Json.decode(&frame.into_data()).unwrap();
+    assert_eq!(decoded.content, reply.content);
+}
+
+#[tokio::test]
+async fn test_websocket_shutdown_stops_tasks() {
+    let mut gateway = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    let mut events = gateway.subscribe_events();
+    gateway.start().await.unwrap();
+    let addr = gateway.local_addr().unwrap();
+    // Just the accept loop.
+    assert_eq!(gateway.active_tasks(), 1);
+
+    let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+        .await
+        .unwrap();
+    let peer = match timeout(Duration::from_secs(5), events.recv()).await {
+        Ok(Ok(TransportEvent::PeerConnected(peer))) => peer,
+        other => panic!("expected PeerConnected, got {other:?}"),
+    };
+    // Plus the connection's reader and writer.
+    timeout(Duration::from_secs(5), async {
+        while gateway.active_tasks() != 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("connection tasks did not start");
+
+    gateway.shutdown().await.unwrap();
+    assert_eq!(gateway.active_tasks(), 0);
+    assert!(gateway.get_peers().is_empty());
+    assert!(matches!(
+        events.recv().await,
+        Ok(TransportEvent::PeerDisconnected(p)) if p == peer
+    ));
+
+    // The client sees the socket close and the listener is gone.
+    let closed = timeout(Duration::from_secs(5), client.next())
+        .await
+        .unwrap();
+    assert!(!matches!(closed, Some(Ok(WsMessage::Binary(_)))));
+    assert!(tokio_tungstenite::connect_async(format!("ws://{addr}"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_websocket_gateway_connects_to_another() {
+    let mut a = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    let mut b = WebSocketTransport::new("127.0.0.1:0".parse().unwrap());
+    a.start().await.unwrap();
+    b.start().await.unwrap();
+    let mut a_events = a.subscribe_events();
+    let mut b_events = b.subscribe_events();
+    let b_addr = PeerAddress::Socket(b.local_addr().unwrap());
+
+    a.connect(&b_addr).await.unwrap();
+    let to_b = match timeout(Duration::from_secs(5), a_events.recv()).await {
+        Ok(Ok(TransportEvent::PeerConnected(peer))) => peer,
+        other => panic!("expected PeerConnected, got {other:?}"),
+    };
+    assert_eq!(a.peer_address(to_b), Some(b_addr.clone()));
+    let to_a = match timeout(Duration::from_secs(5), b_events.recv()).await {
+        Ok(Ok(TransportEvent::PeerConnected(peer))) => peer,
+        other => panic!("expected PeerConnected, got {other:?}"),
+    };
+    assert_eq!(b.peer_address(to_a), None);
+
+    a.send(to_b, b"hello".to_vec()).await.unwrap();
+    match timeout(Duration::from_secs(5), b_events.recv()).await {
+        Ok(Ok(TransportEvent::DataReceived { peer, data })) => {
+            assert_eq!(peer, to_a);
+            assert_eq!(data, b"hello");
+        }
+        other => panic!("expected DataReceived, got {other:?}"),
+    }
+
+    a.shutdown().await.unwrap();
+    b.shutdown().await.unwrap();
+}
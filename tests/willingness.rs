@@ -0,0 +1,142 @@
+use disaster_mesh::{
+    ControlledFlood, ForwardDecision, Forwarder, Identity, Message, MessageContent,
+    MessagePriority, MockTransport, PeerId, RoutingControl, RoutingEngine, Transport,
+    TransportEvent, UserId,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_reluctant_neighbour_is_avoided_as_relay() {
+    let (reluctant, willing) = (PeerId([1; 32]), PeerId([2; 32]));
+    let reluctant_id = Identity::generate();
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([9; 32])).await;
+    let mut events = transport.subscribe_events();
+    let forwarder = Forwarder::new(
+        reluctant_id.clone(),
+        reluctant,
+        Arc::new(ControlledFlood::default()),
+        Arc::new(transport.clone()),
+    )
+    .with_willingness(0.0);
+
+    // The low-battery node advertises itself when we connect.
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    forwarder
+        .send_routes(
+            PeerId([9; 32]),
+            &RoutingEngine::new(Duration::from_secs(60)),
+        )
+        .await
+        .unwrap();
+    let advert = loop {
+        if let TransportEvent::DataReceived { data, .. } = events.recv().await.unwrap() {
+            break transport.wire_format().decode(&data).unwrap();
+        }
+    };
+    routing
+        .apply_control(&advert, reluctant, 1.0)
+        .await
+        .unwrap();
+    assert_eq!(routing.willingness(&reluctant), 0.0);
+
+    // A longer path via a willing neighbour beats a shorter one through it.
+    let far = UserId::random();
+    routing.update_route(far, reluctant, 2, 1.0).await;
+    routing.update_route(far, willing, 4, 0.5).await;
+    assert_eq!(routing.next_hop(&far).await, Some(willing));
+    routing.update_route(far, reluctant, 2, 1.0).await;
+    assert_eq!(routing.next_hop(&far).await, Some(willing));
+
+    // It still relays when there is no alternative, and stays reachable.
+    let isolated = UserId::random();
+    routing.update_route(isolated, reluctant, 2, 1.0).await;
+    assert_eq!(routing.next_hop(&isolated).await, Some(reluctant));
+    routing
+        .update_route(reluctant_id.user_id(), reluctant, 1, 1.0)
+        .await;
+    routing
+        .update_route(reluctant_id.user_id(), willing, 2, 1.0)
+        .await;
+    assert_eq!(
+        routing.next_hop(&reluctant_id.user_id()).await,
+        Some(reluctant)
+    );
+}
+
+#[tokio::test]
+async fn test_unwilling_node_relays_only_emergencies() {
+    let transport = MockTransport::new();
+    transport.add_peer(PeerId([9; 32])).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(ControlledFlood::default()),
+        Arc::new(transport),
+    )
+    .with_willingness(0.0);
+    let origin = Identity::generate();
+    let flood = |priority| {
+        let mut msg = Message::new(
+            origin.user_id(),
+            None,
+            MessageContent::Text("road to the hospital is open".into()),
+        );
+        msg.priority = priority;
+        msg.sign(&origin).unwrap();
+        msg
+    };
+
+    let normal = flood(MessagePriority::Normal);
+    assert_eq!(
+        forwarder
+            .handle_incoming(&normal, PeerId([9; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Drop
+    );
+    assert_eq!(forwarder.stats().snapshot().unwilling_drops, 1);
+
+    let mayday = flood(MessagePriority::Emergency);
+    assert_eq!(
+        forwarder
+            .handle_incoming(&mayday, PeerId([9; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Broadcast
+    );
+
+    // Raising it again resumes relaying.
+    forwarder.set_willingness(0.5);
+    let normal = flood(MessagePriority::Normal);
+    assert_eq!(
+        forwarder
+            .handle_incoming(&normal, PeerId([9; 32]))
+            .await
+            .unwrap(),
+        ForwardDecision::Broadcast
+    );
+}
+
+#[tokio::test]
+async fn test_non_finite_willingness_is_rejected() {
+    let neighbour = Identity::generate();
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    for willingness in [f32::NAN, f32::INFINITY] {
+        let mut advert = Message::new(
+            neighbour.user_id(),
+            None,
+            MessageContent::Routing(RoutingControl::Willingness {
+                origin: neighbour.user_id(),
+                willingness,
+            }),
+        );
+        advert.sign(&neighbour).unwrap();
+        assert!(routing
+            .apply_control(&advert, PeerId([2; 32]), 1.0)
+            .await
+            .is_err());
+    }
+    assert_eq!(routing.willingness(&PeerId([2; 32])), 1.0);
+}
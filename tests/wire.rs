@@ -0,0 +1,212 @@
+use disaster_mesh::{
+    decode_message, timestamp_to_millis, AodvReactive, ChecksummedTransport, Forwarder, Identity,
+    MeshStats, Message, MessageContent, MockTransport, PeerId, RoutingControl, RoutingEngine,
+    Transport, TransportEvent, UserId, WireFormat,
+};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn test_decode_message_round_trip() {
+    let msg = Message::new(UserId::random(), None, MessageContent::Text("ok".into()));
+    let decoded = decode_message(&bincode::serialize(&msg).unwrap()).unwrap();
+    assert_eq!(decoded.id, msg.id);
+    assert_eq!(decoded.content, msg.content);
+}
+
+#[test]
+fn test_gigantic_declared_file_length_is_rejected() {
+    let data = vec![0xAB; 4];
+    let msg = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::File {
+            name: "x".into(),
+            data: data.clone(),
+        },
+    );
+    let mut bytes = bincode::serialize(&msg).unwrap();
+
+    // Patch the u64 length prefix of `data` to claim 1 TiB.
+    let mut needle = 4u64.to_le_bytes().to_vec();
+    needle.extend_from_slice(&data);
+    let at = bytes
+        .windows(needle.len())
+        .position(|w| w == needle.as_slice())
+        .unwrap();
+    bytes[at..at + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+
+    assert!(decode_message(&bytes).is_err());
+}
+
+#[test]
+fn test_deeply_nested_batches_are_rejected() {
+    let mut control = RoutingControl::Rerr {
+        unreachable: vec![UserId::random()],
+    };
+    for _ in 0..64 {
+        control = RoutingControl::Batch(vec![control]);
+    }
+    let msg = Message::new(UserId::random(), None, MessageContent::Routing(control));
+    assert!(decode_message(&bincode::serialize(&msg).unwrap()).is_err());
+
+    let shallow = Message::new(
+        UserId::random(),
+        None,
+        MessageContent::Routing(RoutingControl::Batch(vec![RoutingControl::Batch(vec![])])),
+    );
+    assert!(decode_message(&bincode::serialize(&shallow).unwrap()).is_ok());
+}
+
+#[test]
+fn test_cbor_round_trip_and_size() {
+    let mut control = RoutingControl::Batch(vec![
+        RoutingControl::Rreq {
+            origin: UserId::random(),
+            destination: UserId::random(),
+            request_id: 7,
+            hop_count: 2,
+        },
+        RoutingControl::Rerr {
+            unreachable: vec![UserId::random()],
+        },
+    ]);
+    let identity = Identity::generate();
+    let mut msg = Message::new(
+        identity.user_id(),
+        Some(UserId::random()),
+        MessageContent::Text("water at the school".into()),
+    );
+    msg.sign(&identity).unwrap();
+
+    let cbor = WireFormat::Cbor.encode(&msg).unwrap();
+    let bincode = WireFormat::Bincode.encode(&msg).unwrap();
+    println!(
+        "message: cbor {} bytes, bincode {} bytes",
+        cbor.len(),
+        bincode.len()
+    );
+    let decoded: Message = WireFormat::Cbor.decode(&cbor).unwrap();
+    assert_eq!(decoded.id, msg.id);
+    assert_eq!(decoded.content, msg.content);
+    decoded.verify_signature().unwrap();
+
+    let cbor = WireFormat::Cbor.encode(&control).unwrap();
+    let bincode = WireFormat::Bincode.encode(&control).unwrap();
+    println!(
+        "control: cbor {} bytes, bincode {} bytes",
+        cbor.len(),
+        bincode.len()
+    );
+    let decoded: RoutingControl = WireFormat::Cbor.decode(&cbor).unwrap();
+    assert_eq!(decoded, control);
+
+    for _ in 0..64 {
+        control = RoutingControl::Batch(vec![control]);
+    }
+    let deep = WireFormat::Cbor.encode(&control).unwrap();
+    assert!(WireFormat::Cbor.decode::<RoutingControl>(&deep).is_err());
+}
+
+#[tokio::test]
+async fn test_forwarder_frames_with_transport_wire_format() {
+    let transport = Arc::new(MockTransport::new().with_wire_format(WireFormat::Cbor));
+    let mut events = transport.subscribe_events();
+    let routing = RoutingEngine::new(Duration::from_secs(60));
+    let dest = UserId::random();
+    let next = PeerId([2; 32]);
+    routing.update_route(dest, next, 1, 1.0).await;
+    let forwarder = Forwarder::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(AodvReactive::new(routing)),
+        transport.clone(),
+    );
+
+    let msg = Message::new(
+        UserId::random(),
+        Some(dest),
+        MessageContent::Text("hi".into()),
+    );
+    forwarder
+        .handle_incoming(&msg, PeerId([0; 32]))
+        .await
+        .unwrap();
+
+    let Ok(TransportEvent::DataReceived { peer, data }) = events.recv().await else {
+        panic!("forwarded message not sent");
+    };
+    assert_eq!(peer, next);
+    let relayed: Message = WireFormat::Cbor.decode(&data).unwrap();
+    assert_eq!(relayed.id, msg.id);
+    assert_eq!(relayed.hop_count, 1);
+}
+
+#[test]
+fn test_checksum_rejects_flipped_byte() {
+    assert_eq!(disaster_mesh::crc32(b"123456789"), 0xCBF4_3926);
+
+    let msg = Message::new(UserId::random(), None, MessageContent::Text("flood".into()));
+    for format in [WireFormat::Bincode, WireFormat::Json, WireFormat::Cbor] {
+        let mut frame = format.encode_checked(&msg).unwrap();
+        let decoded: Message = format.decode_checked(&frame).unwrap();
+        assert_eq!(decoded.id, msg.id);
+
+        frame[10] ^= 0x04;
+        let err = format.decode_checked::<Message>(&frame).unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+    }
+    assert!(WireFormat::Bincode
+        .decode_checked::<Message>(&[1, 2])
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_checksummed_transport_drops_corrupted_frames() {
+    let peer = PeerId([2; 32]);
+    let radio = MockTransport::new();
+    radio.add_peer(peer).await;
+    let mut link = ChecksummedTransport::new(Box::new(radio.clone()));
+    link.start().await.unwrap();
+    let mut events = link.subscribe_events();
+
+    let msg = Message::new(UserId::random(), None, MessageContent::Text("flood".into()));
+    let payload = link.wire_format().encode(&msg).unwrap();
+    link.send(peer, payload.clone()).await.unwrap();
+    // The same frame with a bit flipped in transit.
+    let mut corrupted = payload.clone();
+    disaster_mesh::append_crc32(&mut corrupted);
+    corrupted[10] ^= 0x04;
+    radio.send(peer, corrupted).await.unwrap();
+    link.send(peer, payload.clone()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let mut received = Vec::new();
+    while let Ok(TransportEvent::DataReceived { data, .. }) = events.try_recv() {
+        received.push(data);
+    }
+    assert_eq!(received, [payload.clone(), payload]);
+    assert_eq!(MeshStats::get(&link.stats().checksum_failures), 1);
+    assert!(link.mtu() < radio.mtu());
+}
+
+#[test]
+fn test_timestamp_survives_wire_at_millisecond_precision() {
+    let identity = Identity::generate();
+    let mut msg = Message::new(identity.user_id(), None, MessageContent::Text("t".into()));
+    msg.timestamp = UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+    msg.sign(&identity).unwrap();
+
+    for format in [WireFormat::Bincode, WireFormat::Json, WireFormat::Cbor] {
+        let decoded = format
+            .decode::<Message>(&format.encode(&msg).unwrap())
+            .unwrap();
+        assert_eq!(timestamp_to_millis(decoded.timestamp), 1_700_000_000_123);
+        assert_eq!(
+            decoded.timestamp,
+            UNIX_EPOCH + Duration::from_millis(1_700_000_000_123)
+        );
+        assert!(decoded.verify_signature().is_ok(), "{format:?}");
+    }
+    assert_eq!(timestamp_to_millis(UNIX_EPOCH - Duration::from_secs(1)), 0);
+}
@@ -0,0 +1,102 @@
+use disaster_mesh::{
+    Identity, MeshNode, Message, MessageContent, MessageFilter, MessageManager, MessagePriority,
+    MockClock, MockTransport, PeerId, WriteBatching,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+async fn ingest(manager: &MessageManager, count: usize) {
+    let me = manager.add_identity(Identity::generate());
+    for i in 0..count {
+        manager
+            .create_message(me, None, MessageContent::Text(format!("burst {i}")))
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_burst_is_flushed_in_batches() {
+    let unbatched = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_flush_on_write(true);
+    ingest(&unbatched, 200).await;
+
+    let batched = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_flush_on_write(true)
+        .with_write_batching(Some(WriteBatching {
+            max_writes: 50,
+            max_delay: Duration::from_secs(60),
+        }));
+    ingest(&batched, 200).await;
+    assert!(batched.flush_count() * 10 <= unbatched.flush_count());
+
+    // Emergencies skip the batch.
+    let before = batched.flush_count();
+    let me = batched.default_identity().unwrap().user_id();
+    batched
+        .create_from(
+            Message::builder()
+                .sender(me)
+                .content(MessageContent::Text("mayday".into()))
+                .priority(MessagePriority::Emergency),
+        )
+        .await
+        .unwrap();
+    assert_eq!(batched.flush_count(), before + 1);
+    assert!(!batched.flush().await.unwrap(), "nothing left pending");
+
+    batched
+        .create_message(me, None, MessageContent::Text("tail".into()))
+        .await
+        .unwrap();
+    assert!(batched.flush().await.unwrap());
+    let stored = batched
+        .list_messages(&MessageFilter::default())
+        .await
+        .unwrap();
+    assert_eq!(stored.len(), 202);
+}
+
+#[tokio::test]
+async fn test_node_flushes_the_tail_of_a_burst() {
+    let clock = Arc::new(MockClock::default());
+    let manager = MessageManager::in_memory()
+        .await
+        .unwrap()
+        .with_clock(clock.clone())
+        .with_write_batching(Some(WriteBatching {
+            max_writes: 50,
+            max_delay: Duration::from_millis(40),
+        }));
+    ingest(&manager, 3).await;
+    assert!(!manager.flush_due().await.unwrap(), "not due yet");
+    clock.advance(Duration::from_millis(40));
+
+    let node = MeshNode::new(
+        Identity::generate(),
+        PeerId([1; 32]),
+        Arc::new(MockTransport::new()),
+        manager.clone(),
+    );
+    let before = manager.flush_count();
+    let task = tokio::spawn({
+        let node = node.clone();
+        async move { node.run().await }
+    });
+    // No further write arrives; the node's timer closes the batch.
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while manager.flush_count() == before {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("batch was never flushed");
+    assert!(!manager.flush().await.unwrap(), "nothing left pending");
+
+    node.shutdown();
+    task.await.unwrap().unwrap();
+}